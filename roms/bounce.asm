@@ -0,0 +1,40 @@
+; A ball bouncing around the screen, paced with the delay timer
+
+  cls
+  ld v0, 10
+  ld v1, 5
+  ld v2, 1
+  ld v3, 1
+  ld v4, 8
+  ld f, v4
+  ld v5, 30
+  ld v6, 13
+  drw v5, v6, 5
+  ld i, ball
+  drw v0, v1, 4
+
+loop:
+  ld v5, 2
+  ld dt, v5
+wait:
+  ld v5, dt
+  se v5, 0
+  jp wait
+
+  ld i, ball
+  drw v0, v1, 4
+  add v0, v2
+  add v1, v3
+  sne v0, 60
+  ld v2, 0xFF
+  sne v0, 0
+  ld v2, 1
+  sne v1, 28
+  ld v3, 0xFF
+  sne v1, 0
+  ld v3, 1
+  drw v0, v1, 4
+  jp loop
+
+ball:
+  db 0x60, 0xF0, 0xF0, 0x60
@@ -0,0 +1,128 @@
+; Striped IBM logo, drawn as six 8x15 sprites
+
+  cls
+  ld v0, 4
+  ld v1, 8
+  ld i, i_left
+  drw v0, v1, 15
+  add v0, 8
+  ld i, i_right
+  drw v0, v1, 15
+  add v0, 12
+  ld i, b_left
+  drw v0, v1, 15
+  add v0, 8
+  ld i, b_right
+  drw v0, v1, 15
+  add v0, 12
+  ld i, m_left
+  drw v0, v1, 15
+  add v0, 8
+  ld i, m_right
+  drw v0, v1, 15
+  add v0, 12
+
+halt:
+  jp halt
+
+i_left:
+  db 0b11111111
+  db 0b00000000
+  db 0b00001111
+  db 0b00000000
+  db 0b00001111
+  db 0b00000000
+  db 0b00001111
+  db 0b00000000
+  db 0b00001111
+  db 0b00000000
+  db 0b00001111
+  db 0b00000000
+  db 0b00001111
+  db 0b00000000
+  db 0b11111111
+
+i_right:
+  db 0b11111100
+  db 0b00000000
+  db 0b11000000
+  db 0b00000000
+  db 0b11000000
+  db 0b00000000
+  db 0b11000000
+  db 0b00000000
+  db 0b11000000
+  db 0b00000000
+  db 0b11000000
+  db 0b00000000
+  db 0b11000000
+  db 0b00000000
+  db 0b11111100
+
+b_left:
+  db 0b11111111
+  db 0b00000000
+  db 0b00111100
+  db 0b00000000
+  db 0b00111100
+  db 0b00000000
+  db 0b00111111
+  db 0b00000000
+  db 0b00111100
+  db 0b00000000
+  db 0b00111100
+  db 0b00000000
+  db 0b00111100
+  db 0b00000000
+  db 0b11111111
+
+b_right:
+  db 0b11111000
+  db 0b00000000
+  db 0b00111100
+  db 0b00000000
+  db 0b00111100
+  db 0b00000000
+  db 0b11111000
+  db 0b00000000
+  db 0b00111100
+  db 0b00000000
+  db 0b00111100
+  db 0b00000000
+  db 0b00111100
+  db 0b00000000
+  db 0b11111000
+
+m_left:
+  db 0b11111000
+  db 0b00000000
+  db 0b11111100
+  db 0b00000000
+  db 0b00111011
+  db 0b00000000
+  db 0b00111001
+  db 0b00000000
+  db 0b00111000
+  db 0b00000000
+  db 0b00111000
+  db 0b00000000
+  db 0b00111000
+  db 0b00000000
+  db 0b11111000
+
+m_right:
+  db 0b00011111
+  db 0b00000000
+  db 0b00111111
+  db 0b00000000
+  db 0b11011100
+  db 0b00000000
+  db 0b10011100
+  db 0b00000000
+  db 0b00011100
+  db 0b00000000
+  db 0b00011100
+  db 0b00000000
+  db 0b00011100
+  db 0b00000000
+  db 0b00011111
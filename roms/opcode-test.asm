@@ -0,0 +1,141 @@
+; Runs eight small instruction checks and draws a tick or a cross
+;  next to each test number
+
+  cls
+  ld ve, 1
+  ld vb, 4
+  ld vc, 2
+
+; 1: 7xkk wraps around
+  ld v0, 0xFF
+  add v0, 2
+  ld vd, 1
+  se v0, 1
+  ld vd, 0
+  call report
+
+; 2: 8xy4 sets the carry flag
+  ld v0, 0xF0
+  ld v1, 0x20
+  add v0, v1
+  ld vd, 1
+  se v0, 0x10
+  ld vd, 0
+  se vf, 1
+  ld vd, 0
+  call report
+
+; 3: 8xy5 clears VF on borrow
+  ld v0, 0x10
+  ld v1, 0x20
+  sub v0, v1
+  ld vd, 1
+  se v0, 0xF0
+  ld vd, 0
+  se vf, 0
+  ld vd, 0
+  call report
+
+; 4: 8xy7 subtracts Vx from Vy
+  ld v0, 0x10
+  ld v1, 0x30
+  subn v0, v1
+  ld vd, 1
+  se v0, 0x20
+  ld vd, 0
+  se vf, 1
+  ld vd, 0
+  call report
+
+; 5: 8xy1, 8xy2 and 8xy3
+  ld v0, 0x0F
+  ld v1, 0x3C
+  ld v2, v0
+  or v2, v1
+  ld v3, v0
+  and v3, v1
+  ld v4, v0
+  xor v4, v1
+  ld vd, 1
+  se v2, 0x3F
+  ld vd, 0
+  se v3, 0x0C
+  ld vd, 0
+  se v4, 0x33
+  ld vd, 0
+  call report
+
+; 6: Fx33 stores BCD digits
+  ld v0, 234
+  ld i, scratch
+  ld b, v0
+  ld i, scratch
+  ld v2, [i]
+  ld vd, 1
+  se v0, 2
+  ld vd, 0
+  se v1, 3
+  ld vd, 0
+  se v2, 4
+  ld vd, 0
+  call report
+
+; 7: Fx55 and Fx65 round-trip registers
+  ld v0, 0x12
+  ld v1, 0x34
+  ld v2, 0x56
+  ld i, scratch
+  ld [i], v2
+  ld v0, 0
+  ld v1, 0
+  ld v2, 0
+  ld i, scratch
+  ld v2, [i]
+  ld vd, 1
+  se v0, 0x12
+  ld vd, 0
+  se v1, 0x34
+  ld vd, 0
+  se v2, 0x56
+  ld vd, 0
+  call report
+
+; 8: 2nnn and 00EE nest correctly
+  ld v0, 0
+  call increment
+  call increment
+  ld vd, 1
+  se v0, 2
+  ld vd, 0
+  call report
+
+halt:
+  jp halt
+
+increment:
+  add v0, 1
+  ret
+
+; Draw the test number followed by a tick or a cross
+report:
+  ld f, ve
+  drw vb, vc, 5
+  add vb, 6
+  ld i, fail
+  se vd, 0
+  ld i, pass
+  drw vb, vc, 5
+  add ve, 1
+  add vb, 26
+  se vb, 68
+  ret
+  ld vb, 4
+  add vc, 7
+  ret
+
+pass:
+  db 0x02, 0x04, 0x88, 0x50, 0x20
+fail:
+  db 0x88, 0x50, 0x20, 0x50, 0x88
+scratch:
+  db 0, 0, 0
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::palette::PlaneColors;
+use crate::quirks::{Quirks, Variant};
+
+// Metadata for a single program in the CHIP-8 Archive's `programs.json`
+//  (https://github.com/JohnEarnest/chip8Archive). Programs are keyed by the
+//  file stem of their ROM, e.g. `roms/octojam1title.ch8`.
+#[derive(Debug, Deserialize)]
+pub struct Program {
+    pub title: String,
+    #[serde(default)]
+    pub authors: Vec<String>,
+    #[serde(default)]
+    pub platform: String,
+    #[serde(default)]
+    pub options: ProgramOptions,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgramOptions {
+    // Instructions executed per 60 Hz frame
+    pub tickrate: Option<u32>,
+    pub fill_color: Option<String>,
+    pub fill_color2: Option<String>,
+    pub blend_color: Option<String>,
+    pub background_color: Option<String>,
+    pub shift_quirks: Option<bool>,
+    pub load_store_quirks: Option<bool>,
+    pub jump_quirks: Option<bool>,
+    pub logic_quirks: Option<bool>,
+    pub clip_quirks: Option<bool>,
+}
+
+impl Program {
+    pub fn variant(&self) -> Option<Variant> {
+        Variant::from_name(&self.platform)
+    }
+
+    // Start from the platform's defaults and apply any explicit overrides
+    pub fn quirks(&self) -> Quirks {
+        let mut quirks = match self.variant() {
+            Some(variant) => Quirks::for_variant(variant),
+            None => Quirks::default(),
+        };
+
+        let options = &self.options;
+        if let Some(shift) = options.shift_quirks {
+            quirks.shift = shift;
+        }
+        if let Some(load_store) = options.load_store_quirks {
+            quirks.load_store = load_store;
+        }
+        if let Some(jump) = options.jump_quirks {
+            quirks.jump = jump;
+        }
+        if let Some(logic) = options.logic_quirks {
+            quirks.logic = logic;
+        }
+        if let Some(clip) = options.clip_quirks {
+            quirks.clip = clip;
+        }
+
+        quirks
+    }
+
+    // The time per instruction at the program's tickrate, if it has one
+    pub fn cycle_period(&self) -> Option<Duration> {
+        let tickrate = self.options.tickrate.filter(|&rate| rate > 0)?;
+        Some(Duration::from_secs_f64(1.0 / (60.0 * tickrate as f64)))
+    }
+
+    pub fn foreground(&self) -> Option<[u8; 3]> {
+        self.options.fill_color.as_deref().and_then(parse_hex_color)
+    }
+
+    pub fn background(&self) -> Option<[u8; 3]> {
+        self.options.background_color.as_deref().and_then(parse_hex_color)
+    }
+
+    pub fn plane_colors(&self) -> PlaneColors {
+        PlaneColors {
+            plane2: self.options.fill_color2.as_deref().and_then(parse_hex_color),
+            both: self.options.blend_color.as_deref().and_then(parse_hex_color),
+        }
+    }
+}
+
+// Parse "#RRGGBB" or "RRGGBB"
+pub fn parse_hex_color(text: &str) -> Option<[u8; 3]> {
+    let hex = text.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    Some([(value >> 16) as u8, (value >> 8) as u8, value as u8])
+}
+
+pub fn load(path: &Path) -> io::Result<HashMap<String, Program>> {
+    let text = fs::read_to_string(path)?;
+    serde_json::from_str(&text).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        )
+    })
+}
+
+// The archive keeps ROMs in `roms/` next to `programs.json`, so look beside
+//  the ROM first and then one directory up.
+fn find_programs_json(rom_path: &Path) -> Option<PathBuf> {
+    let rom_dir = rom_path.parent()?;
+
+    [Some(rom_dir), rom_dir.parent()]
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join("programs.json"))
+        .find(|candidate| candidate.is_file())
+}
+
+pub fn lookup(rom_path: &Path) -> Option<Program> {
+    let programs_json = find_programs_json(rom_path)?;
+    let key = rom_path.file_stem()?.to_str()?;
+
+    match load(&programs_json) {
+        Ok(mut programs) => programs.remove(key),
+        Err(e) => {
+            eprintln!("Ignoring CHIP-8 Archive metadata: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Program {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn colors_parse_with_or_without_hash() {
+        assert_eq!(parse_hex_color("#FF8000"), Some([0xFF, 0x80, 0x00]));
+        assert_eq!(parse_hex_color(" 1a2b3c "), Some([0x1A, 0x2B, 0x3C]));
+        assert_eq!(parse_hex_color("#FFF"), None);
+        assert_eq!(parse_hex_color("#GG0000"), None);
+
+        let program = parse(r#"{"title": "T", "options": {"fillColor2": "FF0000", "blendColor": "bad"}}"#);
+        assert_eq!(program.plane_colors(), PlaneColors { plane2: Some([0xFF, 0, 0]), both: None });
+    }
+
+    #[test]
+    fn options_override_the_platform_quirks() {
+        let program = parse(r#"{"title": "T", "platform": "schip", "options": {"jumpQuirks": false}}"#);
+        let expected = Quirks { jump: false, ..Quirks::for_variant(Variant::SuperChip) };
+        assert_eq!(program.quirks(), expected);
+
+        let program = parse(r#"{"title": "T", "platform": "unknown"}"#);
+        assert_eq!(program.quirks(), Quirks::default());
+    }
+
+    #[test]
+    fn tickrate_is_instructions_per_frame() {
+        let program = parse(r#"{"title": "T", "options": {"tickrate": 10}}"#);
+        assert_eq!(program.cycle_period(), Some(Duration::from_secs_f64(1.0 / 600.0)));
+
+        assert_eq!(parse(r#"{"title": "T", "options": {"tickrate": 0}}"#).cycle_period(), None);
+        assert_eq!(parse(r#"{"title": "T"}"#).cycle_period(), None);
+    }
+
+    #[test]
+    fn lookup_finds_programs_json_above_roms() {
+        let dir = std::env::temp_dir().join(format!("chip8-archive-{}", std::process::id()));
+        fs::create_dir_all(dir.join("roms")).unwrap();
+        fs::write(dir.join("programs.json"), r#"{"pong": {"title": "Pong", "authors": ["A"]}}"#).unwrap();
+
+        let found = lookup(&dir.join("roms").join("pong.ch8"));
+        let missing = lookup(&dir.join("roms").join("tetris.ch8"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(found.map(|program| program.title), Some(String::from("Pong")));
+        assert!(missing.is_none());
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+
+use crate::opcode::Instruction;
+
+// A small two-pass assembler for Cowgod-style CHIP-8 source, as written by
+//  the disassembler:
+//
+//      ; comment
+//      org 0x200         ; address of the next byte (the first org sets the
+//                        ;  image's load address, later ones pad forward)
+//      start:
+//        ld v0, 0x0A
+//        jp start
+//      sprite:
+//        db 0b11110000, 0x90
+//        dw 0x1234
+//
+// Operands may be numbers (decimal, 0x hex, 0b binary), labels, or sums and
+//  differences of them.
+
+pub const DEFAULT_ORIGIN: u16 = 0x200;
+
+enum Operand {
+    Register(u8),
+    I,
+    IndirectI,
+    Dt,
+    St,
+    K,
+    F,
+    B,
+    Value(String),
+}
+
+struct Statement<'a> {
+    line: usize,
+    address: u16,
+    mnemonic: String,
+    operands: Vec<&'a str>,
+}
+
+fn error(line: usize, message: impl Into<String>) -> String {
+    format!("line {}: {}", line, message.into())
+}
+
+fn parse_number(text: &str) -> Option<i64> {
+    let text = text.trim();
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = text.strip_prefix("0b").or_else(|| text.strip_prefix("0B")) {
+        i64::from_str_radix(binary, 2).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+fn parse_operand(text: &str) -> Operand {
+    let lower = text.to_ascii_lowercase();
+    match lower.as_str() {
+        "i" => return Operand::I,
+        "[i]" => return Operand::IndirectI,
+        "dt" => return Operand::Dt,
+        "st" => return Operand::St,
+        "k" => return Operand::K,
+        "f" => return Operand::F,
+        "b" => return Operand::B,
+        _ => {}
+    }
+
+    if let Some(digit) = lower.strip_prefix('v') {
+        if digit.len() == 1 {
+            if let Ok(register) = u8::from_str_radix(digit, 16) {
+                return Operand::Register(register);
+            }
+        }
+    }
+
+    Operand::Value(String::from(text))
+}
+
+// Split off a leading `label:`, if present
+fn split_label(text: &str) -> (Option<&str>, &str) {
+    match text.split_once(':') {
+        Some((label, rest)) if is_identifier(label.trim()) => (Some(label.trim()), rest.trim()),
+        _ => (None, text),
+    }
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+struct Assembler<'a> {
+    labels: HashMap<&'a str, u16>,
+}
+
+impl<'a> Assembler<'a> {
+    // Evaluate `term (+|- term)*`
+    fn evaluate(&self, line: usize, expression: &str) -> Result<i64, String> {
+        let mut total: i64 = 0;
+        let mut sign: i64 = 1;
+        let mut term = String::new();
+
+        let finish = |term: &mut String, sign: i64, total: &mut i64| -> Result<(), String> {
+            let text = term.trim();
+            if text.is_empty() {
+                return Err(error(line, format!("malformed expression '{}'", expression)));
+            }
+            let value = match parse_number(text) {
+                Some(value) => value,
+                None => *self
+                    .labels
+                    .get(text)
+                    .ok_or_else(|| error(line, format!("unknown label '{}'", text)))?
+                    as i64,
+            };
+            *total += sign * value;
+            term.clear();
+            Ok(())
+        };
+
+        for c in expression.chars() {
+            if (c == '+' || c == '-') && !term.trim().is_empty() {
+                finish(&mut term, sign, &mut total)?;
+                sign = if c == '+' { 1 } else { -1 };
+            } else {
+                term.push(c);
+            }
+        }
+        finish(&mut term, sign, &mut total)?;
+
+        Ok(total)
+    }
+
+    fn value(&self, line: usize, operand: &Operand, max: i64) -> Result<i64, String> {
+        match operand {
+            Operand::Value(text) => {
+                let value = self.evaluate(line, text)?;
+                if value < 0 || value > max {
+                    return Err(error(line, format!("value {} out of range (0 to {})", value, max)));
+                }
+                Ok(value)
+            }
+            _ => Err(error(line, "expected a number or label")),
+        }
+    }
+
+    fn encode(&self, statement: &Statement) -> Result<Instruction, String> {
+        use Operand::*;
+
+        let line = statement.line;
+        let operands: Vec<Operand> = statement.operands.iter().map(|o| parse_operand(o)).collect();
+
+        let address = |operand: &Operand| self.value(line, operand, 0xFFF).map(|v| v as u16);
+        let byte = |operand: &Operand| self.value(line, operand, 0xFF).map(|v| v as u8);
+        let nibble = |operand: &Operand| self.value(line, operand, 0xF).map(|v| v as u8);
+
+        let instruction = match (statement.mnemonic.as_str(), operands.as_slice()) {
+            ("cls", []) => Instruction::Cls,
+            ("ret", []) => Instruction::Ret,
+            ("sys", [a]) => Instruction::Sys(address(a)?),
+            ("jp", [Register(0), a]) => Instruction::JpV0(address(a)?),
+            ("jp", [a]) => Instruction::Jp(address(a)?),
+            ("call", [a]) => Instruction::Call(address(a)?),
+            ("se", [Register(x), Register(y)]) => Instruction::SeReg(*x, *y),
+            ("se", [Register(x), kk]) => Instruction::SeByte(*x, byte(kk)?),
+            ("sne", [Register(x), Register(y)]) => Instruction::SneReg(*x, *y),
+            ("sne", [Register(x), kk]) => Instruction::SneByte(*x, byte(kk)?),
+            ("ld", [Register(x), Register(y)]) => Instruction::LdReg(*x, *y),
+            ("ld", [Register(x), Dt]) => Instruction::LdFromDt(*x),
+            ("ld", [Register(x), K]) => Instruction::LdKey(*x),
+            ("ld", [Register(x), IndirectI]) => Instruction::Load(*x),
+            ("ld", [Register(x), kk]) => Instruction::LdByte(*x, byte(kk)?),
+            ("ld", [I, a]) => Instruction::LdI(address(a)?),
+            ("ld", [Dt, Register(x)]) => Instruction::LdDt(*x),
+            ("ld", [St, Register(x)]) => Instruction::LdSt(*x),
+            ("ld", [F, Register(x)]) => Instruction::LdFont(*x),
+            ("ld", [B, Register(x)]) => Instruction::LdBcd(*x),
+            ("ld", [IndirectI, Register(x)]) => Instruction::Store(*x),
+            ("add", [I, Register(x)]) => Instruction::AddI(*x),
+            ("add", [Register(x), Register(y)]) => Instruction::AddReg(*x, *y),
+            ("add", [Register(x), kk]) => Instruction::AddByte(*x, byte(kk)?),
+            ("or", [Register(x), Register(y)]) => Instruction::Or(*x, *y),
+            ("and", [Register(x), Register(y)]) => Instruction::And(*x, *y),
+            ("xor", [Register(x), Register(y)]) => Instruction::Xor(*x, *y),
+            ("sub", [Register(x), Register(y)]) => Instruction::Sub(*x, *y),
+            ("subn", [Register(x), Register(y)]) => Instruction::Subn(*x, *y),
+            ("shr", [Register(x)]) => Instruction::Shr(*x, 0),
+            ("shr", [Register(x), Register(y)]) => Instruction::Shr(*x, *y),
+            ("shl", [Register(x)]) => Instruction::Shl(*x, 0),
+            ("shl", [Register(x), Register(y)]) => Instruction::Shl(*x, *y),
+            ("rnd", [Register(x), kk]) => Instruction::Rnd(*x, byte(kk)?),
+            ("drw", [Register(x), Register(y), n]) => Instruction::Drw(*x, *y, nibble(n)?),
+            ("skp", [Register(x)]) => Instruction::Skp(*x),
+            ("sknp", [Register(x)]) => Instruction::Sknp(*x),
+            _ => {
+                return Err(error(
+                    line,
+                    format!("invalid instruction '{} {}'", statement.mnemonic, statement.operands.join(", ")),
+                ))
+            }
+        };
+
+        Ok(instruction)
+    }
+}
+
+// Assemble source into a ROM image, returning the image and the address it
+//  must be loaded at
+pub fn assemble(source: &str) -> Result<(Vec<u8>, u16), String> {
+    let mut labels: HashMap<&str, u16> = HashMap::new();
+    let mut statements: Vec<Statement> = Vec::new();
+    let mut origin: Option<u16> = None;
+    let mut address: u32 = DEFAULT_ORIGIN as u32;
+
+    // First pass: strip comments, record labels and lay out addresses
+    for (index, raw_line) in source.lines().enumerate() {
+        let line = index + 1;
+        let text = raw_line.split(';').next().unwrap_or("").trim();
+
+        let (label, text) = split_label(text);
+        if let Some(label) = label {
+            if labels.insert(label, address as u16).is_some() {
+                return Err(error(line, format!("label '{}' defined twice", label)));
+            }
+        }
+        if text.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, rest) = match text.split_once(char::is_whitespace) {
+            Some((mnemonic, rest)) => (mnemonic, rest.trim()),
+            None => (text, ""),
+        };
+        let mnemonic = mnemonic.to_ascii_lowercase();
+        let operands: Vec<&str> = if rest.is_empty() {
+            Vec::new()
+        } else {
+            rest.split(',').map(str::trim).collect()
+        };
+
+        if mnemonic == "org" {
+            let [target] = operands.as_slice() else {
+                return Err(error(line, "org takes one address"));
+            };
+            let target = parse_number(target)
+                .filter(|&value| (0..=0xFFF).contains(&value))
+                .ok_or_else(|| error(line, format!("bad org address '{}'", target)))?
+                as u32;
+
+            if statements.is_empty() {
+                // Labels before the first org refer to it
+                origin = Some(target as u16);
+                for value in labels.values_mut() {
+                    *value = target as u16;
+                }
+            } else if target < address {
+                return Err(error(line, format!("org 0x{:03x} moves backwards", target)));
+            } else if target > address {
+                // Fill the gap with zeros
+                statements.push(Statement {
+                    line,
+                    address: target as u16,
+                    mnemonic: String::from("pad"),
+                    operands: Vec::new(),
+                });
+            }
+
+            address = target;
+            continue;
+        }
+
+        let size = match mnemonic.as_str() {
+            "db" => operands.len() as u32,
+            "dw" => operands.len() as u32 * 2,
+            _ => 2,
+        };
+
+        statements.push(Statement {
+            line,
+            address: address as u16,
+            mnemonic,
+            operands,
+        });
+        address += size;
+
+        if address > 0x1000 {
+            return Err(error(line, "program runs past the end of memory"));
+        }
+    }
+
+    let origin = origin.unwrap_or(DEFAULT_ORIGIN);
+    let assembler = Assembler { labels };
+
+    // Second pass: encode
+    let mut image: Vec<u8> = Vec::new();
+    for statement in &statements {
+        let offset = (statement.address - origin) as usize;
+        match statement.mnemonic.as_str() {
+            "pad" => image.resize(offset, 0),
+            "db" => {
+                for operand in &statement.operands {
+                    let value = assembler.value(statement.line, &parse_operand(operand), 0xFF)?;
+                    image.push(value as u8);
+                }
+            }
+            "dw" => {
+                for operand in &statement.operands {
+                    let value = assembler.value(statement.line, &parse_operand(operand), 0xFFFF)?;
+                    image.extend_from_slice(&(value as u16).to_be_bytes());
+                }
+            }
+            _ => {
+                let opcode = assembler.encode(statement)?.encode();
+                image.extend_from_slice(&opcode.to_be_bytes());
+            }
+        }
+    }
+
+    Ok((image, origin))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_resolve_forwards_and_backwards() {
+        let source = "start:\n  jp end\n  ld v0, 0x0A\nend:\n  jp start\n";
+        let (image, origin) = assemble(source).unwrap();
+        assert_eq!(origin, DEFAULT_ORIGIN);
+        assert_eq!(image, [0x12, 0x04, 0x60, 0x0A, 0x12, 0x00]);
+    }
+
+    #[test]
+    fn org_sets_the_origin_and_pads_forward() {
+        let (image, origin) = assemble("org 0x300\n  db 1\norg 0x303\n  dw 0x1234\n").unwrap();
+        assert_eq!(origin, 0x300);
+        assert_eq!(image, [1, 0, 0, 0x12, 0x34]);
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        assert_eq!(assemble("cls\nfoo: jp foo\nfoo: cls\n").unwrap_err(), "line 3: label 'foo' defined twice");
+        assert!(assemble("  db 1\norg 0x100\n").unwrap_err().starts_with("line 2:"));
+        assert!(assemble("\n\n  ld v0, nowhere\n").unwrap_err().starts_with("line 3:"));
+    }
+}
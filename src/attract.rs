@@ -0,0 +1,101 @@
+use std::io;
+use std::time::Duration;
+
+use crate::chip8::Chip8;
+use crate::replay::Replay;
+use crate::state::Snapshot;
+use crate::timing;
+
+// Attract mode: after a stretch without input, the machine is restarted and
+//  a demo replay plays on a loop. The interrupted game is kept aside and
+//  handed back as soon as a key is pressed.
+pub struct Attract {
+    replay: Replay,
+    pub idle: Duration,
+    // The game that was running before the demo, and its frame number
+    saved: Option<(Snapshot, u64)>,
+    frame: u64,
+}
+
+impl Attract {
+    pub fn new(replay: Replay, idle: Duration) -> Attract {
+        Attract {
+            replay,
+            idle,
+            saved: None,
+            frame: 0,
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        self.saved.is_some()
+    }
+
+    pub fn start(&mut self, chip8: &mut Chip8, rom: &[u8], frame: u64) -> io::Result<()> {
+        let saved = Snapshot::capture(chip8);
+        self.restart(chip8, rom)?;
+        self.saved = Some((saved, frame));
+        Ok(())
+    }
+
+    // Give the game back, returning the frame it was interrupted at
+    pub fn stop(&mut self, chip8: &mut Chip8) -> io::Result<u64> {
+        let (saved, frame) = self.saved.take().expect("attract mode is running");
+        saved.restore(chip8)?;
+        Ok(frame)
+    }
+
+    // Play one frame of the demo, starting it over once it runs out
+    pub fn run_frame(&mut self, chip8: &mut Chip8, rom: &[u8]) -> io::Result<()> {
+        if self.frame >= self.replay.frames.len() as u64 {
+            self.restart(chip8, rom)?;
+        }
+
+        chip8.keypad = self.replay.keys(self.frame).unwrap_or([0; 16]);
+        for _ in 0..timing::cycles_in_frame(self.frame, self.replay.cycle_period) {
+            chip8.cycle();
+        }
+        chip8.tick_timers();
+        self.frame += 1;
+        Ok(())
+    }
+
+    // Power-on with the ROM loaded, as the replay was recorded
+    fn restart(&mut self, chip8: &mut Chip8, rom: &[u8]) -> io::Result<()> {
+        *chip8 = self.replay.machine.power_on(rom)?;
+        chip8.reseed(self.replay.seed);
+        self.frame = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builtin;
+
+    #[test]
+    fn the_game_comes_back_after_the_demo() {
+        let bounce = builtin::find("bounce").unwrap();
+        let replay = Replay::from_bytes(bounce.demo.unwrap()).unwrap();
+        let frames = replay.frames.len();
+        let mut attract = Attract::new(replay, Duration::from_secs(30));
+
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(bounce.data).unwrap();
+        chip8.registers[0] = 0x42;
+        let game = Snapshot::capture(&chip8);
+
+        attract.start(&mut chip8, bounce.data, 120).unwrap();
+        assert!(attract.active());
+        // Run past the end so the demo starts over
+        for _ in 0..=frames {
+            attract.run_frame(&mut chip8, bounce.data).unwrap();
+        }
+        assert_eq!(attract.frame, 1);
+
+        assert_eq!(attract.stop(&mut chip8).unwrap(), 120);
+        assert!(!attract.active());
+        assert_eq!(Snapshot::capture(&chip8).to_bytes(), game.to_bytes());
+    }
+}
@@ -0,0 +1,96 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use sdl2::rect::Rect;
+
+// Decorative artwork drawn around the display, like the bezels of arcade
+//  and retro console frontends. The fully transparent part of the image
+//  marks where the screen goes; the display is fitted into it.
+pub struct Bezel {
+    pub width: u32,
+    pub height: u32,
+    // Four bytes per pixel, R, G, B, A
+    pub rgba: Vec<u8>,
+    // Where the display shows through, in image pixels
+    pub screen: Rect,
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub fn load(path: &Path) -> io::Result<Bezel> {
+    let mut decoder = png::Decoder::new(File::open(path)?);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(|e| invalid(e.to_string()))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(|e| invalid(e.to_string()))?;
+    buffer.truncate(info.buffer_size());
+
+    let rgba: Vec<u8> = match info.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => buffer.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 0xFF]).collect(),
+        png::ColorType::GrayscaleAlpha => buffer.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => buffer.iter().flat_map(|&v| [v, v, v, 0xFF]).collect(),
+        png::ColorType::Indexed => return Err(invalid(String::from("unexpanded palette image"))),
+    };
+
+    let screen = transparent_bounds(&rgba, info.width)
+        .ok_or_else(|| invalid(String::from("the image has no transparent area for the screen")))?;
+
+    Ok(Bezel {
+        width: info.width,
+        height: info.height,
+        rgba,
+        screen,
+    })
+}
+
+// The smallest rectangle holding every fully transparent pixel
+fn transparent_bounds(rgba: &[u8], width: u32) -> Option<Rect> {
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    for (i, _) in rgba.chunks_exact(4).enumerate().filter(|(_, pixel)| pixel[3] == 0) {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        bounds = Some(match bounds {
+            Some((left, top, right, bottom)) => (left.min(x), top.min(y), right.max(x), bottom.max(y)),
+            None => (x, y, x, y),
+        });
+    }
+    bounds.map(|(left, top, right, bottom)| Rect::new(left as i32, top as i32, right - left + 1, bottom - top + 1))
+}
+
+impl Bezel {
+    // The window size that gives the screen area a width of `display_width`
+    pub fn window_size(&self, display_width: u32) -> (u32, u32) {
+        let factor = display_width as f64 / self.screen.width() as f64;
+        (
+            (self.width as f64 * factor).round() as u32,
+            (self.height as f64 * factor).round() as u32,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_screen_is_the_transparent_area() {
+        // A 4x3 frame with a 2x1 hole in the middle row
+        let mut rgba = vec![0xFF; 4 * 3 * 4];
+        rgba[(4 + 1) * 4 + 3] = 0;
+        rgba[(4 + 2) * 4 + 3] = 0;
+        let screen = transparent_bounds(&rgba, 4).unwrap();
+        assert_eq!(screen, Rect::new(1, 1, 2, 1));
+        assert_eq!(transparent_bounds(&[0xFF; 16], 2), None);
+
+        let bezel = Bezel {
+            width: 4,
+            height: 3,
+            rgba,
+            screen,
+        };
+        assert_eq!(bezel.window_size(64), (128, 96));
+    }
+}
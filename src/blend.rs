@@ -0,0 +1,58 @@
+use std::collections::VecDeque;
+
+// Frame blending for the window: each pixel shows the average of the last
+//  few frames, so a sprite that's erased and redrawn every other frame
+//  shows steadily at partial brightness instead of flickering
+pub struct Blend {
+    count: usize,
+    history: VecDeque<Vec<u8>>,
+    levels: Vec<f32>,
+}
+
+impl Blend {
+    pub fn new(frames: usize) -> Blend {
+        Blend {
+            count: frames.max(1),
+            history: VecDeque::with_capacity(frames),
+            levels: Vec::new(),
+        }
+    }
+
+    // Brightness of each pixel from 0 to 1 after showing `video`
+    pub fn apply(&mut self, video: &[u8]) -> &[f32] {
+        if self.history.len() == self.count {
+            self.history.pop_front();
+        }
+        self.history.push_back(video.to_vec());
+
+        self.levels.clear();
+        self.levels.resize(video.len(), 0.0);
+        for frame in &self.history {
+            for (level, &pixel) in self.levels.iter_mut().zip(frame) {
+                if pixel != 0 {
+                    *level += 1.0;
+                }
+            }
+        }
+
+        let frames = self.history.len() as f32;
+        for level in &mut self.levels {
+            *level /= frames;
+        }
+        &self.levels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixels_show_the_average_of_the_last_frames() {
+        let mut blend = Blend::new(2);
+        assert_eq!(blend.apply(&[1, 0, 1]), [1.0, 0.0, 1.0]);
+        assert_eq!(blend.apply(&[0, 1, 1]), [0.5, 0.5, 1.0]);
+        // The first frame has dropped out
+        assert_eq!(blend.apply(&[0, 1, 0]), [0.0, 1.0, 0.5]);
+    }
+}
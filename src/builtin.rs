@@ -0,0 +1,55 @@
+// Public-domain ROMs compiled into the binary, so the emulator can be tried
+//  without any external files. Sources live next to the images in `roms/`.
+pub struct BuiltinRom {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub data: &'static [u8],
+    // A replay of the ROM for attract mode (`--attract demo`)
+    pub demo: Option<&'static [u8]>,
+}
+
+pub const BUILTIN_ROMS: &[BuiltinRom] = &[
+    BuiltinRom {
+        name: "ibm-logo",
+        description: "Striped IBM logo, the classic first ROM to get running",
+        data: include_bytes!("../roms/ibm-logo.ch8"),
+        demo: None,
+    },
+    BuiltinRom {
+        name: "opcode-test",
+        description: "Instruction self-test that marks each check as passed or failed",
+        data: include_bytes!("../roms/opcode-test.ch8"),
+        demo: None,
+    },
+    BuiltinRom {
+        name: "bounce",
+        description: "A ball bouncing around the screen",
+        data: include_bytes!("../roms/bounce.ch8"),
+        demo: Some(include_bytes!("../roms/bounce.rpl")),
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static BuiltinRom> {
+    BUILTIN_ROMS.iter().find(|rom| rom.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::{Chip8, MAX_ROM_SIZE};
+
+    #[test]
+    fn builtin_roms_are_found_by_name() {
+        assert_eq!(find("ibm-logo").map(|rom| rom.name), Some("ibm-logo"));
+        assert!(find("IBM-LOGO").is_none());
+        assert!(find("").is_none());
+    }
+
+    #[test]
+    fn builtin_roms_load() {
+        for rom in BUILTIN_ROMS {
+            assert!(!rom.data.is_empty() && rom.data.len() <= MAX_ROM_SIZE, "{}", rom.name);
+            Chip8::new().load_rom(rom.data).unwrap();
+        }
+    }
+}
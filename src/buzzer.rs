@@ -0,0 +1,336 @@
+use std::sync::Arc;
+
+use crate::chip8::{Chip8, DEFAULT_PITCH};
+use crate::sample::Sample;
+use crate::timing;
+
+pub const SAMPLE_RATE: u32 = 44100;
+
+pub const DEFAULT_FREQUENCY: f32 = 440.0;
+const AMPLITUDE: f32 = i16::MAX as f32 / 4.0;
+// Fading in and out instead of cutting the wave off mid-cycle avoids a pop
+//  at each end of a beep. The fade in is kept short so beeps still start
+//  crisply; the fade out can be gentler.
+const ATTACK_SECONDS: f32 = 0.002;
+const RELEASE_SECONDS: f32 = 0.008;
+
+// The shape of the buzzer's tone
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Waveform {
+    Square,
+    Sine,
+    Triangle,
+    Noise,
+}
+
+pub const WAVEFORMS: [Waveform; 4] = [Waveform::Square, Waveform::Sine, Waveform::Triangle, Waveform::Noise];
+
+impl Waveform {
+    pub fn from_name(name: &str) -> Option<Waveform> {
+        WAVEFORMS.iter().copied().find(|waveform| waveform.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Waveform::Square => "square",
+            Waveform::Sine => "sine",
+            Waveform::Triangle => "triangle",
+            Waveform::Noise => "noise",
+        }
+    }
+
+    pub fn next(self) -> Waveform {
+        let index = WAVEFORMS.iter().position(|&waveform| waveform == self).unwrap_or(0);
+        WAVEFORMS[(index + 1) % WAVEFORMS.len()]
+    }
+}
+
+// What the buzzer sounds like
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tone {
+    pub waveform: Waveform,
+    pub frequency: f32,
+    // Looped in place of the waveform when given
+    pub sample: Option<Arc<Sample>>,
+}
+
+impl Default for Tone {
+    // A 440 Hz square wave, as the buzzer has always been
+    fn default() -> Self {
+        Tone {
+            waveform: Waveform::Square,
+            frequency: DEFAULT_FREQUENCY,
+            sample: None,
+        }
+    }
+}
+
+// XO-CHIP sound: 128 one-bit samples, highest bit first, looped at 4000
+//  samples a second at the default pitch and an octave higher or lower for
+//  every 48 steps of pitch above or below it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    pub bits: [u8; 16],
+    pub pitch: u8,
+}
+
+impl Pattern {
+    // What the program has loaded, if anything
+    pub fn of(chip8: &Chip8) -> Option<Pattern> {
+        chip8.audio_pattern.map(|bits| Pattern { bits, pitch: chip8.pitch })
+    }
+
+    // Samples a second
+    fn rate(self) -> f64 {
+        4000.0 * 2f64.powf((self.pitch as f64 - DEFAULT_PITCH as f64) / 48.0)
+    }
+
+    fn bit(self, index: usize) -> bool {
+        self.bits[index / 8] >> (7 - index % 8) & 1 != 0
+    }
+}
+
+// Generates a tone one sample at a time, from -1 to 1. Noise takes a new
+//  random level every half cycle, so it keeps the tone's pitch. A program's
+//  own pattern takes the place of the tone while it has one.
+pub struct Oscillator {
+    pub tone: Tone,
+    pub pattern: Option<Pattern>,
+    sample_rate: f32,
+    phase: f32,
+    noise: u32,
+    noise_level: f32,
+    // How far into the tone's sample or the pattern playback is, in their
+    //  own samples
+    position: f64,
+}
+
+impl Oscillator {
+    pub fn new(tone: Tone, sample_rate: u32) -> Oscillator {
+        Oscillator {
+            tone,
+            pattern: None,
+            sample_rate: sample_rate as f32,
+            phase: 0.0,
+            noise: 0x1234_5678,
+            noise_level: 1.0,
+            position: 0.0,
+        }
+    }
+
+    // Play a sample or pattern from its start at the next beep
+    pub fn restart(&mut self) {
+        self.position = 0.0;
+    }
+
+    pub fn next(&mut self) -> f32 {
+        if let Some(pattern) = self.pattern {
+            let bits = pattern.bits.len() * 8;
+            let level = if pattern.bit(self.position as usize % bits) { 1.0 } else { -1.0 };
+            self.position = (self.position + pattern.rate() / self.sample_rate as f64) % bits as f64;
+            return level;
+        }
+        if let Some(sample) = &self.tone.sample {
+            // Nearest neighbour is plenty for a beep
+            let level = sample.samples[self.position as usize % sample.samples.len()];
+            self.position = (self.position + sample.rate as f64 / self.sample_rate as f64) % sample.samples.len() as f64;
+            return level;
+        }
+
+        let phase = self.phase;
+        let sample = match self.tone.waveform {
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Sine => (phase * std::f32::consts::TAU).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Noise => self.noise_level,
+        };
+
+        self.phase = (phase + self.tone.frequency / self.sample_rate).fract();
+        if self.tone.waveform == Waveform::Noise && (self.phase < phase || (phase < 0.5) != (self.phase < 0.5)) {
+            // xorshift32: cheap, and the same every run for recordings
+            self.noise ^= self.noise << 13;
+            self.noise ^= self.noise >> 17;
+            self.noise ^= self.noise << 5;
+            self.noise_level = self.noise as f32 / u32::MAX as f32 * 2.0 - 1.0;
+        }
+        sample
+    }
+}
+
+// The buzzer's loudness from 0 to 1, a sample at a time, rising while it
+//  is on and falling while it is off
+pub struct Envelope {
+    gain: f32,
+    attack: f32,
+    release: f32,
+}
+
+impl Envelope {
+    pub fn new(sample_rate: u32) -> Envelope {
+        Envelope {
+            gain: 0.0,
+            attack: 1.0 / (ATTACK_SECONDS * sample_rate as f32),
+            release: 1.0 / (RELEASE_SECONDS * sample_rate as f32),
+        }
+    }
+
+    pub fn next(&mut self, on: bool) -> f32 {
+        self.gain = if on {
+            (self.gain + self.attack).min(1.0)
+        } else {
+            (self.gain - self.release).max(0.0)
+        };
+        self.gain
+    }
+}
+
+// The CHIP-8 buzzer: a tone that sounds while the sound timer is non-zero.
+//  Samples are produced a frame at a time so they stay in step with the
+//  display.
+pub struct Buzzer {
+    oscillator: Oscillator,
+    envelope: Envelope,
+    frames: u64,
+    samples: u64,
+}
+
+impl Buzzer {
+    pub fn new(tone: Tone) -> Buzzer {
+        Buzzer {
+            oscillator: Oscillator::new(tone, SAMPLE_RATE),
+            envelope: Envelope::new(SAMPLE_RATE),
+            frames: 0,
+            samples: 0,
+        }
+    }
+
+    pub fn set_tone(&mut self, tone: Tone) {
+        self.oscillator.tone = tone;
+    }
+
+    pub fn set_pattern(&mut self, pattern: Option<Pattern>) {
+        self.oscillator.pattern = pattern;
+    }
+
+    // Samples for the next 60 Hz frame, counted as a running total so the
+    //  audio never drifts from the frame count
+    pub fn frame(&mut self, on: bool) -> Vec<i16> {
+        self.frames += 1;
+        let end = self.frames * SAMPLE_RATE as u64 / timing::FRAMES_PER_SECOND as u64;
+        let count = (end - self.samples) as usize;
+        self.samples = end;
+
+        (0..count)
+            .map(|_| {
+                // Silence between beeps rewinds the sample, so each beep
+                //  plays it from the start
+                let gain = self.envelope.next(on);
+                if gain == 0.0 {
+                    self.oscillator.restart();
+                    return 0;
+                }
+                (self.oscillator.next() * gain * AMPLITUDE) as i16
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_second_of_frames_is_a_second_of_samples() {
+        let mut buzzer = Buzzer::new(Tone::default());
+        let lengths: Vec<usize> = (0..timing::FRAMES_PER_SECOND).map(|_| buzzer.frame(true).len()).collect();
+        assert_eq!(lengths.iter().sum::<usize>(), SAMPLE_RATE as usize);
+        assert!(lengths.iter().all(|&length| length == 735));
+    }
+
+    #[test]
+    fn the_tone_is_a_square_wave_only_while_on() {
+        let mut buzzer = Buzzer::new(Tone::default());
+        assert!(buzzer.frame(false).iter().all(|&sample| sample == 0));
+        let samples = buzzer.frame(true);
+        let loudest = AMPLITUDE as i16;
+        // Once it has faded in
+        let attack = (ATTACK_SECONDS * SAMPLE_RATE as f32).ceil() as usize;
+        assert!(samples[attack..].iter().all(|&sample| sample == loudest || sample == -loudest));
+        // 440 Hz changes sign about 880 times a second
+        let flips = samples.windows(2).filter(|pair| pair[0].signum() != pair[1].signum()).count();
+        assert!((14..=15).contains(&flips), "{}", flips);
+    }
+
+    #[test]
+    fn every_waveform_keeps_its_pitch() {
+        for waveform in WAVEFORMS {
+            assert_eq!(Waveform::from_name(waveform.name()), Some(waveform));
+            let mut oscillator = Oscillator::new(Tone { waveform, frequency: 100.0, sample: None }, 800);
+            let samples: Vec<f32> = (0..80).map(|_| oscillator.next()).collect();
+            assert!(samples.iter().all(|sample| (-1.0..=1.0).contains(sample)), "{:?}", waveform);
+            // Ten cycles cross from negative to positive about ten times
+            let rises = samples.windows(2).filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0).count();
+            assert!((9..=10).contains(&rises) || waveform == Waveform::Noise, "{:?}: {}", waveform, rises);
+        }
+        assert_eq!(Waveform::Noise.next(), Waveform::Square);
+    }
+
+    #[test]
+    fn samples_loop_at_their_own_rate_and_restart_with_each_beep() {
+        let sample = Sample {
+            rate: 22050,
+            samples: vec![0.0, 0.5, 1.0],
+        };
+        let tone = Tone {
+            sample: Some(Arc::new(sample)),
+            ..Tone::default()
+        };
+        let mut oscillator = Oscillator::new(tone.clone(), 44100);
+        let levels: Vec<f32> = (0..8).map(|_| oscillator.next()).collect();
+        assert_eq!(levels, [0.0, 0.0, 0.5, 0.5, 1.0, 1.0, 0.0, 0.0]);
+
+        let mut buzzer = Buzzer::new(tone);
+        let first = buzzer.frame(true)[0..4].to_vec();
+        buzzer.frame(false);
+        assert_eq!(buzzer.frame(true)[0..4], first);
+    }
+
+    #[test]
+    fn fades_take_the_same_time_at_any_sample_rate() {
+        for rate in [22050, 44100, 48000] {
+            let mut envelope = Envelope::new(rate);
+            let attack = (ATTACK_SECONDS * rate as f32).round() as usize;
+            let rising: Vec<f32> = (0..attack).map(|_| envelope.next(true)).collect();
+            assert!(rising[attack / 2] < 0.6, "{}", rate);
+            assert!((rising[attack - 1] - 1.0).abs() < 0.01, "{}", rate);
+
+            let release = (RELEASE_SECONDS * rate as f32).round() as usize;
+            let falling: Vec<f32> = (0..release + 1).map(|_| envelope.next(false)).collect();
+            assert!(falling[release / 2] > 0.4, "{}", rate);
+            assert_eq!(falling[release], 0.0, "{}", rate);
+        }
+    }
+
+    #[test]
+    fn patterns_play_their_bits_in_place_of_the_tone() {
+        let mut oscillator = Oscillator::new(Tone::default(), 8000);
+        let mut bits = [0; 16];
+        bits[0] = 0b1010_0000;
+        let pattern = Pattern { bits, pitch: DEFAULT_PITCH };
+        assert_eq!(pattern.rate(), 4000.0);
+        oscillator.pattern = Some(pattern);
+        // Two output samples per bit at the default pitch
+        let levels: Vec<f32> = (0..8).map(|_| oscillator.next()).collect();
+        assert_eq!(levels, [1.0, 1.0, -1.0, -1.0, 1.0, 1.0, -1.0, -1.0]);
+
+        // An octave up every 48 steps
+        let higher = Pattern { bits, pitch: DEFAULT_PITCH + 48 };
+        assert_eq!(higher.rate(), 8000.0);
+    }
+}
@@ -0,0 +1,1011 @@
+pub struct Chip8 {
+    pub registers: [u8; 16],
+    pub memory: [u8; 4096],
+    pub index: u16,
+    pub pc: u16,
+    pub stack: [u16; 16],
+    pub sp: u8,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub keypad: [u8; 16],
+    // Each pixel holds the XO-CHIP planes lit there, plane 1 in bit 0 and
+    //  plane 2 in bit 1. Programs that never select a plane only use plane 1.
+    pub video: [u8; 64 * 32],
+    pub opcode: u16,
+    pub rng_core: ChaCha8Rng,
+    pub rng_seed: u64,
+    pub table: [fn(&mut Chip8); 0x10],
+    pub table_0: [fn(&mut Chip8); 0x10],
+    pub table_8: [fn(&mut Chip8); 0x10],
+    pub table_e: [fn(&mut Chip8); 0x10],
+    pub table_f: [fn(&mut Chip8); 0x100],
+    pub quirks: Quirks,
+    // XO-CHIP: the planes Dxyn and 00E0 work on, selected by Fn01
+    pub planes: u8,
+    // XO-CHIP sound: a loop of 128 one-bit samples loaded by F002, played
+    //  at a rate set by Fx3A. None until a program loads one, and the
+    //  buzzer sounds its usual tone.
+    pub audio_pattern: Option<[u8; 16]>,
+    pub pitch: u8,
+    // The keys held when Fx0A began waiting and still down, which don't
+    //  count until released and pressed again; None when not waiting. Not
+    //  part of savestates: a loaded state waits afresh.
+    pub key_wait: Option<u16>,
+    // The keys Ex9E, ExA1 and Fx0A found pressed since the frontend last
+    //  cleared it, as a bit mask; not part of savestates
+    pub key_reads: u16,
+    // Where the last Dxyn drew, for views that follow the action; not part
+    //  of savestates
+    pub last_draw: Option<Draw>,
+    // The program as last loaded, for a hard reset to load again; not part
+    //  of savestates
+    pub rom: Vec<u8>,
+    // Where programs are loaded and start: 0x200, or 0x600 for the ETI-660;
+    //  not part of savestates
+    pub start_address: u16,
+}
+
+// A sprite drawn by Dxyn: its top-left corner on screen and its height
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Draw {
+    pub x: u8,
+    pub y: u8,
+    pub height: u8,
+}
+
+use std::fs::File;
+use std::io::Read;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::quirks::Quirks;
+
+const START_ADDRESS: u16 = 0x200;
+// The download limit for fetched ROMs
+#[cfg(any(feature = "http", test))]
+pub const MAX_ROM_SIZE: usize = 4096 - START_ADDRESS as usize;
+
+// Sprites
+const FONTSET_SIZE: usize = 80;
+const FONTSET_START_ADDRESS: usize = 0x50;
+const FONTSET: [u8; FONTSET_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+// The planes Dxyn draws to until a program selects others with Fn01
+pub const DEFAULT_PLANES: u8 = 0b01;
+pub const PLANE_COUNT: usize = 2;
+// Fx3A's pitch for 4000 samples a second, XO-CHIP's starting rate
+pub const DEFAULT_PITCH: u8 = 64;
+
+pub const VIDEO_HEIGHT: u8 = 32;
+pub const VIDEO_WIDTH: u8 = 64;
+
+// The name to report for a ROM argument, and a reader for its bytes. "-"
+//  reads the program from standard input, so ROMs can be piped in.
+fn open_rom(filename: &str) -> Result<(&str, Box<dyn Read>), std::io::Error> {
+    if filename == "-" {
+        return Ok(("<stdin>", Box::new(std::io::stdin())));
+    }
+
+    // Open the file as a binary read-only stream
+    let file = File::open(filename).map_err(|e| {
+        std::io::Error::new(e.kind(), format!("could not open ROM '{}': {}", filename, e))
+    })?;
+    Ok((filename, Box::new(file)))
+}
+
+// Read a ROM image from a file, or from standard input when given "-"
+pub fn read_rom(filename: &str) -> Result<Vec<u8>, std::io::Error> {
+    let (source, mut reader) = open_rom(filename)?;
+
+    // Read the contents into a buffer
+    let mut buffer = Vec::new();
+    reader.read_to_end(&mut buffer).map_err(|e| {
+        std::io::Error::new(e.kind(), format!("could not read ROM '{}': {}", source, e))
+    })?;
+
+    Ok(buffer)
+}
+
+impl Chip8 {
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), std::io::Error> {
+        if rom.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "ROM is empty",
+            ));
+        }
+
+        let start = self.start_address as usize;
+        let available = self.memory.len() - start;
+        if rom.len() > available {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("ROM is {} bytes, larger than the {} bytes available", rom.len(), available),
+            ));
+        }
+
+        // Load the ROM contents into Chip8's memory, at the start address
+        self.memory[start..start + rom.len()].copy_from_slice(rom);
+        self.rom = rom.to_vec();
+
+        Ok(())
+    }
+
+    // Initialize Program Counter
+    pub fn new() -> Chip8 {
+        let mut memory = [0; 4096];
+        
+        // Load fonts into memory
+        memory[FONTSET_START_ADDRESS..FONTSET_START_ADDRESS + FONTSET_SIZE].copy_from_slice(&FONTSET);
+
+        // Initialize RNG. A seeded generator keeps its position in the
+        //  stream, so savestates can capture it
+        let rng_seed: u64 = rand::random();
+        let rng_core = ChaCha8Rng::seed_from_u64(rng_seed);
+
+        let mut chip8 = Chip8 {
+            registers: [0; 16],
+            memory,
+            index: 0,
+            pc: START_ADDRESS,
+            stack: [0; 16],
+            sp: 0,
+            delay_timer: 0,
+            sound_timer: 0,
+            keypad: [0; 16],
+            video: [0; 64 * 32],
+            opcode: 0,
+            rng_core,
+            rng_seed,
+            table: [Chip8::op_null; 0x10],
+            table_0: [Chip8::op_null; 0x10],
+            table_8: [Chip8::op_null; 0x10],
+            table_e: [Chip8::op_null; 0x10],
+            table_f: [Chip8::op_null; 0x100],
+            quirks: Quirks::default(),
+            planes: DEFAULT_PLANES,
+            audio_pattern: None,
+            pitch: DEFAULT_PITCH,
+            key_wait: None,
+            key_reads: 0,
+            last_draw: None,
+            rom: Vec::new(),
+            start_address: START_ADDRESS,
+        };
+
+        chip8.table[0x0] = Chip8::table_0;
+        chip8.table[0x1] = Chip8::op_1nnn;
+        chip8.table[0x2] = Chip8::op_2nnn;
+        chip8.table[0x3] = Chip8::op_3xkk;
+        chip8.table[0x4] = Chip8::op_4xkk;
+        chip8.table[0x5] = Chip8::op_5xy0;
+        chip8.table[0x6] = Chip8::op_6xkk;
+        chip8.table[0x7] = Chip8::op_7xkk;
+        chip8.table[0x8] = Chip8::table_8;
+        chip8.table[0x9] = Chip8::op_9xy0;
+        chip8.table[0xA] = Chip8::op_annn;
+        chip8.table[0xB] = Chip8::op_bnnn;
+        chip8.table[0xC] = Chip8::op_cxkk;
+        chip8.table[0xD] = Chip8::op_dxyn;
+        chip8.table[0xE] = Chip8::table_e;
+        chip8.table[0xF] = Chip8::table_f;
+
+        // Sub-tables
+        chip8.table_0[0x0] = Chip8::op_00e0;
+        chip8.table_0[0xE] = Chip8::op_00ee;
+
+        chip8.table_8[0x0] = Chip8::op_8xy0;
+        chip8.table_8[0x1] = Chip8::op_8xy1;
+        chip8.table_8[0x2] = Chip8::op_8xy2;
+        chip8.table_8[0x3] = Chip8::op_8xy3;
+        chip8.table_8[0x4] = Chip8::op_8xy4;
+        chip8.table_8[0x5] = Chip8::op_8xy5;
+        chip8.table_8[0x6] = Chip8::op_8xy6;
+        chip8.table_8[0x7] = Chip8::op_8xy7;
+        chip8.table_8[0xE] = Chip8::op_8xye;
+
+        chip8.table_e[0x1] = Chip8::op_exa1;
+        chip8.table_e[0xE] = Chip8::op_ex9e;
+
+        chip8.table_f[0x01] = Chip8::op_fn01;
+        chip8.table_f[0x02] = Chip8::op_fx02;
+        chip8.table_f[0x07] = Chip8::op_fx07;
+        chip8.table_f[0x0A] = Chip8::op_fx0a;
+        chip8.table_f[0x15] = Chip8::op_fx15;
+        chip8.table_f[0x18] = Chip8::op_fx18;
+        chip8.table_f[0x1E] = Chip8::op_fx1e;
+        chip8.table_f[0x29] = Chip8::op_fx29;
+        chip8.table_f[0x33] = Chip8::op_fx33;
+        chip8.table_f[0x3A] = Chip8::op_fx3a;
+        chip8.table_f[0x55] = Chip8::op_fx55;
+        chip8.table_f[0x65] = Chip8::op_fx65;
+
+        chip8
+    }
+
+    // Function pointer helper functions
+    fn table_0(&mut self) {
+        self.table_0[(self.opcode & 0x000F) as usize](self);
+    }
+
+    fn table_8(&mut self) {
+        self.table_8[(self.opcode & 0x000F) as usize](self);
+    }
+    
+    fn table_e(&mut self) {
+        self.table_e[(self.opcode & 0x000F) as usize](self);
+    }
+    
+    fn table_f(&mut self) {
+        // Fx instructions are distinguished by their whole low byte
+        self.table_f[(self.opcode & 0x00FF) as usize](self);
+    }
+
+    fn op_null(&mut self) {}
+
+    fn op_00e0(&mut self) {
+        // Clear the selected planes, leaving any others as they are
+        for pixel in self.video.iter_mut() {
+            *pixel &= !self.planes;
+        }
+    }
+
+    fn op_00ee(&mut self) {
+        self.sp -= 1;
+        self.pc = self.stack[self.sp as usize];
+    }
+
+    fn op_1nnn(&mut self) {
+        let address: u16 = self.opcode & 0x0FFF;
+        self.pc = address;
+    }
+
+    fn op_2nnn(&mut self) {
+        let address: u16 = self.opcode & 0x0FFF;
+        self.stack[self.sp as usize] = self.pc;
+        self.sp += 1;
+        self.pc = address;
+    }
+
+    fn op_3xkk(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let byte: u8 = (self.opcode & 0x00FF) as u8;
+
+        if self.registers[vx as usize] == byte {
+            self.pc += 2;
+        }
+    }
+
+    fn op_4xkk(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let byte: u8 = (self.opcode & 0x00FF) as u8;
+
+        if self.registers[vx as usize] != byte {
+            self.pc += 2;
+        }
+    }
+
+    fn op_5xy0(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let vy: u16 = (self.opcode & 0x00F0) >> 4;
+
+        if self.registers[vx as usize] == self.registers[vy as usize] {
+            self.pc += 2;
+        }
+    }
+
+    fn op_6xkk(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let byte: u8 = (self.opcode & 0x00FF) as u8;
+
+        self.registers[vx as usize] = byte;
+    } 
+
+    fn op_7xkk(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let byte: u8 = (self.opcode & 0x00FF) as u8;
+
+        self.registers[vx as usize] 
+            = self.registers[vx as usize].wrapping_add(byte);
+    } 
+
+    fn op_8xy0(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let vy: u16 = (self.opcode & 0x00F0) >> 4;
+
+        self.registers[vx as usize] = self.registers[vy as usize];
+    }
+
+    fn op_8xy1(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let vy: u16 = (self.opcode & 0x00F0) >> 4;
+
+        self.registers[vx as usize] |= self.registers[vy as usize];
+
+        if self.quirks.logic {
+            self.registers[0xF] = 0;
+        }
+    }
+
+    fn op_8xy2(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let vy: u16 = (self.opcode & 0x00F0) >> 4;
+
+        self.registers[vx as usize] &= self.registers[vy as usize];
+
+        if self.quirks.logic {
+            self.registers[0xF] = 0;
+        }
+    }
+
+    fn op_8xy3(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let vy: u16 = (self.opcode & 0x00F0) >> 4;
+
+        self.registers[vx as usize] ^= self.registers[vy as usize];
+
+        if self.quirks.logic {
+            self.registers[0xF] = 0;
+        }
+    }
+
+    fn op_8xy4(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let vy: u16 = (self.opcode & 0x00F0) >> 4;
+
+        let sum: u16 = self.registers[vx as usize] as u16 
+                + self.registers[vy as usize] as u16;
+
+        if sum > 255 {
+            self.registers[0xF] = 1;
+        }
+        else {
+            self.registers[0xF] = 0;
+        }
+
+        self.registers[vx as usize] = (sum & 0xFF) as u8;
+    }
+
+    fn op_8xy5(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let vy: u16 = (self.opcode & 0x00F0) >> 4;
+
+        if self.registers[vx as usize] > self.registers[vy as usize] {
+            self.registers[0xF] = 1;
+        }
+        else {
+            self.registers[0xF] = 0;
+        }
+
+        // self.registers[vx as usize] -= self.registers[vy as usize];
+        self.registers[vx as usize] = self.registers[vx as usize].wrapping_sub(self.registers[vy as usize]);
+    }
+
+    fn op_8xy6(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let vy: u16 = (self.opcode & 0x00F0) >> 4;
+
+        // Original interpreters shift Vy into Vx
+        if !self.quirks.shift {
+            self.registers[vx as usize] = self.registers[vy as usize];
+        }
+
+        // Save LSB in VF
+        self.registers[0xF] = self.registers[vx as usize] & 0x1;
+
+        self.registers[vx as usize] >>= 1;
+    }
+
+    fn op_8xy7(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let vy: u16 = (self.opcode & 0x00F0) >> 4;
+
+        if self.registers[vy as usize] > self.registers[vx as usize] {
+            self.registers[0xF] = 1;
+        }
+        else {
+            self.registers[0xF] = 0;
+        }
+
+        self.registers[vx as usize] = 
+            self.registers[vy as usize] - self.registers[vx as usize];
+    }
+
+    fn op_8xye(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let vy: u16 = (self.opcode & 0x00F0) >> 4;
+
+        if !self.quirks.shift {
+            self.registers[vx as usize] = self.registers[vy as usize];
+        }
+
+        // Save MSB in VF
+        self.registers[0xF] = (self.registers[vx as usize] & 0x80) >> 7;
+
+        // Shift the register value to the left by 1
+        self.registers[vx as usize] <<= 1;
+    }
+
+    fn op_9xy0(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let vy: u16 = (self.opcode & 0x00F0) >> 4;
+
+        if self.registers[vx as usize] != self.registers[vy as usize] {
+            self.pc += 2;
+        }
+    }
+
+    fn op_annn(&mut self) {
+        let address: u16 = self.opcode & 0x0FFF;
+        self.index = address;
+    }
+
+    fn op_bnnn(&mut self) {
+        let address: u16 = self.opcode & 0x0FFF;
+
+        // SUPER-CHIP reads the offset from Vx, where x is the top nibble of nnn
+        let offset_register: usize = if self.quirks.jump {
+            ((self.opcode & 0x0F00) >> 8) as usize
+        } else {
+            0
+        };
+
+        self.pc = self.registers[offset_register] as u16 + address;
+    }
+
+    fn op_cxkk(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let byte: u8 = (self.opcode & 0x00FF) as u8;
+
+        self.registers[vx as usize] = self.rng_core.gen::<u8>() & byte;
+    }
+
+    fn op_dxyn(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let vy: u16 = (self.opcode & 0x00F0) >> 4;
+        let height: u8 = (self.opcode & 0x000F) as u8;
+
+        // Wrap if going beyond screen boundaries
+        let x_pos: u8 = self.registers[vx as usize] % VIDEO_WIDTH;
+        let y_pos: u8 = self.registers[vy as usize] % VIDEO_HEIGHT;
+
+        self.registers[0xF] = 0;
+        self.last_draw = Some(Draw { x: x_pos, y: y_pos, height });
+
+        // XO-CHIP: each selected plane gets its own sprite, one after another
+        //  in memory from I, plane 1's first
+        let selected = self.planes;
+        let planes = (0..PLANE_COUNT as u8).map(|plane| 1 << plane).filter(|bit| selected & bit != 0);
+        for (sprite, plane) in planes.enumerate() {
+            let sprite_start = self.index as usize + sprite * height as usize;
+            self.draw_plane(plane, sprite_start, x_pos, y_pos, height);
+        }
+    }
+
+    fn draw_plane(&mut self, plane: u8, sprite_start: usize, x_pos: u8, y_pos: u8, height: u8) {
+        for row in 0..height {
+            let sprite_byte: u8 =
+                self.memory[(sprite_start + row as usize) % self.memory.len()];
+
+            let mut y: usize = (y_pos + row) as usize;
+            if y >= VIDEO_HEIGHT as usize {
+                if self.quirks.clip {
+                    break;
+                }
+                y %= VIDEO_HEIGHT as usize;
+            }
+
+            for col in 0..8 {
+                let sprite_pixel: u8 = sprite_byte & (0x80 >> col);
+
+                let mut x: usize = (x_pos + col) as usize;
+                if x >= VIDEO_WIDTH as usize {
+                    if self.quirks.clip {
+                        break;
+                    }
+                    x %= VIDEO_WIDTH as usize;
+                }
+
+                let screen_pixel_index: usize = y * VIDEO_WIDTH as usize + x;
+                let screen_pixel 
+                    = &mut self.video[screen_pixel_index];
+
+                // Sprite pixel is on
+                if sprite_pixel != 0 {
+                    // #Collision: screen pixel also on in this plane
+                    if *screen_pixel & plane != 0 {
+                        self.registers[0xF] = 1;
+                    }
+
+                    // Effectively XOR with the sprite pixel
+                    *screen_pixel ^= plane;
+                }
+            }
+        }
+    }
+
+    fn op_ex9e(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let key: u8 = self.registers[vx as usize];
+
+        if self.keypad[key as usize] > 0 {
+            self.key_reads |= 1 << (key & 0xF);
+            self.pc += 2;
+        }
+    }
+
+    fn op_exa1(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let key: u8 = self.registers[vx as usize];
+
+        if self.keypad[key as usize] == 0 {
+            self.pc += 2;
+        } else {
+            self.key_reads |= 1 << (key & 0xF);
+        }
+    }
+
+    // XO-CHIP: select the planes to draw to and clear, as a mask in n
+    fn op_fn01(&mut self) {
+        self.planes = ((self.opcode & 0x0F00) >> 8) as u8 & 0b11;
+    }
+
+    // XO-CHIP: load the 16-byte audio pattern at I
+    fn op_fx02(&mut self) {
+        let mut pattern = [0; 16];
+        for (i, byte) in pattern.iter_mut().enumerate() {
+            *byte = self.memory[(self.index as usize + i) % self.memory.len()];
+        }
+        self.audio_pattern = Some(pattern);
+    }
+
+    fn op_fx07(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        self.registers[vx as usize] = self.delay_timer;
+    }
+
+    fn op_fx0a(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let held = (0..16).filter(|&i| self.keypad[i] > 0).fold(0u16, |bits, i| bits | 1 << i);
+
+        // Only a new press ends the wait, unless held keys count again
+        let waiting_on = if self.quirks.key_repeat { 0 } else { *self.key_wait.get_or_insert(held) & held };
+        let pressed = held & !waiting_on;
+        if pressed != 0 {
+            self.registers[vx as usize] = pressed.trailing_zeros() as u8;
+            self.key_reads |= pressed & pressed.wrapping_neg();
+            self.key_wait = None;
+            return;
+        }
+
+        if !self.quirks.key_repeat {
+            self.key_wait = Some(waiting_on);
+        }
+        self.pc -= 2;
+    }
+
+    fn op_fx15(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        self.delay_timer = self.registers[vx as usize];
+    }
+
+    fn op_fx18(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        self.sound_timer = self.registers[vx as usize];
+    }
+
+    fn op_fx1e(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        self.index += self.registers[vx as usize] as u16;
+    }
+
+    fn op_fx29(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let digit: u8 = self.registers[vx as usize];
+
+        self.index = (FONTSET_START_ADDRESS + (5 * digit) as usize) as u16;
+    }
+
+    fn op_fx33(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        let mut value: u8 = self.registers[vx as usize];
+
+        // Ones-place
+        self.memory[(self.index + 2) as usize] = value % 10;
+        value /= 10;
+
+        // Tens-place
+        self.memory[(self.index + 1) as usize] = value % 10;
+        value /= 10;
+
+        // Hundreds-place
+        self.memory[self.index as usize] = value % 10;
+    }
+
+    // XO-CHIP: set the audio pattern's playback pitch
+    fn op_fx3a(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        self.pitch = self.registers[vx as usize];
+    }
+
+    fn op_fx55(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+
+        for i in 0..=vx {
+            self.memory[(self.index + i) as usize] = self.registers[i as usize];
+        }
+
+        // Original interpreters leave I pointing past the last stored byte
+        if !self.quirks.load_store {
+            self.index += vx + 1;
+        }
+    }
+
+    fn op_fx65(&mut self) {
+        let vx: u16 = (self.opcode & 0x0F00) >> 8;
+        
+        for i in 0..=vx {
+            self.registers[i as usize] = self.memory[(self.index + i) as usize];
+        }
+
+        if !self.quirks.load_store {
+            self.index += vx + 1;
+        }
+    }
+
+    // Restart the random number stream from a known seed, so runs can be
+    //  reproduced exactly
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng_seed = seed;
+        self.rng_core = ChaCha8Rng::seed_from_u64(seed);
+    }
+
+    // Load programs at `address` and start them there, before load_rom
+    pub fn set_start_address(&mut self, address: u16) {
+        self.start_address = address;
+        self.pc = address;
+    }
+
+    // Restart the program. A soft reset clears the registers, stack, timers
+    //  and display but leaves memory as the program left it; a hard reset
+    //  also clears memory and loads the fonts and ROM again. Quirks, held
+    //  keys and the random number generator carry on either way.
+    pub fn reset(&mut self, hard: bool) {
+        self.registers = [0; 16];
+        self.index = 0;
+        self.pc = self.start_address;
+        self.stack = [0; 16];
+        self.sp = 0;
+        self.delay_timer = 0;
+        self.sound_timer = 0;
+        self.video = [0; 64 * 32];
+        self.opcode = 0;
+        self.planes = DEFAULT_PLANES;
+        self.audio_pattern = None;
+        self.pitch = DEFAULT_PITCH;
+        self.key_wait = None;
+        self.key_reads = 0;
+        self.last_draw = None;
+
+        if hard {
+            self.memory = [0; 4096];
+            self.memory[FONTSET_START_ADDRESS..FONTSET_START_ADDRESS + FONTSET_SIZE].copy_from_slice(&FONTSET);
+            let start = self.start_address as usize;
+            self.memory[start..start + self.rom.len()].copy_from_slice(&self.rom);
+        }
+    }
+
+    // Cycle
+    pub fn cycle(&mut self) {
+        // Fetch next instruction. A program that runs off the end of memory
+        //  wraps around to the start rather than reading past it.
+        let pc = self.pc as usize % self.memory.len();
+        self.opcode = ((self.memory[pc] as u16) << 8)
+            | self.memory[(pc + 1) % self.memory.len()] as u16;
+        
+        // Increment pc before execution
+        self.pc = pc as u16 + 2;
+
+        // Get the instruction and execute
+        self.table[(self.opcode & 0xF000) as usize >> 12](self);
+    }
+
+    // Count the timers down; called once per 60 Hz frame, however many
+    //  cycles the frame ran, so beeps and delays last as long at any speed
+    pub fn tick_timers(&mut self) {
+        // Decrement the delay timer if set
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+
+        // Decrement the sound timer if set
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A machine with `program` at 0x200 that has run `cycles` instructions
+    fn run(quirks: Quirks, program: &[u8], cycles: usize) -> Chip8 {
+        let mut chip8 = Chip8::new();
+        chip8.quirks = quirks;
+        chip8.memory[START_ADDRESS as usize..][..program.len()].copy_from_slice(program);
+        for _ in 0..cycles {
+            chip8.cycle();
+        }
+        chip8
+    }
+
+    #[test]
+    fn roms_load_at_the_start_address() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&[0x12, 0x34]).unwrap();
+        assert_eq!(chip8.memory[0x200..0x203], [0x12, 0x34, 0x00]);
+
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&vec![0xAA; MAX_ROM_SIZE]).unwrap();
+        assert_eq!(chip8.memory[4095], 0xAA);
+
+        // ETI-660 programs load and start at 0x600, with less room
+        let mut chip8 = Chip8::new();
+        chip8.set_start_address(0x600);
+        assert!(chip8.load_rom(&vec![0; MAX_ROM_SIZE]).is_err());
+        chip8.load_rom(&[0x12, 0x34]).unwrap();
+        assert_eq!((chip8.pc, &chip8.memory[0x600..0x602]), (0x600, &[0x12, 0x34][..]));
+        chip8.cycle();
+        chip8.reset(true);
+        assert_eq!((chip8.pc, &chip8.memory[0x600..0x602]), (0x600, &[0x12, 0x34][..]));
+    }
+
+    #[test]
+    fn instruction_fetches_wrap_at_the_end_of_memory() {
+        // ld v0, 7 in the last two bytes, then on from 0x000
+        let mut chip8 = Chip8::new();
+        chip8.set_start_address(0xFFE);
+        chip8.load_rom(&[0x60, 0x07]).unwrap();
+        chip8.cycle();
+        assert_eq!((chip8.pc, chip8.registers[0]), (0x1000, 7));
+        chip8.cycle();
+        assert_eq!(chip8.pc, 2);
+    }
+
+    #[test]
+    fn empty_and_oversized_roms_are_refused() {
+        let mut chip8 = Chip8::new();
+        assert!(chip8.load_rom(&[]).is_err());
+        assert!(chip8.load_rom(&vec![0; MAX_ROM_SIZE + 1]).is_err());
+        assert_eq!(chip8.memory[0x200..], [0; MAX_ROM_SIZE]);
+    }
+
+    #[test]
+    fn missing_files_name_the_rom() {
+        let error = read_rom("no/such/rom.ch8").unwrap_err();
+        assert!(error.to_string().contains("'no/such/rom.ch8'"), "{}", error);
+    }
+
+    #[test]
+    fn a_dash_reads_standard_input() {
+        let (source, _) = open_rom("-").unwrap();
+        assert_eq!(source, "<stdin>");
+    }
+
+    #[test]
+    fn the_last_sprite_drawn_is_remembered() {
+        // V0 = 70, V1 = 5, D015: x wraps to 6
+        let program = [0x60, 70, 0x61, 0x05, 0xD0, 0x15];
+        assert_eq!(run(Quirks::default(), &program, 2).last_draw, None);
+        let chip8 = run(Quirks::default(), &program, 3);
+        assert_eq!(chip8.last_draw, Some(Draw { x: 6, y: 5, height: 5 }));
+    }
+
+    #[test]
+    fn shift_quirk_picks_the_source_register() {
+        // V1 = 0x81, V2 = 0x04, 8126
+        let program = [0x61, 0x81, 0x62, 0x04, 0x81, 0x26];
+
+        let chip8 = run(Quirks { shift: true, ..Quirks::default() }, &program, 3);
+        assert_eq!((chip8.registers[1], chip8.registers[0xF]), (0x40, 1));
+
+        let chip8 = run(Quirks { shift: false, ..Quirks::default() }, &program, 3);
+        assert_eq!((chip8.registers[1], chip8.registers[0xF]), (0x02, 0));
+    }
+
+    #[test]
+    fn load_store_quirk_leaves_i_alone() {
+        // I = 0x300, F255
+        let program = [0xA3, 0x00, 0xF2, 0x55];
+
+        assert_eq!(run(Quirks { load_store: true, ..Quirks::default() }, &program, 2).index, 0x300);
+        assert_eq!(run(Quirks { load_store: false, ..Quirks::default() }, &program, 2).index, 0x303);
+    }
+
+    #[test]
+    fn jump_quirk_adds_vx() {
+        // V0 = 1, V3 = 2, B310
+        let program = [0x60, 0x01, 0x63, 0x02, 0xB3, 0x10];
+
+        assert_eq!(run(Quirks { jump: false, ..Quirks::default() }, &program, 3).pc, 0x311);
+        assert_eq!(run(Quirks { jump: true, ..Quirks::default() }, &program, 3).pc, 0x312);
+    }
+
+    #[test]
+    fn logic_quirk_resets_vf() {
+        // VF = 5, 8011
+        let program = [0x6F, 0x05, 0x80, 0x11];
+
+        assert_eq!(run(Quirks { logic: false, ..Quirks::default() }, &program, 2).registers[0xF], 5);
+        assert_eq!(run(Quirks { logic: true, ..Quirks::default() }, &program, 2).registers[0xF], 0);
+    }
+
+    #[test]
+    fn waiting_for_a_key_takes_a_new_press_unless_held_keys_repeat() {
+        // F30A
+        let program = [0xF3, 0x0A];
+        for key_repeat in [false, true] {
+            let mut chip8 = run(Quirks { key_repeat, ..Quirks::default() }, &program, 0);
+            chip8.keypad[0x4] = 1;
+            chip8.cycle();
+            chip8.cycle();
+            assert_eq!(chip8.pc == START_ADDRESS, !key_repeat);
+        }
+
+        let mut chip8 = run(Quirks::default(), &program, 0);
+        chip8.keypad[0x4] = 1;
+        chip8.cycle();
+        // Another key pressed while 4 is still held ends the wait
+        chip8.keypad[0x7] = 1;
+        chip8.cycle();
+        assert_eq!((chip8.pc, chip8.registers[3], chip8.key_wait), (START_ADDRESS + 2, 0x7, None));
+
+        // As does 4 once it is let go and pressed again
+        let mut chip8 = run(Quirks::default(), &program, 0);
+        chip8.keypad[0x4] = 1;
+        chip8.cycle();
+        chip8.keypad[0x4] = 0;
+        chip8.cycle();
+        chip8.keypad[0x4] = 1;
+        chip8.cycle();
+        assert_eq!((chip8.pc, chip8.registers[3]), (START_ADDRESS + 2, 0x4));
+    }
+
+    #[test]
+    fn key_reads_note_the_keys_found_pressed() {
+        // V0 = 5, E09E, E0A1, F10A
+        let program = [0x60, 0x05, 0xE0, 0x9E, 0xE0, 0xA1, 0xF1, 0x0A];
+        let mut chip8 = run(Quirks::default(), &program, 1);
+        chip8.cycle();
+        chip8.cycle();
+        assert_eq!(chip8.key_reads, 0);
+
+        let mut chip8 = run(Quirks::default(), &program, 1);
+        chip8.keypad[0x5] = 1;
+        chip8.cycle();
+        assert_eq!(chip8.key_reads, 1 << 0x5);
+        chip8.key_reads = 0;
+        chip8.pc = START_ADDRESS + 4;
+        chip8.cycle();
+        assert_eq!(chip8.key_reads, 1 << 0x5);
+        chip8.key_reads = 0;
+        chip8.key_wait = Some(0);
+        chip8.keypad[0x9] = 1;
+        chip8.cycle();
+        // Fx0A reads only the key it takes, the lowest
+        assert_eq!(chip8.key_reads, 1 << 0x5);
+    }
+
+    #[test]
+    fn clip_quirk_stops_sprites_at_the_edge() {
+        // V0 = 62, V1 = 0, I = font 0, D011
+        let program = [0x60, 0x3E, 0x61, 0x00, 0xA0, 0x50, 0xD0, 0x11];
+
+        let wrapped = run(Quirks { clip: false, ..Quirks::default() }, &program, 4);
+        assert_ne!(wrapped.video[1], 0);
+
+        let clipped = run(Quirks { clip: true, ..Quirks::default() }, &program, 4);
+        assert_eq!(clipped.video[..2], [0; 2]);
+        assert_ne!(clipped.video[62], 0);
+    }
+
+    #[test]
+    fn each_plane_draws_its_own_sprite() {
+        // F301 (both planes), I = 0x300, D001 with 0x80 then 0xC0 at I
+        let program = [0xF3, 0x01, 0xA3, 0x00, 0xD0, 0x01];
+        let mut chip8 = Chip8::new();
+        chip8.memory[0x300..0x302].copy_from_slice(&[0x80, 0xC0]);
+        chip8.memory[0x200..][..program.len()].copy_from_slice(&program);
+        for _ in 0..3 {
+            chip8.cycle();
+        }
+        assert_eq!(chip8.video[..3], [0b11, 0b10, 0b00]);
+        assert_eq!(chip8.registers[0xF], 0);
+    }
+
+    #[test]
+    fn clearing_and_collisions_stay_in_the_selected_planes() {
+        let mut chip8 = Chip8::new();
+        chip8.video[..3].copy_from_slice(&[0b01, 0b10, 0b11]);
+        // F201 (plane 2), I = font 0, D001, 00E0
+        let program = [0xF2, 0x01, 0xA0, 0x50, 0xD0, 0x01, 0x00, 0xE0];
+        chip8.memory[0x200..][..program.len()].copy_from_slice(&program);
+        for _ in 0..3 {
+            chip8.cycle();
+        }
+        // Font 0's top row is 0xF0, which hits plane 2 at x = 1 and 2
+        assert_eq!(chip8.video[..5], [0b11, 0b00, 0b01, 0b10, 0b00]);
+        assert_eq!(chip8.registers[0xF], 1);
+
+        chip8.cycle();
+        assert_eq!(chip8.video[..5], [0b01, 0b00, 0b01, 0b00, 0b00]);
+    }
+
+    #[test]
+    fn timers_count_frames_not_instructions() {
+        // v0 = 2, delay = sound = v0, then loop
+        let mut chip8 = run(Quirks::default(), &[0x60, 0x02, 0xF0, 0x15, 0xF0, 0x18, 0x12, 0x06], 50);
+        assert_eq!((chip8.delay_timer, chip8.sound_timer), (2, 2));
+        chip8.tick_timers();
+        chip8.tick_timers();
+        chip8.tick_timers();
+        assert_eq!((chip8.delay_timer, chip8.sound_timer), (0, 0));
+    }
+
+    #[test]
+    fn xo_chip_sound_loads_the_pattern_at_i_and_sets_the_pitch() {
+        // ld i, 0x300; audio; ld v0, 0x70; pitch v0
+        let mut chip8 = Chip8::new();
+        chip8.memory[0x300..0x310].copy_from_slice(&[0xF0; 16]);
+        chip8.load_rom(&[0xA3, 0x00, 0xF0, 0x02, 0x60, 0x70, 0xF0, 0x3A]).unwrap();
+        assert_eq!((chip8.audio_pattern, chip8.pitch), (None, DEFAULT_PITCH));
+        for _ in 0..4 {
+            chip8.cycle();
+        }
+        assert_eq!((chip8.audio_pattern, chip8.pitch), (Some([0xF0; 16]), 0x70));
+    }
+
+    #[test]
+    fn soft_resets_keep_memory_and_hard_resets_load_the_rom_again() {
+        // ld v0, 5; ld i, 0x200; ld [i], v0 (overwriting the first byte)
+        let program = [0x60, 0x05, 0xA2, 0x00, 0xF0, 0x55];
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&program).unwrap();
+        chip8.quirks.shift = true;
+        chip8.video[0] = 1;
+        for _ in 0..3 {
+            chip8.cycle();
+        }
+        assert_eq!(chip8.memory[0x200], 0x05);
+
+        chip8.reset(false);
+        assert_eq!((chip8.pc, chip8.index, chip8.registers[0], chip8.video[0]), (0x200, 0, 0, 0));
+        assert_eq!(chip8.memory[0x200], 0x05);
+        assert!(chip8.quirks.shift);
+
+        chip8.memory[0x050] = 0;
+        chip8.memory[0x300] = 0xFF;
+        chip8.reset(true);
+        assert_eq!(chip8.memory[0x200..0x206], program);
+        assert_eq!(chip8.memory[0x050], FONTSET[0]);
+        assert_eq!(chip8.memory[0x300], 0);
+        assert!(chip8.quirks.shift);
+    }
+}
@@ -0,0 +1,1924 @@
+use clap::builder::{PossibleValue, PossibleValuesParser, TypedValueParser};
+use clap::error::ErrorKind;
+use clap::{Arg, ArgAction, Args, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+
+use crate::archive;
+use crate::builtin::{self, BuiltinRom};
+use crate::buzzer::{Tone, Waveform, WAVEFORMS};
+use crate::config::{self, Defaults};
+use crate::fetch;
+use crate::headless::FrameFormat;
+use crate::keymap::{self, Preset};
+use crate::palette::{self, Palette, PlaneColors};
+use crate::quirks::Quirk;
+use crate::sound::AudioConfig;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::timing;
+
+pub enum RomSource {
+    // A path, "-" for standard input, or an http(s):// URL
+    File(String),
+    Builtin(&'static BuiltinRom),
+    // None given, so one is picked in a file dialog
+    Pick,
+}
+
+impl RomSource {
+    // How the ROM is referred to in messages
+    pub fn name(&self) -> &str {
+        match self {
+            RomSource::File(argument) if argument == "-" => "<stdin>",
+            RomSource::File(argument) => argument,
+            RomSource::Builtin(rom) => rom.name,
+            RomSource::Pick => "<file dialog>",
+        }
+    }
+}
+
+// The window scale; `auto` picks the largest that fits on the screen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scale {
+    Fixed(u8),
+    Auto,
+}
+
+// Which window library shows the display
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Frontend {
+    Sdl,
+    Pixels,
+    // Half blocks, or braille dots with `braille`
+    Terminal { braille: bool },
+}
+
+// How SDL draws the window: on the GPU, or on the CPU for VMs and remote
+//  desktops where GPU rendering fails or crawls
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderBackend {
+    Accelerated,
+    Software,
+}
+
+pub enum Command {
+    // Boxed, being far larger than the tools' arguments
+    Run(Box<Options>),
+    Disassemble { rom: String },
+    Assemble { source: String, output: String },
+    VerifyRoundtrip { rom: String },
+    Lint { rom: String },
+    Diff { old: String, new: String },
+    StateExport { state: String, output: String },
+    StateImport { input: String, state: String },
+    StateDiff { old: String, new: String },
+    ReplaySplice { first: String, second: String, frame: usize, output: String },
+    ReplayExport { replay: String, output: String },
+    ReplayImport { input: String, replay: String },
+    Verify { rom: RomSource, replay: String, expect_hash: Option<String>, expect_sound: Option<u64> },
+    ListAudio,
+    Info { rom: RomSource },
+    Bench { rom: RomSource, frames: u64, cycle_period: Duration },
+    Debug { rom: RomSource, cycle_period: Duration, watch: bool },
+    Test,
+    Completions { shell: Shell },
+    // --help or --version, with the text to print
+    Help(String),
+}
+
+pub struct Options {
+    pub scale: Scale,
+    // Time per CPU cycle, from --ips, --delay or the <Delay> in milliseconds;
+    //  None leaves it to the CHIP-8 Archive or the default
+    pub cycle_period: Option<Duration>,
+    pub rom: RomSource,
+    // Every ROM argument, the first being `rom`, when several were given
+    pub playlist: Vec<String>,
+    // Move on to the next ROM in the playlist after this long without input
+    pub playlist_idle: Option<Duration>,
+    pub patches: Vec<String>,
+    pub state: Option<String>,
+    pub seed: Option<u64>,
+    // Where the ROM is loaded and starts, when not 0x200
+    pub start_address: Option<u16>,
+    pub record: Option<String>,
+    pub play: Option<String>,
+    pub tas: Option<String>,
+    pub headless: bool,
+    pub frames: Option<u64>,
+    pub dump_frames: Option<String>,
+    // A PNG of the last frame, written on exit
+    pub exit_screenshot: Option<String>,
+    pub dump_format: FrameFormat,
+    pub video: Option<String>,
+    pub video_fps: u32,
+    pub record_audio: Option<String>,
+    pub tone: Tone,
+    // Percent
+    pub volume: u8,
+    // A WAV file looped in place of the tone
+    pub beep: Option<String>,
+    pub audio: AudioConfig,
+    // Shake game controllers while the buzzer sounds
+    pub rumble: bool,
+    // Print details of the audio device
+    pub verbose: bool,
+    pub resume: bool,
+    pub attract: Option<String>,
+    pub attract_after: u64,
+    pub autosave: Option<u64>,
+    // Reload the ROM file when it changes
+    pub watch: bool,
+    pub portable: bool,
+    pub palette: Option<&'static Palette>,
+    pub foreground: Option<[u8; 3]>,
+    pub background: Option<[u8; 3]>,
+    pub plane_colors: PlaneColors,
+    pub phosphor: Option<u32>,
+    pub blend: Option<usize>,
+    pub crt: bool,
+    pub lcd: bool,
+    // Blend between frames on screens faster than 60 Hz
+    pub interpolate: bool,
+    pub integer_scale: bool,
+    pub rotate: u16,
+    pub frontend: Frontend,
+    // Linear rather than nearest-neighbour scaling
+    pub smooth: bool,
+    pub frame_hash: bool,
+    // Print how long the buzzer sounded at the end of a headless run
+    pub sound_report: bool,
+    // Report errors, and how the run ended, as JSON lines for other programs
+    pub json_errors: bool,
+    pub json_status: bool,
+    // Accelerated, falling back to software, when not given
+    pub renderer: Option<RenderBackend>,
+    pub render_driver: Option<String>,
+    // Counted from 0; the window is centred on it unless `window_pos` says
+    //  where its top-left corner goes
+    pub monitor: i32,
+    pub window_pos: Option<(i32, i32)>,
+    pub always_on_top: bool,
+    // Start paused before the first instruction runs
+    pub paused: bool,
+    pub keypad: bool,
+    // The keyboard layout, before keymap.toml; qwerty when not given
+    pub keymap: Option<&'static Preset>,
+    pub bezel: Option<String>,
+    // Stop at every sprite draw and highlight it
+    pub draw_viz: bool,
+    // Turn on the key repeat quirk: a held key answers Fx0A again and again
+    pub key_repeat: bool,
+    // Mouse movement presses keypad keys
+    pub mouse: bool,
+    // Time key presses until the program reads them, reporting on exit
+    pub input_latency: bool,
+    // Take keypad input from other programs on this local TCP port
+    pub input_port: Option<u16>,
+    // Quirks set on or off, over the archive's settings for the ROM
+    pub quirks: Vec<(Quirk, bool)>,
+}
+
+/// A CHIP-8, SUPER-CHIP and XO-CHIP emulator
+///
+/// <ROM> may be a file, - for standard input, or an http(s):// URL when built
+/// with the `http` feature. Several ROMs, or a folder of them, make a
+/// playlist: N moves on to the next. The older `<Scale> <Delay> <ROM>` form,
+/// the size of a CHIP-8 pixel and the milliseconds per instruction, still
+/// works, and the delay may be left out. The tools other than run take a file, or
+/// --builtin <NAME> where they run the program.
+#[derive(Parser)]
+#[command(version, args_conflicts_with_subcommands = true, args_override_self = true)]
+struct Cli {
+    #[command(subcommand)]
+    tool: Option<Tool>,
+    #[command(flatten)]
+    run: RunArgs,
+    /// List the sound outputs and exit
+    #[arg(long)]
+    list_audio: bool,
+    /// Read default options from FILE instead of config.toml in the
+    /// chip8-emulator settings folder
+    #[arg(long, value_name = "FILE", global = true)]
+    config: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Tool {
+    /// Play a program in a window (the default)
+    Run(Box<RunArgs>),
+    /// Size, hash, archive entry and lint summary of a ROM
+    Info(Program),
+    /// Step through a program from the command line
+    Debug {
+        #[command(flatten)]
+        program: Program,
+        /// Instructions per second
+        #[arg(long, value_name = "N", value_parser = parse_ips, default_value = "700")]
+        ips: Duration,
+        /// Reload the ROM whenever its file changes, keeping the breakpoints
+        #[arg(long)]
+        watch: bool,
+    },
+    /// Time a run of 3600 frames without a window
+    Bench {
+        #[command(flatten)]
+        program: Program,
+        /// Instructions per second
+        #[arg(long, value_name = "N", value_parser = parse_ips, default_value = "700")]
+        ips: Duration,
+        /// Frames to run, 60 a second
+        #[arg(long, value_name = "N", default_value_t = 3600)]
+        frames: u64,
+    },
+    /// Run the built-in instruction self-test
+    Test,
+    /// Print a shell completion script for bash, zsh, fish, elvish or
+    /// PowerShell
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Print reassemblable source for a ROM
+    #[command(name = "disasm")]
+    Disassemble { rom: String },
+    /// Assemble source into a ROM image
+    #[command(name = "asm")]
+    Assemble { source: String, output: String },
+    /// Check that disassembling and reassembling a ROM reproduces it byte
+    /// for byte
+    VerifyRoundtrip { rom: String },
+    /// Report portability problems and suggest platforms
+    Lint { rom: String },
+    /// Compare two ROMs instruction by instruction
+    Diff { old: String, new: String },
+    /// Savestate tools
+    #[command(subcommand)]
+    State(StateTool),
+    /// Replay tools
+    #[command(subcommand)]
+    Replay(ReplayTool),
+    /// Play a replay headlessly and check the final state hash (and the
+    /// frames the buzzer sounded in), exiting with 1 if it differs
+    Verify {
+        #[command(flatten)]
+        program: Program,
+        /// The replay to play
+        #[arg(long, value_name = "REPLAY")]
+        play: String,
+        /// The state hash the run should end with
+        #[arg(long, value_name = "HEX")]
+        expect_hash: Option<String>,
+        /// The frames the buzzer should have sounded in
+        #[arg(long, value_name = "N")]
+        expect_sound: Option<u64>,
+    },
+}
+
+#[derive(Subcommand)]
+enum StateTool {
+    /// Convert a savestate to JSON (- writes to stdout)
+    Export {
+        state: String,
+        output: String,
+        /// JSON is the only format, so this may be left out
+        #[arg(long)]
+        json: bool,
+    },
+    /// Convert a JSON savestate back to the binary format
+    Import {
+        input: String,
+        state: String,
+        /// JSON is the only format, so this may be left out
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show the registers, memory and pixels that differ between two
+    /// savestates
+    Diff { old: String, new: String },
+}
+
+#[derive(Subcommand)]
+enum ReplayTool {
+    /// Join the inputs of FIRST before FRAME with those of SECOND from FRAME
+    /// on
+    Splice {
+        first: String,
+        second: String,
+        frame: usize,
+        output: String,
+    },
+    /// Write a replay as text, one line of keys per frame (- writes to
+    /// stdout)
+    Export { replay: String, output: String },
+    /// Convert a text replay back to the binary format
+    Import { input: String, replay: String },
+}
+
+// The ROM a tool reads or runs
+#[derive(Args)]
+struct Program {
+    /// The ROM file
+    #[arg(required_unless_present = "builtin")]
+    rom: Option<String>,
+    /// Use one of the built-in ROMs instead of a file
+    #[arg(long, value_name = "NAME", conflicts_with = "rom", value_parser = named(builtin::BUILTIN_ROMS, |rom| rom.name, |rom| rom.description, builtin::find))]
+    builtin: Option<&'static BuiltinRom>,
+}
+
+impl Program {
+    fn source(self) -> RomSource {
+        match (self.builtin, self.rom) {
+            (Some(rom), _) => RomSource::Builtin(rom),
+            (None, rom) => RomSource::File(rom.unwrap_or_default()),
+        }
+    }
+}
+
+// Running a program. Everything is optional here, with the defaults filled
+//  in by `options`, which also checks the options go together. Switches
+//  are None when not given, as they can also be turned off with `=false`
+//  over the config file.
+#[derive(Args)]
+#[command(mut_args = switch)]
+struct RunArgs {
+    /// The program to run, after the older <Scale> and <Delay> if given;
+    /// several, or a folder, make a playlist
+    #[arg(value_name = "ROM")]
+    roms: Vec<String>,
+    /// The program to run
+    #[arg(long, value_name = "ROM")]
+    rom: Option<String>,
+    /// Screen pixels per CHIP-8 pixel, or `auto` [default: 10]
+    #[arg(long, value_name = "N", value_parser = parse_scale)]
+    scale: Option<Scale>,
+    /// Instructions per second; by default the CHIP-8 Archive's speed for
+    /// the ROM, or 700
+    #[arg(long, value_name = "N", value_parser = parse_ips, overrides_with = "delay")]
+    ips: Option<Duration>,
+    /// Milliseconds per instruction, instead of --ips
+    #[arg(long, value_name = "MS", value_parser = parse_delay, overrides_with = "ips")]
+    delay: Option<Duration>,
+    /// Run one of the built-in ROMs
+    #[arg(long, value_name = "NAME", value_parser = named(builtin::BUILTIN_ROMS, |rom| rom.name, |rom| rom.description, builtin::find))]
+    builtin: Option<&'static BuiltinRom>,
+    /// Apply an IPS or text patch before loading (repeatable)
+    #[arg(long = "patch", value_name = "FILE")]
+    patches: Vec<String>,
+    /// Resume from a savestate instead of the ROM's start
+    #[arg(long, value_name = "FILE")]
+    state: Option<String>,
+    /// Continue where this ROM was last quit, and save on quit
+    #[arg(long)]
+    resume: Option<bool>,
+    /// Save an autosave state every S seconds of play
+    #[arg(long, value_name = "S", value_parser = clap::value_parser!(u64).range(1..))]
+    autosave: Option<u64>,
+    /// Reload the ROM whenever its file changes, for trying out a program as
+    /// it is written
+    #[arg(long)]
+    watch: Option<bool>,
+    /// Move on to the next ROM in the playlist after S seconds without input
+    #[arg(long, value_name = "S", value_parser = clap::value_parser!(u64).range(1..))]
+    playlist_idle: Option<u64>,
+    /// Keep states, macros and captures in a chip8-data folder next to the
+    /// ROM
+    #[arg(long)]
+    portable: Option<bool>,
+    /// Loop REPLAY after a while without input; `demo` uses the bundled
+    /// replay of a built-in ROM
+    #[arg(long, value_name = "REPLAY")]
+    attract: Option<String>,
+    /// Seconds without input before attract mode [default: 30]
+    #[arg(long, value_name = "S")]
+    attract_after: Option<u64>,
+    /// Use one of the color themes
+    #[arg(long, value_name = "NAME", value_parser = named(palette::PALETTES, |palette| palette.name, |palette| palette.description, palette::find))]
+    palette: Option<&'static Palette>,
+    /// Color of lit pixels, overriding the palette
+    #[arg(long = "fg", value_name = "RRGGBB", value_parser = parse_color)]
+    foreground: Option<[u8; 3]>,
+    /// Color of unlit pixels, overriding the palette
+    #[arg(long = "bg", value_name = "RRGGBB", value_parser = parse_color)]
+    background: Option<[u8; 3]>,
+    /// Color of pixels lit in XO-CHIP's plane 2 alone
+    #[arg(long = "fg2", value_name = "RRGGBB", value_parser = parse_color)]
+    plane2: Option<[u8; 3]>,
+    /// Color of pixels lit in both XO-CHIP planes
+    #[arg(long = "fg3", value_name = "RRGGBB", value_parser = parse_color)]
+    both_planes: Option<[u8; 3]>,
+    /// Fade pixels out over N frames to hide flicker
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    phosphor: Option<u32>,
+    /// Show the average of the last N frames (2 or more)
+    #[arg(long, value_name = "N", value_parser = parse_blend)]
+    blend: Option<usize>,
+    /// Add scanlines, glow and rounded corners
+    #[arg(long)]
+    crt: Option<bool>,
+    /// Look like a slow LCD: lagging pixels and grid gaps
+    #[arg(long)]
+    lcd: Option<bool>,
+    /// On 120 Hz and faster screens, fade between frames for smoother motion
+    /// (adds a frame of delay)
+    #[arg(long)]
+    interpolate: Option<bool>,
+    /// Keep pixels crisp by only scaling by whole numbers when the window is
+    /// resized
+    #[arg(long)]
+    integer_scale: Option<bool>,
+    /// Turn the display clockwise
+    #[arg(long, value_name = "DEGREES", value_parser = PossibleValuesParser::new(["0", "90", "180", "270"])
+        .map(|degrees| degrees.parse::<u16>().expect("a listed angle")))]
+    rotate: Option<u16>,
+    /// Frame the display with artwork; it shows through the image's
+    /// transparent area
+    #[arg(long, value_name = "PNG")]
+    bezel: Option<String>,
+    /// Run one sprite draw at a time, highlighting the rows drawn and the
+    /// pixels that collided
+    #[arg(long)]
+    draw_viz: Option<bool>,
+    /// Show a keypad beside the display that can be clicked or touched
+    #[arg(long)]
+    keypad: Option<bool>,
+    /// Let a key held down answer Fx0A (wait for a key) again and again, for
+    /// games written for that
+    #[arg(long)]
+    key_repeat: Option<bool>,
+    /// Set a quirk: shift, load-store, jump, logic, clip or key-repeat
+    /// (repeatable)
+    #[arg(long = "quirk", value_name = "NAME=on|off", value_parser = parse_quirk)]
+    quirks: Vec<(Quirk, bool)>,
+    /// Keyboard layout for the keypad; keymap.toml changes it further
+    #[arg(long, value_name = "NAME", value_parser = named(keymap::PRESETS, |preset| preset.name, |preset| preset.description, keymap::find))]
+    keymap: Option<&'static Preset>,
+    /// Press keys by moving the mouse, for paddle games; the keys are set in
+    /// keymap.toml
+    #[arg(long)]
+    mouse: Option<bool>,
+    /// Scale with `nearest` (crisp) or `linear` (smooth) filtering [default:
+    /// nearest]
+    #[arg(long, value_name = "NAME", value_parser = PossibleValuesParser::new(["nearest", "linear"])
+        .map(|name| name == "linear"))]
+    filter: Option<bool>,
+    /// `accelerated` (GPU) or `software`; by default the GPU, falling back to
+    /// software if it can't be used
+    #[arg(long, value_name = "NAME", value_parser = PossibleValuesParser::new(["accelerated", "software"])
+        .map(|name| if name == "software" { RenderBackend::Software } else { RenderBackend::Accelerated }))]
+    renderer: Option<RenderBackend>,
+    /// SDL render driver, e.g. opengl, direct3d, metal
+    #[arg(long, value_name = "NAME")]
+    render_driver: Option<String>,
+    /// Open the window on monitor N (0 is the primary one)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(i32).range(0..))]
+    monitor: Option<i32>,
+    /// Put the window's corner at X,Y on the monitor instead of centring it
+    #[arg(long, value_name = "X,Y", value_parser = parse_window_pos, allow_hyphen_values = true)]
+    window_pos: Option<(i32, i32)>,
+    /// Keep the window above others (T toggles it)
+    #[arg(long)]
+    always_on_top: Option<bool>,
+    /// `sdl`; `pixels`, a minimal wgpu window in builds with the `pixels`
+    /// feature; or `term` or `term-braille` to draw in the terminal [default:
+    /// sdl]
+    #[arg(long, value_name = "NAME", value_parser = PossibleValuesParser::new(["sdl", "pixels", "term", "term-braille"])
+        .map(|name| match name.as_str() {
+            "pixels" => Frontend::Pixels,
+            "term" => Frontend::Terminal { braille: false },
+            "term-braille" => Frontend::Terminal { braille: true },
+            _ => Frontend::Sdl,
+        }))]
+    frontend: Option<Frontend>,
+    /// Seed the random number generator used by RND
+    #[arg(long, value_name = "N")]
+    seed: Option<u64>,
+    /// Load and start the ROM at ADDR, in hex, instead of 200 (600 for
+    /// ETI-660 programs)
+    #[arg(long, value_name = "ADDR", value_parser = parse_start_address)]
+    start_address: Option<u16>,
+    /// Record keypad input to a replay file
+    #[arg(long, value_name = "FILE")]
+    record: Option<String>,
+    /// Play back a replay made with --record
+    #[arg(long, value_name = "FILE")]
+    play: Option<String>,
+    /// Edit a replay frame by frame, creating it if needed
+    #[arg(long, value_name = "FILE")]
+    tas: Option<String>,
+    /// Run without a window (needs --frames)
+    #[arg(long)]
+    headless: Option<bool>,
+    /// Start paused, before the first instruction runs
+    #[arg(long)]
+    paused: Option<bool>,
+    /// Stop after N frames (60 per second)
+    #[arg(long, value_name = "N")]
+    frames: Option<u64>,
+    /// Write every frame to DIR (headless only)
+    #[arg(long, value_name = "DIR")]
+    dump_frames: Option<String>,
+    /// Save the last frame as a PNG on exit
+    #[arg(long, value_name = "FILE")]
+    exit_screenshot: Option<String>,
+    /// png (in the ROM's colors) or pbm [default: png]
+    #[arg(long, value_name = "FMT", value_parser = PossibleValuesParser::new(["png", "pbm"])
+        .map(|name| FrameFormat::from_name(&name).expect("a listed format")))]
+    dump_format: Option<FrameFormat>,
+    /// Print the SHA-1 of the last frame shown, in its colors and effects, on
+    /// exit
+    #[arg(long)]
+    frame_hash: Option<bool>,
+    /// Print the frames and beeps the buzzer sounded (headless only)
+    #[arg(long)]
+    sound_report: Option<bool>,
+    /// Print errors as JSON on standard output
+    #[arg(long)]
+    json_errors: Option<bool>,
+    /// Print how the run ended as JSON on standard output
+    #[arg(long)]
+    json_status: Option<bool>,
+    /// Print how long key presses took to be read by the program (Ex9E, ExA1,
+    /// Fx0A) on exit
+    #[arg(long)]
+    input_latency: Option<bool>,
+    /// Let a bot press keys through a local TCP port (0 picks a free one);
+    /// see the README
+    #[arg(long, value_name = "PORT")]
+    input_port: Option<u16>,
+    /// Record the display to a video file with ffmpeg
+    #[arg(long, value_name = "FILE")]
+    video: Option<String>,
+    /// Frame rate of the recorded video [default: 60]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    video_fps: Option<u32>,
+    /// Record the buzzer to a WAV file
+    #[arg(long, value_name = "FILE")]
+    record_audio: Option<String>,
+    /// Loudness of the buzzer, 0 to 100 [default: 100]
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=100))]
+    volume: Option<u8>,
+    /// Pitch of the buzzer [default: 440]
+    #[arg(long, value_name = "HZ", value_parser = parse_frequency)]
+    tone: Option<f32>,
+    /// The buzzer's waveform; B switches between them while running
+    /// [default: square]
+    #[arg(long, value_name = "NAME", value_parser = PossibleValuesParser::new(WAVEFORMS.map(Waveform::name))
+        .map(|name| Waveform::from_name(&name).expect("a listed waveform")))]
+    waveform: Option<Waveform>,
+    /// Loop a short recording instead of the tone
+    #[arg(long, value_name = "WAV")]
+    beep: Option<String>,
+    /// Audio sample rate to ask for [default: 44100]
+    #[arg(long, value_name = "HZ", value_parser = clap::value_parser!(u32).range(8000..=192000))]
+    sample_rate: Option<u32>,
+    /// Audio buffer in samples, a power of two [default: 512]; bigger stops
+    /// crackling on slow systems but delays beeps
+    #[arg(long, value_name = "N", value_parser = parse_audio_buffer)]
+    audio_buffer: Option<u16>,
+    /// Play sound through this output (see --list-audio)
+    #[arg(long, value_name = "NAME")]
+    audio_device: Option<String>,
+    /// Print the audio format the device gave
+    #[arg(long)]
+    verbose: Option<bool>,
+    /// Shake connected game controllers while the buzzer sounds
+    #[arg(long)]
+    rumble: Option<bool>,
+}
+
+// One of the entries in a table such as the built-in ROMs, by name, with
+//  the names and their descriptions listed in --help
+fn named<T: Sync>(
+    table: &'static [T],
+    name: fn(&T) -> &'static str,
+    description: fn(&T) -> &'static str,
+    find: fn(&str) -> Option<&'static T>,
+) -> impl TypedValueParser<Value = &'static T> {
+    let values = table.iter().map(|entry| PossibleValue::new(name(entry)).help(description(entry)));
+    PossibleValuesParser::new(values).map(move |chosen| find(&chosen).expect("a listed name"))
+}
+
+// Switches are written `--crt`, or `--crt=false` to turn off one that the
+//  config file turned on. They are the Option<bool> fields taking true or
+//  false; --filter also gives a bool, but from a name.
+fn switch(arg: Arg) -> Arg {
+    let switch = matches!(arg.get_action(), ArgAction::Set)
+        && arg.get_possible_values().iter().map(|value| value.get_name()).eq(["true", "false"]);
+    if !switch {
+        return arg;
+    }
+    arg.num_args(0..=1)
+        .require_equals(true)
+        .default_missing_value("true")
+        .value_name("BOOL")
+        .hide_possible_values(true)
+}
+
+// For the completion scripts
+pub fn command() -> clap::Command {
+    Cli::command()
+}
+
+// clap's message without its `error: ` prefix and pointer to --help, which
+//  are given in the emulator's own words
+fn message(error: clap::Error) -> String {
+    let text = error.to_string();
+    let lines: Vec<&str> = text
+        .trim_start_matches("error: ")
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with("For more information"))
+        .collect();
+    lines.join("\n")
+}
+
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    let cli = match Cli::try_parse_from(args) {
+        Ok(cli) => cli,
+        Err(e) if matches!(e.kind(), ErrorKind::DisplayHelp | ErrorKind::DisplayVersion) => {
+            return Ok(Command::Help(e.to_string()));
+        }
+        Err(e) => return Err(message(e)),
+    };
+
+    let tool = match cli.tool {
+        Some(Tool::Run(_)) | None if cli.list_audio => return Ok(Command::ListAudio),
+        // `run` is optional
+        Some(Tool::Run(run)) => return run_command(*run, cli.config.as_deref()),
+        None => return run_command(cli.run, cli.config.as_deref()),
+        Some(tool) => tool,
+    };
+
+    Ok(match tool {
+        Tool::Run(_) => unreachable!("handled above"),
+        Tool::Info(program) => Command::Info { rom: program.source() },
+        Tool::Debug { program, ips, watch } => {
+            let rom = program.source();
+            if watch && !matches!(rom, RomSource::File(_)) {
+                return Err(String::from("--watch needs a ROM file"));
+            }
+            Command::Debug {
+                rom,
+                cycle_period: ips,
+                watch,
+            }
+        }
+        Tool::Bench { program, ips, frames } => Command::Bench {
+            rom: program.source(),
+            frames,
+            cycle_period: ips,
+        },
+        Tool::Test => Command::Test,
+        Tool::Completions { shell } => Command::Completions { shell },
+        Tool::Disassemble { rom } => Command::Disassemble { rom },
+        Tool::Assemble { source, output } => Command::Assemble { source, output },
+        Tool::VerifyRoundtrip { rom } => Command::VerifyRoundtrip { rom },
+        Tool::Lint { rom } => Command::Lint { rom },
+        Tool::Diff { old, new } => Command::Diff { old, new },
+        Tool::State(StateTool::Export { state, output, .. }) => Command::StateExport { state, output },
+        Tool::State(StateTool::Import { input, state, .. }) => Command::StateImport { input, state },
+        Tool::State(StateTool::Diff { old, new }) => Command::StateDiff { old, new },
+        Tool::Replay(ReplayTool::Splice {
+            first,
+            second,
+            frame,
+            output,
+        }) => Command::ReplaySplice {
+            first,
+            second,
+            frame,
+            output,
+        },
+        Tool::Replay(ReplayTool::Export { replay, output }) => Command::ReplayExport { replay, output },
+        Tool::Replay(ReplayTool::Import { input, replay }) => Command::ReplayImport { input, replay },
+        Tool::Verify {
+            program,
+            play,
+            expect_hash,
+            expect_sound,
+        } => Command::Verify {
+            rom: program.source(),
+            replay: play,
+            expect_hash: expect_hash.map(|hash| hash.to_ascii_lowercase()),
+            expect_sound,
+        },
+    })
+}
+
+// The options for a run, over those from the environment, over those in
+//  the config file
+fn run_command(run: RunArgs, config: Option<&Path>) -> Result<Command, String> {
+    let mut layers = Vec::new();
+    for defaults in config::load(config)?.into_iter().chain(config::environment()?) {
+        layers.push(defaults_layer(&defaults)?);
+    }
+    layered(run, layers).map(Box::new).map(Command::Run)
+}
+
+// Options from the config file or a variable, read like the command line
+fn defaults_layer(defaults: &Defaults) -> Result<RunArgs, String> {
+    let mut args = vec![String::from("chip8")];
+    args.extend_from_slice(&defaults.arguments);
+    let cli = Cli::try_parse_from(&args).map_err(|e| format!("{}: {}", defaults.source, message(e)))?;
+    Ok(cli.run)
+}
+
+// `layers` go from the lowest, each over the one before
+fn layered(mut run: RunArgs, layers: Vec<RunArgs>) -> Result<Options, String> {
+    run.take_legacy()?;
+    let mut layers = layers.into_iter();
+    let run = match layers.next() {
+        Some(first) => run.over(layers.fold(first, |base, layer| layer.over(base))),
+        None => run,
+    };
+    run.options()
+}
+
+fn parse_scale(value: &str) -> Result<Scale, String> {
+    match value {
+        "auto" => Ok(Scale::Auto),
+        value => value
+            .parse()
+            .ok()
+            .filter(|&scale| scale > 0)
+            .map(Scale::Fixed)
+            .ok_or_else(|| format!("Failed to parse scale '{}' (1 to 255, or auto)", value)),
+    }
+}
+
+fn parse_ips(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<u32>()
+        .ok()
+        .filter(|&ips| ips > 0)
+        .map(|ips| Duration::from_secs(1) / ips)
+        .ok_or_else(|| format!("Failed to parse instructions per second '{}'", value))
+}
+
+// Whether a leading argument is the older <Scale> or <Delay> rather than a
+//  ROM
+fn legacy_number(argument: &str) -> bool {
+    argument == "auto" || (!argument.is_empty() && argument.bytes().all(|byte| byte.is_ascii_digit()))
+}
+
+// Hex, with or without 0x, from 200 up: below that is the interpreter's.
+//  The first instruction needs two bytes, so FFE is the last address.
+fn parse_start_address(value: &str) -> Result<u16, String> {
+    let digits = value.trim_start_matches("0x").trim_start_matches("0X");
+    u16::from_str_radix(digits, 16)
+        .ok()
+        .filter(|address| (0x200..=0xFFE).contains(address))
+        .ok_or_else(|| format!("Failed to parse start address '{}' (hex, 200 to FFE)", value))
+}
+
+fn parse_delay(value: &str) -> Result<Duration, String> {
+    value
+        .parse()
+        .map(Duration::from_millis)
+        .map_err(|_| format!("Failed to parse delay '{}' (whole milliseconds)", value))
+}
+
+fn parse_color(value: &str) -> Result<[u8; 3], String> {
+    archive::parse_hex_color(value).ok_or_else(|| format!("Failed to parse color '{}'", value))
+}
+
+fn parse_blend(value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|&frames| frames >= 2)
+        .ok_or_else(|| format!("Failed to parse frames '{}' (2 or more)", value))
+}
+
+fn parse_frequency(value: &str) -> Result<f32, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|frequency| (20.0..=20000.0).contains(frequency))
+        .ok_or_else(|| format!("Failed to parse frequency '{}' (20 to 20000 Hz)", value))
+}
+
+fn parse_audio_buffer(value: &str) -> Result<u16, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|samples: &u16| (64..=16384).contains(samples) && samples.is_power_of_two())
+        .ok_or_else(|| format!("Failed to parse buffer size '{}' (a power of two, 64 to 16384)", value))
+}
+
+fn parse_window_pos(value: &str) -> Result<(i32, i32), String> {
+    value
+        .split_once(',')
+        .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)))
+        .ok_or_else(|| format!("Failed to parse window position '{}', expected X,Y", value))
+}
+
+fn parse_quirk(value: &str) -> Result<(Quirk, bool), String> {
+    let invalid = || format!("Failed to parse quirk '{}' (expected e.g. shift=off)", value);
+    let (name, state) = value.split_once('=').ok_or_else(invalid)?;
+    let quirk = Quirk::from_name(name).ok_or_else(|| format!("Unknown quirk '{}'", name))?;
+    match state {
+        "on" | "true" => Ok((quirk, true)),
+        "off" | "false" => Ok((quirk, false)),
+        _ => Err(invalid()),
+    }
+}
+
+impl RunArgs {
+    // The older `<Scale> [<Delay>] <ROM>` form, read into --scale and
+    //  --delay so that it overrides the config file like they do. Only
+    //  leading numbers are taken for it, the rest being a playlist.
+    fn take_legacy(&mut self) -> Result<(), String> {
+        // The ROM is the last argument unless --rom or --builtin names it
+        let given = self.rom.is_some() || self.builtin.is_some();
+        let numbers = self.roms.iter().take_while(|argument| legacy_number(argument)).count();
+        let count = numbers.min(self.roms.len().saturating_sub(usize::from(!given)));
+        let (scale, delay) = match &self.roms[..count] {
+            [] => return Ok(()),
+            [scale] => (scale, None),
+            [scale, delay] => (scale, Some(delay)),
+            [_, _, extra, ..] => return Err(format!("Unexpected argument '{}'", extra)),
+        };
+        if self.scale.is_some() || self.ips.is_some() || self.delay.is_some() {
+            return Err(String::from("<Scale> <Delay> cannot be combined with --scale, --ips or --delay"));
+        }
+        self.scale = Some(parse_scale(scale)?);
+        if let Some(delay) = delay {
+            self.delay = Some(parse_delay(delay)?);
+        }
+        self.roms.drain(..count);
+        Ok(())
+    }
+
+    // These options over those in `base`, from the config file or the
+    //  environment. Options that can't be used together, such as --crt and
+    //  --lcd, replace each other; a list of patches replaces the one below,
+    //  while quirks are set one by one.
+    fn over(self, base: RunArgs) -> RunArgs {
+        let speed = self.ips.is_some() || self.delay.is_some();
+        let replay = self.record.is_some() || self.play.is_some() || self.tas.is_some() || self.state.is_some();
+        let effect = self.crt == Some(true) || self.phosphor.is_some() || self.blend.is_some();
+        let lcd = self.lcd == Some(true);
+        let mut quirks = base.quirks;
+        quirks.extend(self.quirks);
+
+        RunArgs {
+            roms: self.roms,
+            rom: self.rom.or(base.rom),
+            scale: self.scale.or(base.scale),
+            ips: if speed { self.ips } else { base.ips },
+            delay: if speed { self.delay } else { base.delay },
+            builtin: self.builtin.or(base.builtin),
+            patches: if self.patches.is_empty() { base.patches } else { self.patches },
+            state: if replay { self.state } else { base.state },
+            resume: self.resume.or(base.resume),
+            autosave: self.autosave.or(base.autosave),
+            watch: self.watch.or(base.watch),
+            playlist_idle: self.playlist_idle.or(base.playlist_idle),
+            portable: self.portable.or(base.portable),
+            attract: self.attract.or(base.attract),
+            attract_after: self.attract_after.or(base.attract_after),
+            palette: self.palette.or(base.palette),
+            foreground: self.foreground.or(base.foreground),
+            background: self.background.or(base.background),
+            plane2: self.plane2.or(base.plane2),
+            both_planes: self.both_planes.or(base.both_planes),
+            phosphor: self.phosphor.or(base.phosphor.filter(|_| !lcd && self.blend.is_none())),
+            blend: self.blend.or(base.blend.filter(|_| !lcd && self.phosphor.is_none())),
+            crt: self.crt.or(base.crt.filter(|_| !lcd)),
+            lcd: self.lcd.or(base.lcd.filter(|_| !effect)),
+            interpolate: self.interpolate.or(base.interpolate),
+            integer_scale: self.integer_scale.or(base.integer_scale),
+            rotate: self.rotate.or(base.rotate),
+            bezel: self.bezel.or(base.bezel),
+            draw_viz: self.draw_viz.or(base.draw_viz),
+            keypad: self.keypad.or(base.keypad),
+            key_repeat: self.key_repeat.or(base.key_repeat),
+            quirks,
+            keymap: self.keymap.or(base.keymap),
+            mouse: self.mouse.or(base.mouse),
+            filter: self.filter.or(base.filter),
+            renderer: self.renderer.or(base.renderer),
+            render_driver: self.render_driver.or(base.render_driver),
+            monitor: self.monitor.or(base.monitor),
+            window_pos: self.window_pos.or(base.window_pos),
+            always_on_top: self.always_on_top.or(base.always_on_top),
+            frontend: self.frontend.or(base.frontend),
+            seed: self.seed.or(base.seed),
+            start_address: self.start_address.or(base.start_address),
+            record: if replay { self.record } else { base.record },
+            play: if replay { self.play } else { base.play },
+            tas: if replay { self.tas } else { base.tas },
+            headless: self.headless.or(base.headless),
+            paused: self.paused.or(base.paused),
+            frames: self.frames.or(base.frames),
+            dump_frames: self.dump_frames.or(base.dump_frames),
+            exit_screenshot: self.exit_screenshot.or(base.exit_screenshot),
+            dump_format: self.dump_format.or(base.dump_format),
+            frame_hash: self.frame_hash.or(base.frame_hash),
+            sound_report: self.sound_report.or(base.sound_report),
+            json_errors: self.json_errors.or(base.json_errors),
+            json_status: self.json_status.or(base.json_status),
+            input_latency: self.input_latency.or(base.input_latency),
+            input_port: self.input_port.or(base.input_port),
+            video: self.video.or(base.video),
+            video_fps: self.video_fps.or(base.video_fps),
+            record_audio: self.record_audio.or(base.record_audio),
+            volume: self.volume.or(base.volume),
+            tone: self.tone.or(base.tone),
+            waveform: self.waveform.or(base.waveform),
+            beep: self.beep.or(base.beep),
+            sample_rate: self.sample_rate.or(base.sample_rate),
+            audio_buffer: self.audio_buffer.or(base.audio_buffer),
+            audio_device: self.audio_device.or(base.audio_device),
+            verbose: self.verbose.or(base.verbose),
+            rumble: self.rumble.or(base.rumble),
+        }
+    }
+
+    // Fills in the defaults and checks that the options go together
+    fn options(self) -> Result<Options, String> {
+        let RunArgs {
+            roms,
+            rom: mut rom_file,
+            scale,
+            ips,
+            delay,
+            builtin: builtin_rom,
+            patches,
+            state,
+            resume,
+            autosave,
+            watch,
+            playlist_idle,
+            portable,
+            attract,
+            attract_after,
+            palette,
+            foreground,
+            background,
+            plane2,
+            both_planes,
+            phosphor,
+            blend,
+            crt,
+            lcd,
+            interpolate,
+            integer_scale,
+            rotate,
+            bezel,
+            draw_viz,
+            keypad,
+            key_repeat,
+            quirks,
+            keymap,
+            mouse,
+            filter,
+            renderer,
+            render_driver,
+            monitor,
+            window_pos,
+            always_on_top,
+            frontend,
+            seed,
+            start_address,
+            record,
+            play,
+            tas,
+            headless,
+            paused,
+            frames,
+            dump_frames,
+            exit_screenshot,
+            dump_format,
+            frame_hash,
+            sound_report,
+            json_errors,
+            json_status,
+            input_latency,
+            input_port,
+            video,
+            video_fps,
+            record_audio,
+            volume,
+            tone,
+            waveform,
+            beep,
+            sample_rate,
+            audio_buffer,
+            audio_device,
+            verbose,
+            rumble,
+        } = self;
+
+        // Switches left out are off
+        let [
+            resume, watch, portable, crt, lcd, interpolate, integer_scale, draw_viz, keypad, key_repeat, mouse,
+            always_on_top, headless, paused, frame_hash, sound_report, json_errors, json_status, input_latency,
+            verbose, rumble
+        ] = [
+            resume, watch, portable, crt, lcd, interpolate, integer_scale, draw_viz, keypad, key_repeat, mouse,
+            always_on_top, headless, paused, frame_hash, sound_report, json_errors, json_status, input_latency,
+            verbose, rumble
+        ]
+        .map(Option::unwrap_or_default);
+        let cycle_period = ips.or(delay);
+        let rotate = rotate.unwrap_or(0);
+        let frontend = frontend.unwrap_or(Frontend::Sdl);
+        let smooth = filter.unwrap_or(false);
+        let monitor = monitor.unwrap_or(0);
+
+        // Replays always run from power-on
+        let replay_flags = [&record, &play, &tas].iter().filter(|flag| flag.is_some()).count();
+        if replay_flags > 0 && state.is_some() {
+            return Err(String::from("--record, --play and --tas cannot be combined with --state"));
+        }
+        if replay_flags > 1 {
+            return Err(String::from("Only one of --record, --play and --tas can be used"));
+        }
+
+        if resume && (replay_flags > 0 || headless) {
+            return Err(String::from("--resume cannot be combined with replays or --headless"));
+        }
+        if autosave.is_some() && headless {
+            return Err(String::from("--autosave cannot be combined with --headless"));
+        }
+        // Stopping at draws changes how many cycles each frame runs
+        if draw_viz && replay_flags > 0 {
+            return Err(String::from("--draw-viz cannot be combined with replays"));
+        }
+        if attract.is_some() && (replay_flags > 0 || headless) {
+            return Err(String::from("--attract cannot be combined with replays or --headless"));
+        }
+        if [phosphor.is_some(), blend.is_some(), lcd].iter().filter(|&&set| set).count() > 1 {
+            return Err(String::from("Use only one of --phosphor, --blend and --lcd"));
+        }
+        if crt && lcd {
+            return Err(String::from("Use either --crt or --lcd, not both"));
+        }
+        if paused && headless {
+            return Err(String::from("--paused needs a window; headless runs can't be resumed"));
+        }
+        // The other frontends only have the keypad and the colors, plus the
+        //  display filters in the pixels window
+        let sdl_only = replay_flags > 0
+            || resume
+            || autosave.is_some()
+            || attract.is_some()
+            || video.is_some()
+            || record_audio.is_some()
+            || crt
+            || lcd
+            || interpolate
+            || rotate != 0
+            || smooth
+            || frame_hash
+            || exit_screenshot.is_some()
+            || renderer.is_some()
+            || render_driver.is_some()
+            || monitor != 0
+            || window_pos.is_some()
+            || always_on_top
+            || playlist_idle.is_some()
+            || paused
+            || rumble
+            || keypad
+            || mouse
+            || input_latency
+            || input_port.is_some()
+            || watch
+            || bezel.is_some()
+            || draw_viz
+            || headless;
+        if frontend == Frontend::Pixels && sdl_only {
+            return Err(String::from(
+                "--frontend pixels supports colors, --phosphor and --blend only; \
+                 replays, states, recording, --crt, --rotate, --filter, --frame-hash, --renderer, \
+                 window placement and --headless need the SDL frontend",
+            ));
+        }
+        if frontend == Frontend::Pixels && keymap.is_some() {
+            return Err(String::from("--keymap needs the SDL or terminal frontend"));
+        }
+        if matches!(frontend, Frontend::Terminal { .. }) && (sdl_only || phosphor.is_some() || blend.is_some()) {
+            return Err(String::from(
+                "The terminal frontends support colors only; other display and recording options need a window",
+            ));
+        }
+        if headless && frames.is_none() {
+            return Err(String::from("--headless needs --frames to know when to stop"));
+        }
+        if headless && (record.is_some() || tas.is_some()) {
+            return Err(String::from("--record and --tas need a window for live input"));
+        }
+        if dump_frames.is_some() && !headless {
+            return Err(String::from("--dump-frames only works with --headless"));
+        }
+        if sound_report && !headless {
+            return Err(String::from("--sound-report only works with --headless"));
+        }
+
+        if builtin_rom.is_some() && rom_file.is_some() {
+            return Err(String::from("--rom and --builtin cannot be combined"));
+        }
+        // The ROM comes last, the older <Scale> and <Delay> before it having
+        //  been taken by `take_legacy`; several make a playlist
+        let named = builtin_rom.is_some() || rom_file.is_some();
+        let mut playlist = Vec::new();
+        // A switch's value has to be joined to it, as --crt=false
+        if let Some(value) = roms.iter().find(|rom| matches!(rom.as_str(), "true" | "false")) {
+            return Err(format!("Unexpected argument '{}'; turn a switch off with --<switch>=false", value));
+        }
+        match roms.as_slice() {
+            // A window asks for one, so the emulator can be started from the
+            //  desktop
+            [] if !named && (headless || matches!(frontend, Frontend::Terminal { .. })) => {
+                return Err(String::from("No ROM given: pass a ROM file, --rom <ROM> or --builtin <NAME>"))
+            }
+            [] => {}
+            [rom] if !named => rom_file = Some(rom.clone()),
+            [first, ..] if !named => {
+                rom_file = Some(first.clone());
+                playlist = roms.clone();
+            }
+            [extra, ..] => return Err(format!("Unexpected argument '{}'", extra)),
+        }
+
+        let scale = scale.unwrap_or(Scale::Fixed(10));
+        if scale == Scale::Auto && (headless || frontend != Frontend::Sdl) {
+            return Err(String::from("Only the SDL window can use 'auto' for Scale"));
+        }
+
+        let rom = match (builtin_rom, rom_file) {
+            (Some(rom), _) => RomSource::Builtin(rom),
+            (None, Some(path)) => RomSource::File(path),
+            (None, None) => RomSource::Pick,
+        };
+
+        // Portable data sits next to the ROM, so there has to be a ROM file
+        let rom_file = match &rom {
+            RomSource::File(path) => path != "-" && !fetch::is_url(path),
+            RomSource::Builtin(_) => false,
+            RomSource::Pick => true,
+        };
+        if portable && !rom_file {
+            return Err(String::from("--portable needs a ROM file, not a built-in, URL or standard input"));
+        }
+        if watch && !rom_file {
+            return Err(String::from("--watch needs a ROM file, not a built-in, URL or standard input"));
+        }
+        if playlist_idle.is_some() && !rom_file {
+            return Err(String::from("--playlist-idle needs ROM files or a folder of them"));
+        }
+        let playlist_idle = playlist_idle.map(Duration::from_secs);
+
+        let defaults = Tone::default();
+        let tone = Tone {
+            waveform: waveform.unwrap_or(defaults.waveform),
+            frequency: tone.unwrap_or(defaults.frequency),
+            ..defaults
+        };
+        let defaults = AudioConfig::default();
+        let audio = AudioConfig {
+            sample_rate: sample_rate.unwrap_or(defaults.sample_rate),
+            buffer: audio_buffer.unwrap_or(defaults.buffer),
+            device: audio_device,
+        };
+
+        Ok(Options {
+            scale,
+            cycle_period,
+            rom,
+            playlist,
+            playlist_idle,
+            patches,
+            state,
+            seed,
+            start_address,
+            record,
+            play,
+            tas,
+            headless,
+            frames,
+            dump_frames,
+            exit_screenshot,
+            dump_format: dump_format.unwrap_or(FrameFormat::Png),
+            video,
+            video_fps: video_fps.unwrap_or(timing::FRAMES_PER_SECOND),
+            record_audio,
+            tone,
+            volume: volume.unwrap_or(100),
+            beep,
+            audio,
+            rumble,
+            verbose,
+            resume,
+            attract,
+            attract_after: attract_after.unwrap_or(30),
+            autosave,
+            watch,
+            portable,
+            palette,
+            foreground,
+            background,
+            plane_colors: PlaneColors { plane2, both: both_planes },
+            phosphor,
+            blend,
+            crt,
+            lcd,
+            interpolate,
+            integer_scale,
+            rotate,
+            frontend,
+            smooth,
+            frame_hash,
+            sound_report,
+            json_errors,
+            json_status,
+            renderer,
+            render_driver,
+            monitor,
+            window_pos,
+            always_on_top,
+            paused,
+            keypad,
+            keymap,
+            bezel,
+            draw_viz,
+            key_repeat,
+            mouse,
+            input_latency,
+            input_port,
+            quirks,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command_line(args: &[&str]) -> Result<Command, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        parse(&args)
+    }
+
+    fn run_options(args: &[&str]) -> Options {
+        match command_line(args) {
+            Ok(Command::Run(options)) => *options,
+            _ => panic!("{:?} is not a run", args),
+        }
+    }
+
+    #[test]
+    fn command_is_well_formed() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn completions_cover_the_tools_options_and_names() {
+        let Ok(Command::Completions { shell }) = command_line(&["chip8", "completions", "bash"]) else {
+            panic!("completions bash is not a tool");
+        };
+        let mut script = Vec::new();
+        clap_complete::generate(shell, &mut command(), "Main", &mut script);
+        let script = String::from_utf8(script).unwrap();
+        for word in ["disasm", "--palette", "--crt", "amber", "octo"] {
+            assert!(script.contains(word), "{}", word);
+        }
+        assert!(command_line(&["chip8", "completions", "tcsh"]).is_err());
+    }
+
+    #[test]
+    fn only_the_rom_is_needed() {
+        let options = run_options(&["chip8", "pong.ch8"]);
+        assert_eq!((options.scale, options.cycle_period), (Scale::Fixed(10), None));
+        assert!(matches!(options.rom, RomSource::File(ref path) if path == "pong.ch8"));
+
+        let options = run_options(&["chip8", "--scale", "4", "--ips", "500", "--rom", "pong.ch8"]);
+        assert_eq!((options.scale, options.cycle_period), (Scale::Fixed(4), Some(Duration::from_millis(2))));
+        assert!(matches!(options.rom, RomSource::File(ref path) if path == "pong.ch8"));
+        // The later of --ips and --delay wins
+        let options = run_options(&["chip8", "--ips", "500", "--delay", "5", "pong.ch8"]);
+        assert_eq!(options.cycle_period, Some(Duration::from_millis(5)));
+        assert_eq!(run_options(&["chip8", "--scale", "auto", "pong.ch8"]).scale, Scale::Auto);
+    }
+
+    #[test]
+    fn help_lists_the_options_and_their_choices() {
+        let Ok(Command::Help(text)) = command_line(&["chip8", "--help"]) else {
+            panic!("expected help");
+        };
+        for option in ["--scale <N>", "--ips <N>", "--rom <ROM>", "--palette <NAME>", "disasm"] {
+            assert!(text.contains(option), "{} is missing from\n{}", option, text);
+        }
+        assert!(text.contains("bounce"), "{}", text);
+        assert!(matches!(command_line(&["chip8", "lint", "--help"]), Ok(Command::Help(_))));
+
+        let error = command_line(&["chip8", "--scale", "huge", "pong.ch8"]).err().unwrap();
+        assert!(error.contains("Failed to parse scale 'huge'"), "{}", error);
+        assert!(!error.starts_with("error:") && !error.contains("--help"), "{}", error);
+    }
+
+    #[test]
+    fn delay_is_optional() {
+        let options = run_options(&["chip8", "10", "3", "pong.ch8"]);
+        assert_eq!((options.scale, options.cycle_period), (Scale::Fixed(10), Some(Duration::from_millis(3))));
+        assert!(matches!(options.rom, RomSource::File(ref path) if path == "pong.ch8"));
+
+        let options = run_options(&["chip8", "10", "pong.ch8"]);
+        assert_eq!((options.scale, options.cycle_period), (Scale::Fixed(10), None));
+        assert!(matches!(options.rom, RomSource::File(ref path) if path == "pong.ch8"));
+    }
+
+    #[test]
+    fn scale_may_be_picked_to_fit_the_screen() {
+        assert_eq!(run_options(&["chip8", "auto", "pong.ch8"]).scale, Scale::Auto);
+        assert_eq!(run_options(&["chip8", "auto", "3", "pong.ch8"]).cycle_period, Some(Duration::from_millis(3)));
+    }
+
+    #[test]
+    fn builtin_roms_take_the_place_of_the_path() {
+        let options = run_options(&["chip8", "10", "--builtin", "bounce"]);
+        assert_eq!(options.cycle_period, None);
+        assert!(matches!(options.rom, RomSource::Builtin(rom) if rom.name == "bounce"));
+
+        let options = run_options(&["chip8", "10", "2", "--builtin", "bounce"]);
+        assert_eq!(options.cycle_period, Some(Duration::from_millis(2)));
+    }
+
+    #[test]
+    fn patches_apply_in_order() {
+        let options = run_options(&["chip8", "--patch", "a.ips", "10", "pong.ch8", "--patch", "b.txt"]);
+        assert_eq!(options.patches, ["a.ips", "b.txt"]);
+    }
+
+    #[test]
+    fn a_state_can_replace_the_start() {
+        assert_eq!(run_options(&["chip8", "10", "pong.ch8"]).state, None);
+        let options = run_options(&["chip8", "10", "pong.ch8", "--state", "bug.state"]);
+        assert_eq!(options.state.as_deref(), Some("bug.state"));
+    }
+
+    #[test]
+    fn resume_is_off_unless_asked_for() {
+        assert!(!run_options(&["chip8", "10", "pong.ch8"]).resume);
+        assert!(run_options(&["chip8", "--resume", "10", "pong.ch8"]).resume);
+    }
+
+    #[test]
+    fn colors_are_hex_with_or_without_a_hash() {
+        let options = run_options(&["chip8", "--fg", "#ffb000", "--bg", "1a1a1a", "10", "pong.ch8"]);
+        assert_eq!(options.foreground, Some([0xFF, 0xB0, 0x00]));
+        assert_eq!(options.background, Some([0x1A, 0x1A, 0x1A]));
+        assert_eq!(run_options(&["chip8", "10", "pong.ch8"]).foreground, None);
+
+        let options = run_options(&["chip8", "--palette", "green", "--fg", "ffffff", "10", "pong.ch8"]);
+        assert_eq!(options.palette.map(|palette| palette.name), Some("green"));
+        assert_eq!(options.foreground, Some([0xFF; 3]));
+
+        let options = run_options(&["chip8", "--fg3", "00ff00", "10", "pong.ch8"]);
+        assert_eq!(options.plane_colors, PlaneColors { plane2: None, both: Some([0x00, 0xFF, 0x00]) });
+    }
+
+    #[test]
+    fn flicker_filters_take_a_number_of_frames() {
+        assert_eq!(run_options(&["chip8", "10", "pong.ch8"]).phosphor, None);
+        assert_eq!(run_options(&["chip8", "--phosphor", "4", "10", "pong.ch8"]).phosphor, Some(4));
+        assert_eq!(run_options(&["chip8", "--blend", "2", "10", "pong.ch8"]).blend, Some(2));
+        assert!(run_options(&["chip8", "--crt", "--blend", "2", "10", "pong.ch8"]).crt);
+        assert!(!run_options(&["chip8", "10", "pong.ch8"]).crt);
+        assert!(run_options(&["chip8", "--lcd", "10", "pong.ch8"]).lcd);
+        assert!(run_options(&["chip8", "--interpolate", "10", "pong.ch8"]).interpolate);
+        assert!(!run_options(&["chip8", "10", "pong.ch8"]).interpolate);
+        assert!(run_options(&["chip8", "--integer-scale", "10", "pong.ch8"]).integer_scale);
+        assert!(!run_options(&["chip8", "10", "pong.ch8"]).integer_scale);
+        assert_eq!(run_options(&["chip8", "--rotate", "270", "10", "pong.ch8"]).rotate, 270);
+        assert_eq!(run_options(&["chip8", "10", "pong.ch8"]).rotate, 0);
+        assert!(run_options(&["chip8", "--filter", "linear", "10", "pong.ch8"]).smooth);
+        assert!(!run_options(&["chip8", "--filter", "nearest", "10", "pong.ch8"]).smooth);
+    }
+
+    #[test]
+    fn frame_hashes_are_printed_on_request() {
+        assert!(!run_options(&["chip8", "10", "pong.ch8"]).frame_hash);
+        let options = run_options(&["chip8", "--headless", "--frames", "5", "--frame-hash", "10", "pong.ch8"]);
+        assert!(options.frame_hash);
+    }
+
+    #[test]
+    fn the_renderer_is_left_to_sdl_unless_chosen() {
+        let options = run_options(&["chip8", "10", "pong.ch8"]);
+        assert_eq!((options.renderer, options.render_driver), (None, None));
+        let options = run_options(&["chip8", "--renderer", "software", "--render-driver", "opengl", "10", "pong.ch8"]);
+        assert_eq!(options.renderer, Some(RenderBackend::Software));
+        assert_eq!(options.render_driver.as_deref(), Some("opengl"));
+    }
+
+    #[test]
+    fn windows_open_centred_on_the_primary_monitor_unless_told() {
+        let options = run_options(&["chip8", "10", "pong.ch8"]);
+        assert_eq!((options.monitor, options.window_pos, options.always_on_top), (0, None, false));
+        let options = run_options(&["chip8", "--monitor", "1", "--window-pos", "10, -20", "--always-on-top", "10", "pong.ch8"]);
+        assert_eq!((options.monitor, options.window_pos, options.always_on_top), (1, Some((10, -20)), true));
+        assert!(!options.keypad);
+        assert!(run_options(&["chip8", "--keypad", "10", "pong.ch8"]).keypad);
+        assert!(!options.paused);
+        assert!(run_options(&["chip8", "--paused", "pong.ch8"]).paused);
+        let options = run_options(&["chip8", "--bezel", "arcade.png", "10", "pong.ch8"]);
+        assert_eq!(options.bezel.as_deref(), Some("arcade.png"));
+        assert!(!options.draw_viz);
+        assert!(run_options(&["chip8", "--draw-viz", "10", "pong.ch8"]).draw_viz);
+    }
+
+    #[test]
+    fn frontends_are_picked_by_name() {
+        assert!(run_options(&["chip8", "10", "pong.ch8"]).frontend == Frontend::Sdl);
+        let options = run_options(&["chip8", "--frontend", "pixels", "--phosphor", "3", "10", "pong.ch8"]);
+        assert!(options.frontend == Frontend::Pixels);
+        let options = run_options(&["chip8", "--frontend", "term-braille", "10", "pong.ch8"]);
+        assert!(options.frontend == Frontend::Terminal { braille: true });
+    }
+
+    #[test]
+    fn several_roms_make_a_playlist() {
+        let options = run_options(&["chip8", "pong.ch8"]);
+        assert!(options.playlist.is_empty());
+        let options = run_options(&["chip8", "--playlist-idle", "60", "pong.ch8", "ten", "roms"]);
+        assert_eq!(options.rom.name(), "pong.ch8");
+        assert_eq!(options.playlist, ["pong.ch8", "ten", "roms"]);
+        assert_eq!(options.playlist_idle, Some(Duration::from_secs(60)));
+        // Only leading numbers are the older <Scale> and <Delay>
+        let options = run_options(&["chip8", "10", "fast", "pong.ch8"]);
+        assert_eq!(options.scale, Scale::Fixed(10));
+        assert_eq!(options.playlist, ["fast", "pong.ch8"]);
+    }
+
+    #[test]
+    fn portable_data_needs_a_rom_file() {
+        assert!(run_options(&["chip8", "--portable", "10", "roms/pong.ch8"]).portable);
+        assert!(!run_options(&["chip8", "10", "roms/pong.ch8"]).portable);
+    }
+
+    #[test]
+    fn only_rom_files_are_watched() {
+        assert!(run_options(&["chip8", "--watch", "10", "roms/pong.ch8"]).watch);
+        assert!(!run_options(&["chip8", "10", "roms/pong.ch8"]).watch);
+        assert!(matches!(
+            command_line(&["chip8", "debug", "--watch", "pong.ch8"]),
+            Ok(Command::Debug { watch: true, .. })
+        ));
+    }
+
+    #[test]
+    fn autosaves_happen_every_so_many_seconds() {
+        assert_eq!(run_options(&["chip8", "10", "pong.ch8"]).autosave, None);
+        assert_eq!(run_options(&["chip8", "--autosave", "60", "10", "pong.ch8"]).autosave, Some(60));
+    }
+
+    #[test]
+    fn attract_mode_waits_half_a_minute_unless_told() {
+        let options = run_options(&["chip8", "--attract", "demo", "10", "--builtin", "bounce"]);
+        assert_eq!((options.attract.as_deref(), options.attract_after), (Some("demo"), 30));
+        let options = run_options(&["chip8", "--attract", "a.rpl", "--attract-after", "5", "10", "pong.ch8"]);
+        assert_eq!((options.attract.as_deref(), options.attract_after), (Some("a.rpl"), 5));
+        assert_eq!(run_options(&["chip8", "10", "pong.ch8"]).attract, None);
+    }
+
+    #[test]
+    fn replays_record_or_play() {
+        let options = run_options(&["chip8", "--record", "run.rpl", "--seed", "7", "10", "pong.ch8"]);
+        assert_eq!((options.record.as_deref(), options.play, options.seed), (Some("run.rpl"), None, Some(7)));
+        let options = run_options(&["chip8", "10", "pong.ch8", "--play", "run.rpl"]);
+        assert_eq!(options.play.as_deref(), Some("run.rpl"));
+        let options = run_options(&["chip8", "10", "pong.ch8", "--tas", "run.rpl"]);
+        assert_eq!(options.tas.as_deref(), Some("run.rpl"));
+    }
+
+    #[test]
+    fn programs_can_start_past_0x200() {
+        assert_eq!(run_options(&["chip8", "pong.ch8"]).start_address, None);
+        for (value, address) in [("0x600", 0x600), ("600", 0x600), ("0XFFE", 0xFFE)] {
+            assert_eq!(run_options(&["chip8", "--start-address", value, "pong.ch8"]).start_address, Some(address));
+        }
+        // The last address leaves room for a whole instruction
+        for value in ["0x1FF", "0xFFF", "0x1000", "six"] {
+            assert!(command_line(&["chip8", "--start-address", value, "pong.ch8"]).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn headless_runs_stop_after_their_frames() {
+        let options = run_options(&["chip8", "--headless", "--frames", "60", "--dump-frames", "out", "--dump-format", "pbm", "1", "pong.ch8"]);
+        assert!(options.headless);
+        assert_eq!(options.frames, Some(60));
+        assert_eq!(options.dump_frames.as_deref(), Some("out"));
+        assert!(options.dump_format == FrameFormat::Pbm);
+        assert_eq!(options.exit_screenshot, None);
+        let options = run_options(&["chip8", "--frames", "300", "--exit-screenshot", "pong.png", "pong.ch8"]);
+        assert_eq!((options.frames, options.exit_screenshot.as_deref()), (Some(300), Some("pong.png")));
+        assert!(!options.sound_report);
+        assert!(run_options(&["chip8", "--headless", "--frames", "1", "--sound-report", "1", "pong.ch8"]).sound_report);
+    }
+
+    #[test]
+    fn json_reports_are_asked_for_separately() {
+        let options = run_options(&["chip8", "--json-errors", "pong.ch8"]);
+        assert!(options.json_errors && !options.json_status);
+        let options = run_options(&["chip8", "--json-status", "pong.ch8"]);
+        assert!(!options.json_errors && options.json_status);
+    }
+
+    #[test]
+    fn recordings_go_to_their_own_files() {
+        let options = run_options(&["chip8", "--video", "run.mp4", "10", "pong.ch8"]);
+        assert_eq!((options.video.as_deref(), options.video_fps), (Some("run.mp4"), 60));
+        assert_eq!(run_options(&["chip8", "--video-fps", "30", "10", "pong.ch8"]).video_fps, 30);
+        let options = run_options(&["chip8", "--record-audio", "beep.wav", "10", "pong.ch8"]);
+        assert_eq!(options.record_audio.as_deref(), Some("beep.wav"));
+    }
+
+    #[test]
+    fn the_buzzer_can_change_pitch_shape_and_volume() {
+        assert_eq!(run_options(&["chip8", "10", "pong.ch8"]).tone, Tone::default());
+        let options = run_options(&["chip8", "--tone", "880", "--waveform", "triangle", "10", "pong.ch8"]);
+        assert_eq!(options.tone, Tone { waveform: Waveform::Triangle, frequency: 880.0, sample: None });
+        assert_eq!(run_options(&["chip8", "10", "pong.ch8"]).volume, 100);
+        assert_eq!(run_options(&["chip8", "--volume", "0", "10", "pong.ch8"]).volume, 0);
+        assert_eq!(run_options(&["chip8", "--beep", "ding.wav", "10", "pong.ch8"]).beep.as_deref(), Some("ding.wav"));
+    }
+
+    #[test]
+    fn the_audio_device_can_be_tuned() {
+        let options = run_options(&["chip8", "10", "pong.ch8"]);
+        assert_eq!((options.audio.sample_rate, options.audio.buffer, options.verbose), (44100, 512, false));
+        let options = run_options(&[
+            "chip8", "--sample-rate", "48000", "--audio-buffer", "2048", "--verbose", "10", "pong.ch8",
+        ]);
+        assert_eq!((options.audio.sample_rate, options.audio.buffer, options.verbose), (48000, 2048, true));
+        assert_eq!(options.audio.device, None);
+        let options = run_options(&["chip8", "--audio-device", "USB Audio", "10", "pong.ch8"]);
+        assert_eq!(options.audio.device.as_deref(), Some("USB Audio"));
+        assert!(matches!(command_line(&["chip8", "--list-audio"]), Ok(Command::ListAudio)));
+    }
+
+    #[test]
+    fn controllers_rumble_only_when_asked() {
+        assert!(!run_options(&["chip8", "10", "pong.ch8"]).rumble);
+        assert!(run_options(&["chip8", "--rumble", "10", "pong.ch8"]).rumble);
+    }
+
+    #[test]
+    fn keymaps_are_picked_by_name() {
+        assert!(run_options(&["chip8", "10", "pong.ch8"]).keymap.is_none());
+        let keymap = run_options(&["chip8", "--keymap", "azerty", "10", "pong.ch8"]).keymap;
+        assert_eq!(keymap.map(|preset| preset.name), Some("azerty"));
+        let keymap = run_options(&["chip8", "--frontend", "term", "--keymap", "octo", "10", "pong.ch8"]).keymap;
+        assert_eq!(keymap.map(|preset| preset.name), Some("octo"));
+    }
+
+    #[test]
+    fn held_keys_repeat_only_when_asked() {
+        assert!(!run_options(&["chip8", "10", "pong.ch8"]).key_repeat);
+        assert!(run_options(&["chip8", "--key-repeat", "10", "pong.ch8"]).key_repeat);
+    }
+
+    #[test]
+    fn the_mouse_plays_the_keypad_only_when_asked() {
+        assert!(!run_options(&["chip8", "10", "pong.ch8"]).mouse);
+        assert!(run_options(&["chip8", "--mouse", "10", "pong.ch8"]).mouse);
+    }
+
+    #[test]
+    fn input_latency_is_measured_on_request() {
+        assert!(!run_options(&["chip8", "10", "pong.ch8"]).input_latency);
+        assert!(run_options(&["chip8", "--input-latency", "10", "pong.ch8"]).input_latency);
+    }
+
+    #[test]
+    fn bots_get_a_port_only_when_asked() {
+        assert_eq!(run_options(&["chip8", "10", "pong.ch8"]).input_port, None);
+        assert_eq!(run_options(&["chip8", "--input-port", "0", "10", "pong.ch8"]).input_port, Some(0));
+        assert_eq!(run_options(&["chip8", "--input-port", "7000", "10", "pong.ch8"]).input_port, Some(7000));
+    }
+
+    #[test]
+    fn run_is_optional() {
+        let run = run_options(&["chip8", "run", "--scale", "4", "pong.ch8"]);
+        assert_eq!(run.scale, Scale::Fixed(4));
+        assert_eq!(run.rom.name(), "pong.ch8");
+        assert!(matches!(command_line(&["chip8", "run", "10", "3", "pong.ch8"]), Ok(Command::Run(_))));
+    }
+
+    #[test]
+    fn a_window_asks_for_the_rom_when_none_is_given() {
+        assert!(matches!(run_options(&["chip8"]).rom, RomSource::Pick));
+        assert!(matches!(run_options(&["chip8", "run", "--scale", "4"]).rom, RomSource::Pick));
+        assert!(matches!(run_options(&["chip8", "--frontend", "pixels"]).rom, RomSource::Pick));
+        assert!(run_options(&["chip8", "--portable"]).portable);
+        assert_eq!(RomSource::Pick.name(), "<file dialog>");
+    }
+
+    #[test]
+    fn program_tools_take_a_file_or_a_builtin() {
+        assert!(matches!(
+            command_line(&["chip8", "info", "pong.ch8"]),
+            Ok(Command::Info { rom: RomSource::File(rom) }) if rom == "pong.ch8"
+        ));
+        assert!(matches!(
+            command_line(&["chip8", "debug", "--builtin", "bounce"]),
+            Ok(Command::Debug { rom: RomSource::Builtin(_), cycle_period, watch: false }) if cycle_period == Duration::from_secs(1) / 700
+        ));
+        assert!(matches!(
+            command_line(&["chip8", "bench", "--ips", "1000", "pong.ch8"]),
+            Ok(Command::Bench { frames: 3600, cycle_period, .. }) if cycle_period == Duration::from_millis(1)
+        ));
+        assert!(matches!(
+            command_line(&["chip8", "bench", "--frames", "60", "--builtin", "bounce"]),
+            Ok(Command::Bench { rom: RomSource::Builtin(_), frames: 60, .. })
+        ));
+        assert!(matches!(command_line(&["chip8", "test"]), Ok(Command::Test)));
+    }
+
+    fn config(arguments: &[&str]) -> RunArgs {
+        let defaults = Defaults {
+            source: String::from("config.toml"),
+            arguments: arguments.iter().map(|arg| arg.to_string()).collect(),
+        };
+        defaults_layer(&defaults).unwrap()
+    }
+
+    fn run_args(args: &[&str]) -> RunArgs {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        Cli::try_parse_from(&args).unwrap().run
+    }
+
+    #[test]
+    fn command_line_overrides_config() {
+        let base = config(&["--scale", "8", "--ips", "1000", "--crt", "--patch", "a.ips"]);
+        let run = run_args(&["chip8", "--scale", "4", "--delay", "2", "--lcd", "--patch", "b.ips", "pong.ch8"]);
+        let options = layered(run, vec![base]).unwrap();
+        assert_eq!(options.scale, Scale::Fixed(4));
+        assert_eq!(options.cycle_period, Some(Duration::from_millis(2)));
+        assert!(options.lcd && !options.crt);
+        assert_eq!(options.patches, ["b.ips"]);
+    }
+
+    #[test]
+    fn legacy_form_overrides_config() {
+        let base = config(&["--scale", "8", "--ips", "1000"]);
+        let options = layered(run_args(&["chip8", "20", "3", "pong.ch8"]), vec![base]).unwrap();
+        assert_eq!(options.scale, Scale::Fixed(20));
+        assert_eq!(options.cycle_period, Some(Duration::from_millis(3)));
+
+        // A scale alone leaves the config file's speed
+        let base = config(&["--scale", "8", "--ips", "1000"]);
+        let options = layered(run_args(&["chip8", "20", "pong.ch8"]), vec![base]).unwrap();
+        assert_eq!(options.scale, Scale::Fixed(20));
+        assert_eq!(options.cycle_period, Some(Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn environment_sits_between_config_and_command_line() {
+        let file = config(&["--scale", "8", "--ips", "1000", "--palette", "amber"]);
+        let variable = config(&["--scale", "6"]);
+        let options = layered(run_args(&["chip8", "20", "3", "pong.ch8"]), vec![file, variable]).unwrap();
+        assert_eq!(options.scale, Scale::Fixed(20));
+        assert_eq!(options.cycle_period, Some(Duration::from_millis(3)));
+
+        let file = config(&["--scale", "8", "--palette", "amber", "--verbose"]);
+        let layers = vec![file, config(&["--scale", "6"]), config(&["--verbose=false"])];
+        let options = layered(run_args(&["chip8", "pong.ch8"]), layers).unwrap();
+        assert_eq!(options.scale, Scale::Fixed(6));
+        assert_eq!(options.palette.map(|palette| palette.name), Some("amber"));
+        assert!(!options.verbose);
+    }
+
+    #[test]
+    fn config_fills_in_the_rest() {
+        let base = config(&["--palette", "amber", "--quirk", "shift=off", "--crt", "--volume", "50"]);
+        let options = layered(run_args(&["chip8", "--quirk", "clip=on", "pong.ch8"]), vec![base]).unwrap();
+        assert_eq!(options.palette.map(|palette| palette.name), Some("amber"));
+        assert_eq!(options.quirks, [(Quirk::Shift, false), (Quirk::Clip, true)]);
+        assert!(options.crt);
+        assert_eq!(options.volume, 50);
+        assert_eq!(options.cycle_period, None);
+    }
+
+    #[test]
+    fn switches_can_be_turned_off_over_the_config() {
+        let options = layered(run_args(&["chip8", "--crt=false", "pong.ch8"]), vec![config(&["--crt"])]).unwrap();
+        assert!(!options.crt);
+        assert!(run_options(&["chip8", "--crt=true", "pong.ch8"]).crt);
+        assert!(command_line(&["chip8", "--crt", "false", "pong.ch8"]).is_err());
+    }
+
+    #[test]
+    fn config_files_parse_as_options() {
+        let path = std::env::temp_dir().join(format!("chip8-cli-config-{}.toml", std::process::id()));
+        std::fs::write(&path, "scale = 8\nvolume = 50\nkeymap = \"octo\"\ncrt = true\n\n[quirks]\nload-store = false\n")
+            .unwrap();
+        let defaults = config::load(Some(&path)).unwrap().unwrap();
+        let mut args = vec![String::from("chip8")];
+        args.extend(defaults.arguments);
+        args.push(String::from("pong.ch8"));
+        let cli = Cli::try_parse_from(&args);
+
+        let config = path.display().to_string();
+        let options = run_options(&["chip8", "--config", &config, "--scale", "4", "pong.ch8"]);
+        std::fs::remove_file(&path).unwrap();
+
+        let run = cli.unwrap().run;
+        assert_eq!(run.scale, Some(Scale::Fixed(8)));
+        assert_eq!(run.volume, Some(50));
+        assert_eq!(run.crt, Some(true));
+        assert_eq!(run.quirks, [(Quirk::LoadStore, false)]);
+        assert_eq!(options.scale, Scale::Fixed(4));
+        assert_eq!(options.keymap.map(|preset| preset.name), Some("octo"));
+        assert_eq!(options.quirks, [(Quirk::LoadStore, false)]);
+    }
+
+    #[test]
+    fn config_mistakes_name_the_file() {
+        let defaults = Defaults {
+            source: String::from("config.toml"),
+            arguments: vec![String::from("--scale"), String::from("huge")],
+        };
+        let error = defaults_layer(&defaults).err().unwrap();
+        assert!(error.starts_with("config.toml: "), "{}", error);
+    }
+
+    #[test]
+    fn tools_are_subcommands() {
+        assert!(matches!(command_line(&["chip8", "disasm", "pong.ch8"]), Ok(Command::Disassemble { rom }) if rom == "pong.ch8"));
+        assert!(matches!(
+            command_line(&["chip8", "asm", "pong.8o", "pong.ch8"]),
+            Ok(Command::Assemble { source, output }) if source == "pong.8o" && output == "pong.ch8"
+        ));
+        assert!(matches!(command_line(&["chip8", "verify-roundtrip", "pong.ch8"]), Ok(Command::VerifyRoundtrip { .. })));
+        assert!(matches!(
+            command_line(&["chip8", "diff", "old.ch8", "new.ch8"]),
+            Ok(Command::Diff { old, new }) if old == "old.ch8" && new == "new.ch8"
+        ));
+        assert!(matches!(
+            command_line(&["chip8", "state", "export", "--json", "a.state", "-"]),
+            Ok(Command::StateExport { state, output }) if state == "a.state" && output == "-"
+        ));
+        assert!(matches!(
+            command_line(&["chip8", "state", "import", "a.json", "a.state"]),
+            Ok(Command::StateImport { input, state }) if input == "a.json" && state == "a.state"
+        ));
+        assert!(matches!(
+            command_line(&["chip8", "state", "diff", "a.state", "b.state"]),
+            Ok(Command::StateDiff { old, new }) if old == "a.state" && new == "b.state"
+        ));
+        assert!(matches!(
+            command_line(&["chip8", "replay", "splice", "a.rpl", "b.rpl", "120", "c.rpl"]),
+            Ok(Command::ReplaySplice { frame: 120, .. })
+        ));
+        assert!(matches!(
+            command_line(&["chip8", "replay", "export", "a.rpl", "-"]),
+            Ok(Command::ReplayExport { replay, output }) if replay == "a.rpl" && output == "-"
+        ));
+        assert!(matches!(
+            command_line(&["chip8", "replay", "import", "a.txt", "a.rpl"]),
+            Ok(Command::ReplayImport { input, replay }) if input == "a.txt" && replay == "a.rpl"
+        ));
+        assert!(matches!(
+            command_line(&["chip8", "verify", "pong.ch8", "--play", "a.rpl", "--expect-hash", "ABC123"]),
+            Ok(Command::Verify { rom: RomSource::File(_), replay, expect_hash: Some(hash), expect_sound: None })
+                if replay == "a.rpl" && hash == "abc123"
+        ));
+        assert!(matches!(
+            command_line(&["chip8", "verify", "pong.ch8", "--play", "a.rpl", "--expect-sound", "12"]),
+            Ok(Command::Verify { expect_hash: None, expect_sound: Some(12), .. })
+        ));
+        assert!(matches!(
+            command_line(&["chip8", "verify", "--builtin", "bounce", "--play", "a.rpl"]),
+            Ok(Command::Verify { rom: RomSource::Builtin(_), expect_hash: None, .. })
+        ));
+    }
+
+    #[test]
+    fn mistakes_are_refused() {
+        for args in [
+            &["chip8", "10", "3", "4", "pong.ch8"][..],
+            &["chip8", "--headless", "--frames", "1"],
+            &["chip8", "--headless", "--frames", "1", "--paused", "pong.ch8"],
+            &["chip8", "--frontend", "pixels", "--exit-screenshot", "pong.png", "pong.ch8"],
+            &["chip8", "--frontend", "term"],
+            &["chip8", "0", "pong.ch8"],
+            &["chip8", "--headless", "--frames", "1", "auto", "pong.ch8"],
+            &["chip8", "--rom", "pong.ch8", "tetris.ch8"],
+            &["chip8", "--playlist-idle", "0", "pong.ch8", "tetris.ch8"],
+            &["chip8", "--playlist-idle", "5", "--builtin", "bounce"],
+            &["chip8", "10", "--builtin", "tetris"],
+            &["chip8", "10", "--builtin"],
+            &["chip8", "10", "pong.ch8", "--patch"],
+            &["chip8", "10", "pong.ch8", "--state"],
+            &["chip8", "10", "pong.ch8", "--record", "a.rpl", "--play", "b.rpl"],
+            &["chip8", "10", "pong.ch8", "--play", "a.rpl", "--state", "a.state"],
+            &["chip8", "10", "pong.ch8", "--seed", "lucky"],
+            &["chip8", "10", "pong.ch8", "--tas", "a.rpl", "--record", "b.rpl"],
+            &["chip8", "replay", "splice", "a.rpl", "b.rpl", "later", "c.rpl"],
+            &["chip8", "--headless", "10", "pong.ch8"],
+            &["chip8", "--headless", "--frames", "60", "--record", "a.rpl", "10", "pong.ch8"],
+            &["chip8", "--dump-frames", "out", "10", "pong.ch8"],
+            &["chip8", "--headless", "--frames", "60", "--dump-format", "gif", "10", "pong.ch8"],
+            &["chip8", "--video-fps", "0", "10", "pong.ch8"],
+            &["chip8", "--resume", "--play", "a.rpl", "10", "pong.ch8"],
+            &["chip8", "--resume", "--headless", "--frames", "1", "10", "pong.ch8"],
+            &["chip8", "--attract", "a.rpl", "--play", "b.rpl", "10", "pong.ch8"],
+            &["chip8", "--attract-after", "soon", "10", "pong.ch8"],
+            &["chip8", "--autosave", "0", "10", "pong.ch8"],
+            &["chip8", "--portable", "10", "--builtin", "bounce"],
+            &["chip8", "--portable", "10", "-"],
+            &["chip8", "--watch", "10", "--builtin", "bounce"],
+            &["chip8", "--watch", "10", "https://example.com/pong.ch8"],
+            &["chip8", "--frontend", "pixels", "--watch", "10", "pong.ch8"],
+            &["chip8", "debug", "--watch", "--builtin", "bounce"],
+            &["chip8", "--fg", "orange", "10", "pong.ch8"],
+            &["chip8", "--fg2", "#12345", "10", "pong.ch8"],
+            &["chip8", "--palette", "purple", "10", "pong.ch8"],
+            &["chip8", "--phosphor", "0", "10", "pong.ch8"],
+            &["chip8", "--blend", "1", "10", "pong.ch8"],
+            &["chip8", "--rotate", "45", "10", "pong.ch8"],
+            &["chip8", "--frontend", "qt", "10", "pong.ch8"],
+            &["chip8", "--filter", "cubic", "10", "pong.ch8"],
+            &["chip8", "--renderer", "vulkan", "10", "pong.ch8"],
+            &["chip8", "--monitor", "-1", "10", "pong.ch8"],
+            &["chip8", "--window-pos", "10", "10", "pong.ch8"],
+            &["chip8", "--frontend", "pixels", "--always-on-top", "10", "pong.ch8"],
+            &["chip8", "--frontend", "term", "--keypad", "10", "pong.ch8"],
+            &["chip8", "--frontend", "term", "--renderer", "software", "10", "pong.ch8"],
+            &["chip8", "10", "pong.ch8", "--render-driver"],
+            &["chip8", "10", "pong.ch8", "--bezel"],
+            &["chip8", "--draw-viz", "--record", "a.rpl", "10", "pong.ch8"],
+            &["chip8", "--frontend", "pixels", "--bezel", "arcade.png", "10", "pong.ch8"],
+            &["chip8", "--frontend", "pixels", "--frame-hash", "10", "pong.ch8"],
+            &["chip8", "--frontend", "pixels", "--filter", "linear", "10", "pong.ch8"],
+            &["chip8", "--frontend", "pixels", "--crt", "10", "pong.ch8"],
+            &["chip8", "--frontend", "pixels", "--record", "a.rpl", "10", "pong.ch8"],
+            &["chip8", "--frontend", "pixels", "auto", "pong.ch8"],
+            &["chip8", "--frontend", "term", "--phosphor", "3", "10", "pong.ch8"],
+            &["chip8", "--frontend", "term", "--resume", "10", "pong.ch8"],
+            &["chip8", "--phosphor", "4", "--blend", "2", "10", "pong.ch8"],
+            &["chip8", "--lcd", "--blend", "2", "10", "pong.ch8"],
+            &["chip8", "--lcd", "--crt", "10", "pong.ch8"],
+            &["chip8", "--frontend", "pixels", "--interpolate", "10", "pong.ch8"],
+            &["chip8", "--tone", "10", "10", "pong.ch8"],
+            &["chip8", "--tone", "loud", "10", "pong.ch8"],
+            &["chip8", "--waveform", "sawtooth", "10", "pong.ch8"],
+            &["chip8", "--volume", "101", "10", "pong.ch8"],
+            &["chip8", "--volume", "-5", "10", "pong.ch8"],
+            &["chip8", "--sample-rate", "4000", "10", "pong.ch8"],
+            &["chip8", "--audio-buffer", "1000", "10", "pong.ch8"],
+            &["chip8", "--audio-buffer", "32", "10", "pong.ch8"],
+            &["chip8", "--frontend", "pixels", "--rumble", "10", "pong.ch8"],
+            &["chip8", "10", "pong.ch8", "--beep"],
+            &["chip8", "10", "pong.ch8", "--audio-device"],
+            &["chip8", "--sound-report", "10", "pong.ch8"],
+            &["chip8", "verify", "pong.ch8", "--play", "a.rpl", "--expect-sound", "lots"],
+            &["chip8", "--keymap", "dvorak", "10", "pong.ch8"],
+            &["chip8", "--frontend", "pixels", "--keymap", "octo", "10", "pong.ch8"],
+            &["chip8", "--frontend", "term", "--mouse", "10", "pong.ch8"],
+            &["chip8", "--frontend", "pixels", "--input-latency", "10", "pong.ch8"],
+            &["chip8", "--input-port", "70000", "10", "pong.ch8"],
+            &["chip8", "--frontend", "term", "--input-port", "7000", "10", "pong.ch8"],
+            &["chip8", "info"],
+            &["chip8", "info", "a.ch8", "b.ch8"],
+            &["chip8", "info", "--ips", "500", "pong.ch8"],
+            &["chip8", "bench", "--ips", "0", "pong.ch8"],
+            &["chip8", "bench", "--frames", "lots", "pong.ch8"],
+            &["chip8", "debug", "--frames", "10", "pong.ch8"],
+            &["chip8", "debug", "pong.ch8", "--builtin", "bounce"],
+            &["chip8", "test", "pong.ch8"],
+            &["chip8", "--quirk", "wrap=on", "pong.ch8"],
+            &["chip8", "--quirk", "shift", "pong.ch8"],
+            &["chip8", "--quirk", "shift=maybe", "pong.ch8"],
+            &["chip8", "--crt=maybe", "pong.ch8"],
+            &["chip8", "--config", "/nonexistent/chip8.toml", "pong.ch8"],
+            &["chip8", "10", "pong.ch8", "--bg"],
+            &["chip8", "--autosave", "60", "--headless", "--frames", "1", "10", "pong.ch8"],
+            &["chip8", "disasm"],
+            &["chip8", "asm", "pong.8o"],
+            &["chip8", "verify-roundtrip", "a.ch8", "b.ch8"],
+            &["chip8", "state", "diff", "a.state"],
+            &["chip8", "replay", "export", "a.rpl"],
+            &["chip8", "verify", "pong.ch8"],
+            &["chip8", "verify", "--play", "a.rpl"],
+            &["chip8", "verify", "a.ch8", "b.ch8", "--play", "a.rpl"],
+            &["chip8", "diff", "old.ch8"],
+            &["chip8", "state", "save", "a.state", "a.json"],
+            &["chip8", "10", "--fast", "pong.ch8"],
+            &["chip8", "--ips", "0", "pong.ch8"],
+            &["chip8", "--scale", "4", "10", "pong.ch8"],
+            &["chip8", "--delay", "2", "10", "3", "pong.ch8"],
+            &["chip8", "--rom", "pong.ch8", "--builtin", "bounce"],
+            &["chip8", "--rom", "pong.ch8", "tetris.ch8"],
+        ] {
+            assert!(command_line(args).is_err(), "{:?}", args);
+        }
+    }
+}
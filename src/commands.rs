@@ -0,0 +1,448 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::archive;
+use crate::asm;
+use crate::builtin;
+use crate::chip8;
+use crate::cli::{Command, RomSource};
+use crate::debugger;
+use crate::disasm;
+use crate::headless;
+use crate::lint::{self, Severity};
+use crate::replay::{self, Replay};
+use crate::romdiff;
+use crate::sound;
+use crate::state::{self, Snapshot};
+use crate::statediff;
+use crate::timing;
+use crate::watch::Watch;
+
+// Run a tool subcommand and return the process exit code
+pub fn run(command: Command) -> i32 {
+    let result = match command {
+        Command::Disassemble { rom } => disassemble(&rom),
+        Command::Assemble { source, output } => assemble(&source, &output),
+        Command::VerifyRoundtrip { rom } => verify_roundtrip(&rom),
+        Command::Lint { rom } => lint(&rom),
+        Command::Diff { old, new } => diff(&old, &new),
+        Command::StateExport { state, output } => state_export(&state, &output),
+        Command::StateImport { input, state } => state_import(&input, &state),
+        Command::StateDiff { old, new } => state_diff(&old, &new),
+        Command::ReplaySplice {
+            first,
+            second,
+            frame,
+            output,
+        } => replay_splice(&first, &second, frame, &output),
+        Command::ReplayExport { replay, output } => replay_export(&replay, &output),
+        Command::ReplayImport { input, replay } => replay_import(&input, &replay),
+        Command::Verify {
+            rom,
+            replay,
+            expect_hash,
+            expect_sound,
+        } => verify(&rom, &replay, expect_hash.as_deref(), expect_sound),
+        Command::ListAudio => list_audio(),
+        Command::Info { rom } => info(&rom),
+        Command::Bench {
+            rom,
+            frames,
+            cycle_period,
+        } => bench(&rom, frames, cycle_period),
+        Command::Debug {
+            rom,
+            cycle_period,
+            watch,
+        } => debug(&rom, cycle_period, watch),
+        Command::Test => self_test(),
+        Command::Run(_) | Command::Help(_) | Command::Completions { .. } => {
+            unreachable!("the emulator, help and completions are run from main")
+        }
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            1
+        }
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_program(rom: &RomSource) -> io::Result<Vec<u8>> {
+    match rom {
+        RomSource::Builtin(rom) => Ok(rom.data.to_vec()),
+        RomSource::File(path) => chip8::read_rom(path),
+        RomSource::Pick => unreachable!("tools are always given a ROM"),
+    }
+}
+
+// A machine with the program loaded and, for ROMs from the CHIP-8 Archive,
+//  the quirks it lists, as the emulator sets it up
+fn power_on(rom: &RomSource, data: &[u8]) -> io::Result<chip8::Chip8> {
+    let mut chip8 = chip8::Chip8::new();
+    chip8.load_rom(data)?;
+    if let RomSource::File(path) = rom {
+        if let Some(program) = archive::lookup(Path::new(path)) {
+            chip8.quirks = program.quirks();
+        }
+    }
+    Ok(chip8)
+}
+
+fn info(rom: &RomSource) -> io::Result<i32> {
+    let data = read_program(rom)?;
+    println!("Name:       {}", rom.name());
+    println!("Size:       {} bytes", data.len());
+    println!("SHA-1:      {}", state::rom_hash(&data));
+
+    if let RomSource::File(path) = rom {
+        if let Some(program) = archive::lookup(Path::new(path)) {
+            println!("Title:      {}", program.title);
+            if !program.authors.is_empty() {
+                println!("Authors:    {}", program.authors.join(", "));
+            }
+            if !program.platform.is_empty() {
+                println!("Platform:   {}", program.platform);
+            }
+            if let Some(tickrate) = program.options.tickrate {
+                println!("Speed:      {} instructions per frame", tickrate);
+            }
+        }
+    }
+
+    let (reached, _) = disasm::trace(&data, asm::DEFAULT_ORIGIN);
+    println!("Code:       {} instructions reachable from the start", reached.len());
+    let report = lint::lint(&data, asm::DEFAULT_ORIGIN);
+    let count = |severity| report.findings.iter().filter(|f| f.severity == severity).count();
+    println!(
+        "Lint:       {} errors, {} warnings, {} notes",
+        count(Severity::Error),
+        count(Severity::Warning),
+        count(Severity::Note)
+    );
+    let platforms: Vec<&str> = report.suggested.iter().map(|variant| variant.name()).collect();
+    println!("Platforms:  {}", platforms.join(", "));
+    if !report.quirk_sensitive.is_empty() {
+        println!("Quirks:     {}", report.quirk_sensitive.join(", "));
+    }
+    Ok(0)
+}
+
+// Runs as fast as it can without a window, drawing or sound, so the time is
+//  the interpreter's own
+fn bench(rom: &RomSource, frames: u64, cycle_period: Duration) -> io::Result<i32> {
+    let data = read_program(rom)?;
+    let mut chip8 = power_on(rom, &data)?;
+
+    let start = Instant::now();
+    headless::run(&mut chip8, frames, cycle_period, headless::HeadlessOptions::default())?;
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let instructions: u64 = (0..frames).map(|frame| timing::cycles_in_frame(frame, cycle_period)).sum();
+    let emulated = frames as f64 / timing::FRAMES_PER_SECOND as f64;
+    println!("{} frames, {} instructions in {:.3} s", frames, instructions, elapsed);
+    println!(
+        "{:.1} million instructions per second, {:.0} times real time",
+        instructions as f64 / elapsed / 1e6,
+        emulated / elapsed
+    );
+    Ok(0)
+}
+
+// With `watch`, the ROM is loaded again when its file changes, keeping the
+//  breakpoints set
+fn debug(rom: &RomSource, cycle_period: Duration, watch: bool) -> io::Result<i32> {
+    let data = read_program(rom)?;
+    let mut chip8 = power_on(rom, &data)?;
+    let mut watch = match rom {
+        RomSource::File(path) if watch => Some(Watch::new(Path::new(path))?),
+        _ => None,
+    };
+    debugger::run(&mut chip8, cycle_period, &mut || {
+        let changed = watch.as_mut().is_some_and(|watch| watch.changed());
+        changed.then(|| read_program(rom).and_then(|data| power_on(rom, &data)))
+    })?;
+    Ok(0)
+}
+
+// Runs the built-in opcode-test and reads its results off the display: each
+//  test draws its number and then a tick or a cross, two tests to a row
+fn self_test() -> io::Result<i32> {
+    const TESTS: usize = 8;
+    const PASS: [u8; 5] = [0x02, 0x04, 0x88, 0x50, 0x20];
+
+    let rom = builtin::find("opcode-test").expect("opcode-test is built in");
+    let mut chip8 = chip8::Chip8::new();
+    chip8.load_rom(rom.data)?;
+    headless::run(&mut chip8, 60, Duration::from_secs(1) / 700, headless::HeadlessOptions::default())?;
+
+    let width = chip8::VIDEO_WIDTH as usize;
+    let mut failed = 0;
+    for test in 0..TESTS {
+        let (x, y) = (4 + 32 * (test % 2) + 6, 2 + 7 * (test / 2));
+        let passed = PASS.iter().enumerate().all(|(row, &bits)| {
+            (0..8).all(|column| {
+                let lit = chip8.video[(y + row) * width + x + column] != 0;
+                lit == (bits >> (7 - column) & 1 != 0)
+            })
+        });
+        println!("{} {}", test + 1, if passed { "ok" } else { "FAIL" });
+        failed += !passed as usize;
+    }
+
+    if failed > 0 {
+        println!("FAIL: {} of {} tests failed", failed, TESTS);
+        return Ok(1);
+    }
+    println!("OK: all {} tests passed", TESTS);
+    Ok(0)
+}
+
+fn list_audio() -> io::Result<i32> {
+    let failed = |e: String| io::Error::other(e);
+    let sdl_context = sdl2::init().map_err(failed)?;
+    for name in sound::device_names(&sdl_context).map_err(failed)? {
+        println!("{}", name);
+    }
+    Ok(0)
+}
+
+fn disassemble(rom_path: &str) -> io::Result<i32> {
+    let rom = chip8::read_rom(rom_path)?;
+    print!("{}", disasm::to_source(&rom, asm::DEFAULT_ORIGIN));
+    Ok(0)
+}
+
+fn assemble(source_path: &str, output_path: &str) -> io::Result<i32> {
+    let source = fs::read_to_string(source_path)?;
+    let (image, origin) =
+        asm::assemble(&source).map_err(|e| invalid(format!("{}: {}", source_path, e)))?;
+
+    fs::write(output_path, &image)?;
+    eprintln!("Wrote {} bytes (origin 0x{:03x}) to {}", image.len(), origin, output_path);
+    Ok(0)
+}
+
+fn verify_roundtrip(rom_path: &str) -> io::Result<i32> {
+    let rom = chip8::read_rom(rom_path)?;
+    let source = disasm::to_source(&rom, asm::DEFAULT_ORIGIN);
+
+    let (rebuilt, origin) = asm::assemble(&source)
+        .map_err(|e| invalid(format!("disassembly of {} does not assemble: {}", rom_path, e)))?;
+
+    if origin != asm::DEFAULT_ORIGIN {
+        println!("FAIL: origin changed to 0x{:03x}", origin);
+        return Ok(1);
+    }
+
+    if let Some(offset) = rom.iter().zip(&rebuilt).position(|(a, b)| a != b) {
+        println!(
+            "FAIL: byte at 0x{:03x} differs (0x{:02x} became 0x{:02x})",
+            origin as usize + offset,
+            rom[offset],
+            rebuilt[offset]
+        );
+        return Ok(1);
+    }
+
+    if rom.len() != rebuilt.len() {
+        println!("FAIL: size changed from {} to {} bytes", rom.len(), rebuilt.len());
+        return Ok(1);
+    }
+
+    println!("OK: {} bytes round-tripped", rom.len());
+    Ok(0)
+}
+
+fn lint(rom_path: &str) -> io::Result<i32> {
+    let rom = chip8::read_rom(rom_path)?;
+    let report = lint::lint(&rom, asm::DEFAULT_ORIGIN);
+
+    for finding in &report.findings {
+        println!(
+            "{:03x}  {:04X}  {:<7}  {}",
+            finding.address, finding.opcode, finding.severity, finding.message
+        );
+    }
+
+    let count = |severity| report.findings.iter().filter(|f| f.severity == severity).count();
+    let errors = count(Severity::Error);
+    println!(
+        "\n{} errors, {} warnings, {} notes",
+        errors,
+        count(Severity::Warning),
+        count(Severity::Note)
+    );
+
+    let platforms: Vec<&str> = report.suggested.iter().map(|variant| variant.name()).collect();
+    println!("Suggested platforms: {}", platforms.join(", "));
+    if report.quirk_sensitive.is_empty() {
+        println!("No quirk-dependent instructions found.");
+    } else {
+        println!("Check quirk settings for: {}", report.quirk_sensitive.join(", "));
+    }
+
+    Ok(if errors > 0 { 1 } else { 0 })
+}
+
+// Exits with 1 when the ROMs differ, like diff(1)
+fn diff(old_path: &str, new_path: &str) -> io::Result<i32> {
+    let old = chip8::read_rom(old_path)?;
+    let new = chip8::read_rom(new_path)?;
+
+    if old == new {
+        println!("ROMs are identical ({} bytes)", old.len());
+        return Ok(0);
+    }
+
+    let summary = romdiff::diff(&old, &new, asm::DEFAULT_ORIGIN);
+    println!("--- {}\n+++ {}", old_path, new_path);
+    print!("{}", summary.text);
+    println!(
+        "\n{} instruction lines and {} data lines changed",
+        summary.code_changes, summary.data_changes
+    );
+
+    Ok(1)
+}
+
+fn state_export(state_path: &str, output_path: &str) -> io::Result<i32> {
+    let snapshot = Snapshot::from_bytes(&fs::read(state_path)?)?;
+    let json = snapshot.to_json();
+
+    if output_path == "-" {
+        println!("{}", json);
+    } else {
+        fs::write(output_path, json + "\n")?;
+    }
+    Ok(0)
+}
+
+fn state_import(input_path: &str, state_path: &str) -> io::Result<i32> {
+    let snapshot = Snapshot::from_json(&fs::read_to_string(input_path)?)?;
+    fs::write(state_path, snapshot.to_bytes())?;
+    Ok(0)
+}
+
+fn replay_splice(first_path: &str, second_path: &str, frame: usize, output_path: &str) -> io::Result<i32> {
+    let first = Replay::load(Path::new(first_path))?;
+    let second = Replay::load(Path::new(second_path))?;
+
+    let spliced = replay::splice(&first, &second, frame)?;
+    spliced.save(Path::new(output_path))?;
+    eprintln!(
+        "Wrote {} frames ({} from {}, {} from {}) to {}",
+        spliced.frames.len(),
+        frame,
+        first_path,
+        spliced.frames.len() - frame,
+        second_path,
+        output_path
+    );
+    Ok(0)
+}
+
+fn replay_export(replay_path: &str, output_path: &str) -> io::Result<i32> {
+    let text = Replay::load(Path::new(replay_path))?.to_text();
+
+    if output_path == "-" {
+        print!("{}", text);
+    } else {
+        fs::write(output_path, text)?;
+    }
+    Ok(0)
+}
+
+fn replay_import(input_path: &str, replay_path: &str) -> io::Result<i32> {
+    let replay = Replay::from_text(&fs::read_to_string(input_path)?)?;
+    replay.save(Path::new(replay_path))?;
+    eprintln!("Wrote {} frames to {}", replay.frames.len(), replay_path);
+    Ok(0)
+}
+
+// Exits with 1 when the states differ, like diff
+fn state_diff(old_path: &str, new_path: &str) -> io::Result<i32> {
+    let old = Snapshot::from_bytes(&fs::read(old_path)?)?;
+    let new = Snapshot::from_bytes(&fs::read(new_path)?)?;
+
+    let summary = statediff::diff(&old, &new);
+    if summary.is_empty() {
+        println!("States are identical");
+        return Ok(0);
+    }
+
+    println!("--- {}\n+++ {}", old_path, new_path);
+    print!("{}", summary.text);
+    println!(
+        "\n{} registers, {} memory bytes and {} pixels changed",
+        summary.register_changes, summary.memory_changes, summary.pixel_changes
+    );
+    Ok(1)
+}
+
+// Replays are made from power-on on the machine they record, so verify
+//  sets it up the same way before playing one back
+// `expect_sound` is the number of frames the buzzer should sound in
+fn verify(rom: &RomSource, replay_path: &str, expect_hash: Option<&str>, expect_sound: Option<u64>) -> io::Result<i32> {
+    let data = read_program(rom)?;
+    let replay = Replay::load(Path::new(replay_path))?;
+    if replay.rom_hash != state::rom_hash(&data) {
+        return Err(invalid(format!("{} was recorded with a different ROM", replay_path)));
+    }
+
+    let mut chip8 = replay.machine.power_on(&data)?;
+    chip8.reseed(replay.seed);
+
+    let frames = replay.frames.len() as u64;
+    let outputs = headless::HeadlessOptions {
+        playback: Some(&replay),
+        ..headless::HeadlessOptions::default()
+    };
+    let sink = headless::run(&mut chip8, frames, replay.cycle_period, outputs)?;
+
+    let hash = state::state_hash(&chip8);
+    if let Some(expected) = expect_sound.filter(|&expected| expected != sink.frames()) {
+        println!("FAIL: the buzzer sounded for {} frames, expected {}", sink.frames(), expected);
+        return Ok(1);
+    }
+    match expect_hash {
+        Some(expected) if expected != hash => {
+            println!("FAIL: after {} frames the state hash is {}, expected {}", frames, hash, expected);
+            Ok(1)
+        }
+        Some(_) => {
+            println!("OK: {} frames, state hash {}", frames, hash);
+            Ok(0)
+        }
+        None => {
+            println!("{}", hash);
+            Ok(0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_built_in_self_test_passes() {
+        assert_eq!(self_test().unwrap(), 0);
+    }
+
+    #[test]
+    fn builtins_are_read_without_a_file() {
+        let rom = RomSource::Builtin(builtin::find("bounce").unwrap());
+        let data = read_program(&rom).unwrap();
+        let chip8 = power_on(&rom, &data).unwrap();
+        assert_eq!(chip8.memory[0x200..0x200 + data.len()], data[..]);
+    }
+}
@@ -0,0 +1,227 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use toml_edit::{Document, Item, Value};
+
+// Default options for running a program, from config.toml in the
+//  chip8-emulator settings folder or the file given with --config. Each key
+//  is a command-line option without its dashes; options given on the
+//  command line override them:
+//
+//      scale = 8
+//      ips = 1000
+//      palette = "amber"
+//      keymap = "octo"
+//      crt = true
+//      beep = "/home/me/sounds/beep.wav"
+//      patch = ["fix.ips", "infinite-lives.txt"]
+//
+//      [quirks]
+//      shift = false
+//      clip = true
+//
+//  `true` gives a switch such as --crt and `false` leaves it out; a list
+//  repeats the option. The ROM itself isn't set here.
+
+// Default options as command-line arguments, and where they came from
+pub struct Defaults {
+    pub source: String,
+    pub arguments: Vec<String>,
+}
+
+// Options that CHIP8_* environment variables can set, for containers and
+//  scripts; they override the config file and the command line overrides
+//  them
+const VARIABLES: [(&str, &str); 10] = [
+    ("CHIP8_SCALE", "--scale"),
+    ("CHIP8_IPS", "--ips"),
+    ("CHIP8_DELAY", "--delay"),
+    ("CHIP8_PALETTE", "--palette"),
+    ("CHIP8_KEYMAP", "--keymap"),
+    ("CHIP8_FRONTEND", "--frontend"),
+    ("CHIP8_RENDERER", "--renderer"),
+    ("CHIP8_MONITOR", "--monitor"),
+    ("CHIP8_VOLUME", "--volume"),
+    ("CHIP8_AUDIO_DEVICE", "--audio-device"),
+];
+
+// ~/.config/chip8-emulator/config.toml, or wherever the system keeps
+//  settings
+pub fn path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("chip8-emulator").join("config.toml"))
+}
+
+// The options in the file given with --config, or the one CHIP8_CONFIG
+//  names, or config.toml if there is one
+pub fn load(explicit: Option<&Path>) -> Result<Option<Defaults>, String> {
+    let named = match explicit {
+        Some(path) => Some(path.to_path_buf()),
+        None => env::var_os("CHIP8_CONFIG").filter(|path| !path.is_empty()).map(PathBuf::from),
+    };
+    let Some(path) = named.or_else(|| path().filter(|path| path.exists())) else {
+        return Ok(None);
+    };
+    let arguments = read(&path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    Ok(Some(Defaults {
+        source: path.display().to_string(),
+        arguments,
+    }))
+}
+
+// Options from CHIP8_* variables, one layer each
+pub fn environment() -> Result<Vec<Defaults>, String> {
+    variables(|variable| env::var(variable).ok())
+}
+
+// CHIP8_LOG is `verbose` for --verbose, or `info` for the usual messages
+fn variables(lookup: impl Fn(&str) -> Option<String>) -> Result<Vec<Defaults>, String> {
+    let mut defaults = Vec::new();
+    for (variable, flag) in VARIABLES {
+        if let Some(value) = lookup(variable).filter(|value| !value.is_empty()) {
+            defaults.push(Defaults {
+                source: String::from(variable),
+                arguments: vec![String::from(flag), value],
+            });
+        }
+    }
+    let verbose = match lookup("CHIP8_LOG").as_deref() {
+        Some("verbose") => "--verbose=true",
+        Some("info") => "--verbose=false",
+        Some("") | None => return Ok(defaults),
+        Some(level) => return Err(format!("CHIP8_LOG: unknown level '{}' (info or verbose)", level)),
+    };
+    defaults.push(Defaults {
+        source: String::from("CHIP8_LOG"),
+        arguments: vec![String::from(verbose)],
+    });
+    Ok(defaults)
+}
+
+fn read(path: &Path) -> io::Result<Vec<String>> {
+    let text = fs::read_to_string(path)?;
+    arguments(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn value_text(name: &str, value: &Value) -> Result<String, String> {
+    match value {
+        Value::String(text) => Ok(text.value().clone()),
+        Value::Integer(number) => Ok(number.value().to_string()),
+        Value::Float(number) => Ok(number.value().to_string()),
+        _ => Err(format!("{}: expected text, a number, true or false", name)),
+    }
+}
+
+fn arguments(text: &str) -> Result<Vec<String>, String> {
+    let document = text.parse::<Document>().map_err(|e| e.to_string())?;
+    let mut arguments = Vec::new();
+
+    for (name, item) in document.iter() {
+        if name == "quirks" {
+            let table = item.as_table_like().ok_or("[quirks] is not a table")?;
+            for (quirk, on) in table.iter() {
+                let on = on.as_bool().ok_or_else(|| format!("[quirks] {}: expected true or false", quirk))?;
+                arguments.push(String::from("--quirk"));
+                arguments.push(format!("{}={}", quirk, if on { "on" } else { "off" }));
+            }
+            continue;
+        }
+        if matches!(name, "rom" | "builtin" | "config" | "help") {
+            return Err(format!("'{}' can only be given on the command line", name));
+        }
+
+        let flag = format!("--{}", name);
+        match item {
+            Item::Value(Value::Boolean(on)) => {
+                if *on.value() {
+                    arguments.push(flag);
+                }
+            }
+            Item::Value(Value::Array(values)) => {
+                for value in values.iter() {
+                    arguments.push(flag.clone());
+                    arguments.push(value_text(name, value)?);
+                }
+            }
+            Item::Value(value) => {
+                arguments.push(flag);
+                arguments.push(value_text(name, value)?);
+            }
+            _ => return Err(format!("[{}] is not a setting", name)),
+        }
+    }
+    Ok(arguments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_become_arguments() {
+        let text = "scale = 8\n\
+                    volume = 50\n\
+                    palette = \"amber\"\n\
+                    crt = true\n\
+                    lcd = false\n\
+                    patch = [\"a.ips\", \"b.txt\"]\n\
+                    \n\
+                    [quirks]\n\
+                    shift = false\n\
+                    clip = true\n";
+        let expected = [
+            "--scale", "8", "--volume", "50", "--palette", "amber", "--crt", "--patch", "a.ips", "--patch", "b.txt",
+            "--quirk", "shift=off", "--quirk", "clip=on",
+        ];
+        assert_eq!(arguments(text).unwrap(), expected);
+    }
+
+    #[test]
+    fn mistakes_are_refused() {
+        for text in [
+            "rom = \"game.ch8\"",
+            "config = \"other.toml\"",
+            "scale = [[1]]",
+            "[display]\nscale = 8",
+            "[quirks]\nshift = \"no\"",
+            "quirks = 1",
+            "scale = ",
+        ] {
+            assert!(arguments(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn a_named_file_is_read_and_a_missing_one_is_an_error() {
+        let path = std::env::temp_dir().join(format!("chip8-config-{}.toml", std::process::id()));
+        fs::write(&path, "ips = 1000\n").unwrap();
+        let defaults = load(Some(&path)).unwrap().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(defaults.source, path.display().to_string());
+        assert_eq!(defaults.arguments, ["--ips", "1000"]);
+
+        let error = load(Some(&path)).err().unwrap();
+        assert!(error.starts_with("could not read "), "{}", error);
+    }
+
+    #[test]
+    fn each_variable_is_a_layer_named_after_it() {
+        let set = |set: &'static [(&str, &str)]| {
+            variables(|name| set.iter().find(|(variable, _)| *variable == name).map(|(_, value)| value.to_string()))
+        };
+        let defaults = set(&[("CHIP8_IPS", "1000"), ("CHIP8_SCALE", "6"), ("CHIP8_PALETTE", ""), ("CHIP8_LOG", "verbose")])
+            .unwrap();
+        let layers: Vec<(&str, Vec<String>)> =
+            defaults.iter().map(|defaults| (defaults.source.as_str(), defaults.arguments.clone())).collect();
+        assert_eq!(layers.len(), 3);
+        assert_eq!(layers[0], ("CHIP8_SCALE", vec![String::from("--scale"), String::from("6")]));
+        assert_eq!(layers[1], ("CHIP8_IPS", vec![String::from("--ips"), String::from("1000")]));
+        assert_eq!(layers[2], ("CHIP8_LOG", vec![String::from("--verbose=true")]));
+
+        assert_eq!(set(&[("CHIP8_LOG", "info")]).unwrap()[0].arguments, ["--verbose=false"]);
+        assert!(set(&[]).unwrap().is_empty());
+        let error = set(&[("CHIP8_LOG", "debug")]).err().unwrap();
+        assert!(error.starts_with("CHIP8_LOG: "), "{}", error);
+    }
+}
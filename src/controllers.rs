@@ -0,0 +1,108 @@
+use sdl2::controller::GameController;
+use sdl2::{GameControllerSubsystem, Sdl};
+
+// Strength of both motors, out of 0xFFFF
+const STRENGTH: u16 = 0x8000;
+// Renewed every frame the buzzer sounds, so the motors stop by themselves
+//  soon after frames stop coming (pausing, rewinding)
+const PULSE_MS: u32 = 100;
+
+// The game controllers plugged in, kept open so their buttons reach the
+//  keypad. Controllers can come and go while the emulator runs. With
+//  --rumble they also shake while the buzzer sounds, for feeling beeps as
+//  well as hearing them.
+pub struct Controllers {
+    subsystem: Option<GameControllerSubsystem>,
+    controllers: Vec<GameController>,
+    rumbling: bool,
+}
+
+impl Controllers {
+    // Opens every controller connected now; without any this does nothing
+    pub fn open(sdl_context: &Sdl) -> Controllers {
+        let mut controllers = Controllers {
+            subsystem: None,
+            controllers: Vec::new(),
+            rumbling: false,
+        };
+        match sdl_context.game_controller() {
+            Ok(subsystem) => {
+                let count = subsystem.num_joysticks().unwrap_or(0);
+                controllers.subsystem = Some(subsystem);
+                for index in 0..count {
+                    controllers.add(index);
+                }
+            }
+            Err(e) => eprintln!("No controllers: {}", e),
+        }
+        controllers
+    }
+
+    // Opens the controller at this joystick index. Returns its name, or
+    //  None if it isn't a controller or is open already: SDL also reports
+    //  the controllers found at startup as added.
+    pub fn add(&mut self, index: u32) -> Option<String> {
+        let subsystem = self.subsystem.as_ref()?;
+        if !subsystem.is_game_controller(index) {
+            return None;
+        }
+        let controller = match subsystem.open(index) {
+            Ok(controller) => controller,
+            Err(e) => {
+                eprintln!("Error opening controller {}: {}", index, e);
+                return None;
+            }
+        };
+        if self.controllers.iter().any(|open| open.instance_id() == controller.instance_id()) {
+            return None;
+        }
+        let name = controller.name();
+        self.controllers.push(controller);
+        Some(name)
+    }
+
+    // Closes the controller with this instance id, returning its name
+    pub fn remove(&mut self, id: u32) -> Option<String> {
+        let index = self.controllers.iter().position(|controller| controller.instance_id() == id)?;
+        Some(self.controllers.remove(index).name())
+    }
+
+    // Instance ids in the order the controllers were connected, which is
+    //  the order of the players
+    pub fn ids(&self) -> Vec<u32> {
+        self.controllers.iter().map(|controller| controller.instance_id()).collect()
+    }
+
+    // Called once a frame with whether the buzzer is sounding
+    pub fn rumble(&mut self, on: bool) {
+        let Some((strength, duration)) = pulse(self.rumbling, on) else { return };
+        self.rumbling = on;
+        for controller in &mut self.controllers {
+            // Controllers without motors just say so; nothing to do about it
+            let _ = controller.set_rumble(strength, strength, duration);
+        }
+    }
+}
+
+// The motors' strength and how long it lasts for a frame in which the buzzer
+//  is `on`, or None when the motors are already still
+fn pulse(was_on: bool, on: bool) -> Option<(u16, u32)> {
+    match (was_on, on) {
+        (false, false) => None,
+        (_, true) => Some((STRENGTH, PULSE_MS)),
+        (true, false) => Some((0, 0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn beeps_are_renewed_each_frame_and_stopped_once() {
+        assert_eq!(pulse(false, true), Some((STRENGTH, PULSE_MS)));
+        assert_eq!(pulse(true, true), Some((STRENGTH, PULSE_MS)));
+        assert_eq!(pulse(true, false), Some((0, 0)));
+        assert_eq!(pulse(false, false), None);
+    }
+}
@@ -0,0 +1,124 @@
+// Only the pixels frontend plays through cpal
+#![cfg_attr(not(feature = "pixels"), allow(dead_code))]
+
+use crate::buzzer::{Pattern, Tone};
+
+// The tone generator, shared with cpal's audio thread
+#[cfg(feature = "cpal")]
+type Shared = std::sync::Arc<std::sync::Mutex<crate::sound::Output>>;
+
+// The buzzer through cpal rather than SDL's audio subsystem, for the pixels
+//  frontend. It shares the SDL output's tone generator and fades, and like
+//  it carries on silently without a working device.
+#[cfg(feature = "cpal")]
+pub struct CpalSound {
+    output: Option<(cpal::Stream, Shared)>,
+}
+
+#[cfg(feature = "cpal")]
+impl CpalSound {
+    // `volume` runs from 0 to 100
+    pub fn open(tone: Tone, volume: u8) -> CpalSound {
+        match open_stream(tone, volume) {
+            Ok(output) => CpalSound { output: Some(output) },
+            Err(e) => {
+                eprintln!("No sound: {}", e);
+                CpalSound { output: None }
+            }
+        }
+    }
+
+    pub fn set_buzzing(&mut self, on: bool) {
+        if let Some((_, output)) = &self.output {
+            if let Ok(mut output) = output.lock() {
+                output.on = on;
+            }
+        }
+    }
+
+    pub fn set_pattern(&mut self, pattern: Option<Pattern>) {
+        if let Some((_, output)) = &self.output {
+            if let Ok(mut output) = output.lock() {
+                output.oscillator.pattern = pattern;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "cpal")]
+fn open_stream(tone: Tone, volume: u8) -> Result<(cpal::Stream, Shared), String> {
+    use std::sync::{Arc, Mutex};
+
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use cpal::SampleFormat;
+
+    use crate::sound::Output;
+
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or_else(|| String::from("no output device"))?;
+    let config = device.default_output_config().map_err(|e| e.to_string())?;
+    let output = Arc::new(Mutex::new(Output::new(tone, volume, config.sample_rate().0)));
+
+    let stream = match config.sample_format() {
+        SampleFormat::F32 => build_stream::<f32>(&device, &config.into(), output.clone()),
+        SampleFormat::I16 => build_stream::<i16>(&device, &config.into(), output.clone()),
+        SampleFormat::U16 => build_stream::<u16>(&device, &config.into(), output.clone()),
+        format => return Err(format!("unsupported sample format {}", format)),
+    }?;
+    stream.play().map_err(|e| e.to_string())?;
+    Ok((stream, output))
+}
+
+// The same mono tone on every channel
+#[cfg(feature = "cpal")]
+fn build_stream<T>(device: &cpal::Device, config: &cpal::StreamConfig, output: Shared) -> Result<cpal::Stream, String>
+where
+    T: cpal::SizedSample + cpal::FromSample<f32>,
+{
+    use cpal::traits::DeviceTrait;
+
+    use crate::sound::LEVEL;
+
+    let channels = config.channels as usize;
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+                let Ok(mut output) = output.lock() else { return };
+                for frame in data.chunks_mut(channels) {
+                    let sample = T::from_sample(output.next() * LEVEL);
+                    frame.fill(sample);
+                }
+            },
+            |e| eprintln!("Sound error: {}", e),
+            None,
+        )
+        .map_err(|e| e.to_string())
+}
+
+// Without the `cpal` feature the pixels frontend is silent
+#[cfg(not(feature = "cpal"))]
+pub struct CpalSound;
+
+#[cfg(not(feature = "cpal"))]
+impl CpalSound {
+    pub fn open(_tone: Tone, _volume: u8) -> CpalSound {
+        CpalSound
+    }
+
+    pub fn set_buzzing(&mut self, _on: bool) {}
+
+    pub fn set_pattern(&mut self, _pattern: Option<Pattern>) {}
+}
+
+#[cfg(all(test, not(feature = "cpal")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_without_cpal_stay_silent() {
+        let mut sound = CpalSound::open(Tone::default(), 100);
+        sound.set_buzzing(true);
+    }
+}
@@ -0,0 +1,74 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Texture, WindowCanvas};
+
+// A retro monitor look drawn with the plain SDL renderer: a faint glow
+//  around lit pixels, dark scanlines and rounded screen corners. Real barrel
+//  distortion needs a shader, which the SDL 2D renderer doesn't offer.
+const GLOW_ALPHA: u8 = 0x30;
+const SCANLINE_ALPHA: u8 = 0x50;
+
+// `display` and `angle` are where and how the display itself was drawn
+pub fn draw(canvas: &mut WindowCanvas, texture: &mut Texture, display: Rect, angle: f64) {
+    let (width, height) = canvas.logical_size();
+
+    // Glow: the display added on top of itself, nudged a little each way
+    let spread = (width as i32 / 256).max(1);
+    texture.set_blend_mode(BlendMode::Add);
+    texture.set_alpha_mod(GLOW_ALPHA);
+    for (dx, dy) in [(-spread, 0), (spread, 0), (0, -spread), (0, spread)] {
+        let mut nudged = display;
+        nudged.offset(dx, dy);
+        let _ = canvas.copy_ex(texture, None, nudged, angle, None, false, false);
+    }
+    texture.set_alpha_mod(0xFF);
+    texture.set_blend_mode(BlendMode::None);
+
+    // Darken every other line of the window
+    let scanlines: Vec<Rect> = (0..height as i32).step_by(2).map(|y| Rect::new(0, y, width, 1)).collect();
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, SCANLINE_ALPHA));
+    let _ = canvas.fill_rects(&scanlines);
+
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+    let _ = canvas.fill_rects(&corners(width, height));
+}
+
+// Quarter circles masking the corners off, like a curved tube's bezel
+fn corners(width: u32, height: u32) -> Vec<Rect> {
+    let radius = (width.min(height) / 12) as i32;
+    let mut corners = Vec::new();
+    for row in 0..radius {
+        let dy = (radius - row) as f32;
+        let inset = radius - ((radius * radius) as f32 - dy * dy).max(0.0).sqrt() as i32;
+        if inset == 0 {
+            continue;
+        }
+        for y in [row, height as i32 - 1 - row] {
+            corners.push(Rect::new(0, y, inset as u32, 1));
+            corners.push(Rect::new(width as i32 - inset, y, inset as u32, 1));
+        }
+    }
+    corners
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corners_are_masked_evenly_and_only_near_the_edges() {
+        let (width, height) = (640, 320);
+        let corners = corners(width, height);
+        assert!(!corners.is_empty());
+        // The top row is cut the most, and each cut has its mirror image
+        let widest = corners.iter().map(|rect| rect.width()).max().unwrap();
+        assert_eq!(corners[0].width(), widest);
+        for rect in &corners {
+            assert!(rect.y() < 26 || rect.y() >= height as i32 - 26);
+            assert!(rect.x() == 0 || rect.right() == width as i32);
+            let mirrored = Rect::new(rect.x(), height as i32 - 1 - rect.y(), rect.width(), 1);
+            assert!(corners.contains(&mirrored));
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+use crate::chip8::{Chip8, VIDEO_HEIGHT, VIDEO_WIDTH};
+use crate::opcode::Instruction;
+use crate::replay::{bits_keypad, bits_text, keypad_bits, text_bits};
+use crate::timing;
+
+const HELP: &str = "\
+s, step [N]        run N instructions (1 unless given)
+c, continue [N]    run until a breakpoint, a halt or N frames (600 unless given)
+b, break [ADDR]    toggle a breakpoint, or list them
+r, regs            show the registers, timers and stack
+m, mem ADDR [LEN]  show LEN bytes of memory (64 unless given)
+d, dis [ADDR] [N]  disassemble N instructions (8 unless given) from ADDR or PC
+v, screen          show the display
+k, keys [KEYS]     hold keys, as hex digits or - for none, or show them
+q, quit            leave the debugger
+";
+
+// A command-line debugger that runs a program an instruction at a time
+//  without a window, taking commands from standard input. The timers count
+//  down once per frame's worth of instructions, as in the emulator.
+struct Debugger<'a> {
+    chip8: &'a mut Chip8,
+    cycle_period: Duration,
+    frame: u64,
+    // Instructions run in the current frame
+    cycles: u64,
+    breakpoints: BTreeSet<u16>,
+}
+
+// Why running stopped early
+enum Stop {
+    Breakpoint,
+    // The program jumps to itself, the usual way to end
+    Halted,
+    WaitingForKey,
+}
+
+// `reload` gives a fresh machine when the ROM has changed, to carry on with
+//  the same breakpoints
+pub fn run(
+    chip8: &mut Chip8,
+    cycle_period: Duration,
+    reload: &mut dyn FnMut() -> Option<io::Result<Chip8>>,
+) -> io::Result<()> {
+    let mut debugger = Debugger {
+        chip8,
+        cycle_period,
+        frame: 0,
+        cycles: 0,
+        breakpoints: BTreeSet::new(),
+    };
+    println!("Type 'help' for commands.");
+    debugger.show_next();
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+        let Some(line) = lines.next().transpose()? else {
+            return Ok(());
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((&command, args)) = words.split_first() else {
+            continue;
+        };
+        if matches!(command, "q" | "quit") {
+            return Ok(());
+        }
+
+        match reload() {
+            Some(Ok(machine)) => {
+                debugger.reload(machine);
+                println!("The ROM changed and was reloaded; breakpoints are kept");
+                debugger.show_next();
+            }
+            Some(Err(e)) => println!("The ROM changed but could not be reloaded: {}", e),
+            None => {}
+        }
+        if let Err(message) = debugger.command(command, args) {
+            println!("{}", message);
+        }
+    }
+}
+
+fn parse_address(text: &str) -> Result<u16, String> {
+    let digits = text.trim_start_matches("0x");
+    u16::from_str_radix(digits, 16)
+        .ok()
+        .filter(|&address| address < 4096)
+        .ok_or_else(|| format!("bad address '{}' (hex, below 1000)", text))
+}
+
+fn parse_count(text: Option<&&str>, default: u64) -> Result<u64, String> {
+    match text {
+        Some(text) => text.parse().map_err(|_| format!("bad number '{}'", text)),
+        None => Ok(default),
+    }
+}
+
+impl Debugger<'_> {
+    // Start over on a new machine, keeping the breakpoints
+    fn reload(&mut self, machine: Chip8) {
+        *self.chip8 = machine;
+        self.frame = 0;
+        self.cycles = 0;
+    }
+
+    fn command(&mut self, command: &str, args: &[&str]) -> Result<(), String> {
+        match command {
+            "s" | "step" => {
+                let count = parse_count(args.first(), 1)?;
+                for _ in 0..count {
+                    if let Some(stop) = self.step() {
+                        self.report(stop);
+                        break;
+                    }
+                }
+                self.show_next();
+            }
+            "c" | "continue" => {
+                let frames = parse_count(args.first(), 600)?;
+                let until = self.frame + frames;
+                // Breakpoints are checked after each step, so continuing
+                //  from one steps off it
+                let stop = loop {
+                    let stop = self.step().or_else(|| {
+                        self.breakpoints.contains(&self.chip8.pc).then_some(Stop::Breakpoint)
+                    });
+                    if stop.is_some() || self.frame >= until {
+                        break stop;
+                    }
+                };
+                match stop {
+                    Some(stop) => self.report(stop),
+                    None => println!("Stopped after {} frames", frames),
+                }
+                self.show_next();
+            }
+            "b" | "break" => match args.first() {
+                Some(text) => {
+                    let address = parse_address(text)?;
+                    if self.breakpoints.remove(&address) {
+                        println!("Removed the breakpoint at {:03X}", address);
+                    } else {
+                        self.breakpoints.insert(address);
+                        println!("Breakpoint at {:03X}", address);
+                    }
+                }
+                None if self.breakpoints.is_empty() => println!("No breakpoints"),
+                None => {
+                    let addresses: Vec<String> = self.breakpoints.iter().map(|a| format!("{:03X}", a)).collect();
+                    println!("Breakpoints: {}", addresses.join(" "));
+                }
+            },
+            "r" | "regs" => self.show_registers(),
+            "m" | "mem" => {
+                let address = parse_address(args.first().ok_or("mem needs an address")?)? as usize;
+                let length = parse_count(args.get(1), 64)? as usize;
+                let end = (address + length).min(self.chip8.memory.len());
+                for (row, bytes) in self.chip8.memory[address..end].chunks(16).enumerate() {
+                    let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+                    println!("{:03X}  {}", address + row * 16, hex.join(" "));
+                }
+            }
+            "d" | "dis" => {
+                let address = match args.first() {
+                    Some(text) => parse_address(text)?,
+                    None => self.chip8.pc,
+                };
+                let count = parse_count(args.get(1), 8)?;
+                for i in 0..count {
+                    let at = address + i as u16 * 2;
+                    if at as usize + 1 >= self.chip8.memory.len() {
+                        break;
+                    }
+                    println!("{}", self.line(at));
+                }
+            }
+            "v" | "screen" => {
+                for row in self.chip8.video.chunks(VIDEO_WIDTH as usize).take(VIDEO_HEIGHT as usize) {
+                    let text: String = row.iter().map(|&pixel| if pixel != 0 { '#' } else { '.' }).collect();
+                    println!("{}", text);
+                }
+            }
+            "k" | "keys" => match args.first() {
+                Some(text) => self.chip8.keypad = bits_keypad(text_bits(text)?),
+                None => println!("Holding {}", bits_text(keypad_bits(&self.chip8.keypad))),
+            },
+            "h" | "help" => print!("{}", HELP),
+            _ => return Err(format!("Unknown command '{}'; type 'help' for commands", command)),
+        }
+        Ok(())
+    }
+
+    // Run one instruction, counting down the timers at the end of a frame
+    fn step(&mut self) -> Option<Stop> {
+        let pc = self.chip8.pc;
+        self.chip8.cycle();
+        self.cycles += 1;
+        if self.cycles >= timing::cycles_in_frame(self.frame, self.cycle_period) {
+            self.chip8.tick_timers();
+            self.frame += 1;
+            self.cycles = 0;
+        }
+
+        if self.chip8.pc != pc {
+            None
+        } else if self.chip8.opcode & 0xF0FF == 0xF00A {
+            Some(Stop::WaitingForKey)
+        } else {
+            Some(Stop::Halted)
+        }
+    }
+
+    fn report(&self, stop: Stop) {
+        match stop {
+            Stop::Breakpoint => println!("Breakpoint at {:03X}", self.chip8.pc),
+            Stop::Halted => println!("Halted: {:03X} jumps to itself", self.chip8.pc),
+            Stop::WaitingForKey => println!("Waiting for a key; hold one with 'keys'"),
+        }
+    }
+
+    fn line(&self, address: u16) -> String {
+        let memory = &self.chip8.memory;
+        let opcode = (memory[address as usize] as u16) << 8 | memory[address as usize + 1] as u16;
+        let text = match Instruction::decode(opcode) {
+            Some(instruction) => instruction.to_string(),
+            None => String::from("(data)"),
+        };
+        let marker = if self.breakpoints.contains(&address) { '*' } else { ' ' };
+        format!("{}{:03X}  {:04X}  {}", marker, address, opcode, text)
+    }
+
+    fn show_next(&self) {
+        println!("frame {}  {}", self.frame, self.line(self.chip8.pc));
+    }
+
+    fn show_registers(&self) {
+        let chip8 = &self.chip8;
+        let registers: Vec<String> = chip8
+            .registers
+            .iter()
+            .enumerate()
+            .map(|(i, value)| format!("V{:X}={:02X}", i, value))
+            .collect();
+        println!("{}", registers[..8].join(" "));
+        println!("{}", registers[8..].join(" "));
+        println!(
+            "PC={:03X} I={:03X} DT={:02X} ST={:02X}",
+            chip8.pc, chip8.index, chip8.delay_timer, chip8.sound_timer
+        );
+        let stack: Vec<String> = chip8.stack.iter().take(chip8.sp as usize).map(|a| format!("{:03X}", a)).collect();
+        println!("Stack: {}", if stack.is_empty() { String::from("-") } else { stack.join(" ") });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 200: V0 += 1, 202: V1 = 5, 204: jump to 204
+    const PROGRAM: [u8; 6] = [0x70, 0x01, 0x61, 0x05, 0x12, 0x04];
+
+    fn debugger(chip8: &mut Chip8) -> Debugger<'_> {
+        chip8.load_rom(&PROGRAM).unwrap();
+        Debugger {
+            chip8,
+            cycle_period: Duration::from_secs(1) / 600,
+            frame: 0,
+            cycles: 0,
+            breakpoints: BTreeSet::new(),
+        }
+    }
+
+    #[test]
+    fn addresses_are_hex_within_memory() {
+        assert_eq!(parse_address("2a0"), Ok(0x2A0));
+        assert_eq!(parse_address("0x200"), Ok(0x200));
+        assert!(parse_address("1000").is_err());
+        assert!(parse_address("pc").is_err());
+        assert_eq!(parse_count(None, 8), Ok(8));
+        assert!(parse_count(Some(&"many"), 8).is_err());
+    }
+
+    #[test]
+    fn stepping_stops_where_the_program_jumps_to_itself() {
+        let mut chip8 = Chip8::new();
+        let mut debugger = debugger(&mut chip8);
+        debugger.command("step", &["5"]).unwrap();
+        assert_eq!(debugger.chip8.pc, 0x204);
+        assert_eq!(debugger.chip8.registers[..2], [1, 5]);
+        assert!(matches!(debugger.step(), Some(Stop::Halted)));
+    }
+
+    #[test]
+    fn continuing_stops_at_breakpoints_and_counts_frames() {
+        let mut chip8 = Chip8::new();
+        let mut debugger = debugger(&mut chip8);
+        debugger.command("break", &["202"]).unwrap();
+        debugger.command("continue", &[]).unwrap();
+        assert_eq!(debugger.chip8.pc, 0x202);
+        assert!(debugger.line(0x202).starts_with("*202  6105"));
+        debugger.command("continue", &[]).unwrap();
+        assert_eq!(debugger.chip8.pc, 0x204);
+
+        // A breakpoint toggles off; ten instructions a frame at 600 a second,
+        //  three of them run already
+        debugger.command("b", &["202"]).unwrap();
+        assert!(debugger.breakpoints.is_empty());
+        for _ in 0..25 {
+            debugger.step();
+        }
+        assert_eq!((debugger.frame, debugger.cycles), (2, 8));
+        assert!(debugger.command("jump", &[]).is_err());
+    }
+
+    #[test]
+    fn reloading_starts_over_with_the_same_breakpoints() {
+        let mut chip8 = Chip8::new();
+        let mut debugger = debugger(&mut chip8);
+        debugger.command("break", &["204"]).unwrap();
+        debugger.command("continue", &[]).unwrap();
+
+        let mut machine = Chip8::new();
+        machine.load_rom(&[0x12, 0x00]).unwrap();
+        debugger.reload(machine);
+        assert_eq!(debugger.chip8.pc, 0x200);
+        assert_eq!(debugger.chip8.registers[..2], [0, 0]);
+        assert_eq!((debugger.frame, debugger.cycles), (0, 0));
+        assert!(debugger.breakpoints.contains(&0x204));
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::BTreeSet;
+
+use crate::opcode::Instruction;
+
+// One 2-byte word of the ROM (or the odd byte at its end), decoded where it
+//  forms a valid instruction
+pub struct Line {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub instruction: Option<Instruction>,
+}
+
+// Linear sweep: every aligned word is decoded, and anything that isn't a
+//  valid instruction is kept as data. CHIP-8 programs mix code and sprites
+//  freely, so this favours fidelity over guessing which is which.
+pub fn disassemble(rom: &[u8], origin: u16) -> Vec<Line> {
+    rom.chunks(2)
+        .enumerate()
+        .map(|(i, bytes)| {
+            let instruction = match bytes {
+                [high, low] => Instruction::decode((*high as u16) << 8 | *low as u16),
+                _ => None,
+            };
+
+            Line {
+                address: origin + (i * 2) as u16,
+                bytes: bytes.to_vec(),
+                instruction,
+            }
+        })
+        .collect()
+}
+
+fn label_name(address: u16) -> String {
+    format!("L{:03X}", address)
+}
+
+// Produce source that `asm::assemble` turns back into the identical image:
+//  an explicit origin, labels for addresses referenced from inside the ROM,
+//  and `db` for everything that is not an instruction.
+pub fn to_source(rom: &[u8], origin: u16) -> String {
+    let lines = disassemble(rom, origin);
+
+    let starts: BTreeSet<u16> = lines.iter().map(|line| line.address).collect();
+    let labels: BTreeSet<u16> = lines
+        .iter()
+        .filter_map(|line| line.instruction.and_then(|i| i.target()))
+        .filter(|target| starts.contains(target))
+        .collect();
+
+    let address = |nnn: u16| {
+        if labels.contains(&nnn) {
+            label_name(nnn)
+        } else {
+            format!("0x{:03x}", nnn)
+        }
+    };
+
+    let mut source = format!("org 0x{:03x}\n\n", origin);
+    for line in &lines {
+        if labels.contains(&line.address) {
+            source.push_str(&format!("{}:\n", label_name(line.address)));
+        }
+
+        let text = match line.instruction {
+            Some(instruction) => instruction.format_with(&address),
+            None => {
+                let bytes: Vec<String> = line.bytes.iter().map(|b| format!("0x{:02x}", b)).collect();
+                format!("db {}", bytes.join(", "))
+            }
+        };
+
+        let hex: String = line.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        source.push_str(&format!("  {:<24}; {:03x}  {}\n", text, line.address, hex));
+    }
+
+    source
+}
+
+// Follow control flow from the entry point and return the addresses of every
+//  instruction that can be reached, plus any computed jumps (`jp v0`) whose
+//  destinations could not be followed.
+pub fn trace(rom: &[u8], origin: u16) -> (BTreeSet<u16>, Vec<u16>) {
+    let end = origin as usize + rom.len();
+    let fetch = |address: u16| -> Option<u16> {
+        let offset = (address as usize).checked_sub(origin as usize)?;
+        match rom.get(offset..offset + 2) {
+            Some([high, low]) => Some((*high as u16) << 8 | *low as u16),
+            _ => None,
+        }
+    };
+
+    let mut reached = BTreeSet::new();
+    let mut computed_jumps = Vec::new();
+    let mut pending = vec![origin];
+
+    while let Some(address) = pending.pop() {
+        if address < origin || (address as usize) >= end || reached.contains(&address) {
+            continue;
+        }
+
+        let Some(opcode) = fetch(address) else { continue };
+        reached.insert(address);
+        let next = address.wrapping_add(2);
+
+        match Instruction::decode(opcode) {
+            Some(Instruction::Jp(target)) => pending.push(target),
+            Some(Instruction::Call(target)) => {
+                pending.push(target);
+                pending.push(next);
+            }
+            Some(Instruction::Ret) => {}
+            Some(Instruction::JpV0(_)) => computed_jumps.push(address),
+            Some(
+                Instruction::SeByte(..)
+                | Instruction::SneByte(..)
+                | Instruction::SeReg(..)
+                | Instruction::SneReg(..)
+                | Instruction::Skp(_)
+                | Instruction::Sknp(_),
+            ) => {
+                pending.push(next);
+                pending.push(next.wrapping_add(2));
+            }
+            // Execution continues past anything else, including words this
+            //  decoder doesn't know, which the caller can report
+            _ => pending.push(next),
+        }
+    }
+
+    (reached, computed_jumps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm;
+
+    fn assert_round_trips(rom: &[u8]) {
+        let source = to_source(rom, asm::DEFAULT_ORIGIN);
+        let (rebuilt, origin) = asm::assemble(&source).unwrap();
+        assert_eq!(origin, asm::DEFAULT_ORIGIN);
+        assert_eq!(rebuilt, rom);
+    }
+
+    #[test]
+    fn bundled_roms_round_trip() {
+        assert_round_trips(include_bytes!("../roms/bounce.ch8"));
+        assert_round_trips(include_bytes!("../roms/ibm-logo.ch8"));
+        assert_round_trips(include_bytes!("../roms/opcode-test.ch8"));
+    }
+
+    #[test]
+    fn data_and_odd_bytes_round_trip() {
+        // Words that aren't instructions, a jump into them and a lone last byte
+        assert_round_trips(&[0x12, 0x04, 0xFF, 0xFF, 0x00, 0x01, 0x5A, 0x1F, 0x80]);
+    }
+
+    #[test]
+    fn jumps_inside_the_rom_get_labels() {
+        let source = to_source(&[0x00, 0xE0, 0x12, 0x00], 0x200);
+        assert!(source.contains("L200:"));
+        assert!(source.contains("jp L200"));
+    }
+}
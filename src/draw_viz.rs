@@ -0,0 +1,127 @@
+use crate::chip8::{Draw, VIDEO_HEIGHT, VIDEO_WIDTH};
+
+// How long each sprite draw stays highlighted before the program goes on
+pub const HOLD_FRAMES: u32 = 20;
+
+const ROW_TINT: [u8; 3] = [0xFF, 0xE0, 0x40];
+const COLLISION_COLOR: [u8; 3] = [0xFF, 0x30, 0x30];
+
+// What one Dxyn did to the display: the rows the sprite covers, and the
+//  pixels its XOR turned off in any plane, which are what set VF
+pub struct Highlight {
+    rows: Vec<usize>,
+    collisions: Vec<usize>,
+}
+
+impl Highlight {
+    // `clip` is the clipping quirk, which decides whether rows past the
+    //  bottom edge wrap around or are left out
+    pub fn new(before: &[u8], after: &[u8], draw: Draw, clip: bool) -> Highlight {
+        let height = VIDEO_HEIGHT as usize;
+        let rows = (draw.y as usize..draw.y as usize + draw.height as usize)
+            .filter(|&y| !clip || y < height)
+            .map(|y| y % height)
+            .collect();
+        let collisions = before
+            .iter()
+            .zip(after)
+            .enumerate()
+            .filter(|(_, (&was, &now))| was & !now != 0)
+            .map(|(i, _)| i)
+            .collect();
+
+        Highlight { rows, collisions }
+    }
+
+    // Paint the highlight over a rendered RGBA frame
+    pub fn apply(&self, rgba: &mut [u8]) {
+        let width = VIDEO_WIDTH as usize;
+        for &row in &self.rows {
+            for texel in rgba[row * width * 4..(row + 1) * width * 4].chunks_exact_mut(4) {
+                for (channel, &tint) in texel.iter_mut().zip(&ROW_TINT) {
+                    *channel = ((*channel as u16 * 2 + tint as u16) / 3) as u8;
+                }
+            }
+        }
+        for &pixel in &self.collisions {
+            rgba[pixel * 4..pixel * 4 + 3].copy_from_slice(&COLLISION_COLOR);
+        }
+    }
+}
+
+// Slows a program down to one sprite draw at a time, so students can watch
+//  how the display is built up
+pub struct DrawViz {
+    remaining: u32,
+    // Cycles left in the frame the draw cut short, run after the hold so
+    //  the program loses no instructions
+    held_cycles: u64,
+}
+
+impl DrawViz {
+    pub fn new() -> DrawViz {
+        DrawViz {
+            remaining: 0,
+            held_cycles: 0,
+        }
+    }
+
+    // Hold the draw on screen, keeping the `cycles` the frame had left
+    pub fn start_hold(&mut self, cycles: u64) {
+        self.remaining = HOLD_FRAMES;
+        self.held_cycles = cycles;
+    }
+
+    // The cycles held over by the last draw, once
+    pub fn take_held_cycles(&mut self) -> u64 {
+        std::mem::take(&mut self.held_cycles)
+    }
+
+    // Whether this frame is spent showing the last draw
+    pub fn hold(&mut self) -> bool {
+        if self.remaining == 0 {
+            return false;
+        }
+        self.remaining -= 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_wrap_unless_clipped_and_collisions_are_pixels_turned_off() {
+        let mut before = [0u8; 64 * 32];
+        let mut after = before;
+        before[3] = 1;
+        before[4] = 3;
+        after[4] = 1;
+        after[5] = 1;
+        let draw = Draw { x: 0, y: 30, height: 4 };
+
+        let wrapped = Highlight::new(&before, &after, draw, false);
+        assert_eq!(wrapped.rows, [30, 31, 0, 1]);
+        // Plane 2 going off at pixel 4 is a collision even with plane 1 still on
+        assert_eq!(wrapped.collisions, [3, 4]);
+        assert_eq!(Highlight::new(&before, &after, draw, true).rows, [30, 31]);
+
+        let mut rgba = vec![0; 64 * 32 * 4];
+        wrapped.apply(&mut rgba);
+        assert_eq!(rgba[3 * 4..4 * 4], [0xFF, 0x30, 0x30, 0]);
+        assert_eq!(rgba[8 * 4..9 * 4], [0x55, 0x4A, 0x15, 0]);
+        assert_eq!(rgba[2 * 64 * 4..][..4], [0; 4]);
+    }
+
+    #[test]
+    fn each_draw_is_held_for_a_while() {
+        let mut viz = DrawViz::new();
+        assert!(!viz.hold());
+        viz.start_hold(7);
+        assert_eq!((0..HOLD_FRAMES + 5).filter(|_| viz.hold()).count(), HOLD_FRAMES as usize);
+        // The rest of the cut-short frame is run once, not dropped
+        assert_eq!(viz.take_held_cycles(), 7);
+        assert_eq!(viz.take_held_cycles(), 0);
+    }
+}
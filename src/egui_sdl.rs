@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use egui::epaint::{ImageDelta, Primitive};
+use egui::{ClippedPrimitive, Context, ImageData, PointerButton, Pos2, RawInput, TextureId};
+use sdl2::event::Event;
+use sdl2::mouse::MouseButton;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::{FPoint, Rect};
+use sdl2::render::{BlendMode, ScaleMode, Texture, TextureCreator, Vertex, WindowCanvas};
+use sdl2::video::WindowContext;
+
+// Runs egui on top of SDL's 2D renderer: mouse events go in, and the
+//  triangles egui paints come out through SDL_RenderGeometry, so the window
+//  doesn't have to move to OpenGL. Everything is in the canvas's logical
+//  pixels, one per egui point.
+pub struct EguiSdl<'tex> {
+    ctx: Context,
+    texture_creator: &'tex TextureCreator<WindowContext>,
+    textures: HashMap<TextureId, Texture<'tex>>,
+    events: Vec<egui::Event>,
+    start: Instant,
+}
+
+impl<'tex> EguiSdl<'tex> {
+    pub fn new(texture_creator: &'tex TextureCreator<WindowContext>) -> Self {
+        EguiSdl {
+            ctx: Context::default(),
+            texture_creator,
+            textures: HashMap::new(),
+            events: Vec::new(),
+            start: Instant::now(),
+        }
+    }
+
+    // Pass on a mouse event; the others are left to the emulator
+    pub fn handle_event(&mut self, event: &Event) {
+        self.events.extend(pointer_event(event));
+    }
+
+    // Whether the pointer is over something egui drew, as of the last frame,
+    //  so clicks there aren't also taken by what is underneath
+    pub fn wants_pointer(&self) -> bool {
+        self.ctx.is_pointer_over_area() || self.ctx.wants_pointer_input()
+    }
+
+    // Lay out and paint one frame of `ui` over whatever the canvas holds
+    pub fn draw(&mut self, canvas: &mut WindowCanvas, ui: impl FnMut(&Context)) {
+        let (width, height) = canvas.logical_size();
+        let input = RawInput {
+            screen_rect: Some(egui::Rect::from_min_size(Pos2::ZERO, egui::vec2(width as f32, height as f32))),
+            time: Some(self.start.elapsed().as_secs_f64()),
+            events: std::mem::take(&mut self.events),
+            ..RawInput::default()
+        };
+        let output = self.ctx.run(input, ui);
+
+        for (id, delta) in &output.textures_delta.set {
+            if let Err(e) = self.set_texture(*id, delta) {
+                eprintln!("Error updating menu texture: {}", e);
+            }
+        }
+        let primitives = self.ctx.tessellate(output.shapes, output.pixels_per_point);
+        for ClippedPrimitive { clip_rect, primitive } in primitives {
+            let Primitive::Mesh(mesh) = primitive else { continue };
+            let Some(clip) = clip(clip_rect) else { continue };
+            canvas.set_clip_rect(clip);
+            let vertices: Vec<Vertex> = mesh.vertices.iter().map(vertex).collect();
+            let texture = self.textures.get(&mesh.texture_id);
+            if let Err(e) = canvas.render_geometry(&vertices, texture, mesh.indices.as_slice()) {
+                eprintln!("Error drawing menu: {}", e);
+            }
+        }
+        canvas.set_clip_rect(None);
+        for id in &output.textures_delta.free {
+            self.textures.remove(id);
+        }
+    }
+
+    fn set_texture(&mut self, id: TextureId, delta: &ImageDelta) -> Result<(), String> {
+        let [width, height] = delta.image.size();
+        let pixels = rgba_pixels(&delta.image);
+        match (delta.pos, self.textures.get_mut(&id)) {
+            (Some([x, y]), Some(texture)) => {
+                let area = Rect::new(x as i32, y as i32, width as u32, height as u32);
+                texture.update(area, &pixels, width * 4).map_err(|e| e.to_string())
+            }
+            _ => {
+                let mut texture = self
+                    .texture_creator
+                    .create_texture_static(PixelFormatEnum::RGBA32, width as u32, height as u32)
+                    .map_err(|e| e.to_string())?;
+                texture.update(None, &pixels, width * 4).map_err(|e| e.to_string())?;
+                texture.set_blend_mode(BlendMode::Blend);
+                texture.set_scale_mode(ScaleMode::Linear);
+                self.textures.insert(id, texture);
+                Ok(())
+            }
+        }
+    }
+}
+
+fn pointer_event(event: &Event) -> Option<egui::Event> {
+    let button = |button: &MouseButton| match button {
+        MouseButton::Left => Some(PointerButton::Primary),
+        MouseButton::Right => Some(PointerButton::Secondary),
+        MouseButton::Middle => Some(PointerButton::Middle),
+        _ => None,
+    };
+    match event {
+        Event::MouseMotion { x, y, .. } => Some(egui::Event::PointerMoved(Pos2::new(*x as f32, *y as f32))),
+        Event::MouseButtonDown { mouse_btn, x, y, .. } | Event::MouseButtonUp { mouse_btn, x, y, .. } => {
+            Some(egui::Event::PointerButton {
+                pos: Pos2::new(*x as f32, *y as f32),
+                button: button(mouse_btn)?,
+                pressed: matches!(event, Event::MouseButtonDown { .. }),
+                modifiers: egui::Modifiers::default(),
+            })
+        }
+        Event::Window {
+            win_event: sdl2::event::WindowEvent::Leave,
+            ..
+        } => Some(egui::Event::PointerGone),
+        _ => None,
+    }
+}
+
+// egui's colors have their alpha premultiplied, which SDL2's blend modes
+//  can't take, so they are divided back out (in linear light, as egui does)
+fn vertex(vertex: &egui::epaint::Vertex) -> Vertex {
+    let [r, g, b, a] = vertex.color.to_srgba_unmultiplied();
+    Vertex {
+        position: FPoint::new(vertex.pos.x, vertex.pos.y),
+        color: Color::RGBA(r, g, b, a),
+        tex_coord: FPoint::new(vertex.uv.x, vertex.uv.y),
+    }
+}
+
+fn rgba_pixels(image: &ImageData) -> Vec<u8> {
+    match image {
+        ImageData::Color(image) => image.pixels.iter().flat_map(|color| color.to_srgba_unmultiplied()).collect(),
+        ImageData::Font(image) => image.srgba_pixels(None).flat_map(|color| color.to_srgba_unmultiplied()).collect(),
+    }
+}
+
+// The whole pixels a clip rectangle touches, or None when it is empty
+fn clip(rect: egui::Rect) -> Option<Rect> {
+    if !rect.is_positive() {
+        return None;
+    }
+    let (left, top) = (rect.min.x.floor(), rect.min.y.floor());
+    let (width, height) = (rect.max.x.ceil() - left, rect.max.y.ceil() - top);
+    Some(Rect::new(left as i32, top as i32, width as u32, height as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{Color32, FontImage};
+
+    #[test]
+    fn colors_are_unmultiplied_for_sdl() {
+        let painted = egui::epaint::Vertex {
+            pos: Pos2::new(1.5, 2.0),
+            uv: Pos2::new(0.25, 1.0),
+            color: Color32::from_rgba_premultiplied(100, 50, 0, 128),
+        };
+        let converted = vertex(&painted);
+        assert_eq!(converted.position, FPoint::new(1.5, 2.0));
+        assert_eq!(converted.tex_coord, FPoint::new(0.25, 1.0));
+        assert_eq!(converted.color, Color::RGBA(138, 71, 0, 128));
+
+        let mut font = FontImage::new([2, 1]);
+        font.pixels = vec![0.0, 1.0];
+        assert_eq!(rgba_pixels(&ImageData::Font(font)), [0, 0, 0, 0, 255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn clip_rects_cover_every_pixel_touched() {
+        let rect = egui::Rect::from_min_max(Pos2::new(0.5, 1.0), Pos2::new(10.2, 4.0));
+        assert_eq!(clip(rect), Some(Rect::new(0, 1, 11, 3)));
+        assert_eq!(clip(egui::Rect::NOTHING), None);
+    }
+
+    #[test]
+    fn mouse_events_become_pointer_events() {
+        let moved = Event::MouseMotion {
+            timestamp: 0,
+            window_id: 0,
+            which: 0,
+            mousestate: sdl2::mouse::MouseState::from_sdl_state(0),
+            x: 3,
+            y: 4,
+            xrel: 0,
+            yrel: 0,
+        };
+        assert_eq!(pointer_event(&moved), Some(egui::Event::PointerMoved(Pos2::new(3.0, 4.0))));
+
+        let released = Event::MouseButtonUp {
+            timestamp: 0,
+            window_id: 0,
+            which: 0,
+            mouse_btn: MouseButton::Left,
+            clicks: 1,
+            x: 3,
+            y: 4,
+        };
+        assert_eq!(
+            pointer_event(&released),
+            Some(egui::Event::PointerButton {
+                pos: Pos2::new(3.0, 4.0),
+                button: PointerButton::Primary,
+                pressed: false,
+                modifiers: egui::Modifiers::default(),
+            })
+        );
+    }
+}
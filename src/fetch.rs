@@ -0,0 +1,93 @@
+use std::io;
+use std::path::PathBuf;
+
+pub fn is_url(source: &str) -> bool {
+    source.starts_with("http://") || source.starts_with("https://")
+}
+
+// Downloaded ROMs are kept in the user cache directory, keyed by URL, so
+//  repeated runs of the same link don't hit the network again.
+#[cfg(feature = "http")]
+fn cache_path(url: &str) -> io::Result<PathBuf> {
+    let cache_dir = dirs::cache_dir().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "no cache directory for this user")
+    })?;
+
+    let name = sha1_smol::Sha1::from(url).digest().to_string();
+    Ok(cache_dir.join("chip8").join("roms").join(format!("{}.ch8", name)))
+}
+
+#[cfg(feature = "http")]
+pub fn fetch_cached(url: &str) -> io::Result<PathBuf> {
+    use std::fs;
+    use std::io::Read;
+
+    let path = cache_path(url)?;
+    if path.is_file() {
+        eprintln!("Using cached download {}", path.display());
+        return Ok(path);
+    }
+
+    eprintln!("Downloading {}...", url);
+    let response = ureq::get(url).call().map_err(|e| {
+        io::Error::other(format!("could not download '{}': {}", url, e))
+    })?;
+
+    // Read one byte past the limit so oversized ROMs are still rejected by
+    //  the usual validation instead of being silently truncated
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(crate::chip8::MAX_ROM_SIZE as u64 + 1)
+        .read_to_end(&mut bytes)?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    // Write next to the final path and rename, so an interrupted download
+    //  never leaves a truncated ROM in the cache
+    let partial = path.with_extension("part");
+    fs::write(&partial, &bytes)?;
+    fs::rename(&partial, &path)?;
+
+    Ok(path)
+}
+
+#[cfg(not(feature = "http"))]
+pub fn fetch_cached(url: &str) -> io::Result<PathBuf> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("cannot load '{}': this build has no URL support (enable the `http` feature)", url),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_http_and_https_are_urls() {
+        assert!(is_url("https://example.com/pong.ch8"));
+        assert!(is_url("http://example.com/pong.ch8"));
+        assert!(!is_url("ftp://example.com/pong.ch8"));
+        assert!(!is_url("roms/https.ch8"));
+        assert!(!is_url("-"));
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn each_url_has_its_own_cache_file() {
+        let pong = cache_path("https://example.com/pong.ch8").unwrap();
+        assert_eq!(pong, cache_path("https://example.com/pong.ch8").unwrap());
+        assert_ne!(pong, cache_path("https://example.com/tetris.ch8").unwrap());
+        assert_eq!(pong.extension().unwrap(), "ch8");
+    }
+
+    #[cfg(not(feature = "http"))]
+    #[test]
+    fn builds_without_http_refuse_urls() {
+        let error = fetch_cached("https://example.com/pong.ch8").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+    }
+}
@@ -0,0 +1,136 @@
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+use crate::chip8;
+use crate::timing;
+
+fn encoding_error(e: gif::EncodingError) -> io::Error {
+    io::Error::other(e)
+}
+
+// Records the display to an animated GIF with a two-color palette. Frames
+//  that repeat the previous one are merged into it, and delays are rounded
+//  to the GIF's centiseconds as a running total, so playback speed matches
+//  the emulator over time.
+pub struct GifRecorder {
+    path: PathBuf,
+    encoder: gif::Encoder<BufWriter<File>>,
+    scale: usize,
+    // The frame waiting to be written, and the frame number it first appeared
+    pending: Option<(Vec<u8>, u64)>,
+    frames: u64,
+    written_centiseconds: u64,
+}
+
+impl GifRecorder {
+    pub fn start(path: &Path, scale: u32, foreground: [u8; 3], background: [u8; 3]) -> io::Result<GifRecorder> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let width = chip8::VIDEO_WIDTH as u16 * scale as u16;
+        let height = chip8::VIDEO_HEIGHT as u16 * scale as u16;
+        let palette = [background, foreground].concat();
+
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = gif::Encoder::new(file, width, height, &palette).map_err(encoding_error)?;
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(encoding_error)?;
+
+        Ok(GifRecorder {
+            path: PathBuf::from(path),
+            encoder,
+            scale: scale as usize,
+            pending: None,
+            frames: 0,
+            written_centiseconds: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Add one 60 Hz frame of the display
+    pub fn push(&mut self, video: &[u8]) -> io::Result<()> {
+        let changed = self.pending.as_ref().is_none_or(|(pending, _)| pending != video);
+        if changed {
+            self.flush()?;
+            self.pending = Some((video.to_vec(), self.frames));
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.flush()
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let Some((video, _)) = self.pending.take() else { return Ok(()) };
+
+        let fps = timing::FRAMES_PER_SECOND as u64;
+        let end = (self.frames * 100 + fps / 2) / fps;
+        let delay = end - self.written_centiseconds;
+        self.written_centiseconds = end;
+
+        let width = chip8::VIDEO_WIDTH as usize;
+        let mut indices = Vec::with_capacity(video.len() * self.scale * self.scale);
+        for row in video.chunks_exact(width) {
+            let line: Vec<u8> = row
+                .iter()
+                .flat_map(|&pixel| std::iter::repeat_n((pixel != 0) as u8, self.scale))
+                .collect();
+            for _ in 0..self.scale {
+                indices.extend_from_slice(&line);
+            }
+        }
+
+        let frame = gif::Frame {
+            width: (width * self.scale) as u16,
+            height: (video.len() / width * self.scale) as u16,
+            delay: delay as u16,
+            buffer: Cow::Owned(indices),
+            ..gif::Frame::default()
+        };
+        self.encoder.write_frame(&frame).map_err(encoding_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Frame delays in centiseconds, read back from a finished GIF
+    fn delays(path: &Path) -> Vec<u16> {
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(File::open(path).unwrap()).unwrap();
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!((frame.width, frame.height), (128, 64));
+            delays.push(frame.delay);
+        }
+        delays
+    }
+
+    #[test]
+    fn repeated_frames_merge_and_delays_add_up() {
+        let path = std::env::temp_dir().join(format!("chip8-gif-{}.gif", std::process::id()));
+        let mut recorder = GifRecorder::start(&path, 2, [0xFF; 3], [0; 3]).unwrap();
+
+        let blank = vec![0; chip8::VIDEO_WIDTH as usize * chip8::VIDEO_HEIGHT as usize];
+        let mut lit = blank.clone();
+        lit[0] = 1;
+        // Half a second of each, then a single frame
+        for video in [&blank; 30].into_iter().chain([&lit; 30]).chain([&blank]) {
+            recorder.push(video).unwrap();
+        }
+        recorder.finish().unwrap();
+
+        let delays = delays(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(delays, [50, 50, 2]);
+    }
+}
@@ -0,0 +1,193 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::buzzer::{Buzzer, Pattern, Tone};
+use crate::chip8::{self, Chip8};
+use crate::render::Renderer;
+use crate::replay::Replay;
+use crate::screenshot;
+use crate::sound::NullSink;
+use crate::timing;
+use crate::video_recorder::VideoRecorder;
+use crate::wav_recorder::WavRecorder;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FrameFormat {
+    Png,
+    Pbm,
+}
+
+impl FrameFormat {
+    pub fn from_name(name: &str) -> Option<FrameFormat> {
+        match name {
+            "png" => Some(FrameFormat::Png),
+            "pbm" => Some(FrameFormat::Pbm),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            FrameFormat::Png => "png",
+            FrameFormat::Pbm => "pbm",
+        }
+    }
+}
+
+// Where and how rendered frames are written
+pub struct FrameDump {
+    pub dir: PathBuf,
+    pub format: FrameFormat,
+    pub scale: u32,
+    // The color of each pixel value, by the planes lit there
+    pub colors: [[u8; 3]; 4],
+}
+
+impl FrameDump {
+    fn write(&self, frame: u64, video: &[u8]) -> io::Result<()> {
+        let width = chip8::VIDEO_WIDTH as usize;
+        let path = self.dir.join(format!("frame-{:05}.{}", frame, self.format.extension()));
+
+        match self.format {
+            FrameFormat::Png => {
+                let rgb = screenshot::render_rgb(video, width, self.colors, self.scale);
+                let height = video.len() / width;
+                screenshot::save_png(&path, width as u32 * self.scale, height as u32 * self.scale, &rgb)
+            }
+            FrameFormat::Pbm => screenshot::save_pbm(&path, video, width, self.scale),
+        }
+    }
+}
+
+// What a headless run reads input from and writes its output to; the
+//  default is a run with no input and no output
+#[derive(Default)]
+pub struct HeadlessOptions<'a> {
+    // Input for every frame, or no keys held
+    pub playback: Option<&'a Replay>,
+    // Every frame written to disk
+    pub dump: Option<&'a FrameDump>,
+    pub video: Option<&'a mut VideoRecorder>,
+    // The buzzer written to a WAV file
+    pub audio: Option<&'a mut WavRecorder>,
+    // Renders each frame as the window would
+    pub renderer: Option<&'a mut Renderer>,
+    // How the buzzer sounds in the WAV file
+    pub tone: Tone,
+}
+
+// Run a fixed number of frames without a window, taking input from a replay
+//  if there is one, and optionally writing every frame to disk, a video or
+//  the buzzer to a WAV file, or rendering it as the window would. Returns
+//  how much the buzzer sounded.
+pub fn run(chip8: &mut Chip8, frames: u64, cycle_period: Duration, options: HeadlessOptions) -> io::Result<NullSink> {
+    let HeadlessOptions { playback, dump, mut video, mut audio, mut renderer, tone } = options;
+    if let Some(dump) = dump {
+        fs::create_dir_all(&dump.dir)?;
+    }
+
+    let mut buzzer = Buzzer::new(tone);
+    let mut sink = NullSink::default();
+
+    for frame in 0..frames {
+        chip8.keypad = playback.and_then(|replay| replay.keys(frame)).unwrap_or([0; 16]);
+
+        let mut buzzing = chip8.sound_timer > 0;
+        for _ in 0..timing::cycles_in_frame(frame, cycle_period) {
+            chip8.cycle();
+            buzzing |= chip8.sound_timer > 0;
+        }
+        chip8.tick_timers();
+        sink.set_buzzing(buzzing);
+
+        if let Some(dump) = dump {
+            dump.write(frame, &chip8.video)?;
+        }
+        // One frame of sound for every recording, so they all stay in step
+        let samples = if audio.is_some() || video.is_some() {
+            buzzer.set_pattern(Pattern::of(chip8));
+            buzzer.frame(buzzing)
+        } else {
+            Vec::new()
+        };
+        if let Some(recorder) = video.as_deref_mut() {
+            recorder.push(&chip8.video, &samples)?;
+        }
+        if let Some(recorder) = audio.as_deref_mut() {
+            recorder.push(&samples)?;
+        }
+        if let Some(renderer) = renderer.as_deref_mut() {
+            renderer.render(&chip8.video);
+        }
+    }
+
+    if let Some(dump) = dump {
+        eprintln!("Wrote {} frames to {}", frames, Path::new(&dump.dir).display());
+    }
+    Ok(sink)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::Machine;
+
+    #[test]
+    fn playback_drives_the_keypad() {
+        // Wait for a key, put it in v1, loop
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&[0xF1, 0x0A, 0x12, 0x02]).unwrap();
+        let mut replay = Replay::new(0, Duration::from_millis(1), "", Machine::of(&chip8));
+        let mut keypad = [0; 16];
+        keypad[7] = 1;
+        replay.record(0, &[0; 16]);
+        replay.record(1, &keypad);
+
+        let options = HeadlessOptions {
+            playback: Some(&replay),
+            ..HeadlessOptions::default()
+        };
+        run(&mut chip8, 3, Duration::from_millis(1), options).unwrap();
+        assert_eq!(chip8.registers[1], 7);
+        assert_eq!(chip8.keypad, [0; 16]);
+    }
+
+    #[test]
+    fn every_frame_is_dumped() {
+        let dir = std::env::temp_dir().join(format!("chip8-headless-{}", std::process::id()));
+        let dump = FrameDump {
+            dir: dir.clone(),
+            format: FrameFormat::Pbm,
+            scale: 1,
+            colors: [[0; 3], [0xFF; 3], [0xAA; 3], [0x55; 3]],
+        };
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&[0x12, 0x00]).unwrap();
+        let options = HeadlessOptions {
+            dump: Some(&dump),
+            ..HeadlessOptions::default()
+        };
+        let result = run(&mut chip8, 3, Duration::from_millis(1), options);
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        fs::remove_dir_all(&dir).unwrap();
+        result.unwrap();
+
+        names.sort();
+        assert_eq!(names, ["frame-00000.pbm", "frame-00001.pbm", "frame-00002.pbm"]);
+    }
+
+    #[test]
+    fn the_buzzer_is_counted() {
+        // Sound for three frames once, then loop; the timer counts frames
+        //  however fast the program runs
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&[0x60, 0x03, 0xF0, 0x18, 0x12, 0x04]).unwrap();
+        let sink = run(&mut chip8, 10, Duration::from_millis(1), HeadlessOptions::default()).unwrap();
+        assert_eq!((sink.frames(), sink.beeps()), (3, 1));
+    }
+}
@@ -0,0 +1,261 @@
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+
+use crate::replay::{bits_keypad, bits_text, keypad_bits, text_bits};
+
+// Keypad input from another program, for bots and experiments: a local TCP
+//  socket taking one command per line, with keys written as in macros, hex
+//  digits or `-` for none:
+//
+//      press 5A      hold keys until released
+//      release 5     let go of keys
+//      set 46        hold exactly these keys
+//      tap 6 3       hold keys for a number of frames (1 unless given)
+//      keys          reply with the keys held
+//      frame         reply with the current frame number
+//      wait 10       reply with the frame number once 10 frames have run
+//
+//  Every other command is answered with `ok`, or `error: ...`. Injected keys
+//  are added to live input at the start of each frame, so they are recorded
+//  in replays like any other press. Only one program is connected at a time,
+//  and its keys are let go of when it disconnects.
+pub struct Injector {
+    listener: TcpListener,
+    client: Option<Client>,
+}
+
+struct Client {
+    stream: TcpStream,
+    // Received text not yet ending in a newline
+    partial: Vec<u8>,
+    held: u16,
+    // Keys tapped and the frames they have left
+    taps: Vec<(u16, u64)>,
+    // The frame after which to answer `wait`
+    waiting: Option<u64>,
+}
+
+impl Injector {
+    pub fn listen(port: u16) -> io::Result<Injector> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        listener.set_nonblocking(true)?;
+        Ok(Injector { listener, client: None })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.listener.local_addr().map_or(0, |address| address.port())
+    }
+
+    // Take new connections and commands, then add the injected keys to the
+    //  frame's keypad
+    pub fn apply(&mut self, frame: u64, keypad: &mut [u8; 16]) {
+        match self.listener.accept() {
+            Ok((stream, address)) if self.client.is_none() && stream.set_nonblocking(true).is_ok() => {
+                eprintln!("Input connection from {}", address);
+                self.client = Some(Client {
+                    stream,
+                    partial: Vec::new(),
+                    held: 0,
+                    taps: Vec::new(),
+                    waiting: None,
+                });
+            }
+            // A second program is turned away
+            Ok((mut stream, _)) => {
+                let _ = stream.write_all(b"error: another program is connected\n");
+            }
+            Err(_) => {}
+        }
+
+        let Some(client) = &mut self.client else {
+            return;
+        };
+        if let Err(e) = client.receive(frame) {
+            eprintln!("Input connection closed: {}", e);
+            self.client = None;
+            return;
+        }
+
+        let tapped = client.taps.iter().fold(0, |bits, &(keys, _)| bits | keys);
+        *keypad = bits_keypad(keypad_bits(keypad) | client.held | tapped);
+        client.taps.retain_mut(|(_, frames)| {
+            *frames -= 1;
+            *frames > 0
+        });
+    }
+}
+
+impl Client {
+    fn receive(&mut self, frame: u64) -> io::Result<()> {
+        let mut buffer = [0; 1024];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return Err(io::Error::new(ErrorKind::UnexpectedEof, "disconnected")),
+                Ok(read) => self.partial.extend_from_slice(&buffer[..read]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        if self.waiting.is_some_and(|until| frame >= until) {
+            self.waiting = None;
+            self.stream.write_all(format!("frame {}\n", frame).as_bytes())?;
+        }
+        // Commands after a `wait` are held back until it is answered
+        while self.waiting.is_none() {
+            let Some(end) = self.partial.iter().position(|&byte| byte == b'\n') else {
+                break;
+            };
+            let line: Vec<u8> = self.partial.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let reply = match self.command(line.trim(), frame) {
+                Ok(Some(reply)) => reply,
+                Ok(None) => continue,
+                Err(message) => format!("error: {}", message),
+            };
+            self.stream.write_all(format!("{}\n", reply).as_bytes())?;
+        }
+        Ok(())
+    }
+
+    // Carry out one command, returning the reply unless it is to come later
+    fn command(&mut self, line: &str, frame: u64) -> Result<Option<String>, String> {
+        let mut words = line.split_whitespace();
+        let Some(name) = words.next() else {
+            return Ok(None);
+        };
+        let keys = |word: Option<&str>| text_bits(word.ok_or_else(|| format!("{} needs keys", name))?);
+        let count = |word: Option<&str>| match word {
+            Some(word) => word
+                .parse::<u64>()
+                .ok()
+                .filter(|&count| count > 0)
+                .ok_or_else(|| format!("bad number of frames '{}'", word)),
+            None => Ok(1),
+        };
+        match name {
+            "press" => self.held |= keys(words.next())?,
+            "release" => {
+                let keys = keys(words.next())?;
+                self.held &= !keys;
+                for (tapped, _) in &mut self.taps {
+                    *tapped &= !keys;
+                }
+                self.taps.retain(|&(tapped, _)| tapped != 0);
+            }
+            "set" => {
+                self.held = keys(words.next())?;
+                self.taps.clear();
+            }
+            "tap" => {
+                let keys = keys(words.next())?;
+                let frames = count(words.next())?;
+                self.taps.push((keys, frames));
+            }
+            "frame" => return Ok(Some(format!("frame {}", frame))),
+            "wait" => {
+                self.waiting = Some(frame + count(words.next())?);
+                return Ok(None);
+            }
+            "keys" => return Ok(Some(bits_text(self.held))),
+            _ => return Err(format!("unknown command '{}'", name)),
+        }
+        Ok(Some(String::from("ok")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::time::Duration;
+
+    // A program connected to an injector, with the keys of each frame run
+    struct Bot {
+        injector: Injector,
+        stream: TcpStream,
+        reader: BufReader<TcpStream>,
+        frames: Vec<u16>,
+    }
+
+    impl Bot {
+        fn connect() -> Bot {
+            let injector = Injector::listen(0).unwrap();
+            let stream = TcpStream::connect((Ipv4Addr::LOCALHOST, injector.port())).unwrap();
+            stream.set_read_timeout(Some(Duration::from_millis(5))).unwrap();
+            let reader = BufReader::new(stream.try_clone().unwrap());
+            Bot { injector, stream, reader, frames: Vec::new() }
+        }
+
+        fn frame(&mut self) {
+            let mut keypad = [0; 16];
+            self.injector.apply(self.frames.len() as u64, &mut keypad);
+            self.frames.push(keypad_bits(&keypad));
+        }
+
+        // Runs frames until the next reply arrives
+        fn reply(&mut self) -> String {
+            let mut line = String::new();
+            for _ in 0..200 {
+                if self.reader.read_line(&mut line).is_ok() && line.ends_with('\n') {
+                    return line.trim_end().to_string();
+                }
+                self.frame();
+            }
+            panic!("no reply, got '{}'", line);
+        }
+    }
+
+    #[test]
+    fn a_connected_program_holds_taps_and_waits() {
+        let mut bot = Bot::connect();
+        bot.stream.write_all(b"press 5A\nkeys\nfly\ntap 6 x\n").unwrap();
+        assert_eq!(bot.reply(), "ok");
+        assert_eq!(bot.reply(), "5A");
+        assert_eq!(bot.reply(), "error: unknown command 'fly'");
+        assert_eq!(bot.reply(), "error: bad number of frames 'x'");
+        assert_eq!(bot.frames.last(), Some(&(1 << 0x5 | 1 << 0xA)));
+
+        // The tap lasts two frames, then nothing is held
+        bot.stream.write_all(b"set -\ntap 6 2\n").unwrap();
+        assert_eq!((bot.reply(), bot.reply()), (String::from("ok"), String::from("ok")));
+        bot.frame();
+        bot.frame();
+        assert_eq!(bot.frames.iter().filter(|&&keys| keys == 1 << 0x6).count(), 2);
+        assert_eq!(bot.frames.last(), Some(&0));
+
+        // `frame` waits for the answer to `wait`
+        let asked = bot.frames.len();
+        bot.stream.write_all(b"wait 3\nframe\n").unwrap();
+        let answered = bot.reply();
+        let frame = bot.frames.len() - 1;
+        assert_eq!(answered, format!("frame {}", frame));
+        assert!(frame >= asked + 3);
+        assert_eq!(bot.reply(), answered);
+    }
+
+    #[test]
+    fn keys_are_let_go_of_on_disconnect_and_a_second_program_is_turned_away() {
+        let mut bot = Bot::connect();
+        bot.stream.write_all(b"press 1\n").unwrap();
+        assert_eq!(bot.reply(), "ok");
+
+        let second = TcpStream::connect((Ipv4Addr::LOCALHOST, bot.injector.port())).unwrap();
+        second.set_read_timeout(Some(Duration::from_millis(5))).unwrap();
+        bot.reader = BufReader::new(second);
+        assert_eq!(bot.reply(), "error: another program is connected");
+        assert_eq!(bot.frames.last(), Some(&(1 << 0x1)));
+
+        bot.stream.shutdown(std::net::Shutdown::Both).unwrap();
+        for _ in 0..200 {
+            bot.frame();
+            if bot.injector.client.is_none() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(bot.injector.client.is_none());
+        assert_eq!(bot.frames.last(), Some(&0));
+    }
+}
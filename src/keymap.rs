@@ -0,0 +1,831 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use sdl2::controller::Button;
+use sdl2::keyboard::{Keycode, Scancode};
+use crate::platform::Action;
+use toml_edit::{value, Array, Document, Item, Table, TableLike};
+
+// A built-in layout: keyboard key names, by SDL's names, for each CHIP-8 key
+//  from 0 to F. Physical layouts name the keys by where they are on a US
+//  keyboard, so the grid keeps its shape whatever layout the system uses;
+//  the others go by what is printed on the keys.
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    physical: bool,
+    keys: [&'static [&'static str]; 16],
+}
+
+pub const PRESETS: &[Preset] = &[
+    // The COSMAC VIP's keypad laid over the left of a QWERTY keyboard:
+    //  1 2 3 C / 4 5 6 D / 7 8 9 E / A 0 B F
+    Preset {
+        name: "qwerty",
+        description: "The 4x4 keys under 1234/QWER/ASDF/ZXCV on any layout (default)",
+        physical: true,
+        keys: [
+            &["X"], &["1"], &["2"], &["3"], &["Q"], &["W"], &["E"], &["A"],
+            &["S"], &["D"], &["Z"], &["C"], &["4"], &["R"], &["F"], &["V"],
+        ],
+    },
+    Preset {
+        name: "cosmac",
+        description: "Each key on the keyboard key it is labelled with, 0-9 and A-F",
+        physical: false,
+        keys: [
+            &["0"], &["1"], &["2"], &["3"], &["4"], &["5"], &["6"], &["7"],
+            &["8"], &["9"], &["A"], &["B"], &["C"], &["D"], &["E"], &["F"],
+        ],
+    },
+    // Octo adds the arrows on the keys most games steer with, and Space
+    Preset {
+        name: "octo",
+        description: "As Octo: qwerty, plus the arrows on 5/7/8/9 and Space on 6",
+        physical: true,
+        keys: [
+            &["X"], &["1"], &["2"], &["3"], &["Q"], &["W", "Up"], &["E", "Space"], &["A", "Left"],
+            &["S", "Down"], &["D", "Right"], &["Z"], &["C"], &["4"], &["R"], &["F"], &["V"],
+        ],
+    },
+    Preset {
+        name: "azerty",
+        description: "The keys labelled &\"'/AZER/QSDF/WXCV, wherever they are",
+        physical: false,
+        keys: [
+            &["X"], &["&"], &["\u{e9}"], &["\""], &["A"], &["Z"], &["E"], &["Q"],
+            &["S"], &["D"], &["W"], &["C"], &["'"], &["R"], &["F"], &["V"],
+        ],
+    },
+    Preset {
+        name: "left-handed",
+        description: "The grid on the right of the keyboard: 7890/UIOP/JKL;/M,./",
+        physical: true,
+        keys: [
+            &[","], &["7"], &["8"], &["9"], &["U"], &["I"], &["O"], &["J"],
+            &["K"], &["L"], &["M"], &["."], &["0"], &["P"], &[";"], &["/"],
+        ],
+    },
+];
+
+// Game controllers steer with the D-pad on the keys most games move with,
+//  and fire with A, whatever the keyboard layout
+const BUTTONS: [(Button, usize); 6] = [
+    (Button::DPadUp, 0x5),
+    (Button::DPadLeft, 0x7),
+    (Button::DPadDown, 0x8),
+    (Button::DPadRight, 0x9),
+    (Button::A, 0x6),
+    (Button::B, 0x4),
+];
+
+// The keys the mouse presses with --mouse, from [mouse] in keymap.toml:
+//
+//      [mouse]
+//      left = "4"
+//      right = "6"
+//      sensitivity = 12
+//
+//  Directions left out press nothing once the table is given.
+#[derive(Clone, Copy)]
+pub struct MouseKeys {
+    pub left: Option<usize>,
+    pub right: Option<usize>,
+    pub up: Option<usize>,
+    pub down: Option<usize>,
+    pub wheel_up: Option<usize>,
+    pub wheel_down: Option<usize>,
+    // Pixels of movement for each frame a key is held
+    pub sensitivity: u32,
+}
+
+impl Default for MouseKeys {
+    // The keys the D-pad presses
+    fn default() -> Self {
+        MouseKeys {
+            left: Some(0x7),
+            right: Some(0x9),
+            up: Some(0x5),
+            down: Some(0x8),
+            wheel_up: None,
+            wheel_down: None,
+            sensitivity: 8,
+        }
+    }
+}
+
+impl MouseKeys {
+    fn parse(table: &dyn TableLike) -> Result<MouseKeys, String> {
+        let mut mouse = MouseKeys {
+            left: None,
+            right: None,
+            up: None,
+            down: None,
+            wheel_up: None,
+            wheel_down: None,
+            ..MouseKeys::default()
+        };
+        for (name, item) in table.iter() {
+            if name == "sensitivity" {
+                mouse.sensitivity = item
+                    .as_integer()
+                    .filter(|&pixels| (1..=1000).contains(&pixels))
+                    .ok_or("[mouse] sensitivity must be 1 to 1000 pixels")? as u32;
+                continue;
+            }
+            let key = item
+                .as_str()
+                .and_then(|key| usize::from_str_radix(key, 16).ok())
+                .filter(|&key| key < 16)
+                .ok_or_else(|| format!("[mouse] {}: expected a CHIP-8 key, \"0\" to \"F\"", name))?;
+            let direction = match name {
+                "left" => &mut mouse.left,
+                "right" => &mut mouse.right,
+                "up" => &mut mouse.up,
+                "down" => &mut mouse.down,
+                "wheel_up" => &mut mouse.wheel_up,
+                "wheel_down" => &mut mouse.wheel_down,
+                _ => return Err(format!("[mouse] has no '{}'", name)),
+            };
+            *direction = Some(key);
+        }
+        Ok(mouse)
+    }
+}
+
+// How the left analog stick presses keys, from [stick] in keymap.toml:
+//
+//      [stick]
+//      up = "2"
+//      left = "4"
+//      right = "6"
+//      down = "8"
+//      deadzone = 0.3
+//      diagonals = true
+//
+//  Directions not given press what the D-pad does. Past the deadzone, a
+//  fraction of the stick's travel, the stick holds the key for its
+//  direction; pointing diagonally holds both keys with `diagonals`, or
+//  else the one for the nearer direction.
+#[derive(Clone, Copy)]
+pub struct Stick {
+    pub left: Option<usize>,
+    pub right: Option<usize>,
+    pub up: Option<usize>,
+    pub down: Option<usize>,
+    pub deadzone: f32,
+    pub diagonals: bool,
+}
+
+impl Default for Stick {
+    fn default() -> Self {
+        Stick {
+            left: None,
+            right: None,
+            up: None,
+            down: None,
+            deadzone: 0.25,
+            diagonals: false,
+        }
+    }
+}
+
+impl Stick {
+    fn parse(table: &dyn TableLike) -> Result<Stick, String> {
+        let mut stick = Stick::default();
+        for (name, item) in table.iter() {
+            match name {
+                "deadzone" => {
+                    stick.deadzone = item
+                        .as_float()
+                        .filter(|deadzone| (0.0..1.0).contains(deadzone))
+                        .ok_or("[stick] deadzone must be a fraction from 0.0 up to 1.0")? as f32;
+                }
+                "diagonals" => {
+                    stick.diagonals = item.as_bool().ok_or("[stick] diagonals must be true or false")?;
+                }
+                _ => {
+                    let key = item
+                        .as_str()
+                        .and_then(|key| usize::from_str_radix(key, 16).ok())
+                        .filter(|&key| key < 16)
+                        .ok_or_else(|| format!("[stick] {}: expected a CHIP-8 key, \"0\" to \"F\"", name))?;
+                    let direction = match name {
+                        "left" => &mut stick.left,
+                        "right" => &mut stick.right,
+                        "up" => &mut stick.up,
+                        "down" => &mut stick.down,
+                        _ => return Err(format!("[stick] has no '{}'", name)),
+                    };
+                    *direction = Some(key);
+                }
+            }
+        }
+        Ok(stick)
+    }
+}
+
+// Keys that fire over and over while held, from [turbo] in keymap.toml:
+//
+//      [turbo]
+//      keys = ["5", "A"]
+//      on = 2
+//      off = 4
+//
+//  Held, they are pressed for `on` frames, let go for `off` frames, and so
+//  on.
+#[derive(Clone, Copy)]
+pub struct Turbo {
+    pub keys: [bool; 16],
+    pub on: u32,
+    pub off: u32,
+}
+
+impl Default for Turbo {
+    // No turbo keys; pressed and released every 3 frames, 10 times a second
+    fn default() -> Self {
+        Turbo {
+            keys: [false; 16],
+            on: 3,
+            off: 3,
+        }
+    }
+}
+
+impl Turbo {
+    // Lets go of the turbo keys held in `keypad` that are in the off part of
+    //  their pulse, counting in `frames` how long each has been held
+    pub fn pulse(&self, frames: &mut [u32; 16], keypad: &mut [u8; 16]) {
+        for (key, frames) in frames.iter_mut().enumerate() {
+            if !self.keys[key] || keypad[key] == 0 {
+                *frames = 0;
+                continue;
+            }
+            if *frames % (self.on + self.off) >= self.on {
+                keypad[key] = 0;
+            }
+            *frames += 1;
+        }
+    }
+
+    fn parse(table: &dyn TableLike) -> Result<Turbo, String> {
+        let mut turbo = Turbo::default();
+        for (name, item) in table.iter() {
+            match name {
+                "keys" => {
+                    let keys = item.as_array().ok_or("[turbo] keys must be a list of CHIP-8 keys")?;
+                    for key in keys.iter() {
+                        let index = key
+                            .as_str()
+                            .and_then(|key| usize::from_str_radix(key, 16).ok())
+                            .filter(|&index| index < 16)
+                            .ok_or("[turbo] keys must be CHIP-8 keys, \"0\" to \"F\"")?;
+                        turbo.keys[index] = true;
+                    }
+                }
+                "on" | "off" => {
+                    let frames = item
+                        .as_integer()
+                        .filter(|&frames| (1..=60).contains(&frames))
+                        .ok_or_else(|| format!("[turbo] {} must be 1 to 60 frames", name))? as u32;
+                    if name == "on" {
+                        turbo.on = frames;
+                    } else {
+                        turbo.off = frames;
+                    }
+                }
+                _ => return Err(format!("[turbo] has no '{}'", name)),
+            }
+        }
+        Ok(turbo)
+    }
+}
+
+// The emulator's hotkeys by the names [hotkeys] in keymap.toml knows them
+//  by, with their usual keys. Alt+Enter also toggles fullscreen, whatever
+//  this says.
+const HOTKEYS: &[(&str, Action, &[Keycode])] = &[
+    ("quit", Action::Quit, &[Keycode::Escape]),
+    ("fullscreen", Action::ToggleFullscreen, &[Keycode::F11]),
+    ("smoothing", Action::ToggleSmoothing, &[Keycode::F1]),
+    ("palette", Action::NextPalette, &[Keycode::F2]),
+    ("record-macro", Action::RecordMacro, &[Keycode::F3]),
+    ("play-macro", Action::PlayMacro, &[Keycode::F4]),
+    ("save-state", Action::SaveState, &[Keycode::F5]),
+    ("next-slot", Action::NextSlot, &[Keycode::F6]),
+    ("load-state", Action::LoadState, &[Keycode::F7]),
+    ("rerecord", Action::ToggleRerecord, &[Keycode::F8]),
+    ("save-replay", Action::SaveReplay, &[Keycode::F9]),
+    ("gif", Action::ToggleGif, &[Keycode::F10]),
+    ("screenshot", Action::Screenshot, &[Keycode::F12]),
+    ("rewind", Action::RewindStart, &[Keycode::Backspace]),
+    ("pause", Action::TogglePause, &[Keycode::P]),
+    ("frame-advance", Action::FrameAdvance, &[Keycode::Period]),
+    ("reset", Action::Reset, &[Keycode::Home]),
+    ("soft-reset", Action::SoftReset, &[Keycode::End]),
+    ("open-rom", Action::OpenRom, &[]),
+    ("reload", Action::ReloadRom, &[]),
+    ("last-rom", Action::OpenRecent(0), &[Keycode::O]),
+    ("next-rom", Action::NextRom, &[Keycode::N]),
+    ("launcher", Action::ShowLauncher, &[Keycode::L]),
+    ("always-on-top", Action::ToggleAlwaysOnTop, &[Keycode::T]),
+    ("magnifier", Action::ToggleMagnifier, &[Keycode::Tab]),
+    ("sound-timer", Action::ToggleSoundIndicator, &[Keycode::I]),
+    ("remap", Action::RemapKeys, &[Keycode::K]),
+    ("waveform", Action::NextWaveform, &[Keycode::B]),
+    ("mute", Action::ToggleMute, &[Keycode::M]),
+    ("volume-down", Action::VolumeDown, &[Keycode::Minus, Keycode::KpMinus]),
+    ("volume-up", Action::VolumeUp, &[Keycode::Equals, Keycode::KpPlus]),
+];
+
+pub fn find(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|preset| preset.name == name)
+}
+
+// Which keyboard keys press which CHIP-8 keys: a preset, with any changes
+//  from keymap.toml in the user's config folder laid over it. Its [keys]
+//  table names SDL keys by label for any of the sixteen, one key or a list
+//  of them each, and [scancodes] names them by position instead:
+//
+//      [keys]
+//      A = "Y"
+//
+//      [scancodes]
+//      5 = ["W", "Up"]
+//
+//      [buttons]
+//      6 = ["a", "x"]
+//
+//  CHIP-8 keys left out keep the preset's keys. [buttons] does the same for
+//  game controllers, by SDL's button names. Two-player games split the
+//  keypad between players, so [buttons2] can give the second controller
+//  its own half; it has only the buttons listed there.
+pub struct Keymap {
+    keys: HashMap<Keycode, usize>,
+    scancodes: HashMap<Scancode, usize>,
+    buttons: HashMap<Button, usize>,
+    // The second controller's, when not the same as the first's
+    second_buttons: Option<HashMap<Button, usize>>,
+    pub mouse: MouseKeys,
+    pub turbo: Turbo,
+    pub stick: Stick,
+    hotkeys: HashMap<Keycode, Action>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::preset(&PRESETS[0])
+    }
+}
+
+impl Keymap {
+    // A key's label counts before its position
+    pub fn chip8_key(&self, key: Option<Keycode>, scancode: Option<Scancode>) -> Option<usize> {
+        key.and_then(|key| self.keys.get(&key))
+            .or_else(|| scancode.and_then(|scancode| self.scancodes.get(&scancode)))
+            .copied()
+    }
+
+    // The keys a player's left stick holds at `x`, `y`, as a bit mask
+    pub fn stick_keys(&self, player: usize, x: i16, y: i16) -> u16 {
+        let deadzone = (self.stick.deadzone * i16::MAX as f32) as i32;
+        let (x, y) = (x as i32, y as i32);
+        let mut horizontal = x.abs() > deadzone;
+        let mut vertical = y.abs() > deadzone;
+        if horizontal && vertical && !self.stick.diagonals {
+            horizontal = x.abs() >= y.abs();
+            vertical = !horizontal;
+        }
+
+        let key = |direction: Option<usize>, button| direction.or_else(|| self.chip8_button(player, button));
+        let held = [
+            (horizontal && x < 0, key(self.stick.left, Button::DPadLeft)),
+            (horizontal && x > 0, key(self.stick.right, Button::DPadRight)),
+            (vertical && y < 0, key(self.stick.up, Button::DPadUp)),
+            (vertical && y > 0, key(self.stick.down, Button::DPadDown)),
+        ];
+        held.into_iter()
+            .filter_map(|(on, key)| key.filter(|_| on))
+            .fold(0, |bits, key| bits | 1 << key)
+    }
+
+    pub fn hotkey(&self, key: Keycode) -> Option<Action> {
+        self.hotkeys.get(&key).copied()
+    }
+
+    // Hotkeys on keys that also play the keypad, described for a warning.
+    //  The keypad gets those keys, apart from the quit key.
+    pub fn collisions(&self) -> Vec<String> {
+        let mut collisions: Vec<String> = self
+            .hotkeys
+            .iter()
+            .filter_map(|(&key, &action)| {
+                let index = self.chip8_key(Some(key), Scancode::from_keycode(key))?;
+                let (name, ..) = HOTKEYS.iter().find(|&&(_, hotkey, _)| hotkey == action)?;
+                Some(format!("{} plays keypad key {:X} instead of {}", key.name(), index, name))
+            })
+            .collect();
+        collisions.sort();
+        collisions
+    }
+
+    // `player` counts from 0, in the order the controllers were connected
+    pub fn chip8_button(&self, player: usize, button: Button) -> Option<usize> {
+        let buttons = match &self.second_buttons {
+            Some(second) if player == 1 => second,
+            _ => &self.buttons,
+        };
+        buttons.get(&button).copied()
+    }
+
+    pub fn preset(preset: &Preset) -> Keymap {
+        let mut keymap = Keymap {
+            keys: HashMap::new(),
+            scancodes: HashMap::new(),
+            buttons: BUTTONS.into_iter().collect(),
+            second_buttons: None,
+            mouse: MouseKeys::default(),
+            turbo: Turbo::default(),
+            stick: Stick::default(),
+            hotkeys: HOTKEYS
+                .iter()
+                .flat_map(|&(_, action, keys)| keys.iter().map(move |&key| (key, action)))
+                .collect(),
+        };
+        for (index, names) in preset.keys.iter().enumerate() {
+            for name in names.iter() {
+                if preset.physical {
+                    keymap.scancodes.extend(Scancode::from_name(name).map(|scancode| (scancode, index)));
+                } else {
+                    keymap.keys.extend(Keycode::from_name(name).map(|key| (key, index)));
+                }
+            }
+        }
+        keymap
+    }
+
+    // Play each CHIP-8 key with the one keyboard key given for it, in
+    //  place of the keys it had
+    pub fn remap(&mut self, scancodes: impl IntoIterator<Item = (usize, Scancode)>) {
+        self.keys.clear();
+        self.scancodes = scancodes.into_iter().map(|(index, scancode)| (scancode, index)).collect();
+    }
+
+    // Give the named hotkeys the keys listed, one or a list; an empty list
+    //  leaves a hotkey without a key
+    fn apply_hotkeys(&mut self, table: &dyn TableLike) -> Result<(), String> {
+        for (name, item) in table.iter() {
+            let (_, action, _) = HOTKEYS
+                .iter()
+                .find(|&&(hotkey, ..)| hotkey == name)
+                .ok_or_else(|| format!("[hotkeys] has no '{}'", name))?;
+            let names: Vec<&str> = match item.as_str() {
+                Some(key) => vec![key],
+                None => item
+                    .as_array()
+                    .ok_or_else(|| format!("[hotkeys] {}: expected a key name or a list of them", name))?
+                    .iter()
+                    .map(|key| key.as_str().ok_or_else(|| format!("[hotkeys] {}: names must be strings", name)))
+                    .collect::<Result<_, _>>()?,
+            };
+
+            self.hotkeys.retain(|_, hotkey| hotkey != action);
+            for key_name in names {
+                let key = Keycode::from_name(key_name)
+                    .ok_or_else(|| format!("[hotkeys] {}: unknown keyboard key '{}'", name, key_name))?;
+                self.hotkeys.insert(key, *action);
+            }
+        }
+        Ok(())
+    }
+
+    // Change the keys the file names, leaving the rest
+    fn apply(&mut self, text: &str) -> Result<(), String> {
+        let document = text.parse::<Document>().map_err(|e| e.to_string())?;
+        if let Some(item) = document.get("mouse") {
+            self.mouse = MouseKeys::parse(item.as_table_like().ok_or("[mouse] is not a table")?)?;
+        }
+        if let Some(item) = document.get("hotkeys") {
+            self.apply_hotkeys(item.as_table_like().ok_or("[hotkeys] is not a table")?)?;
+        }
+        if let Some(item) = document.get("stick") {
+            self.stick = Stick::parse(item.as_table_like().ok_or("[stick] is not a table")?)?;
+        }
+        if let Some(item) = document.get("turbo") {
+            self.turbo = Turbo::parse(item.as_table_like().ok_or("[turbo] is not a table")?)?;
+        }
+        for table_name in ["keys", "scancodes", "buttons", "buttons2"] {
+            let Some(item) = document.get(table_name) else {
+                continue;
+            };
+            let table = item
+                .as_table_like()
+                .ok_or_else(|| format!("[{}] is not a table", table_name))?;
+
+            for (index, names) in entries(table)? {
+                let unknown = |name: &str| format!("[{}] {:X}: unknown key or button '{}'", table_name, index, name);
+                if table_name.starts_with("buttons") {
+                    let buttons = match table_name {
+                        "buttons2" => self.second_buttons.get_or_insert_with(HashMap::new),
+                        _ => &mut self.buttons,
+                    };
+                    buttons.retain(|_, &mut mapped| mapped != index);
+                    for name in names {
+                        buttons.insert(Button::from_string(name).ok_or_else(|| unknown(name))?, index);
+                    }
+                    continue;
+                }
+                self.keys.retain(|_, &mut mapped| mapped != index);
+                self.scancodes.retain(|_, &mut mapped| mapped != index);
+                for name in names {
+                    if table_name == "scancodes" {
+                        self.scancodes.insert(Scancode::from_name(name).ok_or_else(|| unknown(name))?, index);
+                    } else {
+                        self.keys.insert(Keycode::from_name(name).ok_or_else(|| unknown(name))?, index);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// A table's CHIP-8 keys and the names given for each, one or a list
+fn entries(table: &dyn TableLike) -> Result<Vec<(usize, Vec<&str>)>, String> {
+    table
+        .iter()
+        .map(|(key, item)| {
+            let index = usize::from_str_radix(key, 16)
+                .ok()
+                .filter(|&index| index < 16)
+                .ok_or_else(|| format!("'{}' is not a CHIP-8 key (0-F)", key))?;
+            let names = match item {
+                Item::Value(value) if value.is_str() => vec![value.as_str().unwrap_or_default()],
+                Item::Value(value) if value.is_array() => value
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|value| value.as_str().ok_or_else(|| format!("key {}: names must be strings", key)))
+                    .collect::<Result<_, _>>()?,
+                _ => return Err(format!("key {}: expected a name or a list of them", key)),
+            };
+            Ok((index, names))
+        })
+        .collect()
+}
+
+// ~/.config/chip8/keymap.toml, or wherever the system keeps settings
+pub fn path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("chip8").join("keymap.toml"))
+}
+
+// Write the keyboard keys to keymap.toml by position, keeping the rest of
+//  the file, such as its controller buttons, as it was
+pub fn save(keymap: &Keymap) -> io::Result<PathBuf> {
+    let path = path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config folder"))?;
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    let text = with_scancodes(keymap, &text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, text)?;
+    Ok(path)
+}
+
+// The file's text with [scancodes] holding the keymap's keyboard keys, in
+//  place of [keys] and any [scancodes] it had
+fn with_scancodes(keymap: &Keymap, text: &str) -> Result<String, toml_edit::TomlError> {
+    let mut document = text.parse::<Document>()?;
+    let mut table = Table::new();
+    for index in 0..16 {
+        let mut names: Vec<&str> = keymap
+            .scancodes
+            .iter()
+            .filter(|&(_, &mapped)| mapped == index)
+            .map(|(scancode, _)| scancode.name())
+            .collect();
+        names.sort_unstable();
+        match names[..] {
+            [] => {}
+            [name] => {
+                table.insert(&format!("{:X}", index), value(name));
+            }
+            _ => {
+                table.insert(&format!("{:X}", index), value(Array::from_iter(names)));
+            }
+        }
+    }
+    document.remove("keys");
+    document.insert("scancodes", Item::Table(table));
+    Ok(document.to_string())
+}
+
+// The preset alone when there is no keymap file
+pub fn load(preset: &Preset) -> io::Result<Keymap> {
+    let mut keymap = Keymap::preset(preset);
+    if let Some(path) = path().filter(|path| path.exists()) {
+        keymap
+            .apply(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    }
+    Ok(keymap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edited(preset: &str, text: &str) -> Result<Keymap, String> {
+        let mut keymap = Keymap::preset(find(preset).unwrap());
+        keymap.apply(text)?;
+        Ok(keymap)
+    }
+
+    #[test]
+    fn every_preset_covers_the_whole_keypad() {
+        for preset in PRESETS {
+            let keymap = Keymap::preset(preset);
+            let mut covered: Vec<usize> = keymap.keys.values().chain(keymap.scancodes.values()).copied().collect();
+            covered.sort();
+            covered.dedup();
+            assert_eq!(covered, (0..16).collect::<Vec<_>>(), "{}", preset.name);
+        }
+        assert!(find("dvorak").is_none());
+    }
+
+    #[test]
+    fn physical_layouts_go_by_position_and_labels_come_first() {
+        let qwerty = Keymap::default();
+        // Z on a German keyboard sits where Y is on a US one
+        assert_eq!(qwerty.chip8_key(Some(Keycode::Z), Some(Scancode::Y)), None);
+        assert_eq!(qwerty.chip8_key(Some(Keycode::Y), Some(Scancode::Z)), Some(0xA));
+        assert_eq!(qwerty.chip8_key(None, Some(Scancode::X)), Some(0x0));
+
+        let cosmac = Keymap::preset(find("cosmac").unwrap());
+        assert_eq!(cosmac.chip8_key(Some(Keycode::A), Some(Scancode::Q)), Some(0xA));
+        assert_eq!(cosmac.chip8_key(None, Some(Scancode::A)), None);
+
+        let keymap = edited("qwerty", "[keys]\nA = \"X\"").unwrap();
+        assert_eq!(keymap.chip8_key(Some(Keycode::X), Some(Scancode::X)), Some(0xA));
+    }
+
+    #[test]
+    fn the_file_changes_only_the_keys_it_names() {
+        let keymap = edited("cosmac", "[keys]\na = \"Y\"\n[scancodes]\n5 = [\"W\", \"Up\"]").unwrap();
+        assert_eq!(keymap.chip8_key(None, Some(Scancode::W)), Some(0x5));
+        assert_eq!(keymap.chip8_key(Some(Keycode::Up), Some(Scancode::Up)), Some(0x5));
+        assert_eq!(keymap.chip8_key(Some(Keycode::Num5), Some(Scancode::Num5)), None);
+        assert_eq!(keymap.chip8_key(Some(Keycode::Y), None), Some(0xA));
+        assert_eq!(keymap.chip8_key(Some(Keycode::A), None), None);
+        assert_eq!(keymap.chip8_key(Some(Keycode::F), None), Some(0xF));
+        assert_eq!(edited("octo", "").unwrap().chip8_key(None, Some(Scancode::Space)), Some(0x6));
+    }
+
+    #[test]
+    fn controllers_steer_with_the_d_pad_unless_told() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.chip8_button(0, Button::DPadUp), Some(0x5));
+        assert_eq!(keymap.chip8_button(0, Button::A), Some(0x6));
+        assert_eq!(keymap.chip8_button(0, Button::Start), None);
+
+        let keymap = edited("cosmac", "[buttons]\n6 = [\"x\", \"start\"]").unwrap();
+        assert_eq!(keymap.chip8_button(0, Button::A), None);
+        assert_eq!(keymap.chip8_button(0, Button::X), Some(0x6));
+        assert_eq!(keymap.chip8_button(0, Button::Start), Some(0x6));
+        assert_eq!(keymap.chip8_button(0, Button::DPadLeft), Some(0x7));
+    }
+
+    #[test]
+    fn a_second_controller_can_have_its_own_buttons() {
+        let same = Keymap::default();
+        assert_eq!(same.chip8_button(1, Button::DPadUp), Some(0x5));
+
+        let split = edited("qwerty", "[buttons]\n1 = \"dpup\"\n[buttons2]\nC = \"dpup\"").unwrap();
+        assert_eq!(split.chip8_button(0, Button::DPadUp), Some(0x1));
+        assert_eq!(split.chip8_button(1, Button::DPadUp), Some(0xC));
+        // Only what [buttons2] lists
+        assert_eq!(split.chip8_button(1, Button::A), None);
+        // Players past the second share the first's buttons
+        assert_eq!(split.chip8_button(2, Button::DPadUp), Some(0x1));
+    }
+
+    #[test]
+    fn remapped_keys_are_saved_by_position_beside_the_buttons() {
+        let mut keymap = Keymap::default();
+        keymap.remap((0..16).zip([Scancode::Num0, Scancode::Num1].into_iter().cycle()));
+        assert_eq!(keymap.chip8_key(Some(Keycode::X), Some(Scancode::X)), None);
+        assert_eq!(keymap.chip8_key(None, Some(Scancode::Num1)), Some(0xF));
+
+        let mut keymap = Keymap::default();
+        let scancodes = [Scancode::Num7, Scancode::Num8, Scancode::Num9];
+        keymap.remap(scancodes.into_iter().enumerate());
+        let text = with_scancodes(&keymap, "[keys]\n1 = \"Y\"\n\n[buttons]\n6 = \"x\"\n").unwrap();
+        assert!(!text.contains("[keys]"));
+        assert!(text.contains("[buttons]\n6 = \"x\""));
+        assert!(text.contains("[scancodes]\n0 = \"7\"\n1 = \"8\"\n2 = \"9\"\n"));
+
+        let saved = edited("cosmac", &text).unwrap();
+        assert_eq!(saved.chip8_key(Some(Keycode::Num1), Some(Scancode::Num8)), Some(0x1));
+        assert_eq!(saved.chip8_button(0, Button::X), Some(0x6));
+    }
+
+    #[test]
+    fn the_mouse_presses_only_the_keys_given_once_set() {
+        let mouse = Keymap::default().mouse;
+        assert_eq!((mouse.left, mouse.up, mouse.wheel_up, mouse.sensitivity), (Some(0x7), Some(0x5), None, 8));
+
+        let mouse = edited("qwerty", "[mouse]\nleft = \"4\"\nright = \"c\"\nsensitivity = 12").unwrap().mouse;
+        assert_eq!((mouse.left, mouse.right, mouse.up, mouse.sensitivity), (Some(0x4), Some(0xC), None, 12));
+
+        let error = |text| edited("qwerty", text).err().unwrap();
+        assert_eq!(error("[mouse]\nsensitivity = 0"), "[mouse] sensitivity must be 1 to 1000 pixels");
+        assert_eq!(error("[mouse]\nleft = \"G\""), "[mouse] left: expected a CHIP-8 key, \"0\" to \"F\"");
+        assert_eq!(error("[mouse]\nsideways = \"1\""), "[mouse] has no 'sideways'");
+    }
+
+    #[test]
+    fn turbo_keys_pulse_while_held() {
+        let turbo = edited("qwerty", "[turbo]\nkeys = [\"5\"]\non = 2\noff = 1").unwrap().turbo;
+        let mut frames = [0; 16];
+        let mut seen = Vec::new();
+        for _ in 0..6 {
+            let mut keypad = [0; 16];
+            keypad[0x5] = 1;
+            keypad[0x6] = 1;
+            turbo.pulse(&mut frames, &mut keypad);
+            assert_eq!(keypad[0x6], 1);
+            seen.push(keypad[0x5]);
+        }
+        assert_eq!(seen, [1, 1, 0, 1, 1, 0]);
+
+        // Letting go starts the pulse over
+        turbo.pulse(&mut frames, &mut [0; 16]);
+        assert_eq!(frames[0x5], 0);
+
+        let error = |text| edited("qwerty", text).err().unwrap();
+        assert_eq!(error("[turbo]\non = 0"), "[turbo] on must be 1 to 60 frames");
+        assert_eq!(error("[turbo]\nkeys = \"5\""), "[turbo] keys must be a list of CHIP-8 keys");
+        assert_eq!(error("[turbo]\nkeys = [\"G\"]"), "[turbo] keys must be CHIP-8 keys, \"0\" to \"F\"");
+    }
+
+    #[test]
+    fn hotkeys_move_and_clashes_with_the_keypad_are_named() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.hotkey(Keycode::P), Some(Action::TogglePause));
+        assert_eq!(keymap.hotkey(Keycode::O), Some(Action::OpenRecent(0)));
+        assert_eq!(keymap.hotkey(Keycode::N), Some(Action::NextRom));
+        assert_eq!(keymap.hotkey(Keycode::L), Some(Action::ShowLauncher));
+        assert_eq!(keymap.hotkey(Keycode::X), None);
+        assert!(keymap.collisions().is_empty());
+        let cosmac = Keymap::preset(find("cosmac").unwrap());
+        assert_eq!(cosmac.collisions(), ["B plays keypad key B instead of waveform"]);
+
+        let keymap = edited("qwerty", "[hotkeys]\npause = \"Space\"\nopen-rom = [\"O\", \"F\"]\nmute = []").unwrap();
+        assert_eq!(keymap.hotkey(Keycode::P), None);
+        assert_eq!(keymap.hotkey(Keycode::Space), Some(Action::TogglePause));
+        assert_eq!(keymap.hotkey(Keycode::O), Some(Action::OpenRom));
+        assert_eq!(keymap.hotkey(Keycode::M), None);
+        assert_eq!(keymap.collisions(), ["F plays keypad key E instead of open-rom"]);
+
+        let error = |text| edited("qwerty", text).err().unwrap();
+        assert_eq!(error("[hotkeys]\nfly = \"F\""), "[hotkeys] has no 'fly'");
+        assert_eq!(error("[hotkeys]\npause = \"Nope\""), "[hotkeys] pause: unknown keyboard key 'Nope'");
+        assert_eq!(error("[hotkeys]\npause = 1"), "[hotkeys] pause: expected a key name or a list of them");
+    }
+
+    #[test]
+    fn the_stick_holds_the_keys_it_points_to_past_the_deadzone() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.stick_keys(0, -8000, 0), 0);
+        assert_eq!(keymap.stick_keys(0, -20000, 0), 1 << 0x7);
+        // The nearer direction wins a diagonal
+        assert_eq!(keymap.stick_keys(0, 20000, 30000), 1 << 0x8);
+
+        let keymap = edited("qwerty", "[stick]\nup = \"2\"\ndeadzone = 0.5\ndiagonals = true").unwrap();
+        assert_eq!(keymap.stick_keys(0, -20000, -20000), 1 << 0x7 | 1 << 0x2);
+        assert_eq!(keymap.stick_keys(0, 0, -16000), 0);
+
+        let error = |text| edited("qwerty", text).err().unwrap();
+        assert_eq!(error("[stick]\ndeadzone = 1.0"), "[stick] deadzone must be a fraction from 0.0 up to 1.0");
+        assert_eq!(error("[stick]\ndiagonals = 1"), "[stick] diagonals must be true or false");
+        assert_eq!(error("[stick]\nsideways = \"1\""), "[stick] has no 'sideways'");
+    }
+
+    #[test]
+    fn mistakes_name_the_key() {
+        let error = |text| edited("qwerty", text).err().unwrap();
+        assert_eq!(error("[keys]\nG = \"X\""), "'G' is not a CHIP-8 key (0-F)");
+        assert_eq!(error("[keys]\n1 = \"Nope\""), "[keys] 1: unknown key or button 'Nope'");
+        assert_eq!(error("[scancodes]\nc = \"\u{e9}\""), "[scancodes] C: unknown key or button '\u{e9}'");
+        assert_eq!(error("[buttons]\n1 = \"z\""), "[buttons] 1: unknown key or button 'z'");
+        assert_eq!(error("[keys]\n1 = 5"), "key 1: expected a name or a list of them");
+        assert_eq!(error("keys = 1"), "[keys] is not a table");
+    }
+}
@@ -0,0 +1,183 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, WindowCanvas};
+
+use crate::osd::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+
+// The COSMAC VIP's hex keypad as it is laid out on the real thing
+const LAYOUT: [[usize; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+const PANEL_COLOR: Color = Color::RGB(0x18, 0x18, 0x18);
+const KEY_COLOR: Color = Color::RGB(0x44, 0x44, 0x44);
+const PRESSED_COLOR: Color = Color::RGB(0xB0, 0xB0, 0xB0);
+
+// A clickable keypad in a square panel beside the display. On a touch
+//  screen every finger presses its own key, so chords work.
+pub struct Keypad {
+    area: Rect,
+    // The key the mouse button is holding down
+    clicked: Option<usize>,
+    // The keys fingers are holding down, by finger
+    touches: Vec<(i64, usize)>,
+}
+
+impl Keypad {
+    pub fn new(area: Rect) -> Keypad {
+        Keypad {
+            area,
+            clicked: None,
+            touches: Vec::new(),
+        }
+    }
+
+    pub fn area(&self) -> Rect {
+        self.area
+    }
+
+    fn cell(&self) -> i32 {
+        self.area.height().min(self.area.width()) as i32 / 4
+    }
+
+    // The screen rectangle of a key, with a small gap around it
+    fn key_rect(&self, row: usize, column: usize) -> Rect {
+        let cell = self.cell();
+        let gap = (cell / 12).max(1);
+        let left = self.area.x() + (self.area.width() as i32 - cell * 4) / 2;
+        let top = self.area.y() + (self.area.height() as i32 - cell * 4) / 2;
+        Rect::new(
+            left + column as i32 * cell + gap,
+            top + row as i32 * cell + gap,
+            (cell - gap * 2).max(1) as u32,
+            (cell - gap * 2).max(1) as u32,
+        )
+    }
+
+    fn key_at(&self, x: i32, y: i32) -> Option<usize> {
+        (0..4)
+            .flat_map(|row| (0..4).map(move |column| (row, column)))
+            .find(|&(row, column)| self.key_rect(row, column).contains_point((x, y)))
+            .map(|(row, column)| LAYOUT[row][column])
+    }
+
+    // A mouse button went down at `x`, `y`; returns whether it hit a key
+    pub fn press(&mut self, x: i32, y: i32, keys: &mut [u8; 16]) -> bool {
+        self.release(keys);
+        self.clicked = self.key_at(x, y);
+        if let Some(key) = self.clicked {
+            keys[key] = 1;
+        }
+        self.clicked.is_some()
+    }
+
+    // Dragging onto another key moves the press there
+    pub fn drag(&mut self, x: i32, y: i32, keys: &mut [u8; 16]) {
+        if self.clicked.is_some() && self.key_at(x, y) != self.clicked {
+            self.press(x, y, keys);
+        }
+    }
+
+    pub fn release(&mut self, keys: &mut [u8; 16]) {
+        if let Some(key) = self.clicked.take() {
+            self.lift(key, keys);
+        }
+    }
+
+    // A key comes up once nothing is holding it any more
+    fn lift(&self, key: usize, keys: &mut [u8; 16]) {
+        if self.clicked != Some(key) && !self.touches.iter().any(|&(_, held)| held == key) {
+            keys[key] = 0;
+        }
+    }
+
+    // A finger touched or slid to `x`, `y`; returns whether it is on a key
+    pub fn touch(&mut self, finger: i64, x: i32, y: i32, keys: &mut [u8; 16]) -> bool {
+        let key = self.key_at(x, y);
+        let held = self.touches.iter().position(|&(id, _)| id == finger);
+        if held.map(|index| self.touches[index].1) == key {
+            return key.is_some();
+        }
+        self.untouch(finger, keys);
+        if let Some(key) = key {
+            self.touches.push((finger, key));
+            keys[key] = 1;
+        }
+        key.is_some()
+    }
+
+    pub fn untouch(&mut self, finger: i64, keys: &mut [u8; 16]) {
+        if let Some(index) = self.touches.iter().position(|&(id, _)| id == finger) {
+            let (_, key) = self.touches.remove(index);
+            self.lift(key, keys);
+        }
+    }
+
+    // Keys held on the keyboard light up as well as clicked ones
+    pub fn draw(&self, canvas: &mut WindowCanvas, keys: &[u8; 16]) {
+        canvas.set_blend_mode(BlendMode::None);
+        canvas.set_draw_color(PANEL_COLOR);
+        let _ = canvas.fill_rect(self.area);
+
+        let size = (self.cell() / 24).max(1);
+        for (row, line) in LAYOUT.iter().enumerate() {
+            for (column, &key) in line.iter().enumerate() {
+                let rect = self.key_rect(row, column);
+                let pressed = keys[key] != 0;
+                canvas.set_draw_color(if pressed { PRESSED_COLOR } else { KEY_COLOR });
+                let _ = canvas.fill_rect(rect);
+
+                let x = rect.center().x() - GLYPH_WIDTH * size / 2;
+                let y = rect.center().y() - GLYPH_HEIGHT * size / 2;
+                canvas.set_draw_color(if pressed { PANEL_COLOR } else { PRESSED_COLOR });
+                let _ = canvas.fill_rects(&osd::text_rects(&format!("{:X}", key), x, y, size));
+            }
+        }
+        canvas.set_draw_color(Color::RGB(0, 0, 0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clicks_hold_the_key_under_the_pointer() {
+        let mut keypad = Keypad::new(Rect::new(640, 0, 320, 320));
+        let mut keys = [0; 16];
+        // The middle of the top-left key, then of the bottom-right one
+        assert!(keypad.press(680, 40, &mut keys));
+        assert_eq!(keys[0x1], 1);
+        keypad.drag(920, 280, &mut keys);
+        assert_eq!((keys[0x1], keys[0xF]), (0, 1));
+        keypad.release(&mut keys);
+        assert_eq!(keys, [0; 16]);
+
+        // The display beside the panel isn't a key
+        assert!(!keypad.press(100, 100, &mut keys));
+        assert_eq!(keys, [0; 16]);
+    }
+
+    #[test]
+    fn each_finger_holds_its_own_key() {
+        let mut keypad = Keypad::new(Rect::new(640, 0, 320, 320));
+        let mut keys = [0; 16];
+        assert!(keypad.touch(1, 680, 40, &mut keys));
+        assert!(keypad.touch(2, 920, 280, &mut keys));
+        assert_eq!((keys[0x1], keys[0xF]), (1, 1));
+
+        // A finger sliding off its key lets it go; the other stays down
+        assert!(!keypad.touch(1, 100, 100, &mut keys));
+        assert_eq!((keys[0x1], keys[0xF]), (0, 1));
+
+        // A key held by the mouse and a finger comes up when both let go
+        keypad.press(920, 280, &mut keys);
+        keypad.untouch(2, &mut keys);
+        assert_eq!(keys[0xF], 1);
+        keypad.release(&mut keys);
+        assert_eq!(keys, [0; 16]);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::timing;
+
+// Measures how long key presses take to reach the program: from the moment
+//  SDL saw a key go down to the end of the frame in which the program first
+//  found it pressed with Ex9E, ExA1 or Fx0A. Times are SDL's milliseconds.
+pub struct LatencyProbe {
+    // When each key went down and in which frame, until the program reads it
+    pending: [Option<(u32, u64)>; 16],
+    // Milliseconds and frames for each press the program read
+    samples: Vec<(u32, u64)>,
+    // Presses let go of before the program looked at them
+    missed: u64,
+}
+
+impl LatencyProbe {
+    pub fn new() -> LatencyProbe {
+        LatencyProbe {
+            pending: [None; 16],
+            samples: Vec::new(),
+            missed: 0,
+        }
+    }
+
+    pub fn pressed(&mut self, key: usize, at: u32, frame: u64) {
+        if self.pending[key].replace((at, frame)).is_some() {
+            self.missed += 1;
+        }
+    }
+
+    // Called at the end of each frame with the keys the program read as
+    //  pressed in it, as a bit mask
+    pub fn observed(&mut self, reads: u16, now: u32, frame: u64) {
+        for (key, pending) in self.pending.iter_mut().enumerate() {
+            if reads >> key & 1 == 0 {
+                continue;
+            }
+            if let Some((at, pressed_frame)) = pending.take() {
+                self.samples.push((now.saturating_sub(at), frame - pressed_frame));
+            }
+        }
+    }
+
+    pub fn report(&self) -> String {
+        if self.samples.is_empty() {
+            return format!("Input latency: no key presses were read ({} missed)", self.missed);
+        }
+        let mut millis: Vec<u32> = self.samples.iter().map(|&(ms, _)| ms).collect();
+        millis.sort_unstable();
+        let percentile = |p: usize| millis[(millis.len() - 1) * p / 100];
+        let mean = millis.iter().map(|&ms| ms as f64).sum::<f64>() / millis.len() as f64;
+        let frames = self.samples.iter().map(|&(_, frames)| frames as f64).sum::<f64>() / self.samples.len() as f64;
+
+        format!(
+            "Input latency over {} presses ({} missed): mean {:.1} ms ({:.2} frames of {:.1} ms), \
+             median {} ms, 95th percentile {} ms, max {} ms",
+            self.samples.len(),
+            self.missed,
+            mean,
+            frames,
+            1000.0 / timing::FRAMES_PER_SECOND as f64,
+            percentile(50),
+            percentile(95),
+            millis[millis.len() - 1]
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presses_are_timed_until_the_program_reads_them() {
+        let mut probe = LatencyProbe::new();
+        assert_eq!(probe.report(), "Input latency: no key presses were read (0 missed)");
+
+        probe.pressed(0x5, 100, 10);
+        // Reads of other keys don't count
+        probe.observed(1 << 0x4, 110, 10);
+        probe.observed(1 << 0x5, 130, 11);
+        // A press let go of and pressed again before it was read is missed
+        probe.pressed(0x4, 200, 20);
+        probe.pressed(0x4, 250, 23);
+        probe.observed(1 << 0x4, 260, 23);
+
+        assert_eq!(probe.samples, [(30, 1), (10, 0)]);
+        assert_eq!(probe.missed, 1);
+        assert!(probe.report().starts_with("Input latency over 2 presses (1 missed): mean 20.0 ms (0.50 frames"));
+        assert!(probe.report().ends_with("median 10 ms, 95th percentile 10 ms, max 30 ms"));
+    }
+}
@@ -0,0 +1,134 @@
+use crate::chip8::{VIDEO_HEIGHT, VIDEO_WIDTH};
+use crate::osd::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::replay::keypad_bits;
+
+// Names shown at once, one per line of glyphs with a pixel between lines
+const ROWS: usize = VIDEO_HEIGHT as usize / (GLYPH_HEIGHT as usize + 1);
+// Characters of a name that fit across, after a pixel of margin
+const COLUMNS: usize = (VIDEO_WIDTH as usize - 1) / (GLYPH_WIDTH as usize + 1);
+
+const KEY_UP: u16 = 1 << 0x2;
+const KEY_DOWN: u16 = 1 << 0x8;
+const KEY_PAGE_UP: u16 = 1 << 0x4;
+const KEY_PAGE_DOWN: u16 = 1 << 0x6;
+const KEY_PLAY: u16 = 1 << 0x5;
+
+// A list of ROMs to choose from, drawn on the CHIP-8 display itself with the
+//  on-screen font so it works with nothing but the keypad: 2 and 8 move
+//  up and down, 4 and 6 a page at a time, and 5 plays the ROM picked. The
+//  chosen line is drawn inverted.
+pub struct Launcher {
+    names: Vec<String>,
+    selected: usize,
+    // The keys down last frame, so holding one moves only once
+    held: u16,
+}
+
+impl Launcher {
+    pub fn new(names: Vec<String>, selected: usize) -> Launcher {
+        Launcher {
+            names,
+            selected,
+            held: 0,
+        }
+    }
+
+    // Follow this frame's keys, returning the index of the ROM to play once
+    //  one is picked
+    pub fn input(&mut self, keypad: &[u8; 16]) -> Option<usize> {
+        let keys = keypad_bits(keypad);
+        let pressed = keys & !self.held;
+        self.held = keys;
+
+        let last = self.names.len().saturating_sub(1);
+        if pressed & KEY_UP != 0 {
+            self.selected = self.selected.checked_sub(1).unwrap_or(last);
+        }
+        if pressed & KEY_DOWN != 0 {
+            self.selected = if self.selected == last { 0 } else { self.selected + 1 };
+        }
+        if pressed & KEY_PAGE_UP != 0 {
+            self.selected = self.selected.saturating_sub(ROWS);
+        }
+        if pressed & KEY_PAGE_DOWN != 0 {
+            self.selected = (self.selected + ROWS).min(last);
+        }
+        (pressed & KEY_PLAY != 0).then_some(self.selected)
+    }
+
+    // The list as a frame of the CHIP-8 display, a page at a time
+    pub fn render(&self) -> Vec<u8> {
+        let width = VIDEO_WIDTH as usize;
+        let mut video = vec![0; width * VIDEO_HEIGHT as usize];
+        let first = self.selected / ROWS * ROWS;
+
+        for (row, name) in self.names.iter().enumerate().skip(first).take(ROWS) {
+            let top = (row - first) * (GLYPH_HEIGHT as usize + 1);
+            let inverted = row == self.selected;
+            if inverted {
+                video[top * width..(top + GLYPH_HEIGHT as usize + 1) * width].fill(0xFF);
+            }
+            for (column, c) in name.chars().take(COLUMNS).enumerate() {
+                let left = 1 + column * (GLYPH_WIDTH as usize + 1);
+                for (y, bits) in osd::glyph(c).iter().enumerate() {
+                    for x in 0..GLYPH_WIDTH as usize {
+                        if bits >> (GLYPH_WIDTH as usize - 1 - x) & 1 != 0 {
+                            video[(top + 1 + y) * width + left + x] = if inverted { 0 } else { 0xFF };
+                        }
+                    }
+                }
+            }
+        }
+        video
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(launcher: &mut Launcher, key: usize) -> Option<usize> {
+        let mut keypad = [0; 16];
+        keypad[key] = 1;
+        let picked = launcher.input(&keypad);
+        launcher.input(&[0; 16]);
+        picked
+    }
+
+    #[test]
+    fn the_keypad_moves_through_the_list_and_picks() {
+        let names: Vec<String> = (0..12).map(|n| format!("game{}", n)).collect();
+        let mut launcher = Launcher::new(names, 0);
+        press(&mut launcher, 0x2);
+        assert_eq!(launcher.selected, 11);
+        press(&mut launcher, 0x8);
+        press(&mut launcher, 0x6);
+        press(&mut launcher, 0x8);
+        assert_eq!(launcher.selected, ROWS + 1);
+        press(&mut launcher, 0x4);
+        assert_eq!(launcher.selected, 1);
+
+        // Holding a key moves once
+        let mut keypad = [0; 16];
+        keypad[0x8] = 1;
+        launcher.input(&keypad);
+        launcher.input(&keypad);
+        assert_eq!(launcher.selected, 2);
+        launcher.input(&[0; 16]);
+        assert_eq!(press(&mut launcher, 0x5), Some(2));
+    }
+
+    #[test]
+    fn the_chosen_name_is_drawn_inverted() {
+        let launcher = Launcher::new(vec![String::from("a"), String::from("b")], 1);
+        let video = launcher.render();
+        let width = VIDEO_WIDTH as usize;
+        let line = GLYPH_HEIGHT as usize + 1;
+        // The first line is dark around its name, the second lit
+        assert_eq!(video[0], 0);
+        assert_eq!(video[line * width], 0xFF);
+        assert!(video[..line * width].contains(&0xFF));
+        assert!(video[line * width..2 * line * width].contains(&0));
+        assert!(video[2 * line * width..].iter().all(|&pixel| pixel == 0));
+    }
+}
@@ -0,0 +1,75 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, WindowCanvas};
+
+// A slow LCD: each frame a pixel only gets part of the way to its new
+//  brightness, in either direction, so XOR flicker smears into a steady
+//  half-lit pixel much as it did on early handhelds
+const RESPONSE: f32 = 0.45;
+
+const GRID_ALPHA: u8 = 0x70;
+
+pub struct Lcd {
+    levels: Vec<f32>,
+}
+
+impl Lcd {
+    pub fn new() -> Lcd {
+        Lcd { levels: Vec::new() }
+    }
+
+    // Brightness of each pixel from 0 to 1 after showing `video`
+    pub fn apply(&mut self, video: &[u8]) -> &[f32] {
+        self.levels.resize(video.len(), 0.0);
+        for (level, &pixel) in self.levels.iter_mut().zip(video) {
+            let target = if pixel != 0 { 1.0 } else { 0.0 };
+            *level += (target - *level) * RESPONSE;
+        }
+        &self.levels
+    }
+}
+
+// Thin gaps between the pixels of a `columns` x `rows` display shown in
+//  `display`, like the grid of an LCD panel
+pub fn draw_grid(canvas: &mut WindowCanvas, display: Rect, columns: u32, rows: u32) {
+    let cell_width = display.width() as f32 / columns as f32;
+    let cell_height = display.height() as f32 / rows as f32;
+    let thickness = (cell_width.min(cell_height) / 8.0).max(1.0) as u32;
+
+    let mut gaps = Vec::new();
+    for column in 1..columns {
+        let x = display.x() + (column as f32 * cell_width) as i32;
+        gaps.push(Rect::new(x, display.y(), thickness, display.height()));
+    }
+    for row in 1..rows {
+        let y = display.y() + (row as f32 * cell_height) as i32;
+        gaps.push(Rect::new(display.x(), y, display.width(), thickness));
+    }
+
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, GRID_ALPHA));
+    let _ = canvas.fill_rects(&gaps);
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixels_take_frames_to_switch_either_way() {
+        let mut lcd = Lcd::new();
+        let on = lcd.apply(&[1, 0])[0];
+        assert!(on > 0.4 && on < 0.5, "{}", on);
+        assert!(lcd.apply(&[1, 0])[0] > on);
+        for _ in 0..20 {
+            lcd.apply(&[1, 0]);
+        }
+        assert!(lcd.apply(&[0, 0])[0] > 0.5);
+        // Flicker every other frame settles in between
+        for frame in 0..40 {
+            let level = lcd.apply(&[frame % 2, 0])[0];
+            assert!(frame < 20 || (level > 0.25 && level < 0.75), "{}", level);
+        }
+    }
+}
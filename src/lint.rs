@@ -0,0 +1,230 @@
+use std::fmt;
+
+use crate::disasm;
+use crate::opcode::Instruction;
+use crate::quirks::Variant;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Note,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            Severity::Note => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+pub struct Finding {
+    pub address: u16,
+    pub opcode: u16,
+    pub severity: Severity,
+    pub message: String,
+}
+
+pub struct Report {
+    pub findings: Vec<Finding>,
+    pub suggested: Vec<Variant>,
+    // Quirks whose setting changes how this ROM behaves
+    pub quirk_sensitive: Vec<&'static str>,
+}
+
+// Opcodes added by SUPER-CHIP and XO-CHIP, which plain CHIP-8 does not run
+fn extension(opcode: u16) -> Option<(Variant, &'static str)> {
+    let x = (opcode & 0x0F00) >> 8;
+    let kk = opcode & 0x00FF;
+
+    match opcode {
+        0x00FB => Some((Variant::SuperChip, "scroll right")),
+        0x00FC => Some((Variant::SuperChip, "scroll left")),
+        0x00FD => Some((Variant::SuperChip, "exit")),
+        0x00FE => Some((Variant::SuperChip, "low resolution")),
+        0x00FF => Some((Variant::SuperChip, "high resolution")),
+        _ if opcode & 0xFFF0 == 0x00C0 => Some((Variant::SuperChip, "scroll down")),
+        _ if opcode & 0xFFF0 == 0x00D0 => Some((Variant::XoChip, "scroll up")),
+        _ if opcode & 0xF00F == 0xD000 => Some((Variant::SuperChip, "16x16 sprite")),
+        _ if opcode & 0xF00F == 0x5002 => Some((Variant::XoChip, "save register range")),
+        _ if opcode & 0xF00F == 0x5003 => Some((Variant::XoChip, "load register range")),
+        0xF000 => Some((Variant::XoChip, "long index load")),
+        0xF002 => Some((Variant::XoChip, "load audio pattern")),
+        _ if opcode & 0xF000 == 0xF000 => match kk {
+            0x01 if x <= 3 => Some((Variant::XoChip, "select drawing plane")),
+            0x30 => Some((Variant::SuperChip, "large font")),
+            0x3A => Some((Variant::XoChip, "set audio pitch")),
+            0x75 => Some((Variant::SuperChip, "save flags")),
+            0x85 => Some((Variant::SuperChip, "load flags")),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+pub fn lint(rom: &[u8], origin: u16) -> Report {
+    let end = origin as usize + rom.len();
+    let (reached, computed_jumps) = disasm::trace(rom, origin);
+
+    let mut findings = Vec::new();
+    let mut uses_schip = false;
+    let mut uses_xochip = false;
+    let mut shift = false;
+    let mut load_store = false;
+    let mut jump = false;
+
+    let mut add = |address: u16, opcode: u16, severity: Severity, message: String| {
+        findings.push(Finding {
+            address,
+            opcode,
+            severity,
+            message,
+        })
+    };
+
+    for &address in &reached {
+        let offset = (address - origin) as usize;
+        let opcode = (rom[offset] as u16) << 8 | rom[offset + 1] as u16;
+
+        if let Some((variant, what)) = extension(opcode) {
+            match variant {
+                Variant::XoChip => uses_xochip = true,
+                _ => uses_schip = true,
+            }
+            add(address, opcode, Severity::Warning, format!("{} instruction ({})", variant.name(), what));
+            continue;
+        }
+
+        let Some(instruction) = Instruction::decode(opcode) else {
+            add(address, opcode, Severity::Error, String::from("reachable word is not a valid instruction"));
+            continue;
+        };
+
+        match instruction {
+            Instruction::Shr(x, y) | Instruction::Shl(x, y) if x != y => {
+                shift = true;
+                add(address, opcode, Severity::Warning, format!(
+                    "shift quirk: CHIP-8 shifts v{:x} into v{:x}, SUPER-CHIP shifts v{:x} in place",
+                    y, x, x
+                ));
+            }
+            Instruction::Store(_) | Instruction::Load(_) => {
+                load_store = true;
+                add(address, opcode, Severity::Note, String::from(
+                    "load/store quirk: whether I advances afterwards differs between platforms",
+                ));
+            }
+            Instruction::JpV0(nnn) if nnn >> 8 != 0 => {
+                jump = true;
+                add(address, opcode, Severity::Warning, format!(
+                    "jump quirk: SUPER-CHIP adds v{:x} instead of v0",
+                    nnn >> 8
+                ));
+            }
+            Instruction::Sys(_) => {
+                add(address, opcode, Severity::Warning, String::from(
+                    "machine code call only works on the original COSMAC VIP",
+                ));
+            }
+            _ => {}
+        }
+
+        if let Instruction::Jp(target) | Instruction::Call(target) = instruction {
+            if (target as usize) < origin as usize || (target as usize) >= end {
+                add(address, opcode, Severity::Error, format!(
+                    "target 0x{:03x} is outside the program (0x{:03x}-0x{:03x})",
+                    target,
+                    origin,
+                    end - 1
+                ));
+            } else if target % 2 != 0 {
+                add(address, opcode, Severity::Warning, format!(
+                    "target 0x{:03x} is not word-aligned",
+                    target
+                ));
+            }
+        }
+    }
+
+    for address in computed_jumps {
+        let offset = (address - origin) as usize;
+        let opcode = (rom[offset] as u16) << 8 | rom[offset + 1] as u16;
+        add(address, opcode, Severity::Note, String::from(
+            "computed jump: code reached through it was not checked",
+        ));
+    }
+
+    findings.sort_by_key(|finding| finding.address);
+
+    let suggested = if uses_xochip {
+        vec![Variant::XoChip]
+    } else if uses_schip {
+        vec![Variant::SuperChip, Variant::XoChip]
+    } else {
+        vec![Variant::Chip8, Variant::SuperChip, Variant::XoChip]
+    };
+
+    let quirk_sensitive = [("shift", shift), ("load/store", load_store), ("jump", jump)]
+        .into_iter()
+        .filter(|(_, used)| *used)
+        .map(|(name, _)| name)
+        .collect();
+
+    Report {
+        findings,
+        suggested,
+        quirk_sensitive,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn severities(report: &Report) -> Vec<(u16, Severity)> {
+        report.findings.iter().map(|finding| (finding.address, finding.severity)).collect()
+    }
+
+    #[test]
+    fn portable_roms_suit_every_variant() {
+        // ld v0, 1; jp 0x202 (a tight loop)
+        let report = lint(&[0x60, 0x01, 0x12, 0x02], 0x200);
+        assert!(report.findings.is_empty());
+        assert_eq!(report.suggested, [Variant::Chip8, Variant::SuperChip, Variant::XoChip]);
+        assert!(report.quirk_sensitive.is_empty());
+    }
+
+    #[test]
+    fn extensions_narrow_the_suggestion() {
+        // hires; jp 0x202
+        let report = lint(&[0x00, 0xFF, 0x12, 0x02], 0x200);
+        assert_eq!(severities(&report), [(0x200, Severity::Warning)]);
+        assert_eq!(report.suggested, [Variant::SuperChip, Variant::XoChip]);
+
+        // plane 1; jp 0x202
+        let report = lint(&[0xF1, 0x01, 0x12, 0x02], 0x200);
+        assert_eq!(report.suggested, [Variant::XoChip]);
+    }
+
+    #[test]
+    fn quirk_dependent_instructions_are_named() {
+        // shr v1, v2; ld [i], v0; jp v0, 0x300
+        let report = lint(&[0x81, 0x26, 0xF0, 0x55, 0xB3, 0x00], 0x200);
+        assert_eq!(report.quirk_sensitive, ["shift", "load/store", "jump"]);
+        assert_eq!(
+            severities(&report),
+            [(0x200, Severity::Warning), (0x202, Severity::Note), (0x204, Severity::Warning), (0x204, Severity::Note)]
+        );
+    }
+
+    #[test]
+    fn unreachable_data_is_ignored_but_bad_targets_are_not() {
+        // jp 0x204; <data>; jp 0x400
+        let report = lint(&[0x12, 0x04, 0xFF, 0xFF, 0x14, 0x00], 0x200);
+        assert_eq!(severities(&report), [(0x204, Severity::Error)]);
+        assert!(report.findings[0].message.contains("outside the program"));
+    }
+}
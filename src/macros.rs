@@ -0,0 +1,149 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::replay::{bits_keypad, bits_text, keypad_bits, text_bits};
+use crate::storage::Storage;
+
+// A recorded input macro: the keypad for each frame, replayed on top of
+//  live input when its hotkey is pressed. Frames with no keys held at the
+//  start and end of a recording are dropped, so the macro fires at once.
+pub struct Macros {
+    recorded: Vec<u16>,
+    recording: Option<Vec<u16>>,
+    position: Option<usize>,
+}
+
+impl Macros {
+    pub fn new() -> Macros {
+        Macros {
+            recorded: Vec::new(),
+            recording: None,
+            position: None,
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    pub fn start_recording(&mut self) {
+        self.position = None;
+        self.recording = Some(Vec::new());
+    }
+
+    // Returns the length of the new macro in frames
+    pub fn stop_recording(&mut self) -> usize {
+        let mut frames = self.recording.take().unwrap_or_default();
+        let last = frames.iter().rposition(|&bits| bits != 0).map_or(0, |i| i + 1);
+        frames.truncate(last);
+        let first = frames.iter().position(|&bits| bits != 0).unwrap_or(0);
+        frames.drain(..first);
+
+        self.recorded = frames;
+        self.recorded.len()
+    }
+
+    // Returns false if nothing has been recorded yet
+    pub fn play(&mut self) -> bool {
+        if self.recorded.is_empty() {
+            return false;
+        }
+        self.position = Some(0);
+        true
+    }
+
+    // Called once per frame with the live keypad: records it, or adds the
+    //  macro's keys for this frame to it
+    pub fn apply(&mut self, keypad: &mut [u8; 16]) {
+        if let Some(frames) = &mut self.recording {
+            frames.push(keypad_bits(keypad));
+        }
+
+        if let Some(position) = self.position {
+            let bits = keypad_bits(keypad) | self.recorded[position];
+            *keypad = bits_keypad(bits);
+            self.position = Some(position + 1).filter(|&next| next < self.recorded.len());
+        }
+    }
+
+    // One line per frame listing the held keys in hex, or `-` for none
+    pub fn to_text(&self) -> String {
+        self.recorded.iter().map(|&bits| bits_text(bits) + "\n").collect()
+    }
+
+    pub fn from_text(text: &str) -> io::Result<Macros> {
+        let mut recorded = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let bits = text_bits(line.trim()).map_err(|message| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", number + 1, message))
+            })?;
+            recorded.push(bits);
+        }
+
+        Ok(Macros {
+            recorded,
+            ..Macros::new()
+        })
+    }
+}
+
+// Macros are kept per ROM, since each game needs its own combos
+pub fn load(storage: &Storage) -> io::Result<Macros> {
+    let path = storage.macro_path();
+    if !path.exists() {
+        return Ok(Macros::new());
+    }
+    Macros::from_text(&fs::read_to_string(path)?)
+}
+
+pub fn save(macros: &Macros, storage: &Storage) -> io::Result<PathBuf> {
+    let path = storage.macro_path();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, macros.to_text())?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keypad(keys: &[usize]) -> [u8; 16] {
+        let mut keypad = [0; 16];
+        for &key in keys {
+            keypad[key] = 1;
+        }
+        keypad
+    }
+
+    #[test]
+    fn idle_frames_around_a_recording_are_dropped() {
+        let mut macros = Macros::new();
+        macros.start_recording();
+        for keys in [&[][..], &[], &[5], &[], &[5, 0xA], &[]] {
+            macros.apply(&mut keypad(keys));
+        }
+        assert_eq!(macros.stop_recording(), 3);
+        assert_eq!(macros.to_text(), "5\n-\n5A\n");
+    }
+
+    #[test]
+    fn playing_adds_to_live_input_until_the_macro_ends() {
+        let mut macros = Macros::from_text("1\n2\n").unwrap();
+        let mut live = keypad(&[0xF]);
+        assert!(macros.play());
+        macros.apply(&mut live);
+        assert_eq!(live, keypad(&[1, 0xF]));
+        let mut live = keypad(&[]);
+        macros.apply(&mut live);
+        assert_eq!(live, keypad(&[2]));
+        let mut live = keypad(&[]);
+        macros.apply(&mut live);
+        assert_eq!(live, keypad(&[]));
+
+        assert!(!Macros::new().play());
+        assert!(Macros::from_text("1\nG\n").is_err());
+    }
+}
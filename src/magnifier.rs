@@ -0,0 +1,97 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{Texture, WindowCanvas};
+
+use crate::chip8::Draw;
+
+// The magnified part of the display, in CHIP-8 pixels
+const REGION_WIDTH: i32 = 16;
+const REGION_HEIGHT: i32 = 8;
+
+const FRAME_COLOR: Color = Color::RGB(0xFF, 0x40, 0x40);
+
+// A close-up of part of the display in the corner of the window, for
+//  checking sprites pixel by pixel. It follows the last sprite drawn unless
+//  a spot has been clicked.
+pub struct Magnifier {
+    // Centre of the region, in CHIP-8 pixels
+    centre: (i32, i32),
+    pinned: bool,
+}
+
+impl Magnifier {
+    pub fn new() -> Magnifier {
+        Magnifier {
+            centre: (REGION_WIDTH / 2, REGION_HEIGHT / 2),
+            pinned: false,
+        }
+    }
+
+    pub fn follow(&mut self, draw: Option<Draw>) {
+        if let (Some(draw), false) = (draw, self.pinned) {
+            self.centre = (draw.x as i32 + 4, draw.y as i32 + draw.height as i32 / 2);
+        }
+    }
+
+    // Stop following draws and stay on this pixel
+    pub fn pin(&mut self, x: i32, y: i32) {
+        self.centre = (x, y);
+        self.pinned = true;
+    }
+
+    // The region, kept inside a `width` x `height` display
+    fn region(&self, width: i32, height: i32) -> Rect {
+        let x = (self.centre.0 - REGION_WIDTH / 2).clamp(0, (width - REGION_WIDTH).max(0));
+        let y = (self.centre.1 - REGION_HEIGHT / 2).clamp(0, (height - REGION_HEIGHT).max(0));
+        Rect::new(x, y, REGION_WIDTH as u32, REGION_HEIGHT as u32)
+    }
+
+    // `display` is where the `texture_size` display texture was drawn,
+    //  unrotated; the close-up goes in its bottom-right corner
+    pub fn draw(&self, canvas: &mut WindowCanvas, texture: &Texture, display: Rect, texture_size: (u32, u32)) {
+        let region = self.region(texture_size.0 as i32, texture_size.1 as i32);
+
+        // Outline the region on the display itself
+        let scale_x = display.width() as f64 / texture_size.0 as f64;
+        let scale_y = display.height() as f64 / texture_size.1 as f64;
+        canvas.set_draw_color(FRAME_COLOR);
+        let _ = canvas.draw_rect(Rect::new(
+            display.x() + (region.x() as f64 * scale_x) as i32,
+            display.y() + (region.y() as f64 * scale_y) as i32,
+            (region.width() as f64 * scale_x) as u32,
+            (region.height() as f64 * scale_y) as u32,
+        ));
+
+        // Three quarters of the display wide: three times the normal size
+        let width = display.width() * 3 / 4;
+        let height = display.height() * 3 / 4;
+        let inset = Rect::new(
+            display.right() - width as i32 - 4,
+            display.bottom() - height as i32 - 4,
+            width,
+            height,
+        );
+        let _ = canvas.copy(texture, region, inset);
+        let _ = canvas.draw_rect(Rect::new(inset.x() - 1, inset.y() - 1, inset.width() + 2, inset.height() + 2));
+        canvas.set_draw_color(Color::RGB(0, 0, 0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_region_follows_draws_until_pinned() {
+        let mut magnifier = Magnifier::new();
+        magnifier.follow(Some(Draw { x: 30, y: 10, height: 6 }));
+        assert_eq!(magnifier.region(64, 32), Rect::new(26, 9, 16, 8));
+        // Kept inside the display near its edges
+        magnifier.follow(Some(Draw { x: 60, y: 30, height: 2 }));
+        assert_eq!(magnifier.region(64, 32), Rect::new(48, 24, 16, 8));
+
+        magnifier.pin(3, 3);
+        magnifier.follow(Some(Draw { x: 30, y: 10, height: 6 }));
+        assert_eq!(magnifier.region(64, 32), Rect::new(0, 0, 16, 8));
+    }
+}
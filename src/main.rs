@@ -1,95 +1,1462 @@
+mod archive;
+mod asm;
+mod attract;
+mod bezel;
+mod blend;
+mod builtin;
+mod buzzer;
 mod chip8;
+mod cli;
+mod commands;
+mod config;
+mod controllers;
+mod cpal_sound;
+mod crt;
+mod debugger;
+mod disasm;
+mod draw_viz;
+mod egui_sdl;
+mod fetch;
+mod gif_recorder;
+mod headless;
+mod inject;
+mod launcher;
+mod keypad;
+mod keymap;
+mod latency;
+mod lcd;
+mod lint;
+mod macros;
+mod magnifier;
+mod menu;
+mod opcode;
+mod osd;
+mod paddle;
+mod palette;
+mod patch;
+mod phosphor;
+mod picker;
+mod pixels_frontend;
 mod platform;
+mod playlist;
+mod quirks;
+mod recent;
+mod render;
+mod replay;
+mod report;
+mod rewind;
+mod rom_settings;
+mod romdiff;
+mod sample;
+mod screenshot;
+mod sound;
+mod state;
+mod statediff;
+mod storage;
+mod terminal_frontend;
+mod timing;
+mod video_recorder;
+mod watch;
+mod wav_recorder;
 
 use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::Arc;
+use std::thread;
 use std::time::{Duration, Instant};
 
-use chip8::Chip8;
-use sdl2::VideoSubsystem;
-use sdl2::render::Canvas;
-use sdl2::video;
+use gif_recorder::GifRecorder;
+use video_recorder::VideoRecorder;
+use wav_recorder::WavRecorder;
+use platform::{Action, DisplayError};
+use render::Renderer;
+use report::ErrorKind;
+use sample::Sample;
+use storage::Storage;
+use cli::{Command, Frontend, RenderBackend, RomSource, Scale};
+use anyhow::Context;
+use sdl2::render::WindowCanvas;
+use sdl2::{EventPump, Sdl, VideoSubsystem};
+use sdl2::video::{Window, WindowPos};
 
 // Static variables
 static TITLE: &str = "CHIP-8 Emulator";
+// How much - and = change the volume, in percent
+const VOLUME_STEP: u8 = 10;
+
+// The speed when neither the command line nor the archive gives one
+const DEFAULT_IPS: u32 = 700;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
+    // Known before the options are read, so mistakes in them are reported
+    //  as asked too
+    report::configure(
+        args.iter().any(|arg| arg == "--json-errors"),
+        args.iter().any(|arg| arg == "--json-status"),
+    );
 
-    if args.len() != 4 {
-        eprintln!("Usage: {} <Scale> <Delay> <ROM>", args[0]);
-        process::exit(1);
-    }
-
-    let video_scale: u8
-        = args[1].parse().expect("Failed to parse Scale");
-    let cycle_delay: u64
-        = args[2].parse().expect("Failed to parse Delay");
-    let rom_filename = &args[3];
-
-    /* Build sdl context */
-    let sdl_context = sdl2::init().unwrap();
-    let video_subsystem = sdl_context.video().unwrap();
-    let event_pump = sdl_context.event_pump().unwrap();
-
-    let window_width: u32 = chip8::VIDEO_WIDTH as u32 * video_scale as u32;
-    let window_height: u32 = chip8::VIDEO_HEIGHT as u32 * video_scale as u32;
-    
-    let window = video_subsystem.window
-        ("Chip-8 Emulator", window_width, window_height)
-            .position_centered()
-            .build()
-            .unwrap();
-    let canvas 
-        = window.into_canvas().accelerated().build().unwrap();
-    let mut texture_creator 
-        = canvas.texture_creator();
-
-    let mut platform = platform::Platform::new(&video_subsystem, TITLE, (window_width, window_height), canvas, &mut texture_creator, (chip8::VIDEO_WIDTH as u32, chip8::VIDEO_HEIGHT as u32), event_pump);
+    let command = match cli::parse(&args) {
+        Ok(command) => command,
+        Err(e) => {
+            let hints = [format!("Run '{} --help' for usage.", args[0])];
+            report::fail_with_hints(ErrorKind::Usage, e, &hints);
+        }
+    };
 
-    let mut chip8 = chip8::Chip8::new();
-    chip8.load_rom(rom_filename);
+    let mut options = match command {
+        Command::Run(options) => {
+            report::configure(options.json_errors, options.json_status);
+            *options
+        }
+        Command::Help(text) => {
+            print!("{}", text);
+            return;
+        }
+        // Completing the name the program is installed under
+        Command::Completions { shell } => {
+            let program = Path::new(&args[0])
+                .file_name()
+                .map_or(args[0].clone(), |name| name.to_string_lossy().into_owned());
+            clap_complete::generate(shell, &mut cli::command(), program, &mut io::stdout());
+            return;
+        }
+        tool => process::exit(commands::run(tool)),
+    };
+
+    // Started without a ROM, as from the desktop: ask for one
+    if let RomSource::Pick = options.rom {
+        match picker::pick_rom() {
+            Some(path) => options.rom = RomSource::File(path),
+            // Cancelled, or there was no dialog to show
+            None => {
+                eprintln!("No ROM picked. Run '{} --help' for how to give one.", args[0]);
+                report::finish("cancelled", None);
+                return;
+            }
+        }
+    }
+
+    // Several ROMs, or a folder of them, play one after another; a folder
+    //  starts with a list of its ROMs to choose from
+    let folder = match &options.rom {
+        RomSource::File(path) if options.playlist.is_empty() && Path::new(path).is_dir() => Some(path.clone()),
+        _ => None,
+    };
+    let playlist_arguments = match &folder {
+        Some(path) => vec![path.clone()],
+        None => options.playlist.clone(),
+    };
+    let mut playlist = match playlist_arguments.as_slice() {
+        [] if options.playlist_idle.is_some() => {
+            report::fail(ErrorKind::Usage, "--playlist-idle needs several ROMs or a folder of them")
+        }
+        [] => None,
+        _ if options.headless || options.frontend != Frontend::Sdl => {
+            report::fail(ErrorKind::Usage, "a playlist needs the SDL window")
+        }
+        arguments => {
+            let list = playlist::Playlist::new(arguments, options.playlist_idle)
+                .unwrap_or_else(|e| report::fail(ErrorKind::Rom, e));
+            eprintln!("Playing {} ROMs in turn; N moves on to the next and L lists them", list.len());
+            options.rom = RomSource::File(list.current().to_string_lossy().into_owned());
+            Some(list)
+        }
+    };
+    let mut launcher = playlist
+        .as_ref()
+        .filter(|_| folder.is_some())
+        .map(|list| launcher::Launcher::new(list.names(), list.position()));
+
+    // A display turned on its side needs a tall window
+    let (display_width, display_height) = match options.rotate {
+        90 | 270 => (chip8::VIDEO_HEIGHT as u32, chip8::VIDEO_WIDTH as u32),
+        _ => (chip8::VIDEO_WIDTH as u32, chip8::VIDEO_HEIGHT as u32),
+    };
+    let video_scale: u8 = match options.scale {
+        Scale::Fixed(scale) => scale,
+        // Only a window can be auto-sized, so SDL is already needed
+        Scale::Auto => match sdl2::init().and_then(|sdl| sdl.video()) {
+            Ok(video_subsystem) => {
+                let keypad_width = if options.keypad { display_height } else { 0 };
+                platform::auto_scale(&video_subsystem, options.monitor, display_width + keypad_width, display_height)
+            }
+            Err(e) => report::fail(ErrorKind::Display, format!("could not read the screen size: {}", e)),
+        },
+    };
+    // ROMs from the CHIP-8 Archive carry their own platform, speed and colors
+    let mut program_info = match &options.rom {
+        RomSource::File(argument) if argument != "-" && !fetch::is_url(argument) => {
+            archive::lookup(Path::new(argument))
+        }
+        _ => None,
+    };
+    if let Some(program) = &program_info {
+        eprintln!("Found CHIP-8 Archive entry: {} by {}", program.title, program.authors.join(", "));
+    }
+    let mut cycle_period = rom_cycle_period(program_info.as_ref(), options.cycle_period);
+
+    // Load the ROM before creating a window so bad input fails fast
+    let (mut rom, mut chip8) = load_program(&options.rom, &options.patches, options.start_address)
+        .unwrap_or_else(|e| report::fail_error(ErrorKind::Rom, e));
+
+    let mut rom_hash = state::rom_hash(&rom);
+    report::set_rom_hash(&rom_hash);
+    let mut storage = match &options.rom {
+        RomSource::File(path) if options.portable => Storage::portable(Path::new(path), &rom_hash),
+        _ => Storage::user(&rom_hash),
+    };
+    eprintln!("Keeping states and captures in {}", storage.dir().display());
+    let mut rom_settings = rom_settings::load(&storage).unwrap_or_else(|e| {
+        eprintln!("Could not read {}: {}", storage.settings_path().display(), e);
+        rom_settings::RomSettings::default()
+    });
+    let mut state_slot: u8 = 0;
+
+    // Resume from an exact machine state, e.g. one attached to a bug report
+    if let Some(state_file) = &options.state {
+        if let Err(e) = state::load_file(&mut chip8, Path::new(state_file)) {
+            report::fail(ErrorKind::File, format!("could not load state '{}': {}", state_file, e));
+        }
+        eprintln!("Loaded state {}", state_file);
+    } else if options.resume {
+        match state::load_auto(&mut chip8, &storage) {
+            Ok(Some(path)) => eprintln!("Resumed from {}", path.display()),
+            Ok(None) => {}
+            // A stale or broken auto-state shouldn't stop the ROM starting
+            Err(e) => eprintln!("Warning: could not resume: {}", e),
+        }
+    }
+
+    chip8.quirks = rom_quirks(program_info.as_ref());
+
+    // A replay brings its own seed, speed and quirks, so playback matches
+    //  the recorded run exactly
+    let playback = options.play.as_ref().map(|path| match replay::Replay::load(Path::new(path)) {
+        Ok(replay) => {
+            if replay.rom_hash != rom_hash {
+                eprintln!("Warning: replay '{}' was recorded with a different ROM.", path);
+            }
+            eprintln!("Playing replay {} ({} frames)", path, replay.frames.len());
+            replay
+        }
+        Err(e) => report::fail(ErrorKind::File, format!("could not load replay '{}': {}", path, e)),
+    });
+    // TAS editing continues an existing replay, or starts a new one
+    let tas_replay = options.tas.as_ref().and_then(|path| match replay::Replay::load(Path::new(path)) {
+        Ok(replay) => {
+            eprintln!("Editing replay {} ({} frames)", path, replay.frames.len());
+            Some(replay)
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => report::fail(ErrorKind::File, format!("could not load replay '{}': {}", path, e)),
+    });
+
+    if let Some(replay) = playback.as_ref().or(tas_replay.as_ref()) {
+        let machine = replay.machine;
+        if machine.start_address != chip8.start_address {
+            chip8 = machine.power_on(&rom).unwrap_or_else(|e| {
+                report::fail(ErrorKind::Rom, format!("the ROM doesn't fit the replay's start address: {}", e))
+            });
+        }
+        chip8.quirks = machine.quirks;
+        chip8.reseed(replay.seed);
+        cycle_period = replay.cycle_period;
+    } else if let Some(seed) = options.seed {
+        chip8.reseed(seed);
+    }
+
+    let replay_path = options.record.as_ref().or(options.tas.as_ref());
+    let mut recording = tas_replay.or_else(|| {
+        replay_path.map(|_| replay::Replay::new(chip8.rng_seed, cycle_period, &rom_hash, replay::Machine::of(&chip8)))
+    });
+    let tas_mode = options.tas.is_some();
+
+    let mut attract = options.attract.as_ref().map(|source| {
+        let loaded = match (source.as_str(), &options.rom) {
+            ("demo", RomSource::Builtin(builtin)) => match builtin.demo {
+                Some(data) => replay::Replay::from_bytes(data),
+                None => Err(io::Error::new(io::ErrorKind::NotFound, format!("{} has no bundled demo", builtin.name))),
+            },
+            ("demo", _) => Err(io::Error::new(io::ErrorKind::NotFound, "only built-in ROMs have bundled demos")),
+            (path, _) => replay::Replay::load(Path::new(path)),
+        };
+        match loaded {
+            Ok(replay) => {
+                if replay.rom_hash != rom_hash {
+                    eprintln!("Warning: attract replay '{}' was recorded with a different ROM.", source);
+                }
+                attract::Attract::new(replay, Duration::from_secs(options.attract_after))
+            }
+            Err(e) => report::fail(ErrorKind::File, format!("could not load attract replay '{}': {}", source, e)),
+        }
+    });
 
     eprintln!("Finished reading in ROM.");
 
-    let video_pitch 
-        = std::mem::size_of_val(&chip8.video[0]) * chip8::VIDEO_WIDTH as usize;
+    if let Some(program) = &program_info {
+        if let Some(variant) = program.variant().filter(|&variant| variant != quirks::Variant::Chip8) {
+            eprintln!("Warning: {} targets {}, which is only partially supported.", program.title, variant.name());
+        }
+    }
+    choose_quirks(&mut chip8.quirks, &options);
+
+    // Colors given on the command line win over a palette, which wins over
+    //  the palette last picked for this ROM with F2, and then the archive's
+    //  colors. Headless output doesn't depend on what was picked in a window.
+    let saved_palette = rom_settings
+        .palette
+        .as_deref()
+        .and_then(palette::find)
+        .filter(|_| !options.headless);
+    let chosen_palette = options.palette.or(saved_palette);
+    let mut foreground = options
+        .foreground
+        .or(chosen_palette.map(|palette| palette.foreground))
+        .or_else(|| program_info.as_ref().and_then(|program| program.foreground()))
+        .unwrap_or([0xFF, 0xFF, 0xFF]);
+    let mut background = options
+        .background
+        .or(chosen_palette.map(|palette| palette.background))
+        .or_else(|| program_info.as_ref().and_then(|program| program.background()))
+        .unwrap_or([0x00, 0x00, 0x00]);
+    // XO-CHIP's plane colors likewise, apart from the palette, which has none
+    let archive_planes = program_info.as_ref().map(|program| program.plane_colors()).unwrap_or_default();
+    let plane_colors = palette::PlaneColors {
+        plane2: options.plane_colors.plane2.or(archive_planes.plane2),
+        both: options.plane_colors.both.or(archive_planes.both),
+    };
+    let mut palette_index =
+        chosen_palette.and_then(|chosen| palette::PALETTES.iter().position(|palette| palette.name == chosen.name));
+
+    let mut video = options.video.as_ref().map(|path| {
+        let colors = palette::pixel_colors(foreground, background, plane_colors);
+        match VideoRecorder::start(Path::new(path), options.video_fps, video_scale as u32, colors) {
+            Ok(recorder) => recorder,
+            Err(e) => report::fail(ErrorKind::File, format!("could not record video '{}': {}", path, e)),
+        }
+    });
+
+    let mut audio = options.record_audio.as_ref().map(|path| match WavRecorder::start(Path::new(path)) {
+        Ok(recorder) => recorder,
+        Err(e) => report::fail(ErrorKind::File, format!("could not record audio '{}': {}", path, e)),
+    });
+    let mut tone = options.tone.clone();
+    if let Some(path) = &options.beep {
+        match Sample::load(Path::new(path)) {
+            Ok(sample) => tone.sample = Some(Arc::new(sample)),
+            Err(e) => report::fail(ErrorKind::File, format!("could not load beep '{}': {}", path, e)),
+        }
+    }
+    let mut buzzer = buzzer::Buzzer::new(tone.clone());
+
+    // Headless runs never touch SDL, so they work on machines without a display
+    if options.headless {
+        let dump = options.dump_frames.as_ref().map(|dir| headless::FrameDump {
+            dir: PathBuf::from(dir),
+            format: options.dump_format,
+            scale: video_scale as u32,
+            colors: palette::pixel_colors(foreground, background, plane_colors),
+        });
+        let frames = options.frames.unwrap_or(0);
+        // Renders what a window would have shown, without one
+        let mut renderer = options.frame_hash.then(|| {
+            let mut renderer = Renderer::new();
+            renderer.set_colors(foreground, background);
+            renderer.set_plane_colors(plane_colors);
+            renderer.set_phosphor(options.phosphor);
+            renderer.set_blend(options.blend);
+            renderer.set_lcd(options.lcd);
+            renderer
+        });
+
+        let outputs = headless::HeadlessOptions {
+            playback: playback.as_ref(),
+            dump: dump.as_ref(),
+            video: video.as_mut(),
+            audio: audio.as_mut(),
+            renderer: renderer.as_mut(),
+            tone,
+        };
+        let result = headless::run(&mut chip8, frames, cycle_period, outputs);
+        finish_video(video);
+        finish_audio(audio);
+        let sink = match result {
+            Ok(sink) => sink,
+            Err(e) => report::fail(ErrorKind::File, e),
+        };
+        if let Some(path) = &options.exit_screenshot {
+            let width = chip8::VIDEO_WIDTH as usize;
+            let colors = palette::pixel_colors(foreground, background, plane_colors);
+            let rgb = screenshot::render_rgb(&chip8.video, width, colors, video_scale as u32);
+            let height = chip8.video.len() / width;
+            save_exit_screenshot(path, width as u32 * video_scale as u32, height as u32 * video_scale as u32, &rgb);
+        }
+        if let Some(renderer) = renderer {
+            println!("{}", renderer.frame_hash());
+        }
+        if options.sound_report {
+            println!("Sound: {} frames in {} beeps", sink.frames(), sink.beeps());
+        }
+        report::finish("frames", Some(frames));
+        return;
+    }
+
+    if options.frontend == Frontend::Pixels {
+        let settings = pixels_frontend::Settings {
+            scale: video_scale as u32,
+            foreground,
+            background,
+            phosphor: options.phosphor,
+            blend: options.blend,
+            tone: tone.clone(),
+            volume: options.volume,
+        };
+        if let Err(e) = pixels_frontend::run(&mut chip8, cycle_period, &settings, TITLE) {
+            report::fail(ErrorKind::Display, e);
+        }
+        report::finish("quit", None);
+        return;
+    }
+
+    if let Frontend::Terminal { braille } = options.frontend {
+        let settings = terminal_frontend::Settings {
+            foreground,
+            background,
+            braille,
+            keymap: load_keymap(&options),
+        };
+        if let Err(e) = terminal_frontend::run(&mut chip8, cycle_period, &settings, TITLE) {
+            report::fail(ErrorKind::Display, e);
+        }
+        report::finish("quit", None);
+        return;
+    }
+
+    // A bezel from the command line, or else the one set for this ROM
+    let bezel_path = match (&options.bezel, &rom_settings.bezel) {
+        (Some(path), _) => Some(PathBuf::from(path)),
+        (None, Some(path)) => Some(storage.dir().join(path)),
+        (None, None) => None,
+    };
+    let bezel = bezel_path.map(|path| match bezel::load(&path) {
+        Ok(bezel) => bezel,
+        Err(e) => report::fail(ErrorKind::File, format!("could not load bezel '{}': {}", path.display(), e)),
+    });
+
+    let (mut window_width, window_height) = match &bezel {
+        Some(bezel) => bezel.window_size(display_width * video_scale as u32),
+        None => (display_width * video_scale as u32, display_height * video_scale as u32),
+    };
+    // The keypad panel is a square beside the display
+    if options.keypad {
+        window_width += window_height;
+    }
+
+    let (sdl_context, video_subsystem, event_pump, canvas) =
+        open_window(&options, (window_width, window_height)).unwrap_or_else(|e| report::fail_error(ErrorKind::Display, e));
+    let mut volume = options.volume;
+    let mut muted = false;
+    if let Some(name) = &options.audio.device {
+        let names = sound::device_names(&sdl_context).unwrap_or_default();
+        if !names.contains(name) {
+            report::fail(ErrorKind::Audio, format!("unknown audio device '{}', available: {}", name, names.join(", ")));
+        }
+    }
+    let mut sound = sound::Sound::open(&sdl_context, tone.clone(), volume, options.audio.clone(), options.verbose);
+    let mut controllers = controllers::Controllers::open(&sdl_context);
+
+    let texture_creator = canvas.texture_creator();
+
+    let mut platform = platform::Platform::new((window_width, window_height), canvas, &texture_creator, (chip8::VIDEO_WIDTH as u32, chip8::VIDEO_HEIGHT as u32), event_pump)
+        .unwrap_or_else(|e| report::fail_error(ErrorKind::Display, e.into()));
+
+    platform.set_colors(foreground, background);
+    platform.set_plane_colors(plane_colors);
+    platform.set_phosphor(options.phosphor);
+    platform.set_blend(options.blend);
+    platform.set_crt(options.crt);
+    platform.set_lcd(options.lcd);
+    platform.set_integer_scale(options.integer_scale);
+    platform.set_rotation(options.rotate);
+    platform.set_smoothing(options.smooth);
+    platform.set_keypad(options.keypad);
+    platform.set_keymap(load_keymap(&options));
+    for collision in platform.keymap().collisions() {
+        eprintln!("Warning: {}", collision);
+    }
+    platform.set_players(controllers.ids());
+    platform.set_paddle(options.mouse);
+    let opened = match &options.rom {
+        RomSource::File(path) if path != "-" && !fetch::is_url(path) => Some(Path::new(path)),
+        _ => None,
+    };
+    let mut recent_roms = update_recent(&mut platform, opened);
+    let mut rom_path = opened.map(PathBuf::from);
+    // Reloaded, it gets its patches again; other ROMs opened don't
+    let patched_path = rom_path.clone();
+    let mut watch = opened.filter(|_| options.watch).and_then(watch_rom);
+    if let Some(bezel) = &bezel {
+        platform.set_bezel(bezel);
+    }
+    let mut smooth = options.smooth;
 
-    let mut last_cycle_time = Instant::now();
     let mut quit = false;
 
+    // Frames tick at 60 Hz and run a fixed share of cycles each
+    let frame_period = timing::frame_period();
+    let mut next_frame_time = Instant::now();
+    let refresh_period = if options.interpolate {
+        match video_subsystem.current_display_mode(options.monitor) {
+            Ok(mode) if mode.refresh_rate > timing::FRAMES_PER_SECOND as i32 => {
+                eprintln!("Blending frames for a {} Hz screen", mode.refresh_rate);
+                platform.set_interpolation(true);
+                Some(Duration::from_secs_f64(1.0 / mode.refresh_rate as f64))
+            }
+            Ok(mode) => {
+                eprintln!("The screen runs at {} Hz, too slow for --interpolate to help", mode.refresh_rate);
+                None
+            }
+            Err(e) => {
+                eprintln!("Could not read the screen's refresh rate: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let autosave_interval = options.autosave.map(Duration::from_secs);
+    let mut last_autosave = Instant::now();
+    let mut frame: u64 = 0;
+
+    let mut rewind = rewind::Rewind::new(rewind::REWIND_SECONDS * timing::FRAMES_PER_SECOND as usize);
+    let mut rewinding = false;
+    rewind.push(&chip8);
+
+    let mut paused = options.paused;
+    if paused {
+        platform.set_paused(true);
+        eprintln!("Paused at {:03X} before the first instruction", chip8.pc);
+    }
+    let mut draw_viz = options.draw_viz.then(draw_viz::DrawViz::new);
+    let mut advance = false;
+    let mut rerecording = false;
+
+    // The window title names the game and shows the current mode and speed
+    let mut rom_name = String::from(options.rom.name());
+    let mut game_name = game_title(program_info.as_ref(), &rom_name);
+    let mut shown_title = String::new();
+
+    let mut gif: Option<GifRecorder> = None;
+
+    let mut input_macros = match macros::load(&storage) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("Warning: could not load input macros: {}", e);
+            macros::Macros::new()
+        }
+    };
+
+    // The keyboard's keypad, which reaches the CHIP-8 once per frame
+    let mut held_keys = [0u8; 16];
+
+    let mut latency = None;
+    if options.input_latency {
+        match sdl_context.timer() {
+            Ok(timer) => {
+                platform.record_presses();
+                latency = Some((timer, latency::LatencyProbe::new()));
+            }
+            Err(e) => eprintln!("Could not measure input latency: {}", e),
+        }
+    }
+
+    let mut injector = options.input_port.and_then(|port| match inject::Injector::listen(port) {
+        Ok(injector) => {
+            eprintln!("Taking keypad input on 127.0.0.1:{}", injector.port());
+            Some(injector)
+        }
+        Err(e) => {
+            eprintln!("Could not take keypad input on port {}: {}", port, e);
+            None
+        }
+    });
+
     eprintln!("Started drawing graphics.");
 
-    let mut cycle_counter: usize = 1;
-    while !quit {
-        // TODO: debug
+    while !quit && options.frames.is_none_or(|frames| frame < frames) {
+        let mut actions = platform.process_input(&mut held_keys);
+        if watch.as_mut().is_some_and(|watch| watch.changed()) {
+            actions.push(Action::ReloadRom);
+        }
+        let replaying = playback.is_some() || recording.is_some();
+        if let Some(index) = launcher.as_mut().and_then(|list| list.input(&held_keys)) {
+            actions.push(Action::PlaylistRom(index));
+        } else if launcher.is_none()
+            && !paused
+            && !replaying
+            && playlist.as_ref().is_some_and(|list| list.due(platform.idle_time()))
+        {
+            actions.push(Action::NextRom);
+        }
+        for action in actions {
+            match action {
+                Action::Quit => quit = true,
+                Action::SaveState => match state::save_slot(&chip8, &storage, state_slot) {
+                    Ok(path) => {
+                        eprintln!("Saved state to slot {} ({})", state_slot, path.display());
+                        let thumbnail = state::slot_thumbnail(&storage, state_slot);
+                        platform.show_message_with_image(&format!("Saved slot {}", state_slot), thumbnail_image(thumbnail));
+                    }
+                    Err(e) => eprintln!("Could not save state to slot {}: {}", state_slot, e),
+                },
+                // A loaded state would make the replay diverge from the run
+                Action::LoadState if recording.is_some() || playback.is_some() => {
+                    eprintln!("Loading states is disabled while recording or playing a replay.");
+                }
+                Action::LoadState => match state::load_slot(&mut chip8, &storage, state_slot) {
+                    Ok(_) => {
+                        eprintln!("Loaded state from slot {}", state_slot);
+                        platform.show_message(&format!("Loaded slot {}", state_slot));
+                    }
+                    Err(e) => eprintln!("Could not load state from slot {}: {}", state_slot, e),
+                },
+                // Show what's in the slot so states can be told apart
+                Action::NextSlot => {
+                    state_slot = (state_slot + 1) % state::SLOT_COUNT;
+                    eprintln!("Selected state slot {}", state_slot);
+                    match state::slot_thumbnail(&storage, state_slot) {
+                        Some(thumbnail) => platform.show_message_with_image(
+                            &format!("Slot {}", state_slot),
+                            thumbnail_image(Some(thumbnail)),
+                        ),
+                        None => platform.show_message(&format!("Slot {} (empty)", state_slot)),
+                    }
+                }
+                Action::RewindStart => rewinding = true,
+                Action::RewindStop => rewinding = false,
+                Action::TogglePause => {
+                    paused = !paused;
+                    platform.set_paused(paused);
+                    eprintln!("{} at frame {}", if paused { "Paused" } else { "Resumed" }, frame);
+                }
+                Action::FrameAdvance => advance = paused,
+                // Re-recording replaces the edited replay's inputs from the
+                //  current frame on with live ones
+                Action::ToggleRerecord if tas_mode => {
+                    rerecording = !rerecording;
+                    eprintln!("Re-recording {} at frame {}", if rerecording { "on" } else { "off" }, frame);
+                }
+                Action::SaveReplay if tas_mode => save_replay(recording.as_ref(), replay_path),
+                Action::ToggleRerecord | Action::SaveReplay => {}
+                Action::ToggleGif => match gif.take() {
+                    Some(recorder) => {
+                        let path = recorder.path().to_path_buf();
+                        match recorder.finish() {
+                            Ok(()) => {
+                                eprintln!("Saved GIF {}", path.display());
+                                platform.show_message(&format!("Saved {}", path.display()));
+                            }
+                            Err(e) => eprintln!("Could not save GIF {}: {}", path.display(), e),
+                        }
+                    }
+                    None => {
+                        let path = screenshot::capture_path(&storage.capture_dir(), &rom_name, "gif");
+                        match GifRecorder::start(&path, video_scale as u32, foreground, background) {
+                            Ok(recorder) => {
+                                eprintln!("Recording GIF to {}", path.display());
+                                platform.show_message("Recording GIF");
+                                gif = Some(recorder);
+                            }
+                            Err(e) => eprintln!("Could not start GIF {}: {}", path.display(), e),
+                        }
+                    }
+                },
+                Action::RecordMacro if input_macros.is_recording() => {
+                    let length = input_macros.stop_recording();
+                    match macros::save(&input_macros, &storage) {
+                        Ok(path) => eprintln!("Saved {}-frame macro to {}", length, path.display()),
+                        Err(e) => eprintln!("Could not save macro: {}", e),
+                    }
+                    platform.show_message(&format!("Recorded macro ({} frames)", length));
+                }
+                Action::RecordMacro => {
+                    input_macros.start_recording();
+                    platform.show_message("Recording macro, F3 to stop");
+                }
+                Action::PlayMacro => {
+                    if !input_macros.play() {
+                        platform.show_message("No macro recorded (F3)");
+                    }
+                }
+                Action::NextPalette => {
+                    let index = palette_index.map_or(0, |index| (index + 1) % palette::PALETTES.len());
+                    let palette = &palette::PALETTES[index];
+                    palette_index = Some(index);
+                    foreground = palette.foreground;
+                    background = palette.background;
+                    platform.set_colors(foreground, background);
+                    platform.show_message(&format!("Palette: {}", palette.name));
+                    // Remembered for the next time this ROM is started
+                    rom_settings.palette = Some(String::from(palette.name));
+                    if let Err(e) = rom_settings::save(&rom_settings, &storage) {
+                        eprintln!("Could not save the palette choice: {}", e);
+                    }
+                }
+                Action::ToggleSmoothing => {
+                    smooth = !smooth;
+                    platform.set_smoothing(smooth);
+                    platform.show_message(if smooth { "Filter: linear" } else { "Filter: nearest" });
+                }
+                // Changing the machine under a replay would break it
+                Action::Reset
+                | Action::SoftReset
+                | Action::ToggleQuirk(_)
+                | Action::OpenRom
+                | Action::RomDropped
+                | Action::OpenRecent(_)
+                | Action::ReloadRom
+                | Action::NextRom
+                | Action::PlaylistRom(_)
+                | Action::ShowLauncher
+                    if playback.is_some() || recording.is_some() =>
+                {
+                    platform.show_message("Not while a replay is playing or recording");
+                }
+                Action::Reset => {
+                    chip8.reset(true);
+                    if let Some(seed) = options.seed {
+                        chip8.reseed(seed);
+                    }
+                    platform.show_message("Reset");
+                }
+                Action::SoftReset => {
+                    chip8.reset(false);
+                    platform.show_message("Soft reset");
+                }
+                Action::ToggleQuirk(quirk) => {
+                    let on = quirk.toggle(&mut chip8.quirks);
+                    platform.show_message(&format!("Quirk {}: {}", quirk.name(), if on { "on" } else { "off" }));
+                }
+                Action::ShowLauncher => match &playlist {
+                    Some(list) => launcher = Some(launcher::Launcher::new(list.names(), list.position())),
+                    None => platform.show_message("No ROM list: give a folder or several ROMs"),
+                },
+                Action::OpenRom
+                | Action::RomDropped
+                | Action::OpenRecent(_)
+                | Action::ReloadRom
+                | Action::NextRom
+                | Action::PlaylistRom(_) => {
+                    let picked = match action {
+                        Action::RomDropped => platform.take_dropped(),
+                        Action::OpenRecent(index) => recent_roms.get(index).cloned(),
+                        Action::ReloadRom => rom_path.clone(),
+                        Action::NextRom => playlist.as_mut().map(|list| list.advance()),
+                        Action::PlaylistRom(index) => playlist.as_mut().map(|list| list.select(index)),
+                        _ => picker::pick_rom().map(PathBuf::from),
+                    };
+                    let Some(path) = picked else {
+                        platform.show_message(match action {
+                            Action::OpenRecent(_) => "No other ROM opened recently",
+                            Action::ReloadRom => "Only a ROM file can be reloaded",
+                            Action::NextRom => "No playlist: give several ROMs or a folder",
+                            _ => "No ROM picked",
+                        });
+                        continue;
+                    };
+                    let name = path.to_string_lossy().into_owned();
+                    let patches = if patched_path.as_ref() == Some(&path) { options.patches.as_slice() } else { &[] };
+                    let source = RomSource::File(name.clone());
+                    let (data, mut loaded) = match load_program(&source, patches, Some(chip8.start_address)) {
+                        Ok(program) => program,
+                        Err(e) => {
+                            eprintln!("Could not load {}: {:#}", name, e);
+                            platform.show_message("Could not load that ROM");
+                            continue;
+                        }
+                    };
 
-        quit = platform.process_input(&mut chip8.keypad);
-        eprintln!("Finished processing input.");
+                    // The game being left is kept to resume like on quitting
+                    if options.resume {
+                        if let Err(e) = state::save_auto(&chip8, &storage) {
+                            eprintln!("Could not save the resume state: {}", e);
+                        }
+                    }
+
+                    program_info = archive::lookup(&path);
+                    loaded.quirks = rom_quirks(program_info.as_ref());
+                    choose_quirks(&mut loaded.quirks, &options);
+                    if let Some(seed) = options.seed {
+                        loaded.reseed(seed);
+                    }
+                    cycle_period = rom_cycle_period(program_info.as_ref(), options.cycle_period);
+                    rom = data;
+                    rom_hash = state::rom_hash(&rom);
+                    report::set_rom_hash(&rom_hash);
+                    storage = if options.portable {
+                        Storage::portable(&path, &rom_hash)
+                    } else {
+                        Storage::user(&rom_hash)
+                    };
+                    rom_settings = rom_settings::load(&storage).unwrap_or_default();
+                    input_macros = macros::load(&storage).unwrap_or_else(|_| macros::Macros::new());
+                    game_name = game_title(program_info.as_ref(), &name);
+                    rom_name = name;
+                    // A demo of the old game can't play on the new one
+                    attract = None;
+                    launcher = None;
+                    chip8 = loaded;
+                    rewind.clear();
+                    rewind.push(&chip8);
+                    recent_roms = update_recent(&mut platform, Some(&path));
+                    let verb = if action == Action::ReloadRom { "Reloaded" } else { "Loaded" };
+                    eprintln!("{} {}", verb, rom_name);
+                    platform.show_message(&format!("{} {}", verb, game_name));
+                    if watch.is_some() {
+                        watch = watch_rom(&path);
+                    }
+                    rom_path = Some(path);
+                }
+                Action::NextWaveform if tone.sample.is_some() => platform.show_message("Buzzer: --beep sample"),
+                Action::NextWaveform => {
+                    tone.waveform = tone.waveform.next();
+                    sound.set_tone(tone.clone());
+                    buzzer.set_tone(tone.clone());
+                    platform.show_message(&format!("Buzzer: {}", tone.waveform.name()));
+                }
+                Action::ToggleMute => {
+                    muted = !muted;
+                    sound.set_volume(if muted { 0 } else { volume });
+                    if muted {
+                        platform.show_message("Sound muted");
+                    } else {
+                        platform.show_message(&format!("Volume: {}%", volume));
+                    }
+                }
+                Action::VolumeDown | Action::VolumeUp => {
+                    volume = match action {
+                        Action::VolumeUp => (volume + VOLUME_STEP).min(100),
+                        _ => volume.saturating_sub(VOLUME_STEP),
+                    };
+                    muted = false;
+                    sound.set_volume(volume);
+                    platform.show_message(&format!("Volume: {}%", volume));
+                }
+                Action::ControllerAdded(index) => {
+                    if let Some(name) = controllers.add(index) {
+                        platform.show_message(&format!("Connected {} (player {})", name, controllers.ids().len()));
+                        platform.set_players(controllers.ids());
+                    }
+                }
+                Action::ControllerRemoved(id) => {
+                    if let Some(name) = controllers.remove(id) {
+                        platform.show_message(&format!("Disconnected {}", name));
+                        platform.set_players(controllers.ids());
+                    }
+                }
+                Action::ToggleMagnifier => {
+                    let on = platform.toggle_magnifier();
+                    platform.show_message(if on { "Magnifier on" } else { "Magnifier off" });
+                }
+                Action::RemapKeys => platform.start_remap(),
+                Action::KeymapChanged => match keymap::save(platform.keymap()) {
+                    Ok(path) => {
+                        eprintln!("Saved the keymap to {}", path.display());
+                        for collision in platform.keymap().collisions() {
+                            eprintln!("Warning: {}", collision);
+                        }
+                        platform.show_message("Keys remapped");
+                    }
+                    Err(e) => {
+                        eprintln!("Could not save the keymap: {}", e);
+                        platform.show_message("Keys remapped until quitting");
+                    }
+                },
+                Action::ToggleSoundIndicator => {
+                    let on = platform.toggle_sound_indicator();
+                    platform.show_message(if on { "Sound timer shown" } else { "Sound timer hidden" });
+                }
+                Action::ToggleAlwaysOnTop => {
+                    let on_top = platform.toggle_always_on_top();
+                    platform.show_message(if on_top { "Always on top" } else { "Not on top" });
+                }
+                Action::ToggleFullscreen => {
+                    let fullscreen = platform.toggle_fullscreen();
+                    eprintln!("{}", if fullscreen { "Entered fullscreen" } else { "Left fullscreen" });
+                }
+                Action::Screenshot => {
+                    let path = screenshot::capture_path(&storage.capture_dir(), &rom_name, "png");
+                    let (width, height, rgb) = platform.screenshot(&chip8.video, video_scale as u32);
+                    match screenshot::save_png(&path, width, height, &rgb) {
+                        Ok(()) => {
+                            eprintln!("Saved screenshot {}", path.display());
+                            platform.show_message(&format!("Saved {}", path.display()));
+                        }
+                        Err(e) => {
+                            eprintln!("Could not save screenshot {}: {}", path.display(), e);
+                            platform.show_message("Screenshot failed");
+                        }
+                    }
+                }
+            }
+        }
 
         let current_time = Instant::now();
-        let dt = current_time.duration_since(last_cycle_time);
+        if current_time < next_frame_time {
+            match refresh_period.filter(|_| !paused) {
+                // Draw in-between frames at the screen's own rate
+                Some(refresh) => {
+                    thread::sleep((next_frame_time - current_time).min(refresh));
+                    let remaining = next_frame_time.saturating_duration_since(Instant::now());
+                    platform.present_between(1.0 - remaining.as_secs_f32() / frame_period.as_secs_f32());
+                }
+                None => thread::sleep(next_frame_time - current_time),
+            }
+            continue;
+        }
 
-        if dt > Duration::from_millis(cycle_delay) {
-            last_cycle_time = current_time;
+        // Don't try to catch up after a stall, just carry on from now
+        next_frame_time += frame_period;
+        if next_frame_time < current_time {
+            next_frame_time = current_time + frame_period;
+        }
+
+        let mode = if launcher.is_some() {
+            Some("choosing a ROM")
+        } else if attract.as_ref().is_some_and(|demo| demo.active()) {
+            Some("demo")
+        } else if rewinding {
+            Some("rewinding")
+        } else if tas_mode && rerecording {
+            Some("re-recording")
+        } else if tas_mode {
+            Some("TAS")
+        } else if recording.is_some() {
+            Some("recording")
+        } else if playback.as_ref().is_some_and(|replay| frame < replay.frames.len() as u64) {
+            Some("replay")
+        } else {
+            None
+        };
+        platform.set_quirks(chip8.quirks);
+        let title = window_title(&game_name, mode, paused, cycle_period);
+        if title != shown_title {
+            platform.set_title(&title);
+            shown_title = title;
+        }
+
+        // The ROM list stands in for the game until one is picked
+        if let Some(list) = &launcher {
+            sound.set_buzzing(false);
+            platform.update(&list.render());
+            continue;
+        }
+
+        // Attract mode takes over after a while without input and hands the
+        //  game back on the next key press
+        if let Some(demo) = &mut attract {
+            let idle = platform.idle_time() >= demo.idle;
+            if demo.active() && !idle {
+                match demo.stop(&mut chip8) {
+                    Ok(resumed) => frame = resumed,
+                    Err(e) => eprintln!("Could not return from attract mode: {}", e),
+                }
+                rewind.clear();
+                rewind.push(&chip8);
+                eprintln!("Left attract mode at frame {}", frame);
+            } else if !demo.active() && idle && !paused {
+                match demo.start(&mut chip8, &rom, frame) {
+                    Ok(()) => eprintln!("Started attract mode"),
+                    Err(e) => eprintln!("Could not start attract mode: {}", e),
+                }
+            }
+
+            if demo.active() {
+                if let Err(e) = demo.run_frame(&mut chip8, &rom) {
+                    eprintln!("Attract mode failed: {}", e);
+                }
+                sound.set_buzzing(false);
+                platform.update(&chip8.video);
+                continue;
+            }
+        }
+
+        // Step back one frame per frame while rewinding
+        if rewinding {
+            sound.set_buzzing(false);
+            if rewind.pop(&mut chip8) {
+                frame -= 1;
+                platform.update(&chip8.video);
+                capture_gif_frame(&mut gif, &chip8.video);
+                let silence = if video.is_some() { buzzer.frame(false) } else { Vec::new() };
+                capture_video_frame(&mut video, &chip8.video, &silence);
+            }
+            continue;
+        }
+
+        if paused && !advance {
+            sound.set_buzzing(false);
+            // Keep redrawing so on-screen messages appear and expire
+            platform.refresh(&chip8.video);
+            continue;
+        }
+        // Each highlighted draw stays on screen for a moment
+        if draw_viz.as_mut().is_some_and(|viz| viz.hold()) {
+            sound.set_buzzing(false);
+            platform.refresh(&chip8.video);
+            continue;
+        }
+        // Autosaves only follow the game itself, not demos, rewinding or pauses
+        if let Some(interval) = autosave_interval {
+            if last_autosave.elapsed() >= interval {
+                last_autosave = Instant::now();
+                match state::save_autosave(&chip8, &storage) {
+                    Ok(path) => eprintln!("Autosaved to {}", path.display()),
+                    Err(e) => eprintln!("Could not autosave: {}", e),
+                }
+            }
+        }
+
+        if advance {
+            advance = false;
+            eprintln!("Advanced to frame {}", frame + 1);
+        }
+
+        if let Some((_, probe)) = &mut latency {
+            for (key, at) in platform.take_presses() {
+                probe.pressed(key, at, frame);
+            }
+        }
+
+        // Replays drive the keypad while they have inputs left; an edited
+        //  replay falls back to live input once re-recording or past its end
+        let replayed = match (&playback, &recording) {
+            (Some(replay), _) => {
+                if frame == replay.frames.len() as u64 {
+                    eprintln!("Replay finished after {} frames.", frame);
+                }
+                replay.keys(frame)
+            }
+            (None, Some(replay)) if tas_mode && !rerecording => replay.keys(frame),
+            _ => None,
+        };
+        match replayed {
+            Some(keys) => chip8.keypad = keys,
+            None => {
+                chip8.keypad = held_keys;
+                platform.turbo(&mut chip8.keypad);
+                input_macros.apply(&mut chip8.keypad);
+                if let Some(injector) = &mut injector {
+                    injector.apply(frame, &mut chip8.keypad);
+                }
+                if let Some(replay) = &mut recording {
+                    replay.record(frame, &chip8.keypad);
+                }
+            }
+        }
+
+        // The timer counts down at the end of the frame, so it sounds for
+        //  the whole of it even if the frame runs no cycles at a slow speed
+        let mut buzzing = chip8.sound_timer > 0;
+        let mut highlight = None;
+        let held = draw_viz.as_mut().map_or(0, |viz| viz.take_held_cycles());
+        let budget = timing::cycles_in_frame(frame, cycle_period) + held;
+        for done in 1..=budget {
+            let before = draw_viz.is_some().then_some(chip8.video);
             chip8.cycle();
-            eprintln!("Cycle {} completed.", cycle_counter);
-            
-            // eprintln!("---DEBUG--- Pixel data {}", chip8.video.len());
-            // for i in 0..chip8.video.len() {
-            //     eprint!("{} ", chip8.video[i]);
-            // }
-            // eprintln!("---DEBUG---");
+            buzzing |= chip8.sound_timer > 0;
+
+            // The rest of the frame waits until the draw has been shown
+            if let (Some(viz), Some(before), Some(draw)) = (&mut draw_viz, before, chip8.last_draw) {
+                if chip8.opcode & 0xF000 == 0xD000 {
+                    highlight = Some(draw_viz::Highlight::new(&before, &chip8.video, draw, chip8.quirks.clip));
+                    viz.start_hold(budget - done);
+                    break;
+                }
+            }
+        }
+        if let Some((timer, probe)) = &mut latency {
+            probe.observed(chip8.key_reads, timer.ticks(), frame);
+        }
+        chip8.key_reads = 0;
+        chip8.tick_timers();
+        frame += 1;
+
+        // The newest snapshot is always the current frame, so each step
+        //  back lands exactly one frame earlier
+        rewind.push(&chip8);
+
+        let pattern = buzzer::Pattern::of(&chip8);
+        sound.set_pattern(pattern);
+        buzzer.set_pattern(pattern);
+        sound.set_buzzing(buzzing);
+        if options.rumble {
+            controllers.rumble(buzzing);
+        }
+        platform.set_sound_timer(chip8.sound_timer, buzzing);
+        platform.follow_draw(chip8.last_draw);
+        if draw_viz.is_some() {
+            platform.set_highlight(highlight);
+        }
+        platform.update(&chip8.video);
+        capture_gif_frame(&mut gif, &chip8.video);
+        // One frame of sound for every recording, so they all stay in step
+        let samples = if audio.is_some() || video.is_some() { buzzer.frame(buzzing) } else { Vec::new() };
+        capture_video_frame(&mut video, &chip8.video, &samples);
+        if let Some(recorder) = &mut audio {
+            if let Err(e) = recorder.push(&samples) {
+                eprintln!("Stopped audio recording: {}", e);
+                finish_audio(audio.take());
+            }
+        }
+    }
+
+    save_replay(recording.as_ref(), replay_path);
 
-            platform.update(&mut chip8.video, video_pitch);
-            
+    // Quitting during the demo saves the game, not the demo
+    if let Some(demo) = attract.as_mut().filter(|demo| demo.active()) {
+        if let Err(e) = demo.stop(&mut chip8) {
+            eprintln!("Could not return from attract mode: {}", e);
+        }
+    }
+
+    if options.resume {
+        match state::save_auto(&chip8, &storage) {
+            Ok(path) => eprintln!("Saved state for next launch to {}", path.display()),
+            Err(e) => eprintln!("Could not save state for next launch: {}", e),
+        }
+    }
+
+    if let Some(recorder) = gif {
+        let path = recorder.path().to_path_buf();
+        match recorder.finish() {
+            Ok(()) => eprintln!("Saved GIF {}", path.display()),
+            Err(e) => eprintln!("Could not save GIF {}: {}", path.display(), e),
+        }
+    }
+    finish_video(video);
+    finish_audio(audio);
+
+    if let Some(path) = &options.exit_screenshot {
+        let (width, height, rgb) = platform.screenshot(&chip8.video, video_scale as u32);
+        save_exit_screenshot(path, width, height, &rgb);
+    }
+    if options.frame_hash {
+        println!("{}", platform.frame_hash());
+    }
+    if let Some((_, probe)) = &latency {
+        println!("{}", probe.report());
+    }
+    report::finish(if quit { "quit" } else { "frames" }, Some(frame));
+}
 
+// SDL and a window of `size` for the display and what's around it, grown
+//  for the screen's DPI where the system doesn't do that by itself
+fn open_window(options: &cli::Options, size: (u32, u32)) -> anyhow::Result<(Sdl, VideoSubsystem, EventPump, WindowCanvas)> {
+    let sdl_context = sdl2::init().map_err(DisplayError::Sdl)?;
+    let video_subsystem = sdl_context.video().map_err(DisplayError::Video)?;
+    let event_pump = sdl_context.event_pump().map_err(DisplayError::Events)?;
+
+    let displays = video_subsystem.num_video_displays().unwrap_or(1);
+    if options.monitor >= displays {
+        return Err(DisplayError::NoMonitor {
+            monitor: options.monitor,
+            found: displays,
+        }
+        .into());
+    }
+    let (width, height) = size;
+    let (x, y) = platform::window_position(&video_subsystem, options.monitor, options.window_pos, size);
+
+    let mut window_builder = video_subsystem.window(TITLE, width, height);
+    window_builder.position(x, y).resizable().allow_highdpi();
+    if options.always_on_top {
+        window_builder.always_on_top();
+    }
+    let window = window_builder
+        .build()
+        .map_err(|source| DisplayError::Window { width, height, source })?;
+    let mut canvas = build_canvas(window, options.renderer, options.render_driver.as_deref())?;
+
+    // macOS and Wayland size windows in points and hand high-DPI screens a
+    //  bigger drawable by themselves; elsewhere the window is in pixels and
+    //  would come out tiny on a 4K screen, so grow it by the desktop's scaling
+    let drawable_matches_window = canvas.output_size().is_ok_and(|size| size == canvas.window().size());
+    if options.scale != Scale::Auto && drawable_matches_window {
+        let factor = platform::dpi_factor(&video_subsystem, options.monitor);
+        if factor > 1.0 {
+            let width = (width as f32 * factor).round() as u32;
+            let height = (height as f32 * factor).round() as u32;
+            let (x, y) = platform::window_position(&video_subsystem, options.monitor, options.window_pos, (width, height));
+            let window = canvas.window_mut();
+            if window.set_size(width, height).is_ok() {
+                window.set_position(WindowPos::Positioned(x), WindowPos::Positioned(y));
+                eprintln!("Scaled the window by {:.2} for the screen's DPI", factor);
+            }
+        }
+    }
+    Ok((sdl_context, video_subsystem, event_pump, canvas))
+}
+
+// Without a choice SDL tries the GPU drivers first and falls back to its
+//  software renderer on its own
+fn build_canvas(window: Window, backend: Option<RenderBackend>, driver: Option<&str>) -> Result<WindowCanvas, DisplayError> {
+    let mut builder = window.into_canvas();
+    builder = match backend {
+        Some(RenderBackend::Accelerated) => builder.accelerated(),
+        Some(RenderBackend::Software) => builder.software(),
+        None => builder,
+    };
+    if let Some(name) = driver {
+        match sdl2::render::drivers().position(|info| info.name == name) {
+            Some(index) => builder = builder.index(index as u32),
+            None => {
+                let names: Vec<&str> = sdl2::render::drivers().map(|info| info.name).collect();
+                return Err(DisplayError::RenderDriver {
+                    name: name.to_string(),
+                    available: names.join(", "),
+                });
+            }
+        }
+    }
 
-            eprintln!("Platform updated.");
+    let canvas = builder.build().map_err(|source| DisplayError::Renderer {
+        source,
+        software: backend == Some(RenderBackend::Software),
+    })?;
+    eprintln!("Rendering with {}", canvas.info().name);
+    Ok(canvas)
+}
 
-            cycle_counter += 1;
+// A failed write ends the recording, not the emulator
+fn capture_video_frame(video: &mut Option<VideoRecorder>, frame: &[u8], samples: &[i16]) {
+    if let Some(recorder) = video {
+        if let Err(e) = recorder.push(frame, samples) {
+            eprintln!("Stopped video recording: {}", e);
+            finish_video(video.take());
         }
+    }
+}
+
+fn finish_video(video: Option<VideoRecorder>) {
+    if let Some(recorder) = video {
+        let path = recorder.path().to_path_buf();
+        match recorder.finish() {
+            Ok(()) => eprintln!("Saved video {}", path.display()),
+            Err(e) => eprintln!("Could not save video {}: {}", path.display(), e),
+        }
+    }
+}
+
+fn finish_audio(audio: Option<WavRecorder>) {
+    if let Some(recorder) = audio {
+        let path = recorder.path().to_path_buf();
+        match recorder.finish() {
+            Ok(()) => eprintln!("Saved audio {}", path.display()),
+            Err(e) => eprintln!("Could not save audio {}: {}", path.display(), e),
+        }
+    }
+}
+
+fn capture_gif_frame(gif: &mut Option<GifRecorder>, video: &[u8]) {
+    if let Some(recorder) = gif {
+        if let Err(e) = recorder.push(video) {
+            eprintln!("Stopped GIF recording: {}", e);
+            *gif = None;
+        }
+    }
+}
+
+// e.g. "Pong - CHIP-8 Emulator [recording, paused] 500 Hz"
+fn window_title(game: &str, mode: Option<&str>, paused: bool, cycle_period: Duration) -> String {
+    let states: Vec<&str> = mode.into_iter().chain(paused.then_some("paused")).collect();
+    let hertz = (1.0 / cycle_period.max(timing::MIN_CYCLE_PERIOD).as_secs_f64()).round();
+
+    let mut title = format!("{} - {}", game, TITLE);
+    if !states.is_empty() {
+        title += &format!(" [{}]", states.join(", "));
+    }
+    title + &format!(" {} Hz", hertz)
+}
+
+// Put the ROM file being played first among the recent ones, and offer the
+//  others in the menu, returning them in the order of Action::OpenRecent
+fn update_recent(platform: &mut platform::Platform, opened: Option<&Path>) -> Vec<PathBuf> {
+    let recent = match opened.map(recent::add) {
+        Some(Ok(recent)) => recent,
+        Some(Err(e)) => {
+            eprintln!("Could not remember the ROM in the recent list: {}", e);
+            recent::load()
+        }
+        None => recent::load(),
+    };
+    let others = recent::others(recent, opened);
+    platform.set_recent(others.iter().map(|path| recent::name(path)).collect());
+    others
+}
+
+// ROMs from the CHIP-8 Archive run at their own speed, unless the command
+//  line gives one
+fn rom_cycle_period(program: Option<&archive::Program>, chosen: Option<Duration>) -> Duration {
+    chosen
+        .or_else(|| program.and_then(archive::Program::cycle_period))
+        .unwrap_or(Duration::from_secs(1) / DEFAULT_IPS)
+}
+
+fn rom_quirks(program: Option<&archive::Program>) -> quirks::Quirks {
+    program.map_or_else(quirks::Quirks::default, archive::Program::quirks)
+}
+
+// The quirks set on the command line or in config.toml, over the ROM's
+fn choose_quirks(quirks: &mut quirks::Quirks, options: &cli::Options) {
+    quirks.key_repeat |= options.key_repeat;
+    for &(quirk, on) in &options.quirks {
+        quirk.set(quirks, on);
+    }
+}
+
+// The chosen preset with keymap.toml applied over it, or the preset alone if
+//  the file can't be read
+fn load_keymap(options: &cli::Options) -> keymap::Keymap {
+    let preset = options.keymap.unwrap_or(&keymap::PRESETS[0]);
+    keymap::load(preset).unwrap_or_else(|e| {
+        eprintln!("Warning: could not load keymap.toml, using the {} keymap alone: {}", preset.name, e);
+        keymap::Keymap::preset(preset)
+    })
+}
+
+// The program from `source` with `patches` applied in order, and a machine
+//  with it loaded at `start_address`, or 0x200. ROMs opened while running
+//  come through here too, so a reloaded ROM comes back as it started.
+fn load_program(
+    source: &RomSource,
+    patches: &[String],
+    start_address: Option<u16>,
+) -> anyhow::Result<(Vec<u8>, chip8::Chip8)> {
+    let mut rom = match source {
+        RomSource::Builtin(rom) => rom.data.to_vec(),
+        // URLs are downloaded into the cache and then loaded like any other file
+        RomSource::File(argument) if fetch::is_url(argument) => {
+            fetch::fetch_cached(argument).and_then(|path| chip8::read_rom(&path.to_string_lossy()))?
+        }
+        RomSource::File(argument) => chip8::read_rom(argument)?,
+        RomSource::Pick => unreachable!("the ROM was picked above"),
+    };
+
+    // Patches are applied in the order given, on top of one another
+    for patch_file in patches {
+        patch::apply_file(&mut rom, Path::new(patch_file))
+            .with_context(|| format!("could not apply patch '{}'", patch_file))?;
+        eprintln!("Applied patch {}", patch_file);
+    }
+
+    let mut chip8 = chip8::Chip8::new();
+    if let Some(address) = start_address {
+        chip8.set_start_address(address);
+    }
+    chip8.load_rom(&rom).with_context(|| format!("invalid ROM '{}'", source.name()))?;
+    Ok((rom, chip8))
+}
+
+// A watch on the ROM file for --watch, or None if the system won't give one
+fn watch_rom(path: &Path) -> Option<watch::Watch> {
+    match watch::Watch::new(path) {
+        Ok(watch) => Some(watch),
+        Err(e) => {
+            eprintln!("Could not watch {} for changes: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+// The game's title in the archive, or else the ROM's file name
+fn game_title(program: Option<&archive::Program>, rom_name: &str) -> String {
+    match program {
+        Some(program) => program.title.clone(),
+        None => Path::new(rom_name)
+            .file_name()
+            .map_or_else(|| String::from(rom_name), |name| name.to_string_lossy().into_owned()),
+    }
+}
+
+fn thumbnail_image(thumbnail: Option<Vec<u8>>) -> Option<osd::Image> {
+    thumbnail.map(|pixels| osd::Image {
+        pixels,
+        width: state::THUMBNAIL_WIDTH,
+    })
+}
+
+fn save_replay(replay: Option<&replay::Replay>, path: Option<&String>) {
+    if let (Some(replay), Some(path)) = (replay, path) {
+        match replay.save(Path::new(path)) {
+            Ok(()) => eprintln!("Saved replay {} ({} frames)", path, replay.frames.len()),
+            Err(e) => eprintln!("Could not save replay '{}': {}", path, e),
+        }
+    }
+}
+
+// Write the last frame for --exit-screenshot. Scripts rely on the file, so
+//  not being able to is an error.
+fn save_exit_screenshot(path: &str, width: u32, height: u32, rgb: &[u8]) {
+    match screenshot::save_png(Path::new(path), width, height, rgb) {
+        Ok(()) => eprintln!("Saved screenshot {}", path),
+        Err(e) => report::fail(ErrorKind::File, format!("could not save screenshot '{}': {}", path, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn titles_show_the_mode_pause_and_speed() {
+        let period = Duration::from_millis(2);
+        assert_eq!(window_title("Pong", None, false, period), format!("Pong - {} 500 Hz", TITLE));
+        assert_eq!(
+            window_title("Pong", Some("recording"), true, period),
+            format!("Pong - {} [recording, paused] 500 Hz", TITLE)
+        );
+    }
+
+    #[test]
+    fn opened_roms_are_named_by_file_and_run_at_the_chosen_speed() {
+        assert_eq!(game_title(None, "/games/Pong.ch8"), "Pong.ch8");
+        assert_eq!(rom_cycle_period(None, Some(Duration::from_millis(3))), Duration::from_millis(3));
+        assert_eq!(rom_cycle_period(None, None), Duration::from_secs(1) / DEFAULT_IPS);
+        assert_eq!(rom_quirks(None), quirks::Quirks::default());
+    }
+
+    #[test]
+    fn programs_load_with_their_patches_and_must_fit_in_memory() {
+        let dir = std::env::temp_dir().join(format!("chip8-switch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let rom = dir.join("pong.ch8");
+        std::fs::write(&rom, [0x12, 0x00]).unwrap();
+        let big = dir.join("big.ch8");
+        std::fs::write(&big, vec![0; 4096]).unwrap();
+
+        let patch = dir.join("fix.txt");
+        std::fs::write(&patch, "1: 02\n").unwrap();
+        let source = |path: &Path| RomSource::File(path.display().to_string());
+        let load = |path: &Path, patches: &[String]| load_program(&source(path), patches, None);
+        let opened = load(&rom, &[]);
+        let patched = load(&rom, &[patch.display().to_string()]);
+        let too_big = load(&big, &[]);
+        let folder = load(&dir, &[]);
+        let moved = load_program(&source(&rom), &[], Some(0x600));
+        std::fs::remove_dir_all(&dir).unwrap();
 
-        // TODO: debug
+        let (data, chip8) = opened.unwrap();
+        assert_eq!(data, [0x12, 0x00]);
+        assert_eq!(chip8.memory[0x200..0x202], [0x12, 0x00]);
+        assert_eq!(patched.unwrap().0, [0x12, 0x02]);
+        let moved = moved.unwrap().1;
+        assert_eq!((moved.pc, &moved.memory[0x600..0x602]), (0x600, &[0x12, 0x00][..]));
+        let error = format!("{:#}", too_big.err().unwrap());
+        assert!(error.starts_with("invalid ROM") && error.ends_with("larger than the 3584 bytes available"), "{}", error);
+        assert!(folder.is_err());
     }
-}
\ No newline at end of file
+}
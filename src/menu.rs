@@ -0,0 +1,166 @@
+use egui::{Context, TopBottomPanel};
+
+use crate::platform::Action;
+use crate::quirks::{Quirk, Quirks};
+
+// The menus along the bar and their items, in order. Quirks has a menu of
+//  its own, with a tick for each quirk that is on.
+const MENUS: [(&str, &[(&str, Action)]); 3] = [
+    (
+        "File",
+        &[
+            ("Open ROM...", Action::OpenRom),
+            ("Reset", Action::Reset),
+            ("Soft reset", Action::SoftReset),
+            ("Next ROM", Action::NextRom),
+            ("ROM list", Action::ShowLauncher),
+            ("Screenshot", Action::Screenshot),
+            ("Record GIF", Action::ToggleGif),
+            ("Quit", Action::Quit),
+        ],
+    ),
+    (
+        "Emulation",
+        &[
+            ("Pause / resume", Action::TogglePause),
+            ("Save state", Action::SaveState),
+            ("Load state", Action::LoadState),
+            ("Next state slot", Action::NextSlot),
+            ("Remap keys", Action::RemapKeys),
+        ],
+    ),
+    (
+        "View",
+        &[
+            ("Next palette", Action::NextPalette),
+            ("Next buzzer sound", Action::NextWaveform),
+            ("Mute / unmute", Action::ToggleMute),
+            ("Sound timer", Action::ToggleSoundIndicator),
+            ("Smooth scaling", Action::ToggleSmoothing),
+            ("Fullscreen", Action::ToggleFullscreen),
+            ("Always on top", Action::ToggleAlwaysOnTop),
+        ],
+    ),
+];
+
+// How many of the ROMs opened before this one File offers to reopen
+const RECENT_ITEMS: usize = 5;
+
+// A menu bar along the top of the window, opened with the right mouse
+//  button, so the emulator can be used with the mouse alone. `recent` names
+//  the ROMs for Action::OpenRecent. Returns what was picked this frame.
+pub fn show(ctx: &Context, quirks: Quirks, recent: &[String]) -> Vec<Action> {
+    let mut picked = Vec::new();
+    TopBottomPanel::top("menu").show(ctx, |ui| {
+        egui::menu::bar(ui, |ui| {
+            for (title, items) in MENUS {
+                ui.menu_button(title, |ui| {
+                    for &(label, action) in items {
+                        if ui.button(label).clicked() {
+                            picked.push(action);
+                            ui.close_menu();
+                        }
+                        if action == Action::OpenRom {
+                            ui.add_enabled_ui(!recent.is_empty(), |ui| {
+                                ui.menu_button("Open recent", |ui| {
+                                    for (index, name) in recent.iter().take(RECENT_ITEMS).enumerate() {
+                                        if ui.button(name).clicked() {
+                                            picked.push(Action::OpenRecent(index));
+                                            ui.close_menu();
+                                        }
+                                    }
+                                });
+                            });
+                        }
+                    }
+                });
+            }
+            ui.menu_button("Quirks", |ui| {
+                for quirk in Quirk::ALL {
+                    let mut on = quirk.is_on(quirks);
+                    if ui.checkbox(&mut on, quirk.name()).clicked() {
+                        picked.push(Action::ToggleQuirk(quirk));
+                    }
+                }
+            });
+        });
+    });
+    picked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{Event, PointerButton, Pos2, RawInput, Rect};
+
+    fn frame(ctx: &Context, events: Vec<Event>) -> Vec<Action> {
+        frame_with_recent(ctx, events, &[])
+    }
+
+    fn frame_with_recent(ctx: &Context, events: Vec<Event>, recent: &[String]) -> Vec<Action> {
+        let input = RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, egui::vec2(640.0, 320.0))),
+            events,
+            ..RawInput::default()
+        };
+        let mut picked = Vec::new();
+        let _ = ctx.run(input, |ctx| picked = show(ctx, Quirks::default(), recent));
+        picked
+    }
+
+    fn click(pos: Pos2) -> Vec<Event> {
+        let button = |pressed| Event::PointerButton {
+            pos,
+            button: PointerButton::Primary,
+            pressed,
+            modifiers: egui::Modifiers::default(),
+        };
+        vec![Event::PointerMoved(pos), button(true), button(false)]
+    }
+
+    #[test]
+    fn the_bar_sits_along_the_top() {
+        let ctx = Context::default();
+        frame(&ctx, vec![Event::PointerMoved(Pos2::new(5.0, 5.0))]);
+        frame(&ctx, Vec::new());
+        assert!(ctx.is_pointer_over_area());
+
+        frame(&ctx, vec![Event::PointerMoved(Pos2::new(300.0, 200.0))]);
+        frame(&ctx, Vec::new());
+        assert!(!ctx.is_pointer_over_area());
+    }
+
+    #[test]
+    fn items_report_their_action() {
+        let ctx = Context::default();
+        frame(&ctx, Vec::new());
+        // File opens below its button, with Open ROM first and Reset next
+        assert!(frame(&ctx, click(Pos2::new(10.0, 10.0))).is_empty());
+        frame(&ctx, Vec::new());
+        let mut picked = Vec::new();
+        for y in (20..80).step_by(2) {
+            picked = frame(&ctx, click(Pos2::new(30.0, y as f32)));
+            if !picked.is_empty() {
+                break;
+            }
+        }
+        assert_eq!(picked, [Action::OpenRom]);
+    }
+
+    #[test]
+    fn recent_roms_are_listed_under_open_rom() {
+        let pick_second = |recent: &[String]| {
+            let ctx = Context::default();
+            frame_with_recent(&ctx, Vec::new(), recent);
+            // File, then Open recent just below Open ROM, then the second
+            //  name in the list that opens beside it
+            for pos in [Pos2::new(10.0, 10.0), Pos2::new(30.0, 58.0)] {
+                frame_with_recent(&ctx, click(pos), recent);
+                frame_with_recent(&ctx, Vec::new(), recent);
+            }
+            frame_with_recent(&ctx, click(Pos2::new(150.0, 80.0)), recent)
+        };
+        assert_eq!(pick_second(&[String::from("pong"), String::from("tetris")]), [Action::OpenRecent(1)]);
+        assert!(pick_second(&[]).is_empty());
+    }
+}
@@ -0,0 +1,210 @@
+use std::fmt;
+
+// A decoded CHIP-8 instruction, using Cowgod's mnemonics. Register operands
+//  are register numbers, addresses are 12-bit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    Sys(u16),
+    Cls,
+    Ret,
+    Jp(u16),
+    Call(u16),
+    SeByte(u8, u8),
+    SneByte(u8, u8),
+    SeReg(u8, u8),
+    LdByte(u8, u8),
+    AddByte(u8, u8),
+    LdReg(u8, u8),
+    Or(u8, u8),
+    And(u8, u8),
+    Xor(u8, u8),
+    AddReg(u8, u8),
+    Sub(u8, u8),
+    Shr(u8, u8),
+    Subn(u8, u8),
+    Shl(u8, u8),
+    SneReg(u8, u8),
+    LdI(u16),
+    JpV0(u16),
+    Rnd(u8, u8),
+    Drw(u8, u8, u8),
+    Skp(u8),
+    Sknp(u8),
+    LdFromDt(u8),
+    LdKey(u8),
+    LdDt(u8),
+    LdSt(u8),
+    AddI(u8),
+    LdFont(u8),
+    LdBcd(u8),
+    Store(u8),
+    Load(u8),
+}
+
+impl Instruction {
+    // Returns None for words that are not a valid instruction, so callers can
+    //  treat them as data
+    pub fn decode(opcode: u16) -> Option<Instruction> {
+        let x = ((opcode & 0x0F00) >> 8) as u8;
+        let y = ((opcode & 0x00F0) >> 4) as u8;
+        let n = (opcode & 0x000F) as u8;
+        let kk = (opcode & 0x00FF) as u8;
+        let nnn = opcode & 0x0FFF;
+
+        let instruction = match opcode >> 12 {
+            0x0 => match opcode {
+                0x00E0 => Instruction::Cls,
+                0x00EE => Instruction::Ret,
+                _ => Instruction::Sys(nnn),
+            },
+            0x1 => Instruction::Jp(nnn),
+            0x2 => Instruction::Call(nnn),
+            0x3 => Instruction::SeByte(x, kk),
+            0x4 => Instruction::SneByte(x, kk),
+            0x5 if n == 0 => Instruction::SeReg(x, y),
+            0x6 => Instruction::LdByte(x, kk),
+            0x7 => Instruction::AddByte(x, kk),
+            0x8 => match n {
+                0x0 => Instruction::LdReg(x, y),
+                0x1 => Instruction::Or(x, y),
+                0x2 => Instruction::And(x, y),
+                0x3 => Instruction::Xor(x, y),
+                0x4 => Instruction::AddReg(x, y),
+                0x5 => Instruction::Sub(x, y),
+                0x6 => Instruction::Shr(x, y),
+                0x7 => Instruction::Subn(x, y),
+                0xE => Instruction::Shl(x, y),
+                _ => return None,
+            },
+            0x9 if n == 0 => Instruction::SneReg(x, y),
+            0xA => Instruction::LdI(nnn),
+            0xB => Instruction::JpV0(nnn),
+            0xC => Instruction::Rnd(x, kk),
+            0xD => Instruction::Drw(x, y, n),
+            0xE => match kk {
+                0x9E => Instruction::Skp(x),
+                0xA1 => Instruction::Sknp(x),
+                _ => return None,
+            },
+            0xF => match kk {
+                0x07 => Instruction::LdFromDt(x),
+                0x0A => Instruction::LdKey(x),
+                0x15 => Instruction::LdDt(x),
+                0x18 => Instruction::LdSt(x),
+                0x1E => Instruction::AddI(x),
+                0x29 => Instruction::LdFont(x),
+                0x33 => Instruction::LdBcd(x),
+                0x55 => Instruction::Store(x),
+                0x65 => Instruction::Load(x),
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        Some(instruction)
+    }
+
+    pub fn encode(&self) -> u16 {
+        let xy = |base: u16, x: u8, y: u8| base | (x as u16) << 8 | (y as u16) << 4;
+        let xkk = |base: u16, x: u8, kk: u8| base | (x as u16) << 8 | kk as u16;
+        let fx = |low: u16, x: u8| 0xF000 | (x as u16) << 8 | low;
+
+        match *self {
+            Instruction::Sys(nnn) => nnn & 0x0FFF,
+            Instruction::Cls => 0x00E0,
+            Instruction::Ret => 0x00EE,
+            Instruction::Jp(nnn) => 0x1000 | (nnn & 0x0FFF),
+            Instruction::Call(nnn) => 0x2000 | (nnn & 0x0FFF),
+            Instruction::SeByte(x, kk) => xkk(0x3000, x, kk),
+            Instruction::SneByte(x, kk) => xkk(0x4000, x, kk),
+            Instruction::SeReg(x, y) => xy(0x5000, x, y),
+            Instruction::LdByte(x, kk) => xkk(0x6000, x, kk),
+            Instruction::AddByte(x, kk) => xkk(0x7000, x, kk),
+            Instruction::LdReg(x, y) => xy(0x8000, x, y),
+            Instruction::Or(x, y) => xy(0x8001, x, y),
+            Instruction::And(x, y) => xy(0x8002, x, y),
+            Instruction::Xor(x, y) => xy(0x8003, x, y),
+            Instruction::AddReg(x, y) => xy(0x8004, x, y),
+            Instruction::Sub(x, y) => xy(0x8005, x, y),
+            Instruction::Shr(x, y) => xy(0x8006, x, y),
+            Instruction::Subn(x, y) => xy(0x8007, x, y),
+            Instruction::Shl(x, y) => xy(0x800E, x, y),
+            Instruction::SneReg(x, y) => xy(0x9000, x, y),
+            Instruction::LdI(nnn) => 0xA000 | (nnn & 0x0FFF),
+            Instruction::JpV0(nnn) => 0xB000 | (nnn & 0x0FFF),
+            Instruction::Rnd(x, kk) => xkk(0xC000, x, kk),
+            Instruction::Drw(x, y, n) => xy(0xD000, x, y) | (n & 0xF) as u16,
+            Instruction::Skp(x) => xkk(0xE000, x, 0x9E),
+            Instruction::Sknp(x) => xkk(0xE000, x, 0xA1),
+            Instruction::LdFromDt(x) => fx(0x07, x),
+            Instruction::LdKey(x) => fx(0x0A, x),
+            Instruction::LdDt(x) => fx(0x15, x),
+            Instruction::LdSt(x) => fx(0x18, x),
+            Instruction::AddI(x) => fx(0x1E, x),
+            Instruction::LdFont(x) => fx(0x29, x),
+            Instruction::LdBcd(x) => fx(0x33, x),
+            Instruction::Store(x) => fx(0x55, x),
+            Instruction::Load(x) => fx(0x65, x),
+        }
+    }
+
+    // The address operand of jumps, calls and `ld i`, if any
+    pub fn target(&self) -> Option<u16> {
+        match *self {
+            Instruction::Sys(nnn)
+            | Instruction::Jp(nnn)
+            | Instruction::Call(nnn)
+            | Instruction::LdI(nnn)
+            | Instruction::JpV0(nnn) => Some(nnn),
+            _ => None,
+        }
+    }
+
+    // Format the instruction, letting the caller choose how addresses are
+    //  written (e.g. as labels)
+    pub fn format_with(&self, address: &dyn Fn(u16) -> String) -> String {
+        match *self {
+            Instruction::Sys(nnn) => format!("sys {}", address(nnn)),
+            Instruction::Cls => String::from("cls"),
+            Instruction::Ret => String::from("ret"),
+            Instruction::Jp(nnn) => format!("jp {}", address(nnn)),
+            Instruction::Call(nnn) => format!("call {}", address(nnn)),
+            Instruction::SeByte(x, kk) => format!("se v{:x}, 0x{:02x}", x, kk),
+            Instruction::SneByte(x, kk) => format!("sne v{:x}, 0x{:02x}", x, kk),
+            Instruction::SeReg(x, y) => format!("se v{:x}, v{:x}", x, y),
+            Instruction::LdByte(x, kk) => format!("ld v{:x}, 0x{:02x}", x, kk),
+            Instruction::AddByte(x, kk) => format!("add v{:x}, 0x{:02x}", x, kk),
+            Instruction::LdReg(x, y) => format!("ld v{:x}, v{:x}", x, y),
+            Instruction::Or(x, y) => format!("or v{:x}, v{:x}", x, y),
+            Instruction::And(x, y) => format!("and v{:x}, v{:x}", x, y),
+            Instruction::Xor(x, y) => format!("xor v{:x}, v{:x}", x, y),
+            Instruction::AddReg(x, y) => format!("add v{:x}, v{:x}", x, y),
+            Instruction::Sub(x, y) => format!("sub v{:x}, v{:x}", x, y),
+            Instruction::Shr(x, y) => format!("shr v{:x}, v{:x}", x, y),
+            Instruction::Subn(x, y) => format!("subn v{:x}, v{:x}", x, y),
+            Instruction::Shl(x, y) => format!("shl v{:x}, v{:x}", x, y),
+            Instruction::SneReg(x, y) => format!("sne v{:x}, v{:x}", x, y),
+            Instruction::LdI(nnn) => format!("ld i, {}", address(nnn)),
+            Instruction::JpV0(nnn) => format!("jp v0, {}", address(nnn)),
+            Instruction::Rnd(x, kk) => format!("rnd v{:x}, 0x{:02x}", x, kk),
+            Instruction::Drw(x, y, n) => format!("drw v{:x}, v{:x}, {}", x, y, n),
+            Instruction::Skp(x) => format!("skp v{:x}", x),
+            Instruction::Sknp(x) => format!("sknp v{:x}", x),
+            Instruction::LdFromDt(x) => format!("ld v{:x}, dt", x),
+            Instruction::LdKey(x) => format!("ld v{:x}, k", x),
+            Instruction::LdDt(x) => format!("ld dt, v{:x}", x),
+            Instruction::LdSt(x) => format!("ld st, v{:x}", x),
+            Instruction::AddI(x) => format!("add i, v{:x}", x),
+            Instruction::LdFont(x) => format!("ld f, v{:x}", x),
+            Instruction::LdBcd(x) => format!("ld b, v{:x}", x),
+            Instruction::Store(x) => format!("ld [i], v{:x}", x),
+            Instruction::Load(x) => format!("ld v{:x}, [i]", x),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.format_with(&|nnn| format!("0x{:03x}", nnn)))
+    }
+}
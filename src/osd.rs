@@ -0,0 +1,277 @@
+use std::time::{Duration, Instant};
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, WindowCanvas};
+
+// How long a message stays on screen
+const MESSAGE_DURATION: Duration = Duration::from_secs(3);
+
+pub const GLYPH_WIDTH: i32 = 3;
+pub const GLYPH_HEIGHT: i32 = 5;
+
+// 3x5 glyphs, one row per byte with the leftmost pixel in bit 2. Letters are
+//  drawn in upper case and anything else missing here as '?'.
+pub fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '\\' => [0b100, 0b100, 0b010, 0b001, 0b001],
+        '(' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        '[' => [0b011, 0b010, 0b010, 0b010, 0b011],
+        ']' => [0b110, 0b010, 0b010, 0b010, 0b110],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '~' => [0b000, 0b011, 0b110, 0b000, 0b000],
+        _ => [0b111, 0b001, 0b010, 0b000, 0b010],
+    }
+}
+
+// The font pixels of `text` with its top-left corner at `x`, `y`, each
+//  pixel `size` screen pixels square
+pub fn text_rects(text: &str, x: i32, y: i32, size: i32) -> Vec<Rect> {
+    let advance = (GLYPH_WIDTH + 1) * size;
+    let mut rects = Vec::new();
+    for (column, c) in text.chars().enumerate() {
+        let left = x + column as i32 * advance;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for bit in 0..GLYPH_WIDTH {
+                if bits >> (GLYPH_WIDTH - 1 - bit) & 1 != 0 {
+                    let top = y + row as i32 * size;
+                    rects.push(Rect::new(left + bit * size, top, size as u32, size as u32));
+                }
+            }
+        }
+    }
+    rects
+}
+
+// How wide `text` comes out at pixel `size`, without the gap after the
+//  last character
+fn text_width(text: &str, size: i32) -> i32 {
+    text.chars().count() as i32 * (GLYPH_WIDTH + 1) * size - size
+}
+
+// Darken `area` and write `text` large across its middle, for states such
+//  as pausing that last until undone rather than a few seconds
+pub fn draw_banner(canvas: &mut WindowCanvas, text: &str, area: Rect) {
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 0xA0));
+    let _ = canvas.fill_rect(area);
+
+    let size = (area.width() as i32 / 160).max(2);
+    let x = area.center().x() - text_width(text, size) / 2;
+    let y = area.center().y() - GLYPH_HEIGHT * size / 2;
+    canvas.set_draw_color(Color::RGB(0xFF, 0xFF, 0xFF));
+    let _ = canvas.fill_rects(&text_rects(text, x, y, size));
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+}
+
+// Where an indicator for `text` goes in the top-right corner of `area`: its
+//  backing, the text's top left and glyph size, and the lamp
+fn indicator_layout(text: &str, area: Rect) -> (Rect, (i32, i32), i32, Rect) {
+    let size = (area.width() as i32 / 320).max(1);
+    let margin = size * 2;
+    let lamp = GLYPH_HEIGHT * size;
+    let text_width = text_width(text, size);
+    let width = text_width + margin + lamp + margin * 2;
+    let height = GLYPH_HEIGHT * size + margin * 2;
+    let left = area.right() - margin - width;
+    let top = area.y() + margin;
+    let backing = Rect::new(left, top, width as u32, height as u32);
+    let lamp = Rect::new(left + margin + text_width + margin, top + margin, lamp as u32, lamp as u32);
+    (backing, (left + margin, top + margin), size, lamp)
+}
+
+// A small readout in the top-right corner of `area`: `text` beside a lamp
+//  that is lit while `on`
+pub fn draw_indicator(canvas: &mut WindowCanvas, text: &str, on: bool, area: Rect) {
+    let (backing, (x, y), size, lamp_rect) = indicator_layout(text, area);
+
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 0xC0));
+    let _ = canvas.fill_rect(backing);
+    canvas.set_draw_color(Color::RGB(0xFF, 0xFF, 0xFF));
+    let _ = canvas.fill_rects(&text_rects(text, x, y, size));
+
+    if on {
+        canvas.set_draw_color(Color::RGB(0x40, 0xFF, 0x40));
+        let _ = canvas.fill_rect(lamp_rect);
+    } else {
+        let _ = canvas.draw_rect(lamp_rect);
+    }
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+}
+
+// A grayscale image shown under the message text, one byte per pixel
+pub struct Image {
+    pub pixels: Vec<u8>,
+    pub width: usize,
+}
+
+struct Message {
+    text: String,
+    image: Option<Image>,
+    shown: Instant,
+}
+
+// On-screen display: a single line of status text, optionally with a small
+//  picture below it, drawn over the top-left corner of the window for a few
+//  seconds
+pub struct Osd {
+    message: Option<Message>,
+}
+
+impl Osd {
+    pub fn new() -> Osd {
+        Osd { message: None }
+    }
+
+    pub fn show(&mut self, text: &str) {
+        self.show_with_image(text, None);
+    }
+
+    pub fn show_with_image(&mut self, text: &str, image: Option<Image>) {
+        self.message = Some(Message {
+            text: String::from(text),
+            image,
+            shown: Instant::now(),
+        });
+    }
+
+    pub fn draw(&mut self, canvas: &mut WindowCanvas) {
+        let Some(message) = &self.message else { return };
+        if message.shown.elapsed() > MESSAGE_DURATION {
+            self.message = None;
+            return;
+        }
+
+        // Font pixels grow with the window so text stays readable
+        let (window_width, _) = canvas.logical_size();
+        let size = (window_width as i32 / 320).max(1);
+        let advance = (GLYPH_WIDTH + 1) * size;
+        let margin = size * 2;
+
+        let rects = text_rects(&message.text, margin * 2, margin * 2, size);
+
+        // Images are drawn at twice the font's pixel size
+        let image_size = size * 2;
+        let (image_width, image_height) = match &message.image {
+            Some(image) => (
+                image.width as i32 * image_size,
+                (image.pixels.len() / image.width.max(1)) as i32 * image_size + margin,
+            ),
+            None => (0, 0),
+        };
+
+        let text_width = message.text.chars().count() as i32 * advance - size;
+        let width = text_width.max(image_width) + margin * 2;
+        let height = GLYPH_HEIGHT * size + image_height + margin * 2;
+
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, 0xC0));
+        let _ = canvas.fill_rect(Rect::new(margin, margin, width.max(1) as u32, height as u32));
+        canvas.set_draw_color(Color::RGB(0xFF, 0xFF, 0xFF));
+        let _ = canvas.fill_rects(&rects);
+
+        if let Some(image) = &message.image {
+            let top = margin * 3 + GLYPH_HEIGHT * size;
+            for (i, &level) in image.pixels.iter().enumerate().filter(|(_, &level)| level > 0) {
+                let x = margin * 2 + (i % image.width) as i32 * image_size;
+                let y = top + (i / image.width) as i32 * image_size;
+                canvas.set_draw_color(Color::RGB(level, level, level));
+                let _ = canvas.fill_rect(Rect::new(x, y, image_size as u32, image_size as u32));
+            }
+        }
+
+        canvas.set_draw_color(Color::RGB(0, 0, 0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glyphs_fit_their_cell() {
+        for c in (' '..='~').chain(['\u{e9}']) {
+            assert!(glyph(c).iter().all(|&row| row < 1 << GLYPH_WIDTH), "{:?}", c);
+        }
+        assert_eq!(glyph('a'), glyph('A'));
+        assert_eq!(glyph('\u{e9}'), glyph('?'));
+    }
+
+    #[test]
+    fn banners_are_centred_on_their_text() {
+        assert_eq!(text_width("P", 2), GLYPH_WIDTH * 2);
+        let rects = text_rects("PAUSED", 0, 0, 2);
+        let right = rects.iter().map(|rect| rect.right()).max().unwrap();
+        assert_eq!(right, text_width("PAUSED", 2));
+    }
+
+    #[test]
+    fn text_is_drawn_a_cell_per_character() {
+        let one = text_rects("1", 0, 0, 1).len();
+        let rects = text_rects("11", 10, 20, 2);
+        assert_eq!(rects.len(), one * 2);
+        assert!(rects.iter().all(|rect| rect.width() == 2 && rect.y() >= 20));
+        assert!(rects[one..].iter().all(|rect| rect.x() >= 10 + (GLYPH_WIDTH + 1) * 2));
+    }
+
+    #[test]
+    fn indicators_sit_inside_the_top_right_corner() {
+        let area = Rect::new(100, 50, 640, 320);
+        let (backing, (x, y), size, lamp) = indicator_layout("ST 255", area);
+        assert_eq!(size, 2);
+        assert!(area.contains_rect(backing) && area.right() - backing.right() == size * 2);
+        assert_eq!(backing.y(), area.y() + size * 2);
+        assert!(x > backing.x() && y > backing.y() && x + text_width("ST 255", size) < lamp.x());
+        assert!(backing.contains_rect(lamp) && lamp.width() == lamp.height());
+    }
+}
@@ -0,0 +1,135 @@
+use crate::keymap::MouseKeys;
+
+// Movement beyond this many frames of presses is dropped, so a flick of the
+//  mouse doesn't keep the paddle going long after the hand has stopped
+const MAX_FRAMES: i32 = 8;
+// How long one notch of the wheel holds its key
+const WHEEL_FRAMES: i32 = 4;
+
+// Plays paddle games such as Pong and Breakout with the mouse: moving it
+//  holds the key for that direction for a frame per `sensitivity` pixels
+//  moved, and each notch of the wheel holds a key for a few frames
+pub struct Paddle {
+    // Movement and wheel notches not yet turned into presses
+    x: i32,
+    y: i32,
+    wheel: i32,
+    // The keys this pressed last frame
+    pressed: [bool; 16],
+}
+
+impl Paddle {
+    pub fn new() -> Paddle {
+        Paddle {
+            x: 0,
+            y: 0,
+            wheel: 0,
+            pressed: [false; 16],
+        }
+    }
+
+    pub fn moved(&mut self, dx: i32, dy: i32) {
+        self.x += dx;
+        self.y += dy;
+    }
+
+    // Positive is away from the player
+    pub fn scrolled(&mut self, notches: i32) {
+        self.wheel = (self.wheel + notches * WHEEL_FRAMES).clamp(-MAX_FRAMES, MAX_FRAMES);
+    }
+
+    // Called once a frame: lets go of last frame's keys and presses this one's
+    pub fn apply(&mut self, mouse: &MouseKeys, keys: &mut [u8; 16]) {
+        for (key, pressed) in self.pressed.iter_mut().enumerate() {
+            if *pressed {
+                keys[key] = 0;
+                *pressed = false;
+            }
+        }
+
+        let sensitivity = mouse.sensitivity.max(1) as i32;
+        let limit = sensitivity * MAX_FRAMES;
+        self.x = self.x.clamp(-limit, limit);
+        self.y = self.y.clamp(-limit, limit);
+
+        let mut press = |key: Option<usize>| {
+            if let Some(key) = key {
+                keys[key] = 1;
+                self.pressed[key] = true;
+            }
+        };
+        if self.x >= sensitivity {
+            self.x -= sensitivity;
+            press(mouse.right);
+        } else if self.x <= -sensitivity {
+            self.x += sensitivity;
+            press(mouse.left);
+        }
+        if self.y >= sensitivity {
+            self.y -= sensitivity;
+            press(mouse.down);
+        } else if self.y <= -sensitivity {
+            self.y += sensitivity;
+            press(mouse.up);
+        }
+        if self.wheel > 0 {
+            self.wheel -= 1;
+            press(mouse.wheel_up);
+        } else if self.wheel < 0 {
+            self.wheel += 1;
+            press(mouse.wheel_down);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn movement_holds_a_key_a_frame_per_step() {
+        let mouse = MouseKeys::default();
+        let mut paddle = Paddle::new();
+        let mut keys = [0; 16];
+        paddle.moved(-20, 3);
+        paddle.apply(&mouse, &mut keys);
+        assert_eq!((keys[0x7], keys[0x9], keys[0x8]), (1, 0, 0));
+        paddle.apply(&mouse, &mut keys);
+        assert_eq!(keys[0x7], 1);
+        // 4 pixels left over isn't a step
+        paddle.apply(&mouse, &mut keys);
+        assert_eq!(keys, [0; 16]);
+
+        // A flick is cut down to a few frames
+        paddle.moved(0, 1000);
+        let frames = (0..100)
+            .filter(|_| {
+                paddle.apply(&mouse, &mut keys);
+                keys[0x8] == 1
+            })
+            .count();
+        assert_eq!(frames, MAX_FRAMES as usize);
+    }
+
+    #[test]
+    fn wheel_notches_press_their_keys_for_a_while() {
+        let mouse = MouseKeys {
+            wheel_up: Some(0x1),
+            ..MouseKeys::default()
+        };
+        let mut paddle = Paddle::new();
+        let mut keys = [0; 16];
+        paddle.scrolled(1);
+        for _ in 0..WHEEL_FRAMES {
+            paddle.apply(&mouse, &mut keys);
+            assert_eq!(keys[0x1], 1);
+        }
+        paddle.apply(&mouse, &mut keys);
+        assert_eq!(keys[0x1], 0);
+
+        // Down has no key unless given one
+        paddle.scrolled(-1);
+        paddle.apply(&mouse, &mut keys);
+        assert_eq!(keys, [0; 16]);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::phosphor;
+
+// Named color themes for the display, chosen with --palette and cycled at
+//  runtime with F2
+pub struct Palette {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub foreground: [u8; 3],
+    pub background: [u8; 3],
+}
+
+pub const PALETTES: &[Palette] = &[
+    Palette {
+        name: "default",
+        description: "White on black",
+        foreground: [0xFF, 0xFF, 0xFF],
+        background: [0x00, 0x00, 0x00],
+    },
+    Palette {
+        name: "green",
+        description: "Green phosphor monitor",
+        foreground: [0x33, 0xFF, 0x66],
+        background: [0x0A, 0x1F, 0x0F],
+    },
+    Palette {
+        name: "amber",
+        description: "Amber phosphor monitor",
+        foreground: [0xFF, 0xB0, 0x00],
+        background: [0x1F, 0x12, 0x00],
+    },
+    Palette {
+        name: "octo",
+        description: "The Octo IDE's default yellow on brown",
+        foreground: [0xFF, 0xCC, 0x00],
+        background: [0x99, 0x66, 0x00],
+    },
+    Palette {
+        name: "high-contrast",
+        description: "Yellow on black, for low vision",
+        foreground: [0xFF, 0xFF, 0x00],
+        background: [0x00, 0x00, 0x00],
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Palette> {
+    PALETTES.iter().find(|palette| palette.name == name)
+}
+
+// XO-CHIP's colors for pixels lit in plane 2 alone and in both planes, like
+//  Octo's fillColor2 and blendColor. Any not given are shades between the
+//  foreground and background, so they follow the palette.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PlaneColors {
+    pub plane2: Option<[u8; 3]>,
+    pub both: Option<[u8; 3]>,
+}
+
+// The color for each pixel value, which holds the planes lit there
+pub fn pixel_colors(foreground: [u8; 3], background: [u8; 3], planes: PlaneColors) -> [[u8; 3]; 4] {
+    [
+        background,
+        foreground,
+        planes.plane2.unwrap_or_else(|| phosphor::mix(foreground, background, 2.0 / 3.0)),
+        planes.both.unwrap_or_else(|| phosphor::mix(foreground, background, 1.0 / 3.0)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn palettes_are_found_by_unique_names() {
+        assert_eq!(find("amber").map(|palette| palette.foreground), Some([0xFF, 0xB0, 0x00]));
+        assert!(find("purple").is_none());
+        for (index, palette) in PALETTES.iter().enumerate() {
+            assert!(PALETTES[..index].iter().all(|other| other.name != palette.name));
+            assert_ne!(palette.foreground, palette.background, "{}", palette.name);
+        }
+    }
+
+    #[test]
+    fn plane_colors_default_to_shades_between() {
+        let colors = pixel_colors([0xFF; 3], [0; 3], PlaneColors::default());
+        assert_eq!(colors, [[0; 3], [0xFF; 3], [0xAA; 3], [0x55; 3]]);
+
+        let planes = PlaneColors { plane2: Some([1, 2, 3]), both: None };
+        assert_eq!(pixel_colors([0xFF; 3], [0; 3], planes)[2], [1, 2, 3]);
+    }
+}
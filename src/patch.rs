@@ -0,0 +1,175 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Offsets in both patch formats are relative to the start of the ROM file,
+//  not to the address it is loaded at.
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+
+// No patched ROM can be larger than the CHIP-8's 4 KiB of memory
+const MAX_SIZE: usize = 4096;
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// Overwrite bytes at `offset`, growing the ROM if the patch extends it
+fn write_bytes(rom: &mut Vec<u8>, offset: usize, bytes: &[u8]) -> io::Result<()> {
+    let end = offset
+        .checked_add(bytes.len())
+        .filter(|&end| end <= MAX_SIZE)
+        .ok_or_else(|| invalid(format!("patch writes past the {} bytes of memory at 0x{:x}", MAX_SIZE, offset)))?;
+    if rom.len() < end {
+        rom.resize(end, 0);
+    }
+    rom[offset..end].copy_from_slice(bytes);
+    Ok(())
+}
+
+pub fn apply_file(rom: &mut Vec<u8>, path: &Path) -> io::Result<()> {
+    let patch = fs::read(path)?;
+
+    if patch.starts_with(IPS_MAGIC) {
+        apply_ips(rom, &patch)
+    } else {
+        let text = String::from_utf8(patch)
+            .map_err(|_| invalid(String::from("not an IPS patch or a text patch")))?;
+        apply_text(rom, &text)
+    }
+}
+
+// IPS: "PATCH", then records of a 3-byte offset and a 2-byte length followed
+//  by that many bytes. A zero length marks an RLE record holding a 2-byte
+//  count and the byte to repeat. The patch ends with "EOF".
+pub fn apply_ips(rom: &mut Vec<u8>, patch: &[u8]) -> io::Result<()> {
+    let mut pos = IPS_MAGIC.len();
+
+    let mut take = |count: usize| -> io::Result<&[u8]> {
+        let bytes = patch
+            .get(pos..pos + count)
+            .ok_or_else(|| invalid(String::from("IPS patch ends unexpectedly")))?;
+        pos += count;
+        Ok(bytes)
+    };
+
+    loop {
+        let header = take(3)?;
+        if header == IPS_EOF {
+            return Ok(());
+        }
+        let offset = (header[0] as usize) << 16 | (header[1] as usize) << 8 | header[2] as usize;
+
+        let size = take(2)?;
+        let size = (size[0] as usize) << 8 | size[1] as usize;
+
+        if size == 0 {
+            let run = take(3)?;
+            let count = (run[0] as usize) << 8 | run[1] as usize;
+            write_bytes(rom, offset, &vec![run[2]; count])?;
+        } else {
+            let data = take(size)?;
+            write_bytes(rom, offset, data)?;
+        }
+    }
+}
+
+// Text patches list one change per line as a hex offset and the hex bytes
+//  to write there, e.g. `0x1A4: 12 34`. Everything after `#` is a comment.
+pub fn apply_text(rom: &mut Vec<u8>, text: &str) -> io::Result<()> {
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let error = |what: &str| invalid(format!("line {}: {}", number + 1, what));
+
+        let (offset, bytes) = line
+            .split_once(':')
+            .ok_or_else(|| error("expected `<offset>: <bytes>`"))?;
+
+        let offset = parse_hex(offset).ok_or_else(|| error("bad offset"))?;
+
+        let bytes = bytes
+            .split_whitespace()
+            .map(|byte| parse_hex(byte).filter(|&value| value <= 0xFF).map(|value| value as u8))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(|| error("bad byte value"))?;
+
+        write_bytes(rom, offset, &bytes).map_err(|e| error(&e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+fn parse_hex(text: &str) -> Option<usize> {
+    let text = text.trim();
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    usize::from_str_radix(digits, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ips_records_overwrite_and_extend() {
+        let mut rom = vec![0x00, 0x11, 0x22];
+        let mut patch = IPS_MAGIC.to_vec();
+        patch.extend([0x00, 0x00, 0x01, 0x00, 0x01, 0xAA]);
+        patch.extend([0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x02, 0xBB]);
+        patch.extend(IPS_EOF);
+
+        apply_ips(&mut rom, &patch).unwrap();
+        assert_eq!(rom, [0x00, 0xAA, 0x22, 0x00, 0xBB, 0xBB]);
+    }
+
+    #[test]
+    fn ips_rejects_truncated_records() {
+        let mut patch = IPS_MAGIC.to_vec();
+        patch.extend([0x00, 0x00, 0x01, 0x00, 0x04, 0xAA]);
+        assert!(apply_ips(&mut vec![0; 8], &patch).is_err());
+
+        let mut patch = IPS_MAGIC.to_vec();
+        patch.extend([0x00, 0x00]);
+        assert!(apply_ips(&mut vec![0; 8], &patch).is_err());
+    }
+
+    #[test]
+    fn ips_rejects_writes_past_memory() {
+        let mut rom = vec![0; 8];
+        let mut patch = IPS_MAGIC.to_vec();
+        patch.extend([0x01, 0x00, 0x00, 0x00, 0x01, 0xAA]);
+        patch.extend(IPS_EOF);
+
+        assert!(apply_ips(&mut rom, &patch).is_err());
+        assert_eq!(rom.len(), 8);
+    }
+
+    #[test]
+    fn text_patches_skip_comments() {
+        let mut rom = vec![0; 4];
+        apply_text(&mut rom, "# title screen\n0x1: 12 34  # colours\n\n3: ff\n").unwrap();
+        assert_eq!(rom, [0x00, 0x12, 0x34, 0xFF]);
+    }
+
+    #[test]
+    fn text_patches_reject_bad_lines() {
+        assert!(apply_text(&mut vec![0; 4], "0x1 12").is_err());
+        assert!(apply_text(&mut vec![0; 4], "zz: 12").is_err());
+        assert!(apply_text(&mut vec![0; 4], "1: 123").is_err());
+    }
+
+    #[test]
+    fn text_patches_reject_offsets_past_memory() {
+        assert!(apply_text(&mut vec![0; 4], "FFFFFFFFFFFFFFFF: 00").is_err());
+        assert!(apply_text(&mut vec![0; 4], "10000000: 00").is_err());
+        assert!(apply_text(&mut vec![0; 4], "FFF: 00 00").is_err());
+        assert!(apply_text(&mut vec![0; 4], "FFF: 00").is_ok());
+    }
+}
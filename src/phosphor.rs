@@ -0,0 +1,58 @@
+// Phosphor persistence for the window. XOR drawing makes sprites vanish for
+//  a frame while they are erased and redrawn, so lit pixels switch on at
+//  once but fade out linearly over a few frames, the way a slow CRT phosphor
+//  would, hiding the gap.
+pub struct Phosphor {
+    fade: f32,
+    levels: Vec<f32>,
+}
+
+impl Phosphor {
+    // `frames` is how long a pixel takes to fade out completely
+    pub fn new(frames: u32) -> Phosphor {
+        Phosphor {
+            fade: 1.0 / frames.max(1) as f32,
+            levels: Vec::new(),
+        }
+    }
+
+    // Brightness of each pixel from 0 to 1 after showing `video`
+    pub fn apply(&mut self, video: &[u8]) -> &[f32] {
+        self.levels.resize(video.len(), 0.0);
+        for (level, &pixel) in self.levels.iter_mut().zip(video) {
+            *level = if pixel != 0 { 1.0 } else { (*level - self.fade).max(0.0) };
+        }
+        &self.levels
+    }
+}
+
+// The color `level` of the way from `background` to `foreground`
+pub fn mix(foreground: [u8; 3], background: [u8; 3], level: f32) -> [u8; 3] {
+    let mut color = [0; 3];
+    for (channel, (&fg, &bg)) in color.iter_mut().zip(foreground.iter().zip(&background)) {
+        *channel = (bg as f32 + (fg as f32 - bg as f32) * level).round() as u8;
+    }
+    color
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixels_light_at_once_and_fade_over_the_frames() {
+        let mut phosphor = Phosphor::new(4);
+        assert_eq!(phosphor.apply(&[1, 0]), [1.0, 0.0]);
+        assert_eq!(phosphor.apply(&[0, 0]), [0.75, 0.0]);
+        assert_eq!(phosphor.apply(&[0, 1]), [0.5, 1.0]);
+        phosphor.apply(&[0, 1]);
+        assert_eq!(phosphor.apply(&[0, 1]), [0.0, 1.0]);
+    }
+
+    #[test]
+    fn levels_mix_the_colors() {
+        assert_eq!(mix([200, 100, 0], [0, 0, 100], 0.0), [0, 0, 100]);
+        assert_eq!(mix([200, 100, 0], [0, 0, 100], 0.5), [100, 50, 50]);
+        assert_eq!(mix([200, 100, 0], [0, 0, 100], 1.0), [200, 100, 0]);
+    }
+}
@@ -0,0 +1,12 @@
+use rfd::FileDialog;
+
+// Ask for a ROM with the system's file dialog. Returns None when it is
+//  cancelled, or when there is no dialog to show.
+pub fn pick_rom() -> Option<String> {
+    FileDialog::new()
+        .set_title("Open a CHIP-8 ROM")
+        .add_filter("CHIP-8 ROMs", &["ch8", "c8", "rom"])
+        .add_filter("All files", &["*"])
+        .pick_file()
+        .map(|path| path.to_string_lossy().into_owned())
+}
@@ -0,0 +1,186 @@
+use std::io;
+use std::time::Duration;
+
+use crate::buzzer::Tone;
+use crate::chip8::Chip8;
+
+// What the winit + pixels window shows; the same meaning as the SDL options
+#[cfg_attr(not(feature = "pixels"), allow(dead_code))]
+pub struct Settings {
+    pub scale: u32,
+    pub foreground: [u8; 3],
+    pub background: [u8; 3],
+    pub phosphor: Option<u32>,
+    pub blend: Option<usize>,
+    pub tone: Tone,
+    // From 0 to 100
+    pub volume: u8,
+}
+
+// A second, minimal window frontend drawn through wgpu, for systems where
+//  SDL2 misbehaves and as a base for shader effects. It runs the keypad,
+//  display and (with the `cpal` feature) buzzer only; hotkeys, states and
+//  recording are SDL frontend features.
+#[cfg(feature = "pixels")]
+pub fn run(chip8: &mut Chip8, cycle_period: Duration, settings: &Settings, title: &str) -> io::Result<()> {
+    use std::time::Instant;
+
+    use pixels::{Pixels, SurfaceTexture};
+    use winit::dpi::LogicalSize;
+    use winit::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
+    use winit::event_loop::{ControlFlow, EventLoop};
+    use winit::platform::run_return::EventLoopExtRunReturn;
+    use winit::window::WindowBuilder;
+
+    use crate::blend::Blend;
+    use crate::buzzer::Pattern;
+    use crate::chip8::{VIDEO_HEIGHT, VIDEO_WIDTH};
+    use crate::cpal_sound::CpalSound;
+    use crate::phosphor::{self, Phosphor};
+    use crate::timing;
+
+    let failed = |e: &dyn std::fmt::Display| io::Error::other(e.to_string());
+
+    // The same QWERTY layout as the SDL frontend
+    let chip8_key = |key: VirtualKeyCode| match key {
+        VirtualKeyCode::X => Some(0),
+        VirtualKeyCode::Key1 => Some(1),
+        VirtualKeyCode::Key2 => Some(2),
+        VirtualKeyCode::Key3 => Some(3),
+        VirtualKeyCode::Q => Some(4),
+        VirtualKeyCode::W => Some(5),
+        VirtualKeyCode::E => Some(6),
+        VirtualKeyCode::A => Some(7),
+        VirtualKeyCode::S => Some(8),
+        VirtualKeyCode::D => Some(9),
+        VirtualKeyCode::Z => Some(0xA),
+        VirtualKeyCode::C => Some(0xB),
+        VirtualKeyCode::Key4 => Some(0xC),
+        VirtualKeyCode::R => Some(0xD),
+        VirtualKeyCode::F => Some(0xE),
+        VirtualKeyCode::V => Some(0xF),
+        _ => None,
+    };
+
+    let mut event_loop = EventLoop::new();
+    let window_size = LogicalSize::new(VIDEO_WIDTH as u32 * settings.scale, VIDEO_HEIGHT as u32 * settings.scale);
+    let window = WindowBuilder::new()
+        .with_title(title)
+        .with_inner_size(window_size)
+        .with_min_inner_size(LogicalSize::new(VIDEO_WIDTH as u32, VIDEO_HEIGHT as u32))
+        .build(&event_loop)
+        .map_err(|e| failed(&e))?;
+
+    // pixels keeps the 2:1 aspect ratio and letterboxes on resize by itself
+    let surface_size = window.inner_size();
+    let surface = SurfaceTexture::new(surface_size.width, surface_size.height, &window);
+    let mut pixels = Pixels::new(VIDEO_WIDTH as u32, VIDEO_HEIGHT as u32, surface).map_err(|e| failed(&e))?;
+
+    let mut phosphor = settings.phosphor.map(Phosphor::new);
+    let mut blend = settings.blend.map(Blend::new);
+    let mut sound = CpalSound::open(settings.tone.clone(), settings.volume);
+
+    let frame_period = timing::frame_period();
+    let mut next_frame_time = Instant::now();
+    let mut frame = 0u64;
+    let mut held_keys = [0u8; 16];
+    let mut result = Ok(());
+
+    event_loop.run_return(|event, _, control_flow| match event {
+        Event::WindowEvent { event, .. } => match event {
+            WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    virtual_keycode: Some(key),
+                    state,
+                    ..
+                },
+                ..
+            } => {
+                if key == VirtualKeyCode::Escape {
+                    *control_flow = ControlFlow::Exit;
+                } else if let Some(index) = chip8_key(key) {
+                    held_keys[index] = (state == ElementState::Pressed) as u8;
+                }
+            }
+            WindowEvent::Resized(size) => {
+                if let Err(e) = pixels.resize_surface(size.width, size.height) {
+                    eprintln!("Error resizing surface: {}", e);
+                }
+            }
+            _ => {}
+        },
+        Event::MainEventsCleared => {
+            let now = Instant::now();
+            if now >= next_frame_time {
+                // Don't try to catch up after a stall, just carry on from now
+                next_frame_time += frame_period;
+                if next_frame_time < now {
+                    next_frame_time = now + frame_period;
+                }
+
+                chip8.keypad = held_keys;
+                for _ in 0..timing::cycles_in_frame(frame, cycle_period) {
+                    chip8.cycle();
+                }
+                chip8.tick_timers();
+                frame += 1;
+                sound.set_pattern(Pattern::of(chip8));
+                sound.set_buzzing(chip8.sound_timer > 0);
+
+                let levels = match (&mut phosphor, &mut blend) {
+                    (Some(phosphor), _) => Some(phosphor.apply(&chip8.video)),
+                    (None, Some(blend)) => Some(blend.apply(&chip8.video)),
+                    (None, None) => None,
+                };
+                for (i, texel) in pixels.frame_mut().chunks_exact_mut(4).enumerate() {
+                    let [r, g, b] = match levels {
+                        Some(levels) => phosphor::mix(settings.foreground, settings.background, levels[i]),
+                        None if chip8.video[i] != 0 => settings.foreground,
+                        None => settings.background,
+                    };
+                    texel.copy_from_slice(&[r, g, b, 0xFF]);
+                }
+                window.request_redraw();
+            }
+            *control_flow = ControlFlow::WaitUntil(next_frame_time);
+        }
+        Event::RedrawRequested(_) => {
+            if let Err(e) = pixels.render() {
+                result = Err(failed(&e));
+                *control_flow = ControlFlow::Exit;
+            }
+        }
+        _ => {}
+    });
+
+    result
+}
+
+#[cfg(not(feature = "pixels"))]
+pub fn run(_chip8: &mut Chip8, _cycle_period: Duration, _settings: &Settings, _title: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "this build has no pixels frontend (enable the `pixels` feature)",
+    ))
+}
+
+#[cfg(all(test, not(feature = "pixels")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_without_pixels_refuse_to_open_the_window() {
+        let settings = Settings {
+            scale: 10,
+            foreground: [0xFF; 3],
+            background: [0; 3],
+            phosphor: None,
+            blend: None,
+            tone: Tone::default(),
+            volume: 100,
+        };
+        let error = run(&mut Chip8::new(), Duration::from_millis(1), &settings, "test").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+    }
+}
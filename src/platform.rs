@@ -1,47 +1,642 @@
+use sdl2::controller::{Axis, Button};
 use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use sdl2::mouse::{MouseButton, MouseWheelDirection};
+use sdl2::keyboard::{Keycode, Mod, Scancode};
 use sdl2::pixels::PixelFormatEnum;
-use sdl2::render::{Canvas, Texture, TextureCreator, WindowCanvas};
-use sdl2::sys::{KeyCode, SDL_KeyCode};
-use sdl2::video::{Window, WindowContext};
-use sdl2::EventPump;
-use sdl2::VideoSubsystem;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas, ScaleMode, Texture, TextureCreator, TextureValueError, WindowCanvas};
+use sdl2::video::{FullscreenType, Window, WindowBuildError, WindowContext};
+use sdl2::{EventPump, IntegerOrSdlError, VideoSubsystem};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::bezel::Bezel;
+use crate::chip8::Draw;
+use crate::crt;
+use crate::draw_viz::Highlight;
+use crate::egui_sdl::EguiSdl;
+use crate::keymap::Keymap;
+use crate::keypad::Keypad;
+use crate::lcd;
+use crate::magnifier::Magnifier;
+use crate::menu;
+use crate::osd::{self, Image, Osd};
+use crate::paddle::Paddle;
+use crate::palette::PlaneColors;
+use crate::quirks::{Quirk, Quirks};
+use crate::render::Renderer;
+use crate::screenshot;
+
+// Why the window couldn't be opened
+#[derive(Debug, Error)]
+pub enum DisplayError {
+    #[error("SDL could not start: {0}")]
+    Sdl(String),
+    #[error("SDL video init failed: {0} (is a display available? --frontend term runs without one)")]
+    Video(String),
+    #[error("SDL events init failed: {0}")]
+    Events(String),
+    #[error("there is no monitor {monitor} (found {found})")]
+    NoMonitor { monitor: i32, found: i32 },
+    #[error("could not open a {width}x{height} window")]
+    Window {
+        width: u32,
+        height: u32,
+        #[source]
+        source: WindowBuildError,
+    },
+    #[error("unknown render driver '{name}', available: {available}")]
+    RenderDriver { name: String, available: String },
+    #[error("could not create a renderer")]
+    Renderer {
+        #[source]
+        source: IntegerOrSdlError,
+        // Whether the software renderer was asked for, so there's nothing
+        //  left to fall back on
+        software: bool,
+    },
+    #[error("could not create the display texture")]
+    Texture(#[from] TextureValueError),
+}
+
+impl DisplayError {
+    // Advice printed below the message
+    pub fn hints(&self) -> Vec<String> {
+        match self {
+            DisplayError::Renderer { software: false, .. } => vec![String::from("Try --renderer software")],
+            _ => Vec::new(),
+        }
+    }
+}
+
+// Emulator hotkeys reported back to the main loop
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    SaveState,
+    LoadState,
+    NextSlot,
+    RewindStart,
+    RewindStop,
+    TogglePause,
+    FrameAdvance,
+    ToggleRerecord,
+    SaveReplay,
+    Screenshot,
+    ToggleGif,
+    RecordMacro,
+    PlayMacro,
+    NextPalette,
+    ToggleFullscreen,
+    ToggleSmoothing,
+    ToggleAlwaysOnTop,
+    Reset,
+    SoftReset,
+    // Move on to the next ROM in the playlist
+    NextRom,
+    // Play the ROM at this place in the playlist
+    PlaylistRom(usize),
+    // Go back to the list of ROMs to choose from
+    ShowLauncher,
+    ToggleQuirk(Quirk),
+    // Ask for a ROM file with the system's file dialog
+    OpenRom,
+    ToggleMagnifier,
+    ToggleSoundIndicator,
+    NextWaveform,
+    ToggleMute,
+    VolumeDown,
+    VolumeUp,
+    // A controller was plugged in, by joystick index
+    ControllerAdded(u32),
+    // A controller was unplugged, by instance id
+    ControllerRemoved(u32),
+    RemapKeys,
+    // Every key has been given a new keyboard key
+    KeymapChanged,
+    // A file was dropped on the window, to be taken with take_dropped
+    RomDropped,
+    // Switch to another recent ROM, by its place in the list set with
+    //  set_recent: 0 is the one opened before this
+    OpenRecent(usize),
+    // Load the ROM file again, as after rebuilding it
+    ReloadRom,
+}
+
+// The CHIP-8 keys in the keypad's order, to ask for them one by one
+const REMAP_ORDER: [usize; 16] = [0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF];
+
+// The mouse SDL reports touches as, besides reporting them as touches
+const TOUCH_MOUSE_ID: u32 = u32::MAX;
+
+// Room left for the title bar and window borders when fitting the screen
+const WINDOW_DECORATION: u32 = 64;
+
+// The largest whole scale at which a `width` x `height` display fits on the
+//  given screen, leaving out taskbars and docks
+pub fn auto_scale(video_subsystem: &VideoSubsystem, display: i32, width: u32, height: u32) -> u8 {
+    match video_subsystem.display_usable_bounds(display) {
+        Ok(bounds) => fit_scale((bounds.width(), bounds.height()), width, height),
+        Err(e) => {
+            eprintln!("Error reading screen size, using scale 10: {}", e);
+            10
+        }
+    }
+}
+
+fn rotated_rect((width, height): (u32, u32), rotation: u16) -> Rect {
+    let (display_width, display_height) = if rotation % 180 == 90 { (height, width) } else { (width, height) };
+    Rect::new(
+        (width as i32 - display_width as i32) / 2,
+        (height as i32 - display_height as i32) / 2,
+        display_width,
+        display_height,
+    )
+}
+
+fn fit_scale(screen: (u32, u32), width: u32, height: u32) -> u8 {
+    let fit = (screen.0 / width).min(screen.1.saturating_sub(WINDOW_DECORATION) / height);
+    fit.clamp(1, u8::MAX as u32) as u8
+}
+
+// How much the desktop enlarges things, from the screen's DPI against the
+//  96 DPI desktops are designed for; 1 when it can't be told
+pub fn dpi_factor(video_subsystem: &VideoSubsystem, display: i32) -> f32 {
+    video_subsystem
+        .display_dpi(display)
+        .map(|(_, horizontal, _)| (horizontal / 96.0).max(1.0))
+        .unwrap_or(1.0)
+}
+
+// Where a window of `size` goes on a screen: `offset` from its top-left
+//  corner, or centred on it
+pub fn window_position(
+    video_subsystem: &VideoSubsystem,
+    display: i32,
+    offset: Option<(i32, i32)>,
+    size: (u32, u32),
+) -> (i32, i32) {
+    let bounds = video_subsystem
+        .display_bounds(display)
+        .unwrap_or_else(|_| Rect::new(0, 0, size.0, size.1));
+    place_window(bounds, offset, size)
+}
+
+fn place_window(bounds: Rect, offset: Option<(i32, i32)>, size: (u32, u32)) -> (i32, i32) {
+    match offset {
+        Some((x, y)) => (bounds.x() + x, bounds.y() + y),
+        None => (
+            bounds.x() + (bounds.width() as i32 - size.0 as i32) / 2,
+            bounds.y() + (bounds.height() as i32 - size.1 as i32) / 2,
+        ),
+    }
+}
+
+// Bezel artwork ready to draw
+struct BezelTexture<'a> {
+    texture: Texture<'a>,
+    size: (u32, u32),
+    screen: Rect,
+}
+
+// Where the bezel's screen, `screen` in an image of `size`, lands when the
+//  image is stretched over `area`
+fn bezel_screen(area: Rect, size: (u32, u32), screen: Rect) -> Rect {
+    let scale_x = area.width() as f64 / size.0 as f64;
+    let scale_y = area.height() as f64 / size.1 as f64;
+    Rect::new(
+        area.x() + (screen.x() as f64 * scale_x).round() as i32,
+        area.y() + (screen.y() as f64 * scale_y).round() as i32,
+        (screen.width() as f64 * scale_x).round() as u32,
+        (screen.height() as f64 * scale_y).round() as u32,
+    )
+}
+
+// `out` becomes the RGBA frame `progress` of the way from `previous` to
+//  `current`
+fn mix_frames(previous: &[u8], current: &[u8], progress: f32, out: &mut Vec<u8>) {
+    out.clear();
+    out.extend(
+        previous
+            .iter()
+            .zip(current)
+            .map(|(&from, &to)| (from as f32 + (to as f32 - from as f32) * progress).round() as u8),
+    );
+}
 
 pub struct Platform<'a> {
     canvas: WindowCanvas,
+    texture_creator: &'a TextureCreator<WindowContext>,
     texture: Texture<'a>,
     event_pump: EventPump,
+    renderer: Renderer,
+    texture_width: usize,
+    osd: Osd,
+    last_input: Instant,
+    crt: bool,
+    lcd: bool,
+    windowed_size: (u32, u32),
+    rotation: u16,
+    keypad: Option<Keypad>,
+    keymap: Keymap,
+    // Instance ids of the open controllers, player 1's first
+    players: Vec<u32>,
+    // Where each controller's left stick points and the keys that holds,
+    //  by instance id
+    sticks: Vec<(u32, (i16, i16), u16)>,
+    // The keys pressed so far while remapping, in REMAP_ORDER
+    remap: Option<Vec<Scancode>>,
+    paddle: Option<Paddle>,
+    // How many frames each turbo key has been held
+    turbo_frames: [u32; 16],
+    // Keys newly pressed and SDL's time they went down, while measuring
+    //  input latency
+    presses: Option<Vec<(usize, u32)>>,
+    // The file last dropped on the window
+    dropped: Option<PathBuf>,
+    // The keypad as of the last input, for lighting up the on-screen keys
+    keys: [u8; 16],
+    paused: bool,
+    // The framebuffer last rendered to the texture
+    shown: Vec<u8>,
+    egui: EguiSdl<'a>,
+    menu_open: bool,
+    // The quirks ticked in the menu
+    quirks: Quirks,
+    // The names of the ROMs for Action::OpenRecent
+    recent: Vec<String>,
+    // Menu items picked while drawing, handed over with the next input
+    picked: Vec<Action>,
+    bezel: Option<BezelTexture<'a>>,
+    magnifier: Option<Magnifier>,
+    // The two newest rendered frames when blending between them for
+    //  screens faster than 60 Hz
+    interpolation: Option<(Vec<u8>, Vec<u8>)>,
+    mixed: Vec<u8>,
+    // The sound timer and whether the buzzer sounds, while shown
+    sound_indicator: Option<(u8, bool)>,
 }
 
 impl<'tex> Platform<'tex> {
     pub fn new(
-        video_subsystem: &VideoSubsystem,
-        title: &str,
         window_size: (u32, u32),
-        canvas: Canvas<Window>,
-        texture_creator: &'tex mut TextureCreator<WindowContext>,
+        mut canvas: Canvas<Window>,
+        texture_creator: &'tex TextureCreator<WindowContext>,
         texture_size: (u32, u32),
         event_pump: EventPump,
-    ) -> Self {
-        // let window: Window = video_subsystem
-        //     .window(title, window_size.0, window_size.1)
-        //     .position_centered()
-        //     .build()
-        //     .unwrap();
+    ) -> Result<Self, DisplayError> {
+        // Everything is drawn at the launch window size; SDL scales that to
+        //  whatever size the window is resized to, letterboxing the rest
+        if let Err(e) = canvas.set_logical_size(window_size.0, window_size.1) {
+            eprintln!("Error setting logical size: {}", e);
+        }
 
         let texture: Texture<'_> = texture_creator
-            .create_texture_streaming(PixelFormatEnum::RGBA8888, texture_size.0, texture_size.1)
-            .unwrap();
+            .create_texture_streaming(PixelFormatEnum::RGBA32, texture_size.0, texture_size.1)?;
 
-        Platform {
+        Ok(Platform {
             canvas,
+            texture_creator,
             texture,
             event_pump,
+            renderer: Renderer::new(),
+            texture_width: texture_size.0 as usize,
+            osd: Osd::new(),
+            last_input: Instant::now(),
+            crt: false,
+            lcd: false,
+            windowed_size: window_size,
+            rotation: 0,
+            keypad: None,
+            keymap: Keymap::default(),
+            players: Vec::new(),
+            sticks: Vec::new(),
+            remap: None,
+            paddle: None,
+            turbo_frames: [0; 16],
+            presses: None,
+            dropped: None,
+            keys: [0; 16],
+            paused: false,
+            shown: Vec::new(),
+            egui: EguiSdl::new(texture_creator),
+            menu_open: false,
+            quirks: Quirks::default(),
+            recent: Vec::new(),
+            picked: Vec::new(),
+            bezel: None,
+            magnifier: None,
+            interpolation: None,
+            mixed: Vec::new(),
+            sound_indicator: None,
+        })
+    }
+
+    pub fn set_colors(&mut self, foreground: [u8; 3], background: [u8; 3]) {
+        self.renderer.set_colors(foreground, background);
+    }
+
+    pub fn set_plane_colors(&mut self, plane_colors: PlaneColors) {
+        self.renderer.set_plane_colors(plane_colors);
+    }
+
+    // Let pixels fade out over `frames` frames instead of vanishing at once
+    pub fn set_phosphor(&mut self, frames: Option<u32>) {
+        self.renderer.set_phosphor(frames);
+    }
+
+    // Show the average of the last `frames` frames
+    pub fn set_blend(&mut self, frames: Option<usize>) {
+        self.renderer.set_blend(frames);
+    }
+
+    // SHA-1 of the last frame shown, colors and phosphor/blend included;
+    //  the CRT look and rotation are drawn by SDL and aren't part of it
+    pub fn frame_hash(&self) -> String {
+        self.renderer.frame_hash()
+    }
+
+    // Draw scanlines, glow and rounded corners over the display
+    pub fn set_crt(&mut self, enabled: bool) {
+        self.crt = enabled;
+    }
+
+    // Lagging pixels with gaps between them, like an old handheld's LCD
+    pub fn set_lcd(&mut self, enabled: bool) {
+        self.lcd = enabled;
+        self.renderer.set_lcd(enabled);
+    }
+
+    // Turn the display clockwise by 0, 90, 180 or 270 degrees; the window
+    //  must already have the rotated shape
+    pub fn set_rotation(&mut self, degrees: u16) {
+        self.rotation = degrees;
+    }
+
+    // Give the right-hand square of the window, which must have been made
+    //  that much wider, to a clickable keypad
+    pub fn set_keypad(&mut self, enabled: bool) {
+        self.keypad = enabled.then(|| {
+            let (width, height) = self.canvas.logical_size();
+            Keypad::new(Rect::new(width.saturating_sub(height) as i32, 0, height, height))
+        });
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    // Ask for a keyboard key for each CHIP-8 key in turn, until all sixteen
+    //  have one or Escape gives up
+    pub fn start_remap(&mut self) {
+        self.remap = Some(Vec::new());
+    }
+
+    // Let mouse movement press keypad keys, for paddle games
+    pub fn set_paddle(&mut self, enabled: bool) {
+        self.paddle = enabled.then(Paddle::new);
+    }
+
+    // Called once a frame with the keys held: turbo keys among them are
+    //  let go of in the off part of their pulse
+    pub fn turbo(&mut self, keypad: &mut [u8; 16]) {
+        self.keymap.turbo.pulse(&mut self.turbo_frames, keypad);
+    }
+
+    // Start noting when keys go down, for measuring input latency
+    pub fn record_presses(&mut self) {
+        self.presses = Some(Vec::new());
+    }
+
+    // The presses since the last call, with SDL's timestamps
+    pub fn take_presses(&mut self) -> Vec<(usize, u32)> {
+        self.presses.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    // The other ROMs the menu offers to open, most recent first
+    pub fn set_recent(&mut self, names: Vec<String>) {
+        self.recent = names;
+    }
+
+    pub fn take_dropped(&mut self) -> Option<PathBuf> {
+        self.dropped.take()
+    }
+
+    pub fn set_players(&mut self, controllers: Vec<u32>) {
+        self.players = controllers;
+    }
+
+    fn controller_key(keymap: &Keymap, players: &[u32], id: u32, button: Button) -> Option<usize> {
+        let player = players.iter().position(|&player| player == id).unwrap_or(0);
+        keymap.chip8_button(player, button)
+    }
+
+    // Show a close-up of the display, or stop. Returns whether it is on.
+    pub fn toggle_magnifier(&mut self) -> bool {
+        self.magnifier = match self.magnifier {
+            Some(_) => None,
+            None => Some(Magnifier::new()),
+        };
+        self.magnifier.is_some()
+    }
+
+    // Show the sound timer in the corner of the display, or stop. Returns
+    //  whether it is shown.
+    pub fn toggle_sound_indicator(&mut self) -> bool {
+        self.sound_indicator = match self.sound_indicator {
+            Some(_) => None,
+            None => Some((0, false)),
+        };
+        self.sound_indicator.is_some()
+    }
+
+    // What the sound indicator shows from the next frame on
+    pub fn set_sound_timer(&mut self, timer: u8, buzzing: bool) {
+        if let Some(indicator) = &mut self.sound_indicator {
+            *indicator = (timer, buzzing);
+        }
+    }
+
+    // Fade each frame into the next over the time between them instead of
+    //  switching at once, for monitors faster than 60 Hz. Shows everything
+    //  one frame late.
+    pub fn set_interpolation(&mut self, enabled: bool) {
+        self.interpolation = enabled.then(|| (Vec::new(), Vec::new()));
+    }
+
+    // Show the frame `progress` of the way, from 0 to 1, from the previous
+    //  frame to the newest
+    pub fn present_between(&mut self, progress: f32) {
+        let Some((previous, current)) = &self.interpolation else { return };
+        if previous.len() != current.len() {
+            return;
+        }
+        mix_frames(previous, current, progress, &mut self.mixed);
+        if let Err(e) = self.texture.update(None, &self.mixed, self.texture_width * 4) {
+            eprintln!("Error updating texture: {}", e);
+        }
+        self.redraw();
+    }
+
+    // Mark the latest sprite draw, or clear the mark with None
+    pub fn set_highlight(&mut self, highlight: Option<Highlight>) {
+        self.renderer.set_highlight(highlight);
+    }
+
+    // Point the magnifier at the latest sprite
+    pub fn follow_draw(&mut self, draw: Option<Draw>) {
+        if let Some(magnifier) = &mut self.magnifier {
+            magnifier.follow(draw);
         }
     }
 
-    pub fn update(&mut self, pixel_data: &mut [u8], pitch: usize) {
-        match self.texture.update(None, pixel_data, pitch) {
+    // Show the display darkened with PAUSED over it
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    // The quirks the menu shows as on
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    // Where the unrotated display goes so that turning it about its centre
+    //  fills the window, less the keypad panel
+    fn display_rect(&self) -> Rect {
+        let area = match &self.bezel {
+            Some(bezel) => bezel_screen(self.display_area(), bezel.size, bezel.screen),
+            None => self.display_area(),
+        };
+        let mut rect = rotated_rect(area.size(), self.rotation);
+        rect.offset(area.x(), area.y());
+        rect
+    }
+
+    // The window less the keypad panel
+    fn display_area(&self) -> Rect {
+        let (width, height) = self.canvas.logical_size();
+        let width = width - self.keypad.as_ref().map_or(0, |keypad| keypad.area().width());
+        Rect::new(0, 0, width, height)
+    }
+
+    // Draw `bezel` over the display area, with the display showing through
+    //  its transparent screen; the window should have the bezel's shape
+    pub fn set_bezel(&mut self, bezel: &Bezel) {
+        let texture = self
+            .texture_creator
+            .create_texture_static(PixelFormatEnum::RGBA32, bezel.width, bezel.height)
+            .map_err(|e| e.to_string())
+            .and_then(|mut texture| {
+                texture.update(None, &bezel.rgba, bezel.width as usize * 4).map_err(|e| e.to_string())?;
+                texture.set_blend_mode(BlendMode::Blend);
+                Ok(texture)
+            });
+        match texture {
+            Ok(texture) => {
+                self.bezel = Some(BezelTexture {
+                    texture,
+                    size: (bezel.width, bezel.height),
+                    screen: bezel.screen,
+                })
+            }
+            Err(e) => eprintln!("Error loading bezel: {}", e),
+        }
+    }
+
+    // Blend neighbouring pixels when scaling instead of keeping hard edges
+    pub fn set_smoothing(&mut self, linear: bool) {
+        self.texture.set_scale_mode(if linear { ScaleMode::Linear } else { ScaleMode::Nearest });
+    }
+
+    // Only scale the display by whole multiples, so every CHIP-8 pixel is
+    //  the same size, at the cost of a wider border
+    pub fn set_integer_scale(&mut self, enabled: bool) {
+        if let Err(e) = self.canvas.set_integer_scale(enabled) {
+            eprintln!("Error setting integer scaling: {}", e);
+        }
+    }
+
+    // Switch between a window and desktop fullscreen, putting the window
+    //  back at the size it had. Returns whether it is now fullscreen.
+    pub fn toggle_fullscreen(&mut self) -> bool {
+        let window = self.canvas.window_mut();
+        let fullscreen = window.fullscreen_state() == FullscreenType::Off;
+
+        if fullscreen {
+            self.windowed_size = window.size();
+        }
+        let mode = if fullscreen { FullscreenType::Desktop } else { FullscreenType::Off };
+        if let Err(e) = window.set_fullscreen(mode) {
+            eprintln!("Error switching fullscreen: {}", e);
+            return !fullscreen;
+        }
+        if !fullscreen {
+            let (width, height) = self.windowed_size;
+            if let Err(e) = window.set_size(width, height) {
+                eprintln!("Error restoring window size: {}", e);
+            }
+        }
+        fullscreen
+    }
+
+    // Keep the window above all others, or stop doing so. Returns whether
+    //  it is now on top.
+    pub fn toggle_always_on_top(&mut self) -> bool {
+        let window = self.canvas.window_mut();
+        let on_top = !window.is_always_on_top();
+        window.set_always_on_top(on_top);
+        on_top
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        if let Err(e) = self.canvas.window_mut().set_title(title) {
+            eprintln!("Error setting window title: {}", e);
+        }
+    }
+
+    // Time since a key was last pressed
+    pub fn idle_time(&self) -> Duration {
+        self.last_input.elapsed()
+    }
+
+    // Show a short status message over the display
+    pub fn show_message(&mut self, text: &str) {
+        self.osd.show(text);
+    }
+
+    pub fn show_message_with_image(&mut self, text: &str, image: Option<Image>) {
+        self.osd.show_with_image(text, image);
+    }
+
+    // The display as an RGB image in the current colors
+    pub fn screenshot(&self, video: &[u8], scale: u32) -> (u32, u32, Vec<u8>) {
+        let width = self.texture_width as u32;
+        let height = video.len() as u32 / width;
+        let rgb = screenshot::render_rgb(video, self.texture_width, self.renderer.pixel_colors(), scale);
+
+        (width * scale, height * scale, rgb)
+    }
+
+    pub fn update(&mut self, video: &[u8]) {
+        self.shown.clear();
+        self.shown.extend_from_slice(video);
+        let mut pixels = self.renderer.render(video);
+        if let Some((previous, current)) = &mut self.interpolation {
+            std::mem::swap(previous, current);
+            current.clear();
+            current.extend_from_slice(pixels);
+            if previous.len() == current.len() {
+                pixels = previous;
+            }
+        }
+
+        let pitch = self.texture_width * 4;
+        match self.texture.update(None, pixels, pitch) {
             Ok(_) => {
 
             },
@@ -49,75 +644,460 @@ impl<'tex> Platform<'tex> {
                 eprintln!("Error updating texture: {}", e);
             }
         };
-        eprintln!("Texture updated.");
 
+        self.redraw();
+    }
+
+    // Draw the window again, only rendering `video` if it changed (say, a
+    //  state was loaded) so a still picture leaves phosphor and blend alone
+    pub fn refresh(&mut self, video: &[u8]) {
+        if video == self.shown.as_slice() {
+            self.redraw();
+        } else {
+            self.update(video);
+        }
+    }
+
+    fn redraw(&mut self) {
         self.canvas.clear();
-        self.canvas.copy(&self.texture, None, None).unwrap();
+        let display = self.display_rect();
+        let angle = self.rotation as f64;
+        if let Err(e) = self.canvas.copy_ex(&self.texture, None, display, angle, None, false, false) {
+            eprintln!("Error drawing the display: {}", e);
+        }
+        if self.crt {
+            crt::draw(&mut self.canvas, &mut self.texture, display, angle);
+        }
+        if self.lcd {
+            // The grid goes over the display as it appears, turned or not
+            let query = self.texture.query();
+            let (visible, columns, rows) = if self.rotation % 180 == 90 {
+                let centre = display.center();
+                (Rect::from_center(centre, display.height(), display.width()), query.height, query.width)
+            } else {
+                (display, query.width, query.height)
+            };
+            lcd::draw_grid(&mut self.canvas, visible, columns, rows);
+        }
+        // The close-up is only lined up with an unturned display
+        if let Some(magnifier) = self.magnifier.as_ref().filter(|_| self.rotation == 0) {
+            let query = self.texture.query();
+            magnifier.draw(&mut self.canvas, &self.texture, display, (query.width, query.height));
+        }
+        let area = self.display_area();
+        if let Some(bezel) = &self.bezel {
+            let _ = self.canvas.copy(&bezel.texture, None, area);
+        }
+        if let Some((timer, buzzing)) = self.sound_indicator {
+            osd::draw_indicator(&mut self.canvas, &format!("ST {:3}", timer), buzzing, area);
+        }
+        if let Some(pressed) = &self.remap {
+            let text = format!("PRESS THE KEY FOR {:X}", REMAP_ORDER[pressed.len()]);
+            osd::draw_banner(&mut self.canvas, &text, area);
+        } else if self.paused {
+            osd::draw_banner(&mut self.canvas, "PAUSED", area);
+        }
+        if let Some(keypad) = &self.keypad {
+            keypad.draw(&mut self.canvas, &self.keys);
+        }
+        if self.menu_open {
+            let quirks = self.quirks;
+            let recent = &self.recent;
+            let picked = &mut self.picked;
+            self.egui.draw(&mut self.canvas, |ctx| picked.extend(menu::show(ctx, quirks, recent)));
+        }
+        self.osd.draw(&mut self.canvas);
         self.canvas.present();
     }
 
-    fn key_to_chip8_key(key: Keycode) -> Option<usize> {
+    // Hold the keys a moved stick now points to and let go of the rest
+    fn move_stick(
+        keymap: &Keymap,
+        players: &[u32],
+        sticks: &mut Vec<(u32, (i16, i16), u16)>,
+        (id, axis, value): (u32, Axis, i16),
+        keys: &mut [u8; 16],
+    ) {
+        let index = match sticks.iter().position(|&(stick, ..)| stick == id) {
+            Some(index) => index,
+            None => {
+                sticks.push((id, (0, 0), 0));
+                sticks.len() - 1
+            }
+        };
+        let (_, position, held) = &mut sticks[index];
+        if axis == Axis::LeftX {
+            position.0 = value;
+        } else {
+            position.1 = value;
+        }
+
+        let player = players.iter().position(|&player| player == id).unwrap_or(0);
+        let now = keymap.stick_keys(player, position.0, position.1);
+        for (key, state) in keys.iter_mut().enumerate() {
+            if *held >> key & 1 != 0 && now >> key & 1 == 0 {
+                *state = 0;
+            } else if now >> key & 1 != 0 && *held >> key & 1 == 0 {
+                *state = 1;
+            }
+        }
+        *held = now;
+    }
+
+    // Alt+Enter toggles fullscreen as well as the configured hotkey
+    fn key_to_action(keymap: &Keymap, key: Keycode, keymod: Mod) -> Option<Action> {
         match key {
-            Keycode::X => Some(0),
-            Keycode::Num1 => Some(1),
-            Keycode::Num2 => Some(2),
-            Keycode::Num3 => Some(3),
-            Keycode::Q => Some(4),
-            Keycode::W => Some(5),
-            Keycode::E => Some(6),
-            Keycode::A => Some(7),
-            Keycode::S => Some(8),
-            Keycode::D => Some(9),
-            Keycode::Z => Some(0xA),
-            Keycode::C => Some(0xB),
-            Keycode::Num4 => Some(0xC),
-            Keycode::R => Some(0xD),
-            Keycode::F => Some(0xE),
-            Keycode::V => Some(0xF),
-            _ => None,
-        }
-    }
-
-    pub fn process_input(&mut self, keys: &mut [u8; 16]) -> bool {
+            Keycode::Return if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) => Some(Action::ToggleFullscreen),
+            _ => keymap.hotkey(key),
+        }
+    }
+
+    // Where a touch at `x`, `y`, from 0 to 1 across the window, falls in the
+    //  logical coordinates everything is drawn in, allowing for SDL's
+    //  letterboxing
+    fn touch_point(canvas: &WindowCanvas, x: f32, y: f32) -> (i32, i32) {
+        let (window_width, window_height) = canvas.window().size();
+        let (width, height) = canvas.logical_size();
+        let mut scale = (window_width as f32 / width as f32).min(window_height as f32 / height as f32);
+        if canvas.integer_scale() && scale >= 1.0 {
+            scale = scale.floor();
+        }
+        let left = (window_width as f32 - width as f32 * scale) / 2.0;
+        let top = (window_height as f32 - height as f32 * scale) / 2.0;
+        (
+            ((x * window_width as f32 - left) / scale) as i32,
+            ((y * window_height as f32 - top) / scale) as i32,
+        )
+    }
+
+    pub fn process_input(&mut self, keys: &mut [u8; 16]) -> Vec<Action> {
+        let mut actions = std::mem::take(&mut self.picked);
+        let display = self.display_rect();
+        let texture_size = self.texture.query();
+
         for event in self.event_pump.poll_iter() {
+            if self.menu_open {
+                self.egui.handle_event(&event);
+            }
             match event {
                 Event::Quit { .. } => {
-                    return true;
+                    actions.push(Action::Quit);
+                }
+
+                Event::DropFile { filename, .. } => {
+                    self.dropped = Some(PathBuf::from(filename));
+                    actions.push(Action::RomDropped);
                 }
 
                 Event::KeyDown {
+                    timestamp,
                     keycode,
+                    scancode,
+                    keymod,
+                    repeat,
                     ..
                 } => {
-                    if let Some(key) = keycode {
-                        if let Some(index) = Self::key_to_chip8_key(key) {
-                            keys[index] = 1;
+                    self.last_input = Instant::now();
+                    if let Some(pressed) = &mut self.remap {
+                        let quit = keycode.and_then(|key| self.keymap.hotkey(key)) == Some(Action::Quit);
+                        match scancode {
+                            _ if repeat => {}
+                            _ if quit => {
+                                self.remap = None;
+                                self.osd.show("Keys left as they were");
+                            }
+                            Some(scancode) if !pressed.contains(&scancode) => {
+                                pressed.push(scancode);
+                                if pressed.len() == REMAP_ORDER.len() {
+                                    self.keymap.remap(REMAP_ORDER.into_iter().zip(pressed.iter().copied()));
+                                    self.remap = None;
+                                    actions.push(Action::KeymapChanged);
+                                }
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
+
+                    // The system's auto-repeat says nothing new about a held key
+                    let chip8_key = self.keymap.chip8_key(keycode, scancode);
+                    if let Some(index) = chip8_key.filter(|_| !repeat) {
+                        if let Some(presses) = self.presses.as_mut().filter(|_| keys[index] == 0) {
+                            presses.push((index, timestamp));
                         }
-                        if key == Keycode::Escape {
-                            return true;
+                        keys[index] = 1;
+                    }
+                    if let Some(key) = keycode {
+                        // Keys that play the keypad aren't hotkeys as well,
+                        //  except the quit key
+                        let action = Self::key_to_action(&self.keymap, key, keymod)
+                            .filter(|&action| !repeat && (chip8_key.is_none() || action == Action::Quit));
+                        match action {
+                            // The quit key closes the menu before it quits
+                            Some(Action::Quit) if self.menu_open => self.menu_open = false,
+                            Some(action) => actions.push(action),
+                            None => {}
                         }
                     }
                 }
 
                 Event::KeyUp {
                     keycode,
+                    scancode,
                     ..
                 } => {
+                    if let Some(index) = self.keymap.chip8_key(keycode, scancode) {
+                        keys[index] = 0;
+                    }
                     if let Some(key) = keycode {
-                        if let Some(index) = Self::key_to_chip8_key(key) {
-                            keys[index] = 0;
+                        // Rewind lasts for as long as the key is held
+                        if self.keymap.hotkey(key) == Some(Action::RewindStart) {
+                            actions.push(Action::RewindStop);
+                        }
+                    }
+                }
+
+                Event::ControllerButtonDown { timestamp, which, button } => {
+                    self.last_input = Instant::now();
+                    if let Some(index) = Self::controller_key(&self.keymap, &self.players, which, button) {
+                        if let Some(presses) = self.presses.as_mut().filter(|_| keys[index] == 0) {
+                            presses.push((index, timestamp));
+                        }
+                        keys[index] = 1;
+                    }
+                }
+
+                Event::ControllerButtonUp { which, button, .. } => {
+                    if let Some(index) = Self::controller_key(&self.keymap, &self.players, which, button) {
+                        keys[index] = 0;
+                    }
+                }
+
+                Event::ControllerAxisMotion { which, axis, value, .. } if matches!(axis, Axis::LeftX | Axis::LeftY) => {
+                    if value.unsigned_abs() as f32 > self.keymap.stick.deadzone * i16::MAX as f32 {
+                        self.last_input = Instant::now();
+                    }
+                    Self::move_stick(&self.keymap, &self.players, &mut self.sticks, (which, axis, value), keys);
+                }
+
+                Event::ControllerDeviceAdded { which, .. } => actions.push(Action::ControllerAdded(which)),
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    // Let go of whatever an unplugged controller's stick held
+                    for axis in [Axis::LeftX, Axis::LeftY] {
+                        Self::move_stick(&self.keymap, &self.players, &mut self.sticks, (which, axis, 0), keys);
+                    }
+                    self.sticks.retain(|&(stick, ..)| stick != which);
+                    actions.push(Action::ControllerRemoved(which));
+                }
+
+                Event::MouseButtonDown {
+                    mouse_btn: MouseButton::Right,
+                    ..
+                } => {
+                    self.menu_open = !self.menu_open;
+                }
+
+                // Clicks on the menu are the menu's
+                Event::MouseButtonDown {
+                    mouse_btn: MouseButton::Left,
+                    ..
+                } if self.menu_open && self.egui.wants_pointer() => {}
+
+                Event::MouseMotion { which, xrel, yrel, .. } if self.paddle.is_some() && which != TOUCH_MOUSE_ID => {
+                    if let Some(paddle) = &mut self.paddle {
+                        paddle.moved(xrel, yrel);
+                    }
+                }
+
+                Event::MouseWheel { y, direction, .. } => {
+                    if let Some(paddle) = &mut self.paddle {
+                        paddle.scrolled(if direction == MouseWheelDirection::Flipped { -y } else { y });
+                    }
+                }
+
+                Event::FingerDown { finger_id, x, y, .. } | Event::FingerMotion { finger_id, x, y, .. } => {
+                    let (x, y) = Self::touch_point(&self.canvas, x, y);
+                    if let Some(keypad) = &mut self.keypad {
+                        if keypad.touch(finger_id, x, y, keys) {
+                            self.last_input = Instant::now();
+                        }
+                    }
+                }
+
+                Event::FingerUp { finger_id, .. } => {
+                    if let Some(keypad) = &mut self.keypad {
+                        keypad.untouch(finger_id, keys);
+                    }
+                }
+
+                Event::MouseButtonDown {
+                    which,
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } => {
+                    if let Some(keypad) = self.keypad.as_mut().filter(|_| which != TOUCH_MOUSE_ID) {
+                        if keypad.press(x, y, keys) {
+                            self.last_input = Instant::now();
                         }
-                        if key == Keycode::Escape {
-                            return true;
+                    }
+                    // Clicking the display points the magnifier there
+                    if let Some(magnifier) = self.magnifier.as_mut().filter(|_| self.rotation == 0) {
+                        if display.contains_point((x, y)) {
+                            magnifier.pin(
+                                (x - display.x()) * texture_size.width as i32 / display.width() as i32,
+                                (y - display.y()) * texture_size.height as i32 / display.height() as i32,
+                            );
                         }
                     }
                 }
 
+                Event::MouseMotion { which, mousestate, x, y, .. } if mousestate.left() && which != TOUCH_MOUSE_ID => {
+                    if let Some(keypad) = &mut self.keypad {
+                        keypad.drag(x, y, keys);
+                    }
+                }
+
+                Event::MouseButtonUp {
+                    which,
+                    mouse_btn: MouseButton::Left,
+                    ..
+                } if which != TOUCH_MOUSE_ID => {
+                    if let Some(keypad) = &mut self.keypad {
+                        keypad.release(keys);
+                    }
+                }
+
                 _ => {}
             }
         }
-        false
+        if let Some(paddle) = &mut self.paddle {
+            paddle.apply(&self.keymap.mouse, keys);
+        }
+        self.keys = *keys;
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_errors_say_what_to_try() {
+        let error = DisplayError::NoMonitor { monitor: 2, found: 1 };
+        assert_eq!(error.to_string(), "there is no monitor 2 (found 1)");
+        assert!(error.hints().is_empty());
+
+        let renderer = |software| DisplayError::Renderer {
+            source: IntegerOrSdlError::SdlError(String::from("no GPU")),
+            software,
+        };
+        assert_eq!(renderer(false).hints(), ["Try --renderer software"]);
+        assert!(renderer(true).hints().is_empty());
+        let error = anyhow::Error::from(renderer(false));
+        assert_eq!(format!("{:#}", error), "could not create a renderer: SDL error: no GPU");
+    }
+
+    #[test]
+    fn auto_scale_leaves_room_for_the_title_bar() {
+        assert_eq!(fit_scale((1920, 1080), 64, 32), 30);
+        assert_eq!(fit_scale((1920, 1024), 64, 32), 30);
+        assert_eq!(fit_scale((1920, 1023), 64, 32), 29);
+        assert_eq!(fit_scale((40, 20), 64, 32), 1);
+        assert_eq!(fit_scale((100_000, 100_000), 64, 32), u8::MAX);
+    }
+
+    #[test]
+    fn turned_displays_are_centred_in_the_tall_window() {
+        assert_eq!(rotated_rect((640, 320), 0), Rect::new(0, 0, 640, 320));
+        assert_eq!(rotated_rect((640, 320), 180), Rect::new(0, 0, 640, 320));
+        // A 90 degree turn about the centre takes this rect to the window
+        assert_eq!(rotated_rect((320, 640), 90), Rect::new(-160, 160, 640, 320));
+        assert_eq!(rotated_rect((320, 640), 270), Rect::new(-160, 160, 640, 320));
+    }
+
+    #[test]
+    fn alt_enter_and_f11_toggle_fullscreen() {
+        let fullscreen = Some(Action::ToggleFullscreen);
+        assert_eq!(Platform::key_to_action(&Keymap::default(), Keycode::F11, Mod::NOMOD), fullscreen);
+        assert_eq!(Platform::key_to_action(&Keymap::default(), Keycode::Return, Mod::LALTMOD), fullscreen);
+        assert_eq!(Platform::key_to_action(&Keymap::default(), Keycode::Return, Mod::RALTMOD | Mod::NUMMOD), fullscreen);
+        assert_eq!(Platform::key_to_action(&Keymap::default(), Keycode::Return, Mod::NOMOD), None);
+    }
+
+    #[test]
+    fn windows_are_placed_on_their_monitor() {
+        let second_monitor = Rect::new(1920, 0, 1280, 1024);
+        assert_eq!(place_window(second_monitor, None, (640, 320)), (2240, 352));
+        assert_eq!(place_window(second_monitor, Some((10, 20)), (640, 320)), (1930, 20));
+        assert_eq!(Platform::key_to_action(&Keymap::default(), Keycode::T, Mod::NOMOD), Some(Action::ToggleAlwaysOnTop));
     }
 
+    #[test]
+    fn bezel_screens_stretch_with_the_window() {
+        let screen = bezel_screen(Rect::new(0, 0, 800, 600), (400, 300), Rect::new(40, 30, 320, 160));
+        assert_eq!(screen, Rect::new(80, 60, 640, 320));
+    }
 
+    #[test]
+    fn f1_switches_the_scaling_filter() {
+        assert_eq!(Platform::key_to_action(&Keymap::default(), Keycode::F1, Mod::NOMOD), Some(Action::ToggleSmoothing));
+    }
+
+    #[test]
+    fn in_between_frames_fade_from_the_previous_one() {
+        let mut mixed = Vec::new();
+        mix_frames(&[0, 100, 255, 255], &[255, 0, 255, 255], 0.25, &mut mixed);
+        assert_eq!(mixed, [64, 75, 255, 255]);
+        mix_frames(&[0, 100, 255, 255], &[255, 0, 255, 255], 1.0, &mut mixed);
+        assert_eq!(mixed, [255, 0, 255, 255]);
+    }
+
+    #[test]
+    fn tab_shows_the_magnifier() {
+        assert_eq!(Platform::key_to_action(&Keymap::default(), Keycode::Tab, Mod::NOMOD), Some(Action::ToggleMagnifier));
+    }
+
+    #[test]
+    fn k_remaps_the_keypad() {
+        assert_eq!(Platform::key_to_action(&Keymap::default(), Keycode::K, Mod::NOMOD), Some(Action::RemapKeys));
+        let mut order = REMAP_ORDER;
+        order.sort();
+        assert_eq!(order, std::array::from_fn(|index| index));
+    }
+
+    #[test]
+    fn sticks_let_go_of_what_they_no_longer_point_to() {
+        let keymap = Keymap::default();
+        let mut sticks = Vec::new();
+        let mut keys = [0; 16];
+        Platform::move_stick(&keymap, &[4], &mut sticks, (4, Axis::LeftX, -30000), &mut keys);
+        assert_eq!(keys[0x7], 1);
+        // A key also held on the keyboard stays down until the stick moves off it
+        keys[0x5] = 1;
+        Platform::move_stick(&keymap, &[4], &mut sticks, (4, Axis::LeftY, 100), &mut keys);
+        assert_eq!((keys[0x7], keys[0x5]), (1, 1));
+        Platform::move_stick(&keymap, &[4], &mut sticks, (4, Axis::LeftX, 0), &mut keys);
+        assert_eq!((keys[0x7], keys[0x5]), (0, 1));
+        assert_eq!(sticks, [(4, (0, 100), 0)]);
+    }
+
+    #[test]
+    fn b_switches_the_buzzer_sound() {
+        assert_eq!(Platform::key_to_action(&Keymap::default(), Keycode::B, Mod::NOMOD), Some(Action::NextWaveform));
+    }
+
+    #[test]
+    fn m_mutes_and_minus_and_equals_change_the_volume() {
+        assert_eq!(Platform::key_to_action(&Keymap::default(), Keycode::M, Mod::NOMOD), Some(Action::ToggleMute));
+        assert_eq!(Platform::key_to_action(&Keymap::default(), Keycode::Minus, Mod::NOMOD), Some(Action::VolumeDown));
+        assert_eq!(Platform::key_to_action(&Keymap::default(), Keycode::Equals, Mod::NOMOD), Some(Action::VolumeUp));
+    }
+
+    #[test]
+    fn i_shows_the_sound_timer() {
+        assert_eq!(Platform::key_to_action(&Keymap::default(), Keycode::I, Mod::NOMOD), Some(Action::ToggleSoundIndicator));
+    }
 }
@@ -0,0 +1,134 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// What a ROM in a folder is called, besides anything named on purpose
+const EXTENSIONS: [&str; 5] = ["ch8", "c8", "rom", "sc8", "xo8"];
+
+// ROMs to play one after another, for demo tables and trying out a batch of
+//  programs: several given on the command line, or the ROMs in a folder.
+//  N moves on to the next, and so does a while without input if
+//  --playlist-idle says how long; after the last comes the first again.
+pub struct Playlist {
+    roms: Vec<PathBuf>,
+    current: usize,
+    idle: Option<Duration>,
+    // When the current ROM started
+    started: Instant,
+}
+
+impl Playlist {
+    // Folders among `arguments` stand for the ROMs in them, in name order
+    pub fn new(arguments: &[String], idle: Option<Duration>) -> io::Result<Playlist> {
+        let mut roms = Vec::new();
+        for argument in arguments {
+            let path = PathBuf::from(argument);
+            if path.is_dir() {
+                let found = folder_roms(&path)?;
+                if found.is_empty() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("no ROMs (.{}) in {}", EXTENSIONS.join(", ."), path.display()),
+                    ));
+                }
+                roms.extend(found);
+            } else {
+                roms.push(path);
+            }
+        }
+        Ok(Playlist {
+            roms,
+            current: 0,
+            idle,
+            started: Instant::now(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.roms.len()
+    }
+
+    pub fn current(&self) -> &Path {
+        &self.roms[self.current]
+    }
+
+    pub fn position(&self) -> usize {
+        self.current
+    }
+
+    // The ROMs' file names without extensions, for choosing between them
+    pub fn names(&self) -> Vec<String> {
+        self.roms
+            .iter()
+            .map(|rom| rom.file_stem().unwrap_or(rom.as_os_str()).to_string_lossy().into_owned())
+            .collect()
+    }
+
+    // Go to the ROM at `index`, returning it
+    pub fn select(&mut self, index: usize) -> PathBuf {
+        self.current = index % self.roms.len();
+        self.started = Instant::now();
+        self.current().to_path_buf()
+    }
+
+    // Move on to the next ROM, returning it
+    pub fn advance(&mut self) -> PathBuf {
+        self.select(self.current + 1)
+    }
+
+    // Whether the player has left the current ROM alone long enough to move
+    //  on, given how long it has been since the last input
+    pub fn due(&self, idle_time: Duration) -> bool {
+        self.idle.is_some_and(|idle| idle_time >= idle && self.started.elapsed() >= idle)
+    }
+}
+
+fn folder_roms(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut roms = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase());
+        if path.is_file() && extension.is_some_and(|extension| EXTENSIONS.contains(&extension.as_str())) {
+            roms.push(path);
+        }
+    }
+    roms.sort();
+    Ok(roms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folders_stand_for_their_roms_and_the_list_goes_round() {
+        let dir = std::env::temp_dir().join(format!("chip8-playlist-{}", std::process::id()));
+        let folder = dir.join("games");
+        fs::create_dir_all(&folder).unwrap();
+        for name in ["tetris.ch8", "blitz.XO8", "notes.txt"] {
+            fs::write(folder.join(name), [0x12, 0x00]).unwrap();
+        }
+        fs::create_dir_all(dir.join("empty")).unwrap();
+        let arguments = [String::from("pong.ch8"), folder.display().to_string()];
+        let playlist = Playlist::new(&arguments, Some(Duration::from_secs(30)));
+        let empty = Playlist::new(&[dir.join("empty").display().to_string()], None);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut playlist = playlist.unwrap();
+        assert_eq!(playlist.len(), 3);
+        assert_eq!(playlist.current(), Path::new("pong.ch8"));
+        assert_eq!(playlist.advance(), folder.join("blitz.XO8"));
+        assert_eq!(playlist.advance(), folder.join("tetris.ch8"));
+        assert_eq!(playlist.advance(), PathBuf::from("pong.ch8"));
+        assert_eq!(playlist.names(), ["pong", "blitz", "tetris"]);
+        assert_eq!((playlist.select(2), playlist.position()), (folder.join("tetris.ch8"), 2));
+        assert!(empty.is_err());
+
+        // Only a ROM left alone for the whole time moves on
+        assert!(!playlist.due(Duration::from_secs(60)));
+        playlist.started -= Duration::from_secs(60);
+        assert!(playlist.due(Duration::from_secs(60)));
+        assert!(!playlist.due(Duration::from_secs(10)));
+    }
+}
@@ -0,0 +1,187 @@
+// Behavioural differences between the CHIP-8 interpreters that ROMs were
+//  written against. Names follow Octo's quirk flags.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Variant {
+    Chip8,
+    SuperChip,
+    XoChip,
+}
+
+impl Variant {
+    // Parse the platform names used by Octo and the CHIP-8 Archive
+    pub fn from_name(name: &str) -> Option<Variant> {
+        match name.to_ascii_lowercase().as_str() {
+            "chip8" | "chip-8" => Some(Variant::Chip8),
+            "schip" | "superchip" | "schip1.1" => Some(Variant::SuperChip),
+            "xochip" | "xo-chip" => Some(Variant::XoChip),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Variant::Chip8 => "chip8",
+            Variant::SuperChip => "schip",
+            Variant::XoChip => "xochip",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    // 8xy6/8xyE shift Vx in place instead of loading Vy first
+    pub shift: bool,
+    // Fx55/Fx65 leave I unchanged instead of advancing it
+    pub load_store: bool,
+    // Bnnn jumps to xnn + Vx instead of nnn + V0
+    pub jump: bool,
+    // 8xy1/8xy2/8xy3 reset VF to 0
+    pub logic: bool,
+    // Dxyn clips sprites at the screen edge instead of wrapping them
+    pub clip: bool,
+    // Fx0A takes a key held since before it began waiting, so a held key
+    //  (or the keyboard's auto-repeat) counts as press after press
+    pub key_repeat: bool,
+}
+
+impl Default for Quirks {
+    // The behaviour this emulator has always had, apart from Fx0A waiting
+    //  for a new press
+    fn default() -> Self {
+        Quirks {
+            shift: true,
+            load_store: true,
+            jump: false,
+            logic: false,
+            clip: false,
+            key_repeat: false,
+        }
+    }
+}
+
+impl Quirks {
+    pub fn for_variant(variant: Variant) -> Quirks {
+        match variant {
+            Variant::Chip8 => Quirks {
+                shift: false,
+                load_store: false,
+                jump: false,
+                logic: true,
+                clip: true,
+                key_repeat: false,
+            },
+            Variant::SuperChip => Quirks {
+                shift: true,
+                load_store: true,
+                jump: true,
+                logic: false,
+                clip: true,
+                key_repeat: false,
+            },
+            Variant::XoChip => Quirks {
+                shift: false,
+                load_store: false,
+                jump: false,
+                logic: false,
+                clip: false,
+                key_repeat: false,
+            },
+        }
+    }
+}
+
+// A single quirk, for switching one on or off while a game runs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quirk {
+    Shift,
+    LoadStore,
+    Jump,
+    Logic,
+    Clip,
+    KeyRepeat,
+}
+
+impl Quirk {
+    pub const ALL: [Quirk; 6] = [
+        Quirk::Shift,
+        Quirk::LoadStore,
+        Quirk::Jump,
+        Quirk::Logic,
+        Quirk::Clip,
+        Quirk::KeyRepeat,
+    ];
+
+    // Names may be written with a dash or underscore, e.g. `load-store`
+    pub fn from_name(name: &str) -> Option<Quirk> {
+        let name = name.replace(['-', '_'], " ").replace("load store", "load/store");
+        Quirk::ALL.into_iter().find(|quirk| quirk.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Quirk::Shift => "shift",
+            Quirk::LoadStore => "load/store",
+            Quirk::Jump => "jump",
+            Quirk::Logic => "logic",
+            Quirk::Clip => "clip",
+            Quirk::KeyRepeat => "key repeat",
+        }
+    }
+
+    fn flag(self, quirks: &mut Quirks) -> &mut bool {
+        match self {
+            Quirk::Shift => &mut quirks.shift,
+            Quirk::LoadStore => &mut quirks.load_store,
+            Quirk::Jump => &mut quirks.jump,
+            Quirk::Logic => &mut quirks.logic,
+            Quirk::Clip => &mut quirks.clip,
+            Quirk::KeyRepeat => &mut quirks.key_repeat,
+        }
+    }
+
+    pub fn is_on(self, mut quirks: Quirks) -> bool {
+        *self.flag(&mut quirks)
+    }
+
+    pub fn set(self, quirks: &mut Quirks, on: bool) {
+        *self.flag(quirks) = on;
+    }
+
+    // Flip the quirk, returning whether it is now on
+    pub fn toggle(self, quirks: &mut Quirks) -> bool {
+        let flag = self.flag(quirks);
+        *flag = !*flag;
+        *flag
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_quirk_toggles_its_own_flag() {
+        for quirk in Quirk::ALL {
+            let mut quirks = Quirks::default();
+            let was_on = quirk.is_on(quirks);
+            assert_eq!(quirk.toggle(&mut quirks), !was_on);
+            let mut others = Quirk::ALL.iter().filter(|&&other| other != quirk);
+            assert!(others.all(|other| other.is_on(quirks) == other.is_on(Quirks::default())));
+        }
+    }
+
+    #[test]
+    fn quirks_are_named_as_on_the_command_line() {
+        assert_eq!(Quirk::from_name("load-store"), Some(Quirk::LoadStore));
+        assert_eq!(Quirk::from_name("key_repeat"), Some(Quirk::KeyRepeat));
+        assert_eq!(Quirk::from_name("shift"), Some(Quirk::Shift));
+        assert_eq!(Quirk::from_name("wrap"), None);
+
+        let mut quirks = Quirks::default();
+        Quirk::Clip.set(&mut quirks, false);
+        Quirk::Clip.set(&mut quirks, false);
+        assert!(!Quirk::Clip.is_on(quirks));
+        Quirk::Clip.set(&mut quirks, true);
+        assert!(Quirk::Clip.is_on(quirks));
+    }
+}
@@ -0,0 +1,90 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// How many ROMs are remembered
+const LIMIT: usize = 10;
+
+// The ROM files opened most recently, newest first, one path per line in
+//  recent.txt beside config.toml, so the menu can reopen them
+pub fn path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("chip8-emulator").join("recent.txt"))
+}
+
+pub fn load() -> Vec<PathBuf> {
+    path().map(|path| read(&path)).unwrap_or_default()
+}
+
+// Put a ROM first in the list, returning the new list
+pub fn add(rom: &Path) -> io::Result<Vec<PathBuf>> {
+    let path = path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config folder"))?;
+    remember(&path, rom)
+}
+
+// The recent ROMs other than the one being played
+pub fn others(recent: Vec<PathBuf>, current: Option<&Path>) -> Vec<PathBuf> {
+    let current = current.and_then(|path| path.canonicalize().ok());
+    recent.into_iter().filter(|path| Some(path) != current.as_ref()).collect()
+}
+
+// What the menu calls a ROM: its file name without the extension
+pub fn name(rom: &Path) -> String {
+    rom.file_stem().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+// Files that have since gone are left out
+fn read(list: &Path) -> Vec<PathBuf> {
+    let Ok(text) = fs::read_to_string(list) else {
+        return Vec::new();
+    };
+    text.lines().map(PathBuf::from).filter(|rom| rom.is_file()).collect()
+}
+
+fn remember(list: &Path, rom: &Path) -> io::Result<Vec<PathBuf>> {
+    let rom = rom.canonicalize()?;
+    let mut recent = read(list);
+    recent.retain(|other| *other != rom);
+    recent.insert(0, rom);
+    recent.truncate(LIMIT);
+
+    if let Some(dir) = list.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let text: String = recent.iter().map(|rom| format!("{}\n", rom.display())).collect();
+    fs::write(list, text)?;
+    Ok(recent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_newest_rom_comes_first_once_and_gone_files_are_dropped() {
+        let dir = std::env::temp_dir().join(format!("chip8-recent-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dir = dir.canonicalize().unwrap();
+        let list = dir.join("settings").join("recent.txt");
+        let roms: Vec<PathBuf> = (0..LIMIT + 1).map(|n| dir.join(format!("game{}.ch8", n))).collect();
+        for rom in &roms {
+            fs::write(rom, [0x12, 0x00]).unwrap();
+        }
+
+        assert!(read(&list).is_empty());
+        for rom in &roms {
+            remember(&list, rom).unwrap();
+        }
+        let recent = remember(&list, &roms[LIMIT - 1]).unwrap();
+        fs::remove_file(&roms[LIMIT]).unwrap();
+        let reread = read(&list);
+        let others = others(reread.clone(), Some(&roms[LIMIT - 1]));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(recent.len(), LIMIT);
+        assert_eq!(recent[..2], [roms[LIMIT - 1].clone(), roms[LIMIT].clone()]);
+        assert!(!recent.contains(&roms[0]));
+        assert_eq!(reread.len(), LIMIT - 1);
+        assert_eq!(others.len(), LIMIT - 2);
+        assert_eq!(name(&others[0]), "game8");
+    }
+}
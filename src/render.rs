@@ -0,0 +1,139 @@
+use crate::blend::Blend;
+use crate::draw_viz::Highlight;
+use crate::lcd::Lcd;
+use crate::palette::{self, PlaneColors};
+use crate::phosphor::{self, Phosphor};
+
+// Turns the 1-byte-per-pixel framebuffer into the RGBA image the window
+//  shows, colors and phosphor/blend included. It needs no SDL, so headless
+//  runs can render exactly what a window would and hash the result.
+pub struct Renderer {
+    foreground: [u8; 3],
+    background: [u8; 3],
+    plane_colors: PlaneColors,
+    phosphor: Option<Phosphor>,
+    blend: Option<Blend>,
+    lcd: Option<Lcd>,
+    highlight: Option<Highlight>,
+    rgba: Vec<u8>,
+}
+
+impl Renderer {
+    pub fn new() -> Renderer {
+        Renderer {
+            foreground: [0xFF, 0xFF, 0xFF],
+            background: [0x00, 0x00, 0x00],
+            plane_colors: PlaneColors::default(),
+            phosphor: None,
+            blend: None,
+            lcd: None,
+            highlight: None,
+            rgba: Vec::new(),
+        }
+    }
+
+    pub fn set_colors(&mut self, foreground: [u8; 3], background: [u8; 3]) {
+        self.foreground = foreground;
+        self.background = background;
+    }
+
+    pub fn set_plane_colors(&mut self, plane_colors: PlaneColors) {
+        self.plane_colors = plane_colors;
+    }
+
+    // The color of each pixel value, by the XO-CHIP planes lit there
+    pub fn pixel_colors(&self) -> [[u8; 3]; 4] {
+        palette::pixel_colors(self.foreground, self.background, self.plane_colors)
+    }
+
+    // Let pixels fade out over `frames` frames instead of vanishing at once
+    pub fn set_phosphor(&mut self, frames: Option<u32>) {
+        self.phosphor = frames.map(Phosphor::new);
+    }
+
+    // Show the average of the last `frames` frames
+    pub fn set_blend(&mut self, frames: Option<usize>) {
+        self.blend = frames.map(Blend::new);
+    }
+
+    // Let pixels change brightness gradually, like a slow LCD
+    pub fn set_lcd(&mut self, enabled: bool) {
+        self.lcd = enabled.then(Lcd::new);
+    }
+
+    // Mark a sprite draw on the frames rendered from now on
+    pub fn set_highlight(&mut self, highlight: Option<Highlight>) {
+        self.highlight = highlight;
+    }
+
+    // Render the next frame, as R, G, B, A bytes per pixel
+    pub fn render(&mut self, video: &[u8]) -> &[u8] {
+        self.rgba.resize(video.len() * 4, 0);
+
+        let levels = match (&mut self.phosphor, &mut self.blend, &mut self.lcd) {
+            (Some(phosphor), _, _) => Some(phosphor.apply(video)),
+            (None, Some(blend), _) => Some(blend.apply(video)),
+            (None, None, Some(lcd)) => Some(lcd.apply(video)),
+            (None, None, None) => None,
+        };
+        if let Some(levels) = levels {
+            for (texel, &level) in self.rgba.chunks_exact_mut(4).zip(levels) {
+                let [r, g, b] = phosphor::mix(self.foreground, self.background, level);
+                texel.copy_from_slice(&[r, g, b, 0xFF]);
+            }
+        } else {
+            // Fading and blending go by whether a pixel is lit, so only this
+            //  shows the XO-CHIP planes in their own colors
+            let colors = self.pixel_colors();
+            for (texel, &pixel) in self.rgba.chunks_exact_mut(4).zip(video) {
+                let [r, g, b] = colors[pixel as usize % colors.len()];
+                texel.copy_from_slice(&[r, g, b, 0xFF]);
+            }
+        }
+        if let Some(highlight) = &self.highlight {
+            highlight.apply(&mut self.rgba);
+        }
+        &self.rgba
+    }
+
+    // SHA-1 of the last rendered frame, for checking visual output in tests
+    pub fn frame_hash(&self) -> String {
+        sha1_smol::Sha1::from(&self.rgba).digest().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixels_take_the_colors_of_their_planes() {
+        let mut renderer = Renderer::new();
+        renderer.set_colors([9, 9, 9], [1, 1, 1]);
+        renderer.set_plane_colors(PlaneColors { plane2: Some([2, 2, 2]), both: Some([3, 3, 3]) });
+        let rgba = renderer.render(&[0, 1, 2, 3]);
+        assert_eq!(rgba, [1, 1, 1, 0xFF, 9, 9, 9, 0xFF, 2, 2, 2, 0xFF, 3, 3, 3, 0xFF]);
+    }
+
+    #[test]
+    fn frame_hashes_follow_the_colors_shown() {
+        let mut renderer = Renderer::new();
+        renderer.render(&[0, 1]);
+        let white = renderer.frame_hash();
+        assert_eq!(white.len(), 40);
+
+        renderer.set_colors([0xFF, 0xB0, 0x00], [0; 3]);
+        renderer.render(&[0, 1]);
+        assert_ne!(renderer.frame_hash(), white);
+
+        // Phosphor fades the pixel that went out, so the frame differs from
+        //  a plain one showing the same machine display
+        renderer.set_phosphor(Some(4));
+        renderer.render(&[0, 1]);
+        renderer.render(&[0, 0]);
+        let faded = renderer.frame_hash();
+        renderer.set_phosphor(None);
+        renderer.render(&[0, 0]);
+        assert_ne!(renderer.frame_hash(), faded);
+    }
+}
@@ -0,0 +1,458 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::chip8::Chip8;
+use crate::quirks::Quirks;
+
+// Replays record the keypad once per frame, along with everything else a
+//  run depends on: the RNG seed, the CPU speed, the quirks and start address
+//  and the ROM it was made with. Played back from power-on they reproduce
+//  the original run exactly. Version 1 replays have no start address, and
+//  started at 0x200.
+const MAGIC: &[u8; 4] = b"C8RP";
+const VERSION: u16 = 2;
+
+const HASH_SIZE: usize = 40;
+// One bit per quirk, then the start address
+const MACHINE_SIZE: usize = 1 + 2;
+const V1_MACHINE_SIZE: usize = 1;
+const HEADER_SIZE: usize = 4 + 2 + 8 + 8 + HASH_SIZE + MACHINE_SIZE + 4;
+const V1_HEADER_SIZE: usize = HEADER_SIZE - MACHINE_SIZE + V1_MACHINE_SIZE;
+const DEFAULT_START_ADDRESS: u16 = 0x200;
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// One bit per key, key 0 in the lowest bit
+pub fn keypad_bits(keypad: &[u8; 16]) -> u16 {
+    keypad
+        .iter()
+        .enumerate()
+        .filter(|(_, &pressed)| pressed > 0)
+        .fold(0u16, |bits, (key, _)| bits | 1 << key)
+}
+
+pub fn bits_keypad(bits: u16) -> [u8; 16] {
+    let mut keypad = [0; 16];
+    for (key, pressed) in keypad.iter_mut().enumerate() {
+        *pressed = (bits >> key & 1) as u8;
+    }
+    keypad
+}
+
+// Keys as hex digits in ascending order, or `-` when none are held
+pub fn bits_text(bits: u16) -> String {
+    if bits == 0 {
+        return String::from("-");
+    }
+    (0..16).filter(|key| bits >> key & 1 != 0).map(|key| format!("{:X}", key)).collect()
+}
+
+pub fn text_bits(text: &str) -> Result<u16, String> {
+    let mut bits = 0u16;
+    for c in text.chars().filter(|&c| c != '-') {
+        let key = c.to_digit(16).ok_or_else(|| format!("bad key '{}'", c))?;
+        bits |= 1 << key;
+    }
+    Ok(bits)
+}
+
+fn quirk_bits(quirks: Quirks) -> u8 {
+    let Quirks {
+        shift,
+        load_store,
+        jump,
+        logic,
+        clip,
+        key_repeat,
+    } = quirks;
+    [shift, load_store, jump, logic, clip, key_repeat]
+        .into_iter()
+        .enumerate()
+        .fold(0u8, |bits, (index, on)| bits | (on as u8) << index)
+}
+
+fn bits_quirks(bits: u8) -> Quirks {
+    let on = |index: u8| bits >> index & 1 != 0;
+    Quirks {
+        shift: on(0),
+        load_store: on(1),
+        jump: on(2),
+        logic: on(3),
+        clip: on(4),
+        key_repeat: on(5),
+    }
+}
+
+// Quirk names for the text format, in bit order
+const QUIRK_NAMES: [&str; 6] = ["shift", "load-store", "jump", "logic", "clip", "key-repeat"];
+
+fn quirk_names(quirks: Quirks) -> String {
+    let bits = quirk_bits(quirks);
+    let names: Vec<&str> = (0..QUIRK_NAMES.len())
+        .filter(|index| bits >> index & 1 != 0)
+        .map(|index| QUIRK_NAMES[index])
+        .collect();
+    if names.is_empty() {
+        String::from("-")
+    } else {
+        names.join(" ")
+    }
+}
+
+fn names_quirks(text: &str) -> Result<Quirks, String> {
+    let mut bits = 0u8;
+    for name in text.split_whitespace().filter(|&name| name != "-") {
+        let index = QUIRK_NAMES
+            .iter()
+            .position(|&known| known == name)
+            .ok_or_else(|| format!("unknown quirk '{}'", name))?;
+        bits |= 1 << index;
+    }
+    Ok(bits_quirks(bits))
+}
+
+// The machine a run was recorded on, which playback sets up again
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Machine {
+    pub quirks: Quirks,
+    pub start_address: u16,
+}
+
+impl Machine {
+    pub fn of(chip8: &Chip8) -> Machine {
+        Machine {
+            quirks: chip8.quirks,
+            start_address: chip8.start_address,
+        }
+    }
+
+    // A machine at power-on with `rom` loaded, as the run began
+    pub fn power_on(&self, rom: &[u8]) -> io::Result<Chip8> {
+        let mut chip8 = Chip8::new();
+        chip8.quirks = self.quirks;
+        chip8.set_start_address(self.start_address);
+        chip8.load_rom(rom)?;
+        Ok(chip8)
+    }
+}
+
+pub struct Replay {
+    pub seed: u64,
+    pub cycle_period: Duration,
+    pub rom_hash: String,
+    pub machine: Machine,
+    // Keypad state for each frame, one bit per key
+    pub frames: Vec<u16>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, cycle_period: Duration, rom_hash: &str, machine: Machine) -> Replay {
+        Replay {
+            seed,
+            cycle_period,
+            rom_hash: String::from(rom_hash),
+            machine,
+            frames: Vec::new(),
+        }
+    }
+
+    // Record the keypad for a frame. Anything recorded after it is dropped,
+    //  so rewinding and playing on overwrites the old inputs.
+    pub fn record(&mut self, frame: u64, keypad: &[u8; 16]) {
+        self.frames.truncate(frame as usize);
+        self.frames.push(keypad_bits(keypad));
+    }
+
+    pub fn keys(&self, frame: u64) -> Option<[u8; 16]> {
+        self.frames.get(frame as usize).map(|&bits| bits_keypad(bits))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(HEADER_SIZE + self.frames.len() * 2);
+
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&VERSION.to_be_bytes());
+        data.extend_from_slice(&self.seed.to_be_bytes());
+        data.extend_from_slice(&(self.cycle_period.as_nanos() as u64).to_be_bytes());
+
+        let mut hash = self.rom_hash.clone().into_bytes();
+        hash.resize(HASH_SIZE, b'0');
+        data.extend_from_slice(&hash);
+
+        data.push(quirk_bits(self.machine.quirks));
+        data.extend_from_slice(&self.machine.start_address.to_be_bytes());
+
+        data.extend_from_slice(&(self.frames.len() as u32).to_be_bytes());
+        for bits in &self.frames {
+            data.extend_from_slice(&bits.to_be_bytes());
+        }
+
+        data
+    }
+
+    pub fn from_bytes(data: &[u8]) -> io::Result<Replay> {
+        if data.len() < V1_HEADER_SIZE || !data.starts_with(MAGIC) {
+            return Err(invalid(String::from("not a replay file")));
+        }
+
+        let u16_at = |offset: usize| u16::from_be_bytes([data[offset], data[offset + 1]]);
+        let u32_at = |offset: usize| u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap());
+        let u64_at = |offset: usize| u64::from_be_bytes(data[offset..offset + 8].try_into().unwrap());
+
+        let version = u16_at(4);
+        let (machine_size, header_size) = match version {
+            1 => (V1_MACHINE_SIZE, V1_HEADER_SIZE),
+            VERSION if data.len() >= HEADER_SIZE => (MACHINE_SIZE, HEADER_SIZE),
+            VERSION => return Err(invalid(String::from("not a replay file"))),
+            _ => {
+                return Err(invalid(format!(
+                    "replay format version {} is not supported (this emulator reads up to version {})",
+                    version, VERSION
+                )))
+            }
+        };
+
+        let seed = u64_at(6);
+        let cycle_period = Duration::from_nanos(u64_at(14));
+        let rom_hash = String::from_utf8_lossy(&data[22..22 + HASH_SIZE]).into_owned();
+        let machine = Machine {
+            quirks: bits_quirks(data[22 + HASH_SIZE]),
+            start_address: if version == 1 { DEFAULT_START_ADDRESS } else { u16_at(23 + HASH_SIZE) },
+        };
+        let count = u32_at(22 + HASH_SIZE + machine_size) as usize;
+
+        let body = &data[header_size..];
+        if body.len() != count * 2 {
+            return Err(invalid(format!(
+                "replay should hold {} frames but has {} bytes of input",
+                count,
+                body.len()
+            )));
+        }
+        let frames = body.chunks(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+
+        Ok(Replay {
+            seed,
+            cycle_period,
+            rom_hash,
+            machine,
+            frames,
+        })
+    }
+
+    // A line-per-frame text form for generating inputs with scripts and
+    //  diffing them, with the header fields as `name value` lines first
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "# CHIP-8 replay: one line of held keys per frame\n\
+             seed {}\n\
+             cycle-period {}\n\
+             rom {}\n\
+             quirks {}\n\
+             start-address {:X}\n",
+            self.seed,
+            self.cycle_period.as_nanos(),
+            self.rom_hash,
+            quirk_names(self.machine.quirks),
+            self.machine.start_address
+        );
+        for &bits in &self.frames {
+            text += &bits_text(bits);
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn from_text(text: &str) -> io::Result<Replay> {
+        let mut seed = None;
+        let mut cycle_period = None;
+        let mut rom_hash = None;
+        let mut quirks = None;
+        // Left out by texts from before it was recorded
+        let mut start_address = DEFAULT_START_ADDRESS;
+        let mut frames = Vec::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let bad_line = |message: String| invalid(format!("line {}: {}", number + 1, message));
+
+            match line.split_once(char::is_whitespace) {
+                Some((name, value)) if frames.is_empty() => {
+                    let value = value.trim();
+                    let parse = || value.parse::<u64>().map_err(|_| bad_line(format!("bad {} '{}'", name, value)));
+                    match name {
+                        "seed" => seed = Some(parse()?),
+                        "cycle-period" => cycle_period = Some(Duration::from_nanos(parse()?)),
+                        "rom" => rom_hash = Some(String::from(value)),
+                        "quirks" => quirks = Some(names_quirks(value).map_err(bad_line)?),
+                        "start-address" => {
+                            start_address = u16::from_str_radix(value, 16)
+                                .map_err(|_| bad_line(format!("bad start-address '{}'", value)))?
+                        }
+                        _ => return Err(bad_line(format!("unknown field '{}'", name))),
+                    }
+                }
+                _ => frames.push(text_bits(line).map_err(bad_line)?),
+            }
+        }
+
+        let missing = |field: &str| invalid(format!("replay text has no '{}' line", field));
+        Ok(Replay {
+            seed: seed.ok_or_else(|| missing("seed"))?,
+            cycle_period: cycle_period.ok_or_else(|| missing("cycle-period"))?,
+            rom_hash: rom_hash.ok_or_else(|| missing("rom"))?,
+            machine: Machine {
+                quirks: quirks.ok_or_else(|| missing("quirks"))?,
+                start_address,
+            },
+            frames,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+
+    pub fn load(path: &Path) -> io::Result<Replay> {
+        Replay::from_bytes(&fs::read(path)?)
+    }
+}
+
+// Join two takes of the same run: inputs before `frame` come from `first`,
+//  the rest from `second`
+pub fn splice(first: &Replay, second: &Replay, frame: usize) -> io::Result<Replay> {
+    if first.seed != second.seed
+        || first.cycle_period != second.cycle_period
+        || first.rom_hash != second.rom_hash
+        || first.machine != second.machine
+    {
+        return Err(invalid(String::from(
+            "replays were recorded with a different ROM, seed, speed, quirks or start address",
+        )));
+    }
+    if frame > first.frames.len() || frame > second.frames.len() {
+        return Err(invalid(format!("frame {} is past the end of a replay", frame)));
+    }
+
+    let mut frames = first.frames[..frame].to_vec();
+    frames.extend_from_slice(&second.frames[frame..]);
+
+    Ok(Replay {
+        frames,
+        ..Replay::new(first.seed, first.cycle_period, &first.rom_hash, first.machine)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay() -> Replay {
+        let machine = Machine {
+            quirks: Quirks::for_variant(crate::quirks::Variant::SuperChip),
+            start_address: 0x600,
+        };
+        let mut replay = Replay::new(42, Duration::from_micros(1428), &"ab".repeat(20), machine);
+        for (frame, key) in [0, 5, 15].into_iter().enumerate() {
+            let mut keypad = [0; 16];
+            keypad[key] = 1;
+            replay.record(frame as u64, &keypad);
+        }
+        replay
+    }
+
+    #[test]
+    fn replays_round_trip() {
+        let original = replay();
+        let loaded = Replay::from_bytes(&original.to_bytes()).unwrap();
+        assert_eq!(loaded.seed, 42);
+        assert_eq!(loaded.cycle_period, original.cycle_period);
+        assert_eq!(loaded.rom_hash, original.rom_hash);
+        assert_eq!(loaded.machine, original.machine);
+        assert_eq!(loaded.frames, [1 << 0, 1 << 5, 1 << 15]);
+    }
+
+    #[test]
+    fn text_replays_round_trip() {
+        let original = replay();
+        let text = original.to_text();
+        assert!(text.contains("quirks shift load-store jump clip\nstart-address 600\n"));
+        assert!(text.ends_with("0\n5\nF\n"));
+        let loaded = Replay::from_text(&text).unwrap();
+        assert_eq!((loaded.seed, loaded.cycle_period), (original.seed, original.cycle_period));
+        assert_eq!((loaded.rom_hash, loaded.machine), (original.rom_hash, original.machine));
+        assert_eq!(loaded.frames, original.frames);
+
+        let machine = Machine {
+            quirks: bits_quirks(0),
+            start_address: DEFAULT_START_ADDRESS,
+        };
+        let none = Replay::new(1, Duration::from_millis(1), "ab", machine);
+        assert_eq!(Replay::from_text(&none.to_text()).unwrap().machine, none.machine);
+        let older = none.to_text().replace("start-address 200\n", "");
+        assert_eq!(Replay::from_text(&older).unwrap().machine, none.machine);
+    }
+
+    #[test]
+    fn every_quirk_has_its_own_bit() {
+        for bits in 0..64 {
+            assert_eq!(quirk_bits(bits_quirks(bits)), bits);
+        }
+    }
+
+    #[test]
+    fn recording_over_old_frames_drops_the_rest() {
+        let mut replay = replay();
+        replay.record(1, &[0; 16]);
+        assert_eq!(replay.frames, [1, 0]);
+        assert_eq!(replay.keys(0).unwrap()[0], 1);
+        assert_eq!(replay.keys(2), None);
+    }
+
+    #[test]
+    fn broken_replays_are_refused() {
+        let data = replay().to_bytes();
+        assert!(Replay::from_bytes(&data[..data.len() - 1]).is_err());
+        assert!(Replay::from_bytes(&data[..HEADER_SIZE - 1]).is_err());
+        assert!(Replay::from_bytes(b"C8ST").is_err());
+
+        let mut newer = data.clone();
+        newer[5] = 3;
+        assert!(Replay::from_bytes(&newer).is_err());
+
+        let text = replay().to_text();
+        assert!(Replay::from_text(&text.replace("seed 42\n", "")).is_err());
+        assert!(Replay::from_text(&text.replace("quirks shift load-store jump clip\n", "")).is_err());
+        assert!(Replay::from_text(&text.replace("jump", "warp")).is_err());
+        assert!(Replay::from_text(&text.replace("start-address 600", "start-address 0x600")).is_err());
+        assert!(Replay::from_text(&format!("{}XYZ\n", text)).is_err());
+    }
+
+    #[test]
+    fn splicing_takes_each_part_from_its_replay() {
+        let first = replay();
+        let mut second = replay();
+        second.frames = vec![7, 8, 9];
+        assert_eq!(splice(&first, &second, 1).unwrap().frames, [first.frames[0], 8, 9]);
+        assert!(splice(&first, &second, 4).is_err());
+
+        second.machine.quirks.clip = !first.machine.quirks.clip;
+        assert!(splice(&first, &second, 1).is_err());
+    }
+
+    #[test]
+    fn version_1_replays_started_at_0x200() {
+        let data = include_bytes!("../roms/bounce.rpl");
+        assert_eq!(u16::from_be_bytes([data[4], data[5]]), 1);
+        let bundled = Replay::from_bytes(data).unwrap();
+        assert_eq!(bundled.machine.start_address, 0x200);
+        assert!(!bundled.frames.is_empty());
+    }
+}
@@ -0,0 +1,147 @@
+use std::fmt::Display;
+use std::process;
+use std::sync::Mutex;
+
+use serde_json::{json, Value};
+
+use crate::platform::DisplayError;
+
+// How a run tells the program that started it what happened. Errors go to
+//  standard error as text, or with --json-errors as a line of JSON on
+//  standard output:
+//
+//      {"error":"rom","message":"ROM is empty","rom_hash":null}
+//
+//  and --json-status adds a line when the emulator exits, however it ends:
+//
+//      {"exit_code":0,"frames":1800,"reason":"quit","rom_hash":"3a5b...","status":"exit"}
+//
+//  `reason` is `quit`, `frames` when --frames ran out, `cancelled` when no
+//  ROM was picked, or `error`; `frames` is null for frontends that don't
+//  count them.
+struct Report {
+    json_errors: bool,
+    json_status: bool,
+    // Set once the ROM is read, and again when another is opened
+    rom_hash: Option<String>,
+}
+
+static REPORT: Mutex<Report> = Mutex::new(Report {
+    json_errors: false,
+    json_status: false,
+    rom_hash: None,
+});
+
+// What went wrong, as `error` names it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    // Bad options or no ROM
+    Usage,
+    // The ROM couldn't be read, patched or loaded
+    Rom,
+    // A state, replay, bezel, sound or recording file
+    File,
+    // SDL, the window, the renderer or a frontend
+    Display,
+    Audio,
+}
+
+impl ErrorKind {
+    fn name(self) -> &'static str {
+        match self {
+            ErrorKind::Usage => "usage",
+            ErrorKind::Rom => "rom",
+            ErrorKind::File => "file",
+            ErrorKind::Display => "display",
+            ErrorKind::Audio => "audio",
+        }
+    }
+}
+
+fn report() -> std::sync::MutexGuard<'static, Report> {
+    REPORT.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+pub fn configure(json_errors: bool, json_status: bool) {
+    let mut report = report();
+    report.json_errors = json_errors;
+    report.json_status = json_status;
+}
+
+pub fn set_rom_hash(hash: &str) {
+    report().rom_hash = Some(hash.to_string());
+}
+
+// Report an error and exit with status 1
+pub fn fail(kind: ErrorKind, message: impl Display) -> ! {
+    fail_with_hints(kind, message, &[])
+}
+
+// As fail, with lines of advice for people that JSON leaves out
+pub fn fail_with_hints(kind: ErrorKind, message: impl Display, hints: &[String]) -> ! {
+    let report = report();
+    if report.json_errors {
+        println!("{}", error_line(kind, &message.to_string(), report.rom_hash.as_deref()));
+    } else {
+        eprintln!("Error: {}", message);
+        for hint in hints {
+            eprintln!("{}", hint);
+        }
+    }
+    print_status(&report, "error", 1, None);
+    process::exit(1);
+}
+
+// As fail, with the causes of an error and any hints for a display error
+pub fn fail_error(kind: ErrorKind, error: anyhow::Error) -> ! {
+    let hints = error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<DisplayError>())
+        .map(DisplayError::hints)
+        .unwrap_or_default();
+    fail_with_hints(kind, format!("{:#}", error), &hints)
+}
+
+// Note a run that ended without an error
+pub fn finish(reason: &str, frames: Option<u64>) {
+    print_status(&report(), reason, 0, frames);
+}
+
+fn print_status(report: &Report, reason: &str, exit_code: i32, frames: Option<u64>) {
+    if !report.json_status {
+        return;
+    }
+    println!("{}", status_line(reason, exit_code, frames, report.rom_hash.as_deref()));
+}
+
+fn error_line(kind: ErrorKind, message: &str, rom_hash: Option<&str>) -> Value {
+    json!({"error": kind.name(), "message": message, "rom_hash": rom_hash})
+}
+
+fn status_line(reason: &str, exit_code: i32, frames: Option<u64>, rom_hash: Option<&str>) -> Value {
+    json!({
+        "status": "exit",
+        "reason": reason,
+        "exit_code": exit_code,
+        "frames": frames,
+        "rom_hash": rom_hash,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_look_as_documented() {
+        assert_eq!(
+            error_line(ErrorKind::Rom, "ROM is empty", None).to_string(),
+            r#"{"error":"rom","message":"ROM is empty","rom_hash":null}"#
+        );
+        assert_eq!(
+            status_line("quit", 0, Some(1800), Some("3a5b")).to_string(),
+            r#"{"exit_code":0,"frames":1800,"reason":"quit","rom_hash":"3a5b","status":"exit"}"#
+        );
+        assert_eq!(status_line("quit", 0, None, None)["frames"], Value::Null);
+    }
+}
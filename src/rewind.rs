@@ -0,0 +1,165 @@
+use std::collections::VecDeque;
+
+use crate::chip8::Chip8;
+use crate::state::Snapshot;
+
+pub const REWIND_SECONDS: usize = 10;
+
+// History of per-frame snapshots. Only the newest snapshot is kept whole;
+//  each older frame is stored as the XOR against the frame after it,
+//  run-length encoded. Consecutive frames differ in a handful of bytes, so
+//  ten seconds of history costs a few hundred kilobytes at most.
+pub struct Rewind {
+    deltas: VecDeque<Vec<u8>>,
+    newest: Option<Vec<u8>>,
+    capacity: usize,
+}
+
+impl Rewind {
+    pub fn new(capacity: usize) -> Rewind {
+        Rewind {
+            deltas: VecDeque::with_capacity(capacity),
+            newest: None,
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, chip8: &Chip8) {
+        let current = Snapshot::capture(chip8).to_bytes();
+
+        if let Some(previous) = self.newest.take() {
+            if self.deltas.len() == self.capacity {
+                self.deltas.pop_front();
+            }
+            self.deltas.push_back(encode_delta(&previous, &current));
+        }
+
+        self.newest = Some(current);
+    }
+
+    // Step back one frame, returning false once the history is used up
+    pub fn pop(&mut self, chip8: &mut Chip8) -> bool {
+        let (Some(delta), Some(newest)) = (self.deltas.pop_back(), self.newest.as_mut()) else {
+            return false;
+        };
+
+        apply_delta(newest, &delta);
+
+        match Snapshot::from_bytes(newest).and_then(|snapshot| snapshot.restore(chip8)) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("Discarding rewind history: {}", e);
+                self.clear();
+                false
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.deltas.clear();
+        self.newest = None;
+    }
+}
+
+// The delta is a list of (zero run, literal length, literal bytes) records
+//  over the XOR of the two snapshots, which always have the same length
+fn encode_delta(older: &[u8], newer: &[u8]) -> Vec<u8> {
+    let xor: Vec<u8> = older.iter().zip(newer).map(|(a, b)| a ^ b).collect();
+    let mut delta = Vec::new();
+
+    let mut i = 0;
+    while i < xor.len() {
+        let zeros = xor[i..].iter().take_while(|&&b| b == 0).count();
+        i += zeros;
+        let literal = xor[i..].iter().take_while(|&&b| b != 0).count();
+
+        delta.extend_from_slice(&(zeros as u16).to_be_bytes());
+        delta.extend_from_slice(&(literal as u16).to_be_bytes());
+        delta.extend_from_slice(&xor[i..i + literal]);
+        i += literal;
+    }
+
+    delta
+}
+
+fn apply_delta(snapshot: &mut [u8], delta: &[u8]) {
+    let mut position = 0;
+    let mut i = 0;
+    while i + 4 <= delta.len() {
+        let zeros = u16::from_be_bytes([delta[i], delta[i + 1]]) as usize;
+        let literal = u16::from_be_bytes([delta[i + 2], delta[i + 3]]) as usize;
+        i += 4;
+        position += zeros;
+
+        for (byte, change) in snapshot[position..position + literal].iter_mut().zip(&delta[i..i + literal]) {
+            *byte ^= change;
+        }
+        position += literal;
+        i += literal;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::state;
+
+    #[test]
+    fn deltas_restore_random_pairs() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        for _ in 0..200 {
+            let length = rng.gen_range(0..8000);
+            let older: Vec<u8> = (0..length).map(|_| rng.gen()).collect();
+            // Mostly the same, with changes scattered singly and in runs
+            let mut newer = older.clone();
+            for _ in 0..rng.gen_range(0..20) {
+                let start = rng.gen_range(0..length.max(1));
+                let end = (start + rng.gen_range(1..300)).min(length);
+                for byte in &mut newer[start..end] {
+                    *byte = rng.gen();
+                }
+            }
+
+            let mut restored = newer.clone();
+            apply_delta(&mut restored, &encode_delta(&older, &newer));
+            assert_eq!(restored, older);
+        }
+    }
+
+    #[test]
+    fn deltas_restore_edges() {
+        let older = [1, 2, 3, 0, 0, 4];
+        for newer in [[1, 2, 3, 0, 0, 4], [0, 2, 3, 0, 0, 4], [1, 2, 3, 0, 0, 5], [9, 9, 9, 9, 9, 9]] {
+            let mut restored = newer;
+            apply_delta(&mut restored, &encode_delta(&older, &newer));
+            assert_eq!(restored, older);
+        }
+    }
+
+    #[test]
+    fn pop_steps_back_one_frame_at_a_time() {
+        let mut chip8 = Chip8::new();
+        // Draws a random sprite at a random place, forever
+        chip8.load_rom(&[0xC0, 0x3F, 0xC1, 0x1F, 0xC2, 0xFF, 0xA3, 0x00, 0xD0, 0x15, 0x12, 0x00]).unwrap();
+
+        let mut rewind = Rewind::new(REWIND_SECONDS);
+        let mut states = Vec::new();
+        for _ in 0..=REWIND_SECONDS {
+            for _ in 0..10 {
+                chip8.cycle();
+            }
+            rewind.push(&chip8);
+            states.push(state::serialize(&chip8));
+        }
+
+        states.pop();
+        while let Some(expected) = states.pop() {
+            assert!(rewind.pop(&mut chip8));
+            assert_eq!(state::serialize(&chip8), expected);
+        }
+        assert!(!rewind.pop(&mut chip8));
+    }
+}
@@ -0,0 +1,63 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::storage::Storage;
+
+// Choices that stick to a ROM, such as the palette picked with F2 or a bezel
+//  set by hand, kept as JSON beside its states
+#[derive(Default, Serialize, Deserialize)]
+pub struct RomSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub palette: Option<String>,
+    // A bezel PNG, relative to this file's folder unless absolute
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bezel: Option<String>,
+}
+
+pub fn load(storage: &Storage) -> io::Result<RomSettings> {
+    let path = storage.settings_path();
+    if !path.exists() {
+        return Ok(RomSettings::default());
+    }
+    serde_json::from_str(&fs::read_to_string(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn save(settings: &RomSettings, storage: &Storage) -> io::Result<PathBuf> {
+    let path = storage.settings_path();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let text = serde_json::to_string_pretty(settings).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(&path, text + "\n")?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_are_kept_per_rom() {
+        let dir = std::env::temp_dir().join(format!("chip8-settings-{}", std::process::id()));
+        let rom = dir.join("pong.ch8");
+        let storage = Storage::portable(&rom, "abc123");
+        assert!(load(&storage).unwrap().palette.is_none());
+
+        let saved = RomSettings {
+            palette: Some(String::from("amber")),
+            bezel: Some(String::from("arcade.png")),
+        };
+        let path = save(&saved, &storage).unwrap();
+        let loaded = load(&storage).unwrap();
+        let other = load(&Storage::portable(&rom, "def456"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(path, storage.settings_path());
+        assert_eq!(loaded.palette.as_deref(), Some("amber"));
+        assert_eq!(loaded.bezel.as_deref(), Some("arcade.png"));
+        assert!(other.unwrap().palette.is_none());
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::BTreeSet;
+
+use crate::disasm::{self, Line};
+
+// Lines of unchanged context shown around each change
+const CONTEXT: usize = 2;
+
+enum Edit {
+    Same(usize, usize),
+    Removed(usize),
+    Added(usize),
+}
+
+// Longest-common-subsequence diff over the disassembled words, so inserted
+//  or removed code shows up as such instead of shifting every later address
+fn edits(a: &[Line], b: &[Line]) -> Vec<Edit> {
+    let (n, m) = (a.len(), b.len());
+    let mut lcs = vec![vec![0u16; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if a[i].bytes == b[j].bytes {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && a[i].bytes == b[j].bytes {
+            edits.push(Edit::Same(i, j));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            edits.push(Edit::Removed(i));
+            i += 1;
+        } else {
+            edits.push(Edit::Added(j));
+            j += 1;
+        }
+    }
+
+    edits
+}
+
+struct Side {
+    lines: Vec<Line>,
+    code: BTreeSet<u16>,
+}
+
+impl Side {
+    fn new(rom: &[u8], origin: u16) -> Side {
+        Side {
+            lines: disasm::disassemble(rom, origin),
+            code: disasm::trace(rom, origin).0,
+        }
+    }
+
+    fn is_code(&self, index: usize) -> bool {
+        self.code.contains(&self.lines[index].address)
+    }
+
+    // Reachable words are shown as instructions, everything else as data
+    fn render(&self, sign: char, index: usize) -> String {
+        let line = &self.lines[index];
+        let hex: String = line.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        let text = match line.instruction {
+            Some(instruction) if self.is_code(index) => instruction.to_string(),
+            _ => {
+                let bytes: Vec<String> = line.bytes.iter().map(|b| format!("0x{:02x}", b)).collect();
+                format!("db {}", bytes.join(", "))
+            }
+        };
+        format!("{} {:03x}  {:<4}  {}\n", sign, line.address, hex, text)
+    }
+}
+
+pub struct Summary {
+    pub text: String,
+    pub code_changes: usize,
+    pub data_changes: usize,
+}
+
+pub fn diff(a: &[u8], b: &[u8], origin: u16) -> Summary {
+    let a = Side::new(a, origin);
+    let b = Side::new(b, origin);
+    let edits = edits(&a.lines, &b.lines);
+
+    let changed: Vec<bool> = edits.iter().map(|edit| !matches!(edit, Edit::Same(..))).collect();
+
+    let mut text = String::new();
+    let mut code_changes = 0;
+    let mut data_changes = 0;
+
+    let mut index = 0;
+    while index < edits.len() {
+        if !changed[index] {
+            index += 1;
+            continue;
+        }
+
+        // Extend the hunk while changes are within two contexts of each other
+        let start = index.saturating_sub(CONTEXT);
+        let mut end = index;
+        while end < edits.len() {
+            if changed[end] {
+                end += 1;
+                continue;
+            }
+            let next_change = (end..edits.len()).find(|&k| changed[k]);
+            match next_change {
+                Some(k) if k - end <= CONTEXT * 2 => end = k,
+                _ => break,
+            }
+        }
+        let stop = (end + CONTEXT).min(edits.len());
+
+        let address_of = |edit: &Edit| match *edit {
+            Edit::Same(i, j) => (Some(a.lines[i].address), Some(b.lines[j].address)),
+            Edit::Removed(i) => (Some(a.lines[i].address), None),
+            Edit::Added(j) => (None, Some(b.lines[j].address)),
+        };
+        let first_a = edits[start..stop].iter().find_map(|e| address_of(e).0);
+        let first_b = edits[start..stop].iter().find_map(|e| address_of(e).1);
+        let format_address = |address: Option<u16>| match address {
+            Some(address) => format!("0x{:03x}", address),
+            None => String::from("-"),
+        };
+        text.push_str(&format!("@@ -{} +{} @@\n", format_address(first_a), format_address(first_b)));
+
+        for edit in &edits[start..stop] {
+            match *edit {
+                Edit::Same(i, _) => text.push_str(&a.render(' ', i)),
+                Edit::Removed(i) => {
+                    if a.is_code(i) {
+                        code_changes += 1;
+                    } else {
+                        data_changes += 1;
+                    }
+                    text.push_str(&a.render('-', i));
+                }
+                Edit::Added(j) => {
+                    if b.is_code(j) {
+                        code_changes += 1;
+                    } else {
+                        data_changes += 1;
+                    }
+                    text.push_str(&b.render('+', j));
+                }
+            }
+        }
+
+        index = stop;
+    }
+
+    Summary {
+        text,
+        code_changes,
+        data_changes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_roms_have_no_hunks() {
+        let rom = include_bytes!("../roms/ibm-logo.ch8");
+        let summary = diff(rom, rom, 0x200);
+        assert!(summary.text.is_empty());
+        assert_eq!((summary.code_changes, summary.data_changes), (0, 0));
+    }
+
+    #[test]
+    fn inserted_code_does_not_shift_the_rest() {
+        // cls; ld v0, 1; jp 0x204  vs  cls; ld v1, 2; ld v0, 1; jp 0x204
+        let old = [0x00, 0xE0, 0x60, 0x01, 0x12, 0x04];
+        let new = [0x00, 0xE0, 0x61, 0x02, 0x60, 0x01, 0x12, 0x04];
+        let summary = diff(&old, &new, 0x200);
+        assert_eq!((summary.code_changes, summary.data_changes), (1, 0));
+        assert!(summary.text.starts_with("@@ -0x200 +0x200 @@\n"));
+        assert!(summary.text.contains("+ 202  6102  "));
+    }
+
+    #[test]
+    fn unreachable_words_count_as_data() {
+        // jp 0x200 followed by a sprite byte pair that changes
+        let summary = diff(&[0x12, 0x00, 0xF0, 0x90], &[0x12, 0x00, 0xF0, 0x80], 0x200);
+        assert_eq!((summary.code_changes, summary.data_changes), (0, 2));
+        assert!(summary.text.contains("- 202  F090  db 0xf0, 0x90\n"));
+    }
+}
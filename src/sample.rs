@@ -0,0 +1,120 @@
+use std::fs;
+use std::path::Path;
+
+// A recorded sound for the buzzer to loop instead of its tone, mixed down
+//  to mono
+#[derive(Debug, PartialEq)]
+pub struct Sample {
+    pub rate: u32,
+    // From -1 to 1
+    pub samples: Vec<f32>,
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+impl Sample {
+    // Reads 8-, 16-, 24- or 32-bit PCM or 32-bit float WAV files
+    pub fn load(path: &Path) -> Result<Sample, String> {
+        let bytes = fs::read(path).map_err(|e| e.to_string())?;
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            return Err(String::from("not a WAV file"));
+        }
+
+        // (format, channels, rate, bits)
+        let mut format = None;
+        let mut data = None;
+        let mut at = 12;
+        while at + 8 <= bytes.len() {
+            let size = u32_at(&bytes, at + 4) as usize;
+            let body = &bytes[at + 8..(at + 8 + size).min(bytes.len())];
+            match &bytes[at..at + 4] {
+                b"fmt " if body.len() >= 16 => {
+                    format = Some((u16_at(body, 0), u16_at(body, 2), u32_at(body, 4), u16_at(body, 14)));
+                }
+                b"data" => data = Some(body),
+                _ => {}
+            }
+            // Chunks are padded to an even length
+            at += 8 + size + size % 2;
+        }
+
+        let (format, channels, rate, bits) = format.ok_or("no format chunk")?;
+        let data = data.ok_or("no data chunk")?;
+        if channels == 0 || rate == 0 {
+            return Err(String::from("no channels"));
+        }
+        let decode: fn(&[u8]) -> f32 = match (format, bits) {
+            (1, 8) => |b| (b[0] as f32 - 128.0) / 128.0,
+            (1, 16) => |b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+            (1, 24) => |b| i32::from_le_bytes([0, b[0], b[1], b[2]]) as f32 / 2147483648.0,
+            (1, 32) => |b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2147483648.0,
+            (3, 32) => |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            _ => return Err(format!("unsupported format {} with {} bits", format, bits)),
+        };
+
+        let width = bits as usize / 8;
+        let samples: Vec<f32> = data
+            .chunks_exact(width * channels as usize)
+            .map(|frame| frame.chunks_exact(width).map(decode).sum::<f32>() / channels as f32)
+            .collect();
+        if samples.is_empty() {
+            return Err(String::from("no sound in it"));
+        }
+
+        Ok(Sample { rate, samples })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A WAV file with the given format chunk fields and data
+    fn wav(format: u16, channels: u16, bits: u16, data: &[u8]) -> Vec<u8> {
+        let mut bytes = b"RIFF\0\0\0\0WAVE".to_vec();
+        bytes.extend(b"fmt ");
+        bytes.extend(16u32.to_le_bytes());
+        bytes.extend(format.to_le_bytes());
+        bytes.extend(channels.to_le_bytes());
+        bytes.extend(8000u32.to_le_bytes());
+        bytes.extend([0; 6]);
+        bytes.extend(bits.to_le_bytes());
+        // Unknown chunks of odd length are skipped with their padding
+        bytes.extend(b"LIST\x03\0\0\0abc\0");
+        bytes.extend(b"data");
+        bytes.extend((data.len() as u32).to_le_bytes());
+        bytes.extend(data);
+        bytes
+    }
+
+    fn load(bytes: &[u8]) -> Result<Sample, String> {
+        let path = std::env::temp_dir().join(format!("chip8-sample-{}.wav", std::process::id()));
+        fs::write(&path, bytes).unwrap();
+        let sample = Sample::load(&path);
+        let _ = fs::remove_file(&path);
+        sample
+    }
+
+    #[test]
+    fn stereo_is_mixed_down_to_mono() {
+        let data: Vec<u8> = [0x4000i16, 0, -0x8000, -0x8000].iter().flat_map(|s| s.to_le_bytes()).collect();
+        let sample = load(&wav(1, 2, 16, &data)).unwrap();
+        assert_eq!(sample, Sample { rate: 8000, samples: vec![0.25, -1.0] });
+
+        let sample = load(&wav(1, 1, 8, &[128, 255, 0])).unwrap();
+        assert_eq!(sample.samples, [0.0, 127.0 / 128.0, -1.0]);
+    }
+
+    #[test]
+    fn other_files_are_refused() {
+        assert_eq!(load(b"not a wav file at all"), Err(String::from("not a WAV file")));
+        assert_eq!(load(&wav(1, 1, 16, &[])), Err(String::from("no sound in it")));
+        assert!(load(&wav(2, 1, 4, &[0; 8])).unwrap_err().starts_with("unsupported format"));
+    }
+}
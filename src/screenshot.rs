@@ -0,0 +1,145 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+// `<dir>/<rom>-<local time>.<extension>`, numbered if several are taken
+//  within a second
+pub fn capture_path(dir: &Path, rom_name: &str, extension: &str) -> PathBuf {
+    let stem = Path::new(rom_name)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let stem: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    let stem = match stem.trim_matches('_') {
+        "" => "chip8",
+        stem => stem,
+    };
+
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+
+    let mut path = dir.join(format!("{}-{}.{}", stem, timestamp, extension));
+    let mut counter = 2;
+    while path.exists() {
+        path = dir.join(format!("{}-{}-{}.{}", stem, timestamp, counter, extension));
+        counter += 1;
+    }
+    path
+}
+
+// The display as an RGB image, each pixel scaled up to a square block in the
+//  color for its planes
+pub fn render_rgb(video: &[u8], width: usize, colors: [[u8; 3]; 4], scale: u32) -> Vec<u8> {
+    let scale = scale as usize;
+    let mut rgb = Vec::with_capacity(video.len() * scale * scale * 3);
+
+    for row in video.chunks_exact(width) {
+        let mut line = Vec::with_capacity(width * scale * 3);
+        for &pixel in row {
+            let color = colors[pixel as usize % colors.len()];
+            for _ in 0..scale {
+                line.extend_from_slice(&color);
+            }
+        }
+        for _ in 0..scale {
+            rgb.extend_from_slice(&line);
+        }
+    }
+
+    rgb
+}
+
+pub fn save_png(path: &Path, width: u32, height: u32, rgb: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(rgb)?;
+    writer.finish()?;
+    Ok(())
+}
+
+// Binary PBM (P4): one bit per pixel, lit pixels black. Handy for golden
+//  images since the file is exactly the display and nothing else.
+pub fn save_pbm(path: &Path, video: &[u8], width: usize, scale: u32) -> io::Result<()> {
+    let scale = scale as usize;
+    let height = video.len() / width;
+    let mut data = format!("P4\n{} {}\n", width * scale, height * scale).into_bytes();
+
+    for row in video.chunks_exact(width) {
+        let bits: Vec<bool> = row.iter().flat_map(|&pixel| std::iter::repeat_n(pixel != 0, scale)).collect();
+        let packed: Vec<u8> = bits
+            .chunks(8)
+            .map(|byte| byte.iter().enumerate().fold(0u8, |acc, (i, &on)| acc | (on as u8) << (7 - i)))
+            .collect();
+        for _ in 0..scale {
+            data.extend_from_slice(&packed);
+        }
+    }
+
+    fs::write(path, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screenshots_are_named_after_the_rom() {
+        let dir = Path::new("captures");
+        let name = capture_path(dir, "games/Space Invaders [David Winter].ch8", "png");
+        assert_eq!(name.parent(), Some(dir));
+        let name = name.file_name().unwrap().to_string_lossy();
+        assert!(name.starts_with("Space_Invaders__David_Winter-"), "{}", name);
+        assert!(name.ends_with(".png"));
+
+        let name = capture_path(dir, "<stdin>", "png");
+        assert!(name.file_name().unwrap().to_string_lossy().starts_with("stdin-"));
+        let name = capture_path(dir, "???.ch8", "gif");
+        let name = name.file_name().unwrap().to_string_lossy();
+        assert!(name.starts_with("chip8-") && name.ends_with(".gif"), "{}", name);
+    }
+
+    #[test]
+    fn pngs_hold_the_image() {
+        let path = std::env::temp_dir().join(format!("chip8-screenshot-{}.png", std::process::id()));
+        let rgb: Vec<u8> = (0..2 * 3 * 3).map(|i| i as u8 * 10).collect();
+        save_png(&path, 2, 3, &rgb).unwrap();
+
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((info.width, info.height), (2, 3));
+        assert_eq!(pixels, rgb);
+    }
+
+    #[test]
+    fn pixels_scale_up_to_blocks() {
+        let colors = [[1, 1, 1], [9, 9, 9], [5, 5, 5], [7, 7, 7]];
+        let rgb = render_rgb(&[1, 0, 3], 3, colors, 2);
+        let row = [9, 9, 9, 9, 9, 9, 1, 1, 1, 1, 1, 1, 7, 7, 7, 7, 7, 7];
+        assert_eq!(rgb, [row, row].concat());
+    }
+
+    #[test]
+    fn pbms_pack_eight_pixels_a_byte() {
+        let path = std::env::temp_dir().join(format!("chip8-screenshot-{}.pbm", std::process::id()));
+        let mut video = vec![0; 10];
+        video[0] = 1;
+        video[9] = 1;
+        save_pbm(&path, &video, 10, 1).unwrap();
+        let data = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(data, b"P4\n10 1\n\x80\x40");
+    }
+}
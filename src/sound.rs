@@ -0,0 +1,213 @@
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use sdl2::Sdl;
+
+use crate::buzzer::{Envelope, Oscillator, Pattern, Tone, SAMPLE_RATE};
+
+// A quarter of full scale, like the recorded buzzer
+pub const LEVEL: f32 = 0.25;
+const AMPLITUDE: f32 = i16::MAX as f32 * LEVEL;
+const DEFAULT_BUFFER: u16 = 512;
+
+// Generates the buzzer's tone on an audio thread, whichever library owns it
+pub struct Output {
+    pub oscillator: Oscillator,
+    // From 0 to 1
+    pub volume: f32,
+    envelope: Envelope,
+    pub on: bool,
+}
+
+impl Output {
+    pub fn new(tone: Tone, volume: u8, sample_rate: u32) -> Output {
+        Output {
+            oscillator: Oscillator::new(tone, sample_rate),
+            volume: volume as f32 / 100.0,
+            envelope: Envelope::new(sample_rate),
+            on: false,
+        }
+    }
+
+    // The next sample, from -1 to 1
+    pub fn next(&mut self) -> f32 {
+        let gain = self.envelope.next(self.on);
+        if gain == 0.0 {
+            self.oscillator.restart();
+            return 0.0;
+        }
+        self.oscillator.next() * gain * self.volume
+    }
+}
+
+impl AudioCallback for Output {
+    type Channel = i16;
+
+    fn callback(&mut self, out: &mut [i16]) {
+        for sample in out.iter_mut() {
+            *sample = (self.next() * AMPLITUDE) as i16;
+        }
+    }
+}
+
+// The buzzer without a device: it only counts, so headless runs and tests
+//  can check that a program beeps when and for as long as it should
+#[derive(Default)]
+pub struct NullSink {
+    frames: u64,
+    beeps: u64,
+    on: bool,
+}
+
+impl NullSink {
+    // Called once a frame, like `Sound::set_buzzing`
+    pub fn set_buzzing(&mut self, on: bool) {
+        if on {
+            self.frames += 1;
+            if !self.on {
+                self.beeps += 1;
+            }
+        }
+        self.on = on;
+    }
+
+    // Frames the buzzer sounded in
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    // Separate beeps, each a run of sounding frames
+    pub fn beeps(&self) -> u64 {
+        self.beeps
+    }
+}
+
+// What to ask the audio device for. A bigger buffer rides out a busy
+//  system without crackling, at the cost of beeps starting a little later.
+//  The device may not give exactly this.
+#[derive(Clone)]
+pub struct AudioConfig {
+    pub sample_rate: u32,
+    // In samples; a power of two
+    pub buffer: u16,
+    // The system's default output when None
+    pub device: Option<String>,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        AudioConfig {
+            sample_rate: SAMPLE_RATE,
+            buffer: DEFAULT_BUFFER,
+            device: None,
+        }
+    }
+}
+
+// The names of the sound outputs SDL can play through
+pub fn device_names(sdl_context: &Sdl) -> Result<Vec<String>, String> {
+    let audio = sdl_context.audio()?;
+    let count = audio.num_audio_playback_devices().ok_or("could not count audio devices")?;
+    (0..count).map(|index| audio.audio_playback_device_name(index)).collect()
+}
+
+// The buzzer through the speakers. Without a working audio device the
+//  emulator carries on silently.
+pub struct Sound {
+    device: Option<AudioDevice<Output>>,
+}
+
+impl Sound {
+    // `volume` runs from 0 to 100. `verbose` prints what the device gave.
+    pub fn open(sdl_context: &Sdl, tone: Tone, volume: u8, config: AudioConfig, verbose: bool) -> Sound {
+        let device = sdl_context.audio().and_then(|audio| {
+            let desired = AudioSpecDesired {
+                freq: Some(config.sample_rate as i32),
+                channels: Some(1),
+                samples: Some(config.buffer),
+            };
+            audio.open_playback(config.device.as_deref(), &desired, |spec| Output::new(tone, volume, spec.freq as u32))
+        });
+
+        match device {
+            Ok(device) => {
+                if verbose {
+                    let spec = device.spec();
+                    eprintln!(
+                        "Audio: {} Hz, {} channel(s), {} sample buffer ({:.1} ms)",
+                        spec.freq,
+                        spec.channels,
+                        spec.samples,
+                        spec.samples as f32 * 1000.0 / spec.freq as f32
+                    );
+                }
+                device.resume();
+                Sound { device: Some(device) }
+            }
+            Err(e) => {
+                eprintln!("No sound: {}", e);
+                Sound { device: None }
+            }
+        }
+    }
+
+    pub fn set_tone(&mut self, tone: Tone) {
+        if let Some(device) = &mut self.device {
+            device.lock().oscillator.tone = tone;
+        }
+    }
+
+    // The program's XO-CHIP sound, if it has one
+    pub fn set_pattern(&mut self, pattern: Option<Pattern>) {
+        if let Some(device) = &mut self.device {
+            device.lock().oscillator.pattern = pattern;
+        }
+    }
+
+    pub fn set_volume(&mut self, volume: u8) {
+        if let Some(device) = &mut self.device {
+            device.lock().volume = volume as f32 / 100.0;
+        }
+    }
+
+    pub fn set_buzzing(&mut self, on: bool) {
+        if let Some(device) = &mut self.device {
+            device.lock().on = on;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buzzer::Waveform;
+
+    #[test]
+    fn beeps_fade_in_and_out_at_the_volume_set() {
+        // Four samples a cycle, and a two sample attack
+        let tone = Tone { waveform: Waveform::Square, frequency: 250.0, sample: None };
+        let mut output = Output::new(tone, 100, 1000);
+        output.on = true;
+        let mut out = [0; 4];
+        output.callback(&mut out);
+        let full = AMPLITUDE as i16;
+        assert_eq!(out, [full / 2, full, -full, -full]);
+
+        output.volume = 0.5;
+        output.callback(&mut out);
+        assert_eq!(out, [full / 2, full / 2, -full / 2, -full / 2]);
+
+        output.on = false;
+        let mut out = [0; 12];
+        output.callback(&mut out);
+        assert!(out[..8].windows(2).all(|pair| pair[1].abs() < pair[0].abs()), "{:?}", out);
+        assert_eq!(out[8..], [0; 4]);
+    }
+
+    #[test]
+    fn the_null_sink_counts_frames_and_beeps() {
+        let mut sink = NullSink::default();
+        for on in [false, true, true, false, true, false, false] {
+            sink.set_buzzing(on);
+        }
+        assert_eq!((sink.frames(), sink.beeps()), (3, 2));
+    }
+}
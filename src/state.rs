@@ -0,0 +1,649 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::chip8::{self, Chip8};
+use crate::storage::Storage;
+
+pub const SLOT_COUNT: u8 = 10;
+
+// Binary savestates start with a magic number and a format version, so other
+//  files and states from a different emulator version are refused cleanly.
+const MAGIC: &[u8; 4] = b"C8ST";
+pub const VERSION: u16 = 1;
+
+const VIDEO_SIZE: usize = chip8::VIDEO_WIDTH as usize * chip8::VIDEO_HEIGHT as usize;
+
+// Thumbnails are the display at half size, each pixel the coverage of a 2x2
+//  block from 0 (all off) to 255 (all on)
+pub const THUMBNAIL_WIDTH: usize = chip8::VIDEO_WIDTH as usize / 2;
+pub const THUMBNAIL_HEIGHT: usize = chip8::VIDEO_HEIGHT as usize / 2;
+const THUMBNAIL_SIZE: usize = THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT;
+
+// Whether there is an XO-CHIP audio pattern, the pattern, and the pitch
+const AUDIO_SIZE: usize = 1 + 16 + 1;
+
+const PAYLOAD_SIZE: usize =
+    16 + 4096 + 2 + 2 + 16 * 2 + 3 + VIDEO_SIZE + 2 + 8 + 16 + 1 + AUDIO_SIZE + THUMBNAIL_SIZE;
+
+pub fn make_thumbnail(video: &[u8]) -> Vec<u8> {
+    let width = chip8::VIDEO_WIDTH as usize;
+    let mut thumbnail = Vec::with_capacity(THUMBNAIL_SIZE);
+
+    for y in 0..THUMBNAIL_HEIGHT {
+        for x in 0..THUMBNAIL_WIDTH {
+            let lit = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                .iter()
+                .filter(|(dx, dy)| video[(y * 2 + dy) * width + x * 2 + dx] != 0)
+                .count();
+            thumbnail.push((lit * 255 / 4) as u8);
+        }
+    }
+
+    thumbnail
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// Everything the running program can observe: registers, memory, stack,
+//  timers, the display, the RNG position, and the XO-CHIP planes selected and
+//  audio pattern. Keypad state and quirk settings belong to the host and are
+//  left alone on restore.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub registers: [u8; 16],
+    #[serde(with = "hex_bytes")]
+    pub memory: Vec<u8>,
+    pub index: u16,
+    pub pc: u16,
+    pub stack: [u16; 16],
+    pub sp: u8,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    #[serde(with = "video_rows")]
+    pub video: Vec<u8>,
+    pub opcode: u16,
+    pub rng_seed: u64,
+    #[serde(with = "u128_string")]
+    pub rng_word_pos: u128,
+    pub planes: u8,
+    pub audio_pattern: Option<[u8; 16]>,
+    pub pitch: u8,
+    // Only for telling states apart; never restored
+    #[serde(with = "hex_bytes")]
+    pub thumbnail: Vec<u8>,
+}
+
+impl Snapshot {
+    pub fn capture(chip8: &Chip8) -> Snapshot {
+        Snapshot {
+            registers: chip8.registers,
+            memory: chip8.memory.to_vec(),
+            index: chip8.index,
+            pc: chip8.pc,
+            stack: chip8.stack,
+            sp: chip8.sp,
+            delay_timer: chip8.delay_timer,
+            sound_timer: chip8.sound_timer,
+            video: chip8.video.to_vec(),
+            opcode: chip8.opcode,
+            rng_seed: chip8.rng_seed,
+            rng_word_pos: chip8.rng_core.get_word_pos(),
+            planes: chip8.planes,
+            audio_pattern: chip8.audio_pattern,
+            pitch: chip8.pitch,
+            thumbnail: make_thumbnail(&chip8.video),
+        }
+    }
+
+    // States the emulator could never have saved would crash it on the
+    //  next instruction, so they are refused
+    fn check(&self) -> io::Result<()> {
+        if self.memory.len() != 4096 || self.video.len() != VIDEO_SIZE || self.thumbnail.len() != THUMBNAIL_SIZE {
+            return Err(invalid(String::from("savestate memory, display or thumbnail has the wrong size")));
+        }
+        let all_planes = (1 << chip8::PLANE_COUNT) - 1;
+        if self.video.iter().any(|&pixel| pixel & !all_planes != 0) || self.planes & !all_planes != 0 {
+            return Err(invalid(String::from("savestate uses planes XO-CHIP doesn't have")));
+        }
+        if self.sp as usize > self.stack.len() {
+            return Err(invalid(format!("savestate stack pointer {} is past the stack", self.sp)));
+        }
+        if self.pc > 0xFFE {
+            return Err(invalid(format!("savestate program counter 0x{:x} is past memory", self.pc)));
+        }
+        if self.index > 0xFFF {
+            return Err(invalid(format!("savestate index register 0x{:x} is past memory", self.index)));
+        }
+        Ok(())
+    }
+
+    pub fn restore(&self, chip8: &mut Chip8) -> io::Result<()> {
+        self.check()?;
+
+        chip8.registers = self.registers;
+        chip8.memory.copy_from_slice(&self.memory);
+        chip8.index = self.index;
+        chip8.pc = self.pc;
+        chip8.stack = self.stack;
+        chip8.sp = self.sp;
+        chip8.delay_timer = self.delay_timer;
+        chip8.sound_timer = self.sound_timer;
+        chip8.video.copy_from_slice(&self.video);
+        chip8.opcode = self.opcode;
+        chip8.rng_seed = self.rng_seed;
+        chip8.rng_core = ChaCha8Rng::seed_from_u64(self.rng_seed);
+        chip8.rng_core.set_word_pos(self.rng_word_pos);
+        chip8.planes = self.planes;
+        chip8.audio_pattern = self.audio_pattern;
+        chip8.pitch = self.pitch;
+        chip8.key_wait = None;
+
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(MAGIC.len() + 2 + PAYLOAD_SIZE);
+
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&VERSION.to_be_bytes());
+
+        data.extend_from_slice(&self.registers);
+        data.extend_from_slice(&self.memory);
+        data.extend_from_slice(&self.index.to_be_bytes());
+        data.extend_from_slice(&self.pc.to_be_bytes());
+        for entry in &self.stack {
+            data.extend_from_slice(&entry.to_be_bytes());
+        }
+        data.push(self.sp);
+        data.push(self.delay_timer);
+        data.push(self.sound_timer);
+        data.extend_from_slice(&self.video);
+        data.extend_from_slice(&self.opcode.to_be_bytes());
+        data.extend_from_slice(&self.rng_seed.to_be_bytes());
+        data.extend_from_slice(&self.rng_word_pos.to_be_bytes());
+        data.push(self.planes);
+        data.push(self.audio_pattern.is_some() as u8);
+        data.extend_from_slice(&self.audio_pattern.unwrap_or_default());
+        data.push(self.pitch);
+        data.extend_from_slice(&self.thumbnail);
+
+        data
+    }
+
+    pub fn from_bytes(data: &[u8]) -> io::Result<Snapshot> {
+        let rest = data
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| invalid(String::from("not a savestate")))?;
+        let version = u16::from_be_bytes(
+            rest.get(..2)
+                .ok_or_else(|| invalid(String::from("savestate header is truncated")))?
+                .try_into()
+                .unwrap(),
+        );
+        check_version(version)?;
+        let payload = &rest[2..];
+
+        if payload.len() != PAYLOAD_SIZE {
+            return Err(invalid(format!(
+                "savestate payload is {} bytes, expected {}",
+                payload.len(),
+                PAYLOAD_SIZE
+            )));
+        }
+
+        let mut rest = payload;
+        let mut take = |count: usize| {
+            let (head, tail) = rest.split_at(count);
+            rest = tail;
+            head
+        };
+
+        let registers = take(16).try_into().unwrap();
+        let memory = take(4096).to_vec();
+        let index = u16::from_be_bytes(take(2).try_into().unwrap());
+        let pc = u16::from_be_bytes(take(2).try_into().unwrap());
+        let mut stack = [0; 16];
+        for entry in stack.iter_mut() {
+            *entry = u16::from_be_bytes(take(2).try_into().unwrap());
+        }
+        let sp = take(1)[0];
+        let delay_timer = take(1)[0];
+        let sound_timer = take(1)[0];
+        let video = take(VIDEO_SIZE).to_vec();
+        let opcode = u16::from_be_bytes(take(2).try_into().unwrap());
+        let rng_seed = u64::from_be_bytes(take(8).try_into().unwrap());
+        let rng_word_pos = u128::from_be_bytes(take(16).try_into().unwrap());
+        let planes = take(1)[0];
+        let audio = take(AUDIO_SIZE);
+        let audio_pattern = (audio[0] != 0).then(|| audio[1..17].try_into().unwrap());
+        let pitch = audio[17];
+        let thumbnail = take(THUMBNAIL_SIZE).to_vec();
+
+        let snapshot = Snapshot {
+            registers,
+            memory,
+            index,
+            pc,
+            stack,
+            sp,
+            delay_timer,
+            sound_timer,
+            video,
+            opcode,
+            rng_seed,
+            rng_word_pos,
+            planes,
+            audio_pattern,
+            pitch,
+            thumbnail,
+        };
+        snapshot.check()?;
+        Ok(snapshot)
+    }
+
+    pub fn to_json(&self) -> String {
+        let document = JsonState {
+            format: String::from(JSON_FORMAT),
+            version: VERSION,
+            state: self.clone(),
+        };
+        serde_json::to_string_pretty(&document).expect("savestates always serialize")
+    }
+
+    pub fn from_json(text: &str) -> io::Result<Snapshot> {
+        let document: JsonState =
+            serde_json::from_str(text).map_err(|e| invalid(format!("bad JSON savestate: {}", e)))?;
+        if document.format != JSON_FORMAT {
+            return Err(invalid(format!("'{}' is not a JSON savestate", document.format)));
+        }
+        check_version(document.version)?;
+        document.state.check()?;
+        Ok(document.state)
+    }
+}
+
+fn check_version(version: u16) -> io::Result<()> {
+    if version != VERSION {
+        return Err(invalid(format!(
+            "savestate format version {} is not supported (this emulator reads version {})",
+            version, VERSION
+        )));
+    }
+    Ok(())
+}
+
+// What JSON savestates name themselves in their `format` field
+const JSON_FORMAT: &str = "chip8-state";
+
+#[derive(Serialize, Deserialize)]
+struct JsonState {
+    format: String,
+    version: u16,
+    #[serde(flatten)]
+    state: Snapshot,
+}
+
+// Memory as one hex string, which is compact but still greppable
+mod hex_bytes {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let text: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        serializer.serialize_str(&text)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        (0..text.len())
+            .step_by(2)
+            .map(|i| {
+                text.get(i..i + 2)
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                    .ok_or_else(|| de::Error::custom("invalid hex in memory"))
+            })
+            .collect()
+    }
+}
+
+// The display as rows of '#' and '.', readable at a glance. XO-CHIP pixels
+//  lit in plane 2 alone are '2', and in both planes '3'.
+mod video_rows {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use crate::chip8::VIDEO_WIDTH;
+
+    const PIXELS: [char; 4] = ['.', '#', '2', '3'];
+
+    pub fn serialize<S: Serializer>(video: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let rows: Vec<String> = video
+            .chunks(VIDEO_WIDTH as usize)
+            .map(|row| row.iter().map(|&p| PIXELS.get(p as usize).copied().unwrap_or('?')).collect())
+            .collect();
+        serializer.collect_seq(rows)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let rows = Vec::<String>::deserialize(deserializer)?;
+        rows.iter()
+            .flat_map(|row| row.chars())
+            .map(|c| match PIXELS.iter().position(|&pixel| pixel == c) {
+                Some(planes) => Ok(planes as u8),
+                None => Err(de::Error::custom(format!("'{}' is not a pixel", c))),
+            })
+            .collect()
+    }
+}
+
+// JSON numbers can't portably hold 128 bits, so the RNG position is a string
+mod u128_string {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+    }
+}
+
+pub fn serialize(chip8: &Chip8) -> Vec<u8> {
+    Snapshot::capture(chip8).to_bytes()
+}
+
+pub fn deserialize(chip8: &mut Chip8, data: &[u8]) -> io::Result<()> {
+    Snapshot::from_bytes(data)?.restore(chip8)
+}
+
+// ROMs are identified by contents; see storage.rs
+pub fn rom_hash(rom: &[u8]) -> String {
+    sha1_smol::Sha1::from(rom).digest().to_string()
+}
+
+// Periodic autosaves rotate through a few files so that a crash while
+//  writing one still leaves the others intact
+pub const AUTOSAVE_COUNT: u8 = 3;
+
+fn save_file(chip8: &Chip8, path: &Path) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serialize(chip8))
+}
+
+// Overwrites whichever autosave is missing or oldest
+pub fn save_autosave(chip8: &Chip8, storage: &Storage) -> io::Result<PathBuf> {
+    let mut oldest = None;
+    for index in 0..AUTOSAVE_COUNT {
+        let path = storage.autosave_path(index);
+        let Ok(modified) = fs::metadata(&path).and_then(|metadata| metadata.modified()) else {
+            oldest = Some((path, None));
+            break;
+        };
+        if oldest.as_ref().is_none_or(|(_, time)| Some(modified) < *time) {
+            oldest = Some((path, Some(modified)));
+        }
+    }
+
+    let (path, _) = oldest.expect("at least one autosave");
+    save_file(chip8, &path)?;
+    Ok(path)
+}
+
+pub fn save_slot(chip8: &Chip8, storage: &Storage, slot: u8) -> io::Result<PathBuf> {
+    let path = storage.slot_path(slot);
+    save_file(chip8, &path)?;
+    Ok(path)
+}
+
+pub fn load_slot(chip8: &mut Chip8, storage: &Storage, slot: u8) -> io::Result<PathBuf> {
+    let path = storage.slot_path(slot);
+    load_file(chip8, &path)?;
+    Ok(path)
+}
+
+pub fn save_auto(chip8: &Chip8, storage: &Storage) -> io::Result<PathBuf> {
+    let path = storage.auto_path();
+    save_file(chip8, &path)?;
+    Ok(path)
+}
+
+// Returns None when there is nothing to resume yet
+pub fn load_auto(chip8: &mut Chip8, storage: &Storage) -> io::Result<Option<PathBuf>> {
+    let path = storage.auto_path();
+    if !path.exists() {
+        return Ok(None);
+    }
+    load_file(chip8, &path)?;
+    Ok(Some(path))
+}
+
+// A fingerprint of everything the program can observe, independent of the
+//  savestate file layout, for comparing the end results of runs. The
+//  thumbnail is left out, being made from the display.
+pub fn state_hash(chip8: &Chip8) -> String {
+    let data = Snapshot::capture(chip8).to_bytes();
+    let payload = &data[MAGIC.len() + 2..];
+    let thumbnail = PAYLOAD_SIZE - THUMBNAIL_SIZE;
+    let mut hasher = sha1_smol::Sha1::from(&payload[..thumbnail]);
+    hasher.update(&payload[PAYLOAD_SIZE..]);
+    hasher.digest().to_string()
+}
+
+// The thumbnail of a slot, or None if the slot is empty or unreadable
+pub fn slot_thumbnail(storage: &Storage, slot: u8) -> Option<Vec<u8>> {
+    let data = fs::read(storage.slot_path(slot)).ok()?;
+    Snapshot::from_bytes(&data).ok().map(|snapshot| snapshot.thumbnail)
+}
+
+pub fn load_file(chip8: &mut Chip8, path: &Path) -> io::Result<()> {
+    let data = fs::read(path)?;
+    deserialize(chip8, &data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A machine part-way through a program that draws and uses the RNG
+    fn running() -> Chip8 {
+        let mut chip8 = Chip8::new();
+        // ld i, 0x050; drw v0, v0, 5; rnd v1, 0xff; ld dt, v1; jp 0x204
+        chip8.load_rom(&[0xA0, 0x50, 0xD0, 0x05, 0xC1, 0xFF, 0xF1, 0x15, 0x12, 0x04]).unwrap();
+        for _ in 0..20 {
+            chip8.cycle();
+        }
+        chip8
+    }
+
+    #[test]
+    fn states_restore_everything_they_save() {
+        let original = running();
+        let data = serialize(&original);
+        assert_eq!(data.len(), MAGIC.len() + 2 + PAYLOAD_SIZE);
+
+        let mut restored = Chip8::new();
+        deserialize(&mut restored, &data).unwrap();
+        assert_eq!(serialize(&restored), data);
+        assert_eq!(restored.video, original.video);
+        assert_eq!(restored.pc, original.pc);
+    }
+
+    #[test]
+    fn random_numbers_continue_where_they_left_off() {
+        let mut original = running();
+        let mut restored = Chip8::new();
+        deserialize(&mut restored, &serialize(&original)).unwrap();
+
+        for _ in 0..10 {
+            original.cycle();
+            restored.cycle();
+        }
+        assert_eq!(restored.registers, original.registers);
+    }
+
+    #[test]
+    fn truncated_states_are_refused() {
+        let data = serialize(&running());
+        let mut chip8 = Chip8::new();
+        let error = deserialize(&mut chip8, &data[..data.len() - 1]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(chip8.pc, 0x200);
+    }
+
+    #[test]
+    fn json_states_round_trip() {
+        let original = running();
+        let json = Snapshot::capture(&original).to_json();
+        let mut restored = Chip8::new();
+        Snapshot::from_json(&json).unwrap().restore(&mut restored).unwrap();
+        assert_eq!(serialize(&restored), serialize(&original));
+    }
+
+    #[test]
+    fn xo_chip_planes_are_kept() {
+        let mut original = running();
+        original.planes = 0b11;
+        original.video[..4].copy_from_slice(&[0b00, 0b01, 0b10, 0b11]);
+
+        let mut restored = Chip8::new();
+        deserialize(&mut restored, &serialize(&original)).unwrap();
+        assert_eq!(restored.planes, 0b11);
+        assert_eq!(restored.video, original.video);
+
+        let json = Snapshot::capture(&original).to_json();
+        assert!(json.contains("\".#23"));
+        let mut restored = Chip8::new();
+        Snapshot::from_json(&json).unwrap().restore(&mut restored).unwrap();
+        assert_eq!(restored.video, original.video);
+    }
+
+    #[test]
+    fn impossible_states_are_refused() {
+        let chip8 = running();
+        let broken: [fn(&mut Snapshot); 5] = [
+            |state| state.sp = 200,
+            |state| state.pc = 0xFFF,
+            |state| state.index = 0x1000,
+            |state| state.planes = 0b100,
+            |state| state.video[0] = 0xFF,
+        ];
+        for breaking in broken {
+            let mut state = Snapshot::capture(&chip8);
+            breaking(&mut state);
+            assert!(state.restore(&mut Chip8::new()).is_err());
+            assert!(Snapshot::from_bytes(&state.to_bytes()).is_err());
+            assert!(Snapshot::from_json(&state.to_json()).is_err());
+        }
+    }
+
+    #[test]
+    fn other_files_and_versions_are_refused() {
+        let state = Snapshot::capture(&running());
+        let json = state.to_json();
+        assert!(Snapshot::from_json(&json.replace(JSON_FORMAT, "chip8-replay")).is_err());
+        assert!(Snapshot::from_json(&json.replace("\"version\": 1", "\"version\": 2")).is_err());
+
+        let mut data = state.to_bytes();
+        assert!(Snapshot::from_bytes(&data[MAGIC.len() + 2..]).is_err());
+        data[MAGIC.len() + 1] = 2;
+        assert!(Snapshot::from_bytes(&data).is_err());
+        assert!(Snapshot::from_bytes(&MAGIC[..]).is_err());
+    }
+
+    #[test]
+    fn roms_are_told_apart_by_contents() {
+        let hash = rom_hash(b"rom");
+        assert_eq!(hash.len(), 40);
+        assert_ne!(hash, rom_hash(b"other rom"));
+    }
+
+    #[test]
+    fn state_files_load_from_any_path() {
+        let path = std::env::temp_dir().join(format!("chip8-state-{}.state", std::process::id()));
+        let original = running();
+        fs::write(&path, serialize(&original)).unwrap();
+
+        let mut chip8 = Chip8::new();
+        let result = load_file(&mut chip8, &path);
+        fs::remove_file(&path).unwrap();
+        result.unwrap();
+        assert_eq!(chip8.pc, original.pc);
+
+        assert_eq!(load_file(&mut chip8, &path).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    // Storage for a ROM that has never been run, somewhere nothing is saved
+    fn unused_storage() -> Storage {
+        let rom = std::env::temp_dir().join(format!("chip8-unused-{}", std::process::id())).join("rom.ch8");
+        Storage::portable(&rom, &rom_hash(b"never saved"))
+    }
+
+    #[test]
+    fn nothing_is_resumed_before_the_first_save() {
+        assert!(load_auto(&mut Chip8::new(), &unused_storage()).unwrap().is_none());
+    }
+
+    #[test]
+    fn thumbnails_shade_by_coverage() {
+        let width = chip8::VIDEO_WIDTH as usize;
+        let mut video = vec![0; VIDEO_SIZE];
+        // One pixel of the first block, all of the second
+        video[0] = 1;
+        video[2..4].fill(1);
+        video[width + 2..width + 4].fill(1);
+
+        let thumbnail = make_thumbnail(&video);
+        assert_eq!(thumbnail.len(), THUMBNAIL_SIZE);
+        assert_eq!(thumbnail[..3], [63, 255, 0]);
+        assert!(slot_thumbnail(&unused_storage(), 0).is_none());
+    }
+
+    #[test]
+    fn state_hashes_follow_what_the_program_sees() {
+        let data = serialize(&running());
+        let copy = || {
+            let mut chip8 = Chip8::new();
+            deserialize(&mut chip8, &data).unwrap();
+            chip8
+        };
+        let hash = state_hash(&copy());
+        assert_eq!(hash.len(), 40);
+        assert_eq!(state_hash(&copy()), hash);
+
+        let mut changed = copy();
+        changed.registers[0xE] ^= 1;
+        assert_ne!(state_hash(&changed), hash);
+        let mut changed = copy();
+        changed.video[0] ^= 1;
+        assert_ne!(state_hash(&changed), hash);
+    }
+
+    #[test]
+    fn xo_chip_sound_is_kept_and_hashed() {
+        let mut original = running();
+        original.audio_pattern = Some([0x55; 16]);
+        original.pitch = 100;
+        let mut restored = Chip8::new();
+        deserialize(&mut restored, &serialize(&original)).unwrap();
+        assert_eq!((restored.audio_pattern, restored.pitch), (Some([0x55; 16]), 100));
+        let mut restored = Chip8::new();
+        Snapshot::from_json(&Snapshot::capture(&original).to_json()).unwrap().restore(&mut restored).unwrap();
+        assert_eq!((restored.audio_pattern, restored.pitch), (Some([0x55; 16]), 100));
+
+        let chip8 = running();
+        let mut other = running();
+        other.audio_pattern = Some([0x55; 16]);
+        assert_ne!(state_hash(&chip8), state_hash(&other));
+        other.audio_pattern = chip8.audio_pattern;
+        other.pitch += 1;
+        assert_ne!(state_hash(&chip8), state_hash(&other));
+    }
+}
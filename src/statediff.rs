@@ -0,0 +1,174 @@
+use crate::chip8;
+use crate::state::Snapshot;
+
+// Changed bytes closer together than this are reported as one range
+const MERGE_GAP: usize = 4;
+// Longer ranges only show their first bytes
+const MAX_RANGE_BYTES: usize = 16;
+
+pub struct Summary {
+    pub text: String,
+    pub register_changes: usize,
+    pub memory_changes: usize,
+    pub pixel_changes: usize,
+}
+
+impl Summary {
+    pub fn is_empty(&self) -> bool {
+        self.register_changes + self.memory_changes + self.pixel_changes == 0
+    }
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    let shown: Vec<String> = bytes.iter().take(MAX_RANGE_BYTES).map(|b| format!("{:02x}", b)).collect();
+    let more = if bytes.len() > MAX_RANGE_BYTES { " …" } else { "" };
+    format!("{}{}", shown.join(" "), more)
+}
+
+fn registers(a: &Snapshot, b: &Snapshot) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut compare = |name: String, old: String, new: String| {
+        if old != new {
+            lines.push(format!("  {:<9} {} -> {}", name, old, new));
+        }
+    };
+
+    for (i, (old, new)) in a.registers.iter().zip(&b.registers).enumerate() {
+        compare(format!("V{:X}", i), format!("0x{:02x}", old), format!("0x{:02x}", new));
+    }
+    compare(String::from("I"), format!("0x{:03x}", a.index), format!("0x{:03x}", b.index));
+    compare(String::from("PC"), format!("0x{:03x}", a.pc), format!("0x{:03x}", b.pc));
+    compare(String::from("SP"), a.sp.to_string(), b.sp.to_string());
+    for (i, (old, new)) in a.stack.iter().zip(&b.stack).enumerate() {
+        compare(format!("stack[{}]", i), format!("0x{:03x}", old), format!("0x{:03x}", new));
+    }
+    compare(String::from("DT"), a.delay_timer.to_string(), b.delay_timer.to_string());
+    compare(String::from("ST"), a.sound_timer.to_string(), b.sound_timer.to_string());
+    compare(String::from("pitch"), a.pitch.to_string(), b.pitch.to_string());
+    compare(
+        String::from("pattern"),
+        a.audio_pattern.map_or(String::from("none"), |bits| hex_bytes(&bits)),
+        b.audio_pattern.map_or(String::from("none"), |bits| hex_bytes(&bits)),
+    );
+    compare(String::from("opcode"), format!("{:04X}", a.opcode), format!("{:04X}", b.opcode));
+    compare(String::from("RNG seed"), a.rng_seed.to_string(), b.rng_seed.to_string());
+    compare(String::from("RNG pos"), a.rng_word_pos.to_string(), b.rng_word_pos.to_string());
+
+    lines
+}
+
+// Runs of changed addresses, with short unchanged gaps folded in
+fn memory_ranges(a: &[u8], b: &[u8]) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for address in (0..a.len()).filter(|&address| a[address] != b[address]) {
+        match ranges.last_mut() {
+            Some((_, end)) if address - *end <= MERGE_GAP => *end = address + 1,
+            _ => ranges.push((address, address + 1)),
+        }
+    }
+    ranges
+}
+
+// Show the whole display, marking pixels that were turned on (+) or off (-)
+fn display(a: &[u8], b: &[u8]) -> String {
+    let width = chip8::VIDEO_WIDTH as usize;
+    let mut text = String::new();
+    for (old_row, new_row) in a.chunks(width).zip(b.chunks(width)) {
+        text.push_str("  ");
+        for (&old, &new) in old_row.iter().zip(new_row) {
+            text.push(match (old != 0, new != 0) {
+                (false, false) => '.',
+                (true, true) => '#',
+                (false, true) => '+',
+                (true, false) => '-',
+            });
+        }
+        text.push('\n');
+    }
+    text
+}
+
+pub fn diff(a: &Snapshot, b: &Snapshot) -> Summary {
+    let mut text = String::new();
+
+    let register_lines = registers(a, b);
+    if !register_lines.is_empty() {
+        text.push_str("Registers:\n");
+        for line in &register_lines {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+
+    let ranges = memory_ranges(&a.memory, &b.memory);
+    let memory_changes = a.memory.iter().zip(&b.memory).filter(|(old, new)| old != new).count();
+    if !ranges.is_empty() {
+        text.push_str(&format!("Memory ({} bytes in {} ranges):\n", memory_changes, ranges.len()));
+        for &(start, end) in &ranges {
+            text.push_str(&format!(
+                "  0x{:03x}-0x{:03x}  {} -> {}\n",
+                start,
+                end - 1,
+                hex_bytes(&a.memory[start..end]),
+                hex_bytes(&b.memory[start..end])
+            ));
+        }
+    }
+
+    let pixel_changes = a.video.iter().zip(&b.video).filter(|(old, new)| (**old != 0) != (**new != 0)).count();
+    if pixel_changes > 0 {
+        text.push_str(&format!("Display ({} pixels changed, + on, - off):\n", pixel_changes));
+        text.push_str(&display(&a.video, &b.video));
+    }
+
+    Summary {
+        text,
+        register_changes: register_lines.len(),
+        memory_changes,
+        pixel_changes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::Chip8;
+
+    #[test]
+    fn identical_states_have_no_changes() {
+        let chip8 = Chip8::new();
+        let summary = diff(&Snapshot::capture(&chip8), &Snapshot::capture(&chip8));
+        assert!(summary.is_empty());
+        assert!(summary.text.is_empty());
+    }
+
+    #[test]
+    fn changes_are_counted_and_shown() {
+        let old = Snapshot::capture(&Chip8::new());
+        let mut new = Snapshot::capture(&Chip8::new());
+        new.rng_seed = old.rng_seed;
+        new.registers[0xA] = 0x12;
+        new.memory[0x300] = 1;
+        new.memory[0x303] = 1;
+        new.memory[0x400] = 1;
+        new.video[0] = 1;
+
+        let summary = diff(&old, &new);
+        assert_eq!((summary.register_changes, summary.memory_changes, summary.pixel_changes), (1, 3, 1));
+        assert!(summary.text.contains("VA        0x00 -> 0x12"));
+        assert!(summary.text.contains("Memory (3 bytes in 2 ranges)"));
+        assert!(summary.text.contains("  0x300-0x303  00 00 00 00 -> 01 00 00 01"));
+        assert!(summary.text.contains("\n  +......"));
+    }
+
+    #[test]
+    fn xo_chip_sound_changes_are_shown() {
+        let old = Snapshot::capture(&Chip8::new());
+        let mut new = old.clone();
+        new.audio_pattern = Some([0xAB; 16]);
+        new.pitch = 70;
+        let summary = diff(&old, &new);
+        assert!(summary.text.contains("pitch     64 -> 70"));
+        assert!(summary.text.contains("pattern   none -> ab ab ab"));
+    }
+}
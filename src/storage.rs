@@ -0,0 +1,89 @@
+use std::path::{Path, PathBuf};
+
+// Everything the emulator keeps for a ROM lives in one directory named after
+//  the ROM's hash: ~/.local/share/chip8/<rom-hash>, or with --portable a
+//  chip8-data folder beside the ROM file. Keying by contents means renaming a
+//  ROM keeps its files and two ROMs with the same name don't share them.
+pub struct Storage {
+    dir: PathBuf,
+}
+
+impl Storage {
+    // Falls back to the working directory on systems without a data folder
+    pub fn user(rom_hash: &str) -> Storage {
+        let data_dir = dirs::data_dir().unwrap_or_else(|| PathBuf::from(".")).join("chip8");
+        Storage {
+            dir: data_dir.join(rom_hash),
+        }
+    }
+
+    pub fn portable(rom_path: &Path, rom_hash: &str) -> Storage {
+        let rom_dir = rom_path.parent().unwrap_or_else(|| Path::new(""));
+        Storage {
+            dir: rom_dir.join("chip8-data").join(rom_hash),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn slot_path(&self, slot: u8) -> PathBuf {
+        self.dir.join("states").join(format!("slot-{}.state", slot))
+    }
+
+    // The state written on quit with --resume, kept apart from the numbered slots
+    pub fn auto_path(&self) -> PathBuf {
+        self.dir.join("states").join("auto.state")
+    }
+
+    pub fn autosave_path(&self, index: u8) -> PathBuf {
+        self.dir.join("states").join(format!("autosave-{}.state", index))
+    }
+
+    pub fn macro_path(&self) -> PathBuf {
+        self.dir.join("macro.txt")
+    }
+
+    pub fn settings_path(&self) -> PathBuf {
+        self.dir.join("settings.json")
+    }
+
+    // Screenshots, GIFs and other captures made from the window
+    pub fn capture_dir(&self) -> PathBuf {
+        self.dir.join("captures")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{AUTOSAVE_COUNT, SLOT_COUNT};
+
+    #[test]
+    fn files_are_kept_together_under_the_rom_hash() {
+        let storage = Storage::user("abc123");
+        assert!(storage.dir().ends_with("chip8/abc123"));
+        assert!(storage.slot_path(3).ends_with("chip8/abc123/states/slot-3.state"));
+
+        let storage = Storage::portable(Path::new("games/pong.ch8"), "abc123");
+        assert_eq!(storage.dir(), Path::new("games/chip8-data/abc123"));
+        assert_eq!(storage.macro_path(), Path::new("games/chip8-data/abc123/macro.txt"));
+        assert_eq!(storage.capture_dir(), Path::new("games/chip8-data/abc123/captures"));
+        assert_eq!(storage.settings_path(), Path::new("games/chip8-data/abc123/settings.json"));
+        let storage = Storage::portable(Path::new("pong.ch8"), "abc123");
+        assert_eq!(storage.dir(), Path::new("chip8-data/abc123"));
+    }
+
+    #[test]
+    fn every_state_has_its_own_file() {
+        let storage = Storage::user("abc123");
+        let mut paths: Vec<PathBuf> = (0..SLOT_COUNT).map(|slot| storage.slot_path(slot)).collect();
+        paths.extend((0..AUTOSAVE_COUNT).map(|index| storage.autosave_path(index)));
+        paths.push(storage.auto_path());
+        let count = paths.len();
+        paths.sort();
+        paths.dedup();
+        assert_eq!(paths.len(), count);
+    }
+}
@@ -0,0 +1,244 @@
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen, SetTitle};
+use crossterm::{execute, queue};
+use sdl2::keyboard::{Keycode, Scancode};
+
+use crate::chip8::{Chip8, VIDEO_HEIGHT, VIDEO_WIDTH};
+use crate::keymap::Keymap;
+use crate::timing;
+
+// Most terminals only report key presses, so a key counts as held for this
+//  long after its last press or auto-repeat
+const KEY_HOLD_FRAMES: u32 = 8;
+
+pub struct Settings {
+    pub foreground: [u8; 3],
+    pub background: [u8; 3],
+    // 2x4 braille dots per character instead of 1x2 half blocks
+    pub braille: bool,
+    pub keymap: Keymap,
+}
+
+// Puts the terminal back however the frontend exits
+struct TerminalGuard {
+    enhanced_keys: bool,
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        if self.enhanced_keys {
+            let _ = execute!(stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(stdout, ResetColor, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// Runs the display in the terminal, for SSH sessions and machines without a
+//  GUI. Escape or Ctrl+C quit; the keypad uses the window's keymap.
+pub fn run(chip8: &mut Chip8, cycle_period: Duration, settings: &Settings, title: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    // Terminals that can report key releases (the kitty protocol) give real
+    //  held keys; elsewhere presses are held for KEY_HOLD_FRAMES
+    let enhanced_keys = terminal::supports_keyboard_enhancement().unwrap_or(false);
+    let _guard = TerminalGuard { enhanced_keys };
+    execute!(stdout, EnterAlternateScreen, Hide, SetTitle(title))?;
+    if enhanced_keys {
+        execute!(stdout, PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES))?;
+    }
+
+    let [r, g, b] = settings.foreground;
+    let foreground = Color::Rgb { r, g, b };
+    let [r, g, b] = settings.background;
+    let background = Color::Rgb { r, g, b };
+
+    let frame_period = timing::frame_period();
+    let mut next_frame_time = Instant::now();
+    let mut frame = 0u64;
+    // Frames left for each key to stay held
+    let mut held = [0u32; 16];
+    let mut shown: Option<Vec<u8>> = None;
+
+    loop {
+        while event::poll(Duration::ZERO)? {
+            match event::read()? {
+                Event::Key(KeyEvent { code, modifiers, kind, .. }) => {
+                    let quit = code == KeyCode::Esc
+                        || (code == KeyCode::Char('c') && modifiers.contains(KeyModifiers::CONTROL));
+                    if quit {
+                        return Ok(());
+                    }
+                    if let Some(index) = chip8_key(&settings.keymap, code) {
+                        held[index] = match kind {
+                            KeyEventKind::Release => 0,
+                            _ if enhanced_keys => u32::MAX,
+                            _ => KEY_HOLD_FRAMES,
+                        };
+                    }
+                }
+                // Redraw everything after a resize
+                Event::Resize(..) => shown = None,
+                _ => {}
+            }
+        }
+
+        let now = Instant::now();
+        if now < next_frame_time {
+            thread::sleep(next_frame_time - now);
+            continue;
+        }
+        // Don't try to catch up after a stall, just carry on from now
+        next_frame_time += frame_period;
+        if next_frame_time < now {
+            next_frame_time = now + frame_period;
+        }
+
+        for (pressed, frames) in chip8.keypad.iter_mut().zip(held.iter_mut()) {
+            *pressed = (*frames > 0) as u8;
+            *frames = frames.saturating_sub(1);
+        }
+        for _ in 0..timing::cycles_in_frame(frame, cycle_period) {
+            chip8.cycle();
+        }
+        chip8.tick_timers();
+        frame += 1;
+
+        // Terminals are slow to redraw, so only draw when something changed
+        if shown.as_deref() != Some(&chip8.video[..]) {
+            queue!(stdout, SetForegroundColor(foreground), SetBackgroundColor(background))?;
+            let rows = if settings.braille { braille_rows(&chip8.video) } else { half_block_rows(&chip8.video) };
+            for (y, row) in rows.iter().enumerate() {
+                queue!(stdout, MoveTo(0, y as u16), Print(row))?;
+            }
+            stdout.flush()?;
+            shown = Some(chip8.video.to_vec());
+        }
+    }
+}
+
+// Terminals report characters rather than key positions, so physical
+//  layouts take each character to be where it is on a US keyboard
+fn chip8_key(keymap: &Keymap, code: KeyCode) -> Option<usize> {
+    let name = match code {
+        KeyCode::Char(' ') => String::from("Space"),
+        KeyCode::Char(c) => c.to_uppercase().collect(),
+        KeyCode::Up => String::from("Up"),
+        KeyCode::Down => String::from("Down"),
+        KeyCode::Left => String::from("Left"),
+        KeyCode::Right => String::from("Right"),
+        KeyCode::Enter => String::from("Return"),
+        KeyCode::Tab => String::from("Tab"),
+        _ => return None,
+    };
+    keymap.chip8_key(Keycode::from_name(&name), Scancode::from_name(&name))
+}
+
+fn lit(video: &[u8], x: usize, y: usize) -> bool {
+    video[y * VIDEO_WIDTH as usize + x] != 0
+}
+
+// Each character shows two pixels, one above the other
+fn half_block_rows(video: &[u8]) -> Vec<String> {
+    (0..VIDEO_HEIGHT as usize)
+        .step_by(2)
+        .map(|y| {
+            (0..VIDEO_WIDTH as usize)
+                .map(|x| match (lit(video, x, y), lit(video, x, y + 1)) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Each character shows a 2x4 block of pixels as braille dots
+fn braille_rows(video: &[u8]) -> Vec<String> {
+    // Dot bits of the braille pattern block, by row and column
+    const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+    (0..VIDEO_HEIGHT as usize)
+        .step_by(4)
+        .map(|top| {
+            (0..VIDEO_WIDTH as usize)
+                .step_by(2)
+                .map(|left| {
+                    let mut pattern = 0;
+                    for (dy, row) in DOTS.iter().enumerate() {
+                        for (dx, &dot) in row.iter().enumerate() {
+                            if lit(video, left + dx, top + dy) {
+                                pattern |= dot;
+                            }
+                        }
+                    }
+                    char::from_u32(0x2800 + pattern).unwrap_or(' ')
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIXELS: usize = VIDEO_WIDTH as usize * VIDEO_HEIGHT as usize;
+
+    #[test]
+    fn half_blocks_stack_two_pixels() {
+        let mut video = [0u8; PIXELS];
+        video[0] = 1;
+        video[VIDEO_WIDTH as usize + 1] = 1;
+        video[2] = 1;
+        video[VIDEO_WIDTH as usize + 2] = 1;
+        let rows = half_block_rows(&video);
+        assert_eq!(rows.len(), VIDEO_HEIGHT as usize / 2);
+        assert!(rows[0].starts_with("▀▄█ "));
+        assert_eq!(rows[0].chars().count(), VIDEO_WIDTH as usize);
+    }
+
+    #[test]
+    fn braille_dots_follow_the_pixels() {
+        let mut video = [0u8; PIXELS];
+        video[0] = 1;
+        video[3 * VIDEO_WIDTH as usize + 1] = 1;
+        let rows = braille_rows(&video);
+        assert_eq!(rows.len(), VIDEO_HEIGHT as usize / 4);
+        assert_eq!(rows[0].chars().count(), VIDEO_WIDTH as usize / 2);
+        assert_eq!(rows[0].chars().next(), Some('\u{2881}'));
+        assert_eq!(rows[1].chars().next(), Some('\u{2800}'));
+    }
+
+    #[test]
+    fn keys_match_the_window_layout() {
+        let keymap = Keymap::default();
+        assert_eq!(chip8_key(&keymap, KeyCode::Char('x')), Some(0x0));
+        assert_eq!(chip8_key(&keymap, KeyCode::Char('X')), Some(0x0));
+        assert_eq!(chip8_key(&keymap, KeyCode::Char('1')), Some(0x1));
+        assert_eq!(chip8_key(&keymap, KeyCode::Char('v')), Some(0xF));
+        assert_eq!(chip8_key(&keymap, KeyCode::Char('p')), None);
+        assert_eq!(chip8_key(&keymap, KeyCode::Up), None);
+    }
+
+    #[test]
+    fn remapped_keys_work_in_the_terminal_too() {
+        let keymap = Keymap::preset(crate::keymap::find("octo").unwrap());
+        assert_eq!(chip8_key(&keymap, KeyCode::Up), Some(0x5));
+        assert_eq!(chip8_key(&keymap, KeyCode::Char('w')), Some(0x5));
+        assert_eq!(chip8_key(&keymap, KeyCode::Char(' ')), Some(0x6));
+        assert_eq!(chip8_key(&keymap, KeyCode::Char('p')), None);
+    }
+}
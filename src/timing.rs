@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+// The emulator advances in 60 Hz frames. Each frame runs a fixed number of
+//  CPU cycles worked out from the frame number alone, never from wall-clock
+//  time, so the same inputs always produce the same run.
+pub const FRAMES_PER_SECOND: u32 = 60;
+
+// Delays shorter than this (including 0) run at this speed instead
+pub const MIN_CYCLE_PERIOD: Duration = Duration::from_micros(100);
+
+pub fn frame_period() -> Duration {
+    Duration::from_secs(1) / FRAMES_PER_SECOND
+}
+
+pub fn cycles_in_frame(frame: u64, cycle_period: Duration) -> u64 {
+    let cycle = cycle_period.max(MIN_CYCLE_PERIOD).as_nanos();
+    let second = Duration::from_secs(1).as_nanos();
+    let fps = FRAMES_PER_SECOND as u128;
+
+    // Spread the remainder across frames instead of rounding every frame
+    let end = (frame as u128 + 1) * second / (fps * cycle);
+    let start = frame as u128 * second / (fps * cycle);
+    (end - start) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_second_of_frames_runs_the_whole_speed() {
+        // Frame and cycle lengths are whole nanoseconds, which can cost a cycle
+        for ips in [30, 60, 500, 700, 1000] {
+            let period = Duration::from_secs(1) / ips;
+            let total: u64 = (0..FRAMES_PER_SECOND as u64).map(|frame| cycles_in_frame(frame, period)).sum();
+            assert!(total.abs_diff(ips as u64) <= 1, "{} instructions per second ran {}", ips, total);
+        }
+    }
+
+    #[test]
+    fn tiny_delays_are_capped() {
+        assert_eq!(cycles_in_frame(0, Duration::ZERO), cycles_in_frame(0, MIN_CYCLE_PERIOD));
+    }
+}
@@ -0,0 +1,192 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+use crate::chip8;
+use crate::screenshot;
+use crate::timing;
+use crate::wav_recorder::WavRecorder;
+
+// Output frame k shows the emulator frame on screen at time k / fps, so after
+//  `frames` emulator frames this many video frames are due
+fn frames_due(frames: u64, fps: u32) -> u64 {
+    frames * fps as u64 / timing::FRAMES_PER_SECOND as u64
+}
+
+// Records the display by piping raw RGB frames into an ffmpeg process, which
+//  picks the container and codec from the output file's extension. Frames
+//  are repeated or dropped to convert the emulator's 60 Hz to the requested
+//  frame rate.
+//
+// The buzzer is recorded alongside, a frame's worth of samples with each
+//  frame, so picture and sound share the emulator's frame count as their
+//  clock and can't drift apart. They are written to separate files next to
+//  the output and joined into it by a second ffmpeg run at the end.
+pub struct VideoRecorder {
+    path: PathBuf,
+    ffmpeg: Child,
+    input: ChildStdin,
+    // The picture on its own while there is a sound track to add
+    picture_path: PathBuf,
+    sound: Option<WavRecorder>,
+    fps: u32,
+    scale: u32,
+    // The color of each pixel value, by the planes lit there
+    colors: [[u8; 3]; 4],
+    frames: u64,
+    written: u64,
+}
+
+// GIFs have no sound
+fn has_sound(path: &Path) -> bool {
+    path.extension().is_none_or(|extension| !extension.eq_ignore_ascii_case("gif"))
+}
+
+// Where the picture and sound go until they are joined, when the file can
+//  have sound: clip.mp4 is made from clip.video.mp4 and clip.audio.wav
+fn track_paths(path: &Path) -> Option<(PathBuf, PathBuf)> {
+    if !has_sound(path) {
+        return None;
+    }
+    let extension = path.extension().map_or(String::new(), |extension| extension.to_string_lossy().into_owned());
+    Some((path.with_extension(format!("video.{}", extension)), path.with_extension("audio.wav")))
+}
+
+fn ffmpeg_error(e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("could not start ffmpeg (is it installed?): {}", e))
+}
+
+impl VideoRecorder {
+    pub fn start(path: &Path, fps: u32, scale: u32, colors: [[u8; 3]; 4]) -> io::Result<VideoRecorder> {
+        let size = format!(
+            "{}x{}",
+            chip8::VIDEO_WIDTH as u32 * scale,
+            chip8::VIDEO_HEIGHT as u32 * scale
+        );
+
+        let (picture_path, sound) = match track_paths(path) {
+            Some((picture_path, sound_path)) => (picture_path, Some(WavRecorder::start(&sound_path)?)),
+            None => (PathBuf::from(path), None),
+        };
+
+        let mut ffmpeg = Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error"])
+            .args(["-f", "rawvideo", "-pixel_format", "rgb24", "-video_size", &size])
+            .args(["-framerate", &fps.to_string(), "-i", "-"])
+            .args(["-pix_fmt", "yuv420p"])
+            .arg(&picture_path)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(ffmpeg_error)?;
+        let input = ffmpeg.stdin.take().expect("ffmpeg stdin is piped");
+
+        Ok(VideoRecorder {
+            path: PathBuf::from(path),
+            ffmpeg,
+            input,
+            picture_path,
+            sound,
+            fps,
+            scale,
+            colors,
+            frames: 0,
+            written: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Add one 60 Hz frame of the display, and the buzzer's samples for it
+    pub fn push(&mut self, video: &[u8], samples: &[i16]) -> io::Result<()> {
+        self.frames += 1;
+        if let Some(sound) = &mut self.sound {
+            sound.push(samples)?;
+        }
+
+        let due = frames_due(self.frames, self.fps);
+        if self.written >= due {
+            return Ok(());
+        }
+
+        let width = chip8::VIDEO_WIDTH as usize;
+        let rgb = screenshot::render_rgb(video, width, self.colors, self.scale);
+        while self.written < due {
+            self.input.write_all(&rgb)?;
+            self.written += 1;
+        }
+        Ok(())
+    }
+
+    // Close the pipe, wait for ffmpeg to write out the picture, and add the
+    //  sound track to it
+    pub fn finish(self) -> io::Result<()> {
+        let VideoRecorder {
+            path,
+            mut ffmpeg,
+            input,
+            picture_path,
+            sound,
+            ..
+        } = self;
+        drop(input);
+
+        let status = ffmpeg.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("ffmpeg exited with {}", status)));
+        }
+
+        let Some(sound) = sound else { return Ok(()) };
+        let sound_path = sound.path().to_path_buf();
+        sound.finish()?;
+
+        // The picture is copied as it is; only the sound is encoded
+        let status = Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error"])
+            .arg("-i")
+            .arg(&picture_path)
+            .arg("-i")
+            .arg(&sound_path)
+            .args(["-map", "0:v", "-map", "1:a", "-c:v", "copy"])
+            .arg(&path)
+            .status()
+            .map_err(ffmpeg_error)?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "ffmpeg could not add the sound ({}); the picture is in {} and the sound in {}",
+                status,
+                picture_path.display(),
+                sound_path.display()
+            )));
+        }
+
+        fs::remove_file(&picture_path)?;
+        fs::remove_file(&sound_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_rates_convert_evenly() {
+        // 30 fps drops every other frame, 120 fps doubles each one
+        let written = |fps| (1..=6).map(|frames| frames_due(frames, fps)).collect::<Vec<_>>();
+        assert_eq!(written(60), [1, 2, 3, 4, 5, 6]);
+        assert_eq!(written(30), [0, 1, 1, 2, 2, 3]);
+        assert_eq!(written(120), [2, 4, 6, 8, 10, 12]);
+        assert_eq!(frames_due(60 * 60, 25), 25 * 60);
+    }
+
+    #[test]
+    fn sound_is_recorded_beside_the_picture_unless_it_is_a_gif() {
+        let (picture, sound) = track_paths(Path::new("out/clip.mp4")).unwrap();
+        assert_eq!(picture, Path::new("out/clip.video.mp4"));
+        assert_eq!(sound, Path::new("out/clip.audio.wav"));
+        assert!(track_paths(Path::new("clip")).is_some());
+        assert_eq!(track_paths(Path::new("clip.GIF")), None);
+    }
+}
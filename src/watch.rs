@@ -0,0 +1,82 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+// How long a change has to stand before it counts, so a ROM still being
+//  written by an assembler isn't loaded half done
+const SETTLE: Duration = Duration::from_millis(200);
+
+// Hears from the system when a file is rewritten. Editors often save by
+//  writing a new file and renaming it over the old one, so the folder is
+//  watched and the events for the file picked out.
+pub struct Watch {
+    path: PathBuf,
+    // Stops watching when dropped
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    // When the file was last heard to change, until that has settled
+    heard: Option<Instant>,
+}
+
+impl Watch {
+    pub fn new(path: &Path) -> io::Result<Watch> {
+        let path = fs::canonicalize(path)?;
+        let folder = path.parent().unwrap_or(&path).to_path_buf();
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender).map_err(io::Error::other)?;
+        watcher.watch(&folder, RecursiveMode::NonRecursive).map_err(io::Error::other)?;
+        Ok(Watch {
+            path,
+            _watcher: watcher,
+            events,
+            heard: None,
+        })
+    }
+
+    // Whether the file has changed since this last returned true
+    pub fn changed(&mut self) -> bool {
+        for event in self.events.try_iter().flatten() {
+            let written = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_));
+            if written && event.paths.contains(&self.path) {
+                self.heard = Some(Instant::now());
+            }
+        }
+        match self.heard {
+            Some(heard) if heard.elapsed() >= SETTLE => {
+                self.heard = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_rewritten_file_counts_once_it_has_settled() {
+        let dir = std::env::temp_dir().join(format!("chip8-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let rom = dir.join("game.ch8");
+        fs::write(&rom, [0x12, 0x00]).unwrap();
+        let mut watch = Watch::new(&rom).unwrap();
+        assert!(!watch.changed());
+
+        fs::write(dir.join("other.ch8"), [0x00]).unwrap();
+        fs::write(&rom, [0x12, 0x02]).unwrap();
+        let start = Instant::now();
+        let mut changes = 0;
+        while start.elapsed() < SETTLE * 5 {
+            changes += watch.changed() as u32;
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(changes, 1);
+    }
+}
@@ -0,0 +1,95 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::buzzer;
+
+const HEADER_SIZE: u32 = 44;
+
+// Writes 16-bit mono PCM to a WAV file as it is produced. The sizes in the
+//  header are filled in when the recording is finished.
+pub struct WavRecorder {
+    path: PathBuf,
+    file: BufWriter<File>,
+    data_size: u32,
+}
+
+impl WavRecorder {
+    pub fn start(path: &Path) -> io::Result<WavRecorder> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut recorder = WavRecorder {
+            path: PathBuf::from(path),
+            file: BufWriter::new(File::create(path)?),
+            data_size: 0,
+        };
+        recorder.write_header()?;
+        Ok(recorder)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn push(&mut self, samples: &[i16]) -> io::Result<()> {
+        for sample in samples {
+            self.file.write_all(&sample.to_le_bytes())?;
+        }
+        self.data_size += samples.len() as u32 * 2;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(0))?;
+        self.write_header()?;
+        self.file.flush()
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let channels: u16 = 1;
+        let bits: u16 = 16;
+        let block_align = channels * bits / 8;
+        let byte_rate = buzzer::SAMPLE_RATE * block_align as u32;
+
+        let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&(HEADER_SIZE - 8 + self.data_size).to_le_bytes());
+        header.extend_from_slice(b"WAVEfmt ");
+        header.extend_from_slice(&16u32.to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        header.extend_from_slice(&channels.to_le_bytes());
+        header.extend_from_slice(&buzzer::SAMPLE_RATE.to_le_bytes());
+        header.extend_from_slice(&byte_rate.to_le_bytes());
+        header.extend_from_slice(&block_align.to_le_bytes());
+        header.extend_from_slice(&bits.to_le_bytes());
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&self.data_size.to_le_bytes());
+
+        self.file.write_all(&header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finished_files_give_their_sizes() {
+        let path = std::env::temp_dir().join(format!("chip8-wav-{}.wav", std::process::id()));
+        let mut recorder = WavRecorder::start(&path).unwrap();
+        recorder.push(&[1, -1, 2]).unwrap();
+        recorder.push(&[i16::MIN]).unwrap();
+        recorder.finish().unwrap();
+
+        let data = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(data.len(), 44 + 8);
+        assert_eq!(&data[..4], b"RIFF");
+        assert_eq!(data[4..8], (36u32 + 8).to_le_bytes());
+        assert_eq!(data[24..28], buzzer::SAMPLE_RATE.to_le_bytes());
+        assert_eq!(data[40..44], 8u32.to_le_bytes());
+        assert_eq!(data[44..], [1, 0, 0xFF, 0xFF, 2, 0, 0x00, 0x80]);
+    }
+}